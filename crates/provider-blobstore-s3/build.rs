@@ -0,0 +1,45 @@
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+fn main() {
+    // Release builds in CI provide the commit, local builds fall back to `git`
+    println!("cargo:rerun-if-env-changed=GITHUB_SHA");
+    let commit = std::env::var("GITHUB_SHA")
+        .ok()
+        .filter(|sha| !sha.is_empty())
+        .or_else(|| {
+            let git_dir = git(&["rev-parse", "--absolute-git-dir"])?;
+            rerun_if_head_changed(Path::new(&git_dir));
+            git(&["rev-parse", "HEAD"])
+        })
+        .unwrap_or_else(|| "unknown".into());
+    println!("cargo:rustc-env=BLOBSTORE_S3_COMMIT={commit}");
+    println!(
+        "cargo:rustc-env=BLOBSTORE_S3_TARGET={}",
+        std::env::var("TARGET").unwrap_or_default()
+    );
+}
+
+/// Run `git` with `args`, returning its trimmed output if it succeeds
+fn git(args: &[&str]) -> Option<String> {
+    let out = Command::new("git").args(args).output().ok()?;
+    out.status
+        .success()
+        .then(|| String::from_utf8_lossy(&out.stdout).trim().to_string())
+}
+
+/// Rebuild once `HEAD` moves, by switching branches or committing onto the current one
+fn rerun_if_head_changed(git_dir: &Path) {
+    let head = git_dir.join("HEAD");
+    println!("cargo:rerun-if-changed={}", head.display());
+    let Ok(head) = std::fs::read_to_string(&head) else {
+        return;
+    };
+    if let Some(name) = head.trim().strip_prefix("ref: ") {
+        // refs of branches nobody committed to in a while may only be in `packed-refs`
+        let refs: [PathBuf; 2] = [git_dir.join(name), git_dir.join("packed-refs")];
+        for path in refs.iter().filter(|path| path.exists()) {
+            println!("cargo:rerun-if-changed={}", path.display());
+        }
+    }
+}
@@ -0,0 +1,128 @@
+//! Accounting of bytes transferred on behalf of components, for cost attribution between
+//! tenants sharing a provider
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use wasmcloud_provider_sdk::wasmcloud_tracing::{global, Counter, KeyValue};
+
+/// Bytes transferred on behalf of a component to and from a bucket
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TransferStats {
+    /// Bytes written to the bucket
+    pub bytes_uploaded: u64,
+    /// Bytes read from the bucket
+    pub bytes_downloaded: u64,
+}
+
+/// Transfer statistics per component and bucket, which are also exported as metrics
+#[derive(Clone)]
+pub(crate) struct TransferAccounting {
+    stats: Arc<Mutex<HashMap<(String, String), TransferStats>>>,
+    uploaded: Counter<u64>,
+    downloaded: Counter<u64>,
+}
+
+impl Default for TransferAccounting {
+    fn default() -> Self {
+        let meter = global::meter("blobstore-s3-provider");
+        Self {
+            stats: Arc::default(),
+            uploaded: meter
+                .u64_counter("blobstore_s3.bytes_uploaded")
+                .with_description("Number of bytes written to buckets on behalf of components")
+                .init(),
+            downloaded: meter
+                .u64_counter("blobstore_s3.bytes_downloaded")
+                .with_description("Number of bytes read from buckets on behalf of components")
+                .init(),
+        }
+    }
+}
+
+impl TransferAccounting {
+    /// Record `n` bytes written to `bucket` on behalf of `component_id`
+    pub(crate) fn record_upload(&self, component_id: &str, bucket: &str, n: u64) {
+        self.uploaded.add(n, &attributes(component_id, bucket));
+        self.update(component_id, bucket, |stats| {
+            stats.bytes_uploaded = stats.bytes_uploaded.saturating_add(n);
+        });
+    }
+
+    /// Record `n` bytes read from `bucket` on behalf of `component_id`
+    pub(crate) fn record_download(&self, component_id: &str, bucket: &str, n: u64) {
+        self.downloaded.add(n, &attributes(component_id, bucket));
+        self.update(component_id, bucket, |stats| {
+            stats.bytes_downloaded = stats.bytes_downloaded.saturating_add(n);
+        });
+    }
+
+    /// Transfer statistics accumulated since the provider started, by component ID and bucket
+    pub(crate) fn snapshot(&self) -> Vec<(String, String, TransferStats)> {
+        let stats = self.stats.lock().unwrap_or_else(|err| err.into_inner());
+        let mut stats: Vec<_> = stats
+            .iter()
+            .map(|((component_id, bucket), stats)| (component_id.clone(), bucket.clone(), *stats))
+            .collect();
+        stats.sort_unstable_by(|(a, b, _), (c, d, _)| (a, b).cmp(&(c, d)));
+        stats
+    }
+
+    fn update(&self, component_id: &str, bucket: &str, f: impl FnOnce(&mut TransferStats)) {
+        let mut stats = self.stats.lock().unwrap_or_else(|err| err.into_inner());
+        f(stats
+            .entry((component_id.to_string(), bucket.to_string()))
+            .or_default());
+    }
+}
+
+fn attributes(component_id: &str, bucket: &str) -> [KeyValue; 2] {
+    [
+        KeyValue::new("component_id", component_id.to_string()),
+        KeyValue::new("bucket", bucket.to_string()),
+    ]
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn transfer_stats() {
+        let accounting = TransferAccounting::default();
+        accounting.record_upload("b", "x", 10);
+        accounting.record_download("a", "x", 5);
+        accounting.record_upload("a", "x", 3);
+        accounting.record_download("a", "x", 7);
+        accounting.record_download("a", "y", 1);
+        assert_eq!(
+            accounting.snapshot(),
+            [
+                (
+                    "a".into(),
+                    "x".into(),
+                    TransferStats {
+                        bytes_uploaded: 3,
+                        bytes_downloaded: 12
+                    }
+                ),
+                (
+                    "a".into(),
+                    "y".into(),
+                    TransferStats {
+                        bytes_uploaded: 0,
+                        bytes_downloaded: 1
+                    }
+                ),
+                (
+                    "b".into(),
+                    "x".into(),
+                    TransferStats {
+                        bytes_uploaded: 10,
+                        bytes_downloaded: 0
+                    }
+                ),
+            ]
+        );
+    }
+}
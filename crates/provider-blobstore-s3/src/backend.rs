@@ -0,0 +1,159 @@
+//! Storage backends other than S3, selected per link by `backend`
+//!
+//! A [`StorageClient`](crate::StorageClient) of a link with another backend delegates the
+//! operations of `wrpc:blobstore` to it, so that the same components run against it and, once
+//! the link is switched, against S3. Extensions of the provider, like versions, locks or jobs,
+//! remain specific to S3.
+
+use core::fmt;
+
+use std::io;
+use std::sync::Arc;
+
+#[cfg(not(feature = "azure"))]
+use anyhow::bail;
+use anyhow::Context as _;
+use bytes::Bytes;
+use futures::future::BoxFuture;
+use futures::stream::BoxStream;
+
+use crate::client::{ContainerInfo, ObjectInfo};
+use crate::config::{AzureConfig, FsConfig, NatsObjectStoreConfig, StorageBackend};
+
+#[cfg(feature = "azure")]
+mod azure;
+mod fs;
+mod nats;
+
+#[cfg(feature = "azure")]
+pub(crate) use azure::AzureBackend;
+pub(crate) use fs::FsBackend;
+pub(crate) use nats::NatsObjectStoreBackend;
+
+/// Storage implementing the operations of `wrpc:blobstore`, in which containers are addressed
+/// by name and objects by key
+pub(crate) trait Backend: fmt::Debug + Send + Sync {
+    fn container_exists<'a>(&'a self, container: &'a str) -> BoxFuture<'a, anyhow::Result<bool>>;
+
+    fn create_container<'a>(&'a self, container: &'a str) -> BoxFuture<'a, anyhow::Result<()>>;
+
+    /// Delete a container, which must be empty
+    fn delete_container<'a>(&'a self, container: &'a str) -> BoxFuture<'a, anyhow::Result<()>>;
+
+    fn get_container_info<'a>(
+        &'a self,
+        container: &'a str,
+    ) -> BoxFuture<'a, anyhow::Result<ContainerInfo>>;
+
+    /// List the keys of the objects of a container in lexicographical order
+    fn list_container_objects<'a>(
+        &'a self,
+        container: &'a str,
+        limit: Option<u64>,
+        offset: Option<u64>,
+    ) -> BoxFuture<'a, anyhow::Result<Vec<String>>>;
+
+    fn has_object<'a>(
+        &'a self,
+        container: &'a str,
+        key: &'a str,
+    ) -> BoxFuture<'a, anyhow::Result<bool>>;
+
+    fn get_object_info<'a>(
+        &'a self,
+        container: &'a str,
+        key: &'a str,
+    ) -> BoxFuture<'a, anyhow::Result<ObjectInfo>>;
+
+    /// Read the object `key` from byte `start`, `end - start` bytes at most, in chunks of at
+    /// most `chunk_size` bytes
+    fn read_object<'a>(
+        &'a self,
+        container: &'a str,
+        key: &'a str,
+        start: u64,
+        end: u64,
+        chunk_size: usize,
+    ) -> BoxFuture<'a, anyhow::Result<BoxStream<'static, io::Result<Bytes>>>>;
+
+    /// Write an object, replacing it if it exists
+    fn write_object<'a>(
+        &'a self,
+        container: &'a str,
+        key: &'a str,
+        data: Bytes,
+    ) -> BoxFuture<'a, anyhow::Result<()>>;
+
+    /// Delete objects, ignoring those not found
+    fn delete_objects<'a>(
+        &'a self,
+        container: &'a str,
+        keys: Vec<String>,
+    ) -> BoxFuture<'a, anyhow::Result<()>>;
+
+    fn copy_object<'a>(
+        &'a self,
+        src_container: &'a str,
+        src_key: &'a str,
+        dest_container: &'a str,
+        dest_key: &'a str,
+    ) -> BoxFuture<'a, anyhow::Result<()>>;
+
+    fn move_object<'a>(
+        &'a self,
+        src_container: &'a str,
+        src_key: &'a str,
+        dest_container: &'a str,
+        dest_key: &'a str,
+    ) -> BoxFuture<'a, anyhow::Result<()>> {
+        Box::pin(async move {
+            if src_container == dest_container && src_key == dest_key {
+                return Ok(());
+            }
+            self.copy_object(src_container, src_key, dest_container, dest_key)
+                .await?;
+            self.delete_objects(src_container, vec![src_key.to_string()])
+                .await
+        })
+    }
+
+    /// Delete all objects of a container, returning their keys
+    fn clear_container<'a>(
+        &'a self,
+        container: &'a str,
+    ) -> BoxFuture<'a, anyhow::Result<Vec<String>>> {
+        Box::pin(async move {
+            let keys = self.list_container_objects(container, None, None).await?;
+            self.delete_objects(container, keys.clone()).await?;
+            Ok(keys)
+        })
+    }
+}
+
+/// Create the backend of a link, `None` for S3, which the S3 clients implement
+#[cfg_attr(not(feature = "azure"), allow(unused_variables))]
+pub(crate) fn new(
+    backend: StorageBackend,
+    fs: Option<FsConfig>,
+    nats_objstore: Option<NatsObjectStoreConfig>,
+    azure: Option<AzureConfig>,
+) -> anyhow::Result<Option<Arc<dyn Backend>>> {
+    match backend {
+        StorageBackend::S3 => Ok(None),
+        StorageBackend::Fs => {
+            let FsConfig { root } = fs.context("the `fs` backend requires `fs.root`")?;
+            Ok(Some(Arc::new(FsBackend::new(root))))
+        }
+        StorageBackend::NatsObjstore => Ok(Some(Arc::new(NatsObjectStoreBackend::new(
+            nats_objstore.unwrap_or_default(),
+        )))),
+        #[cfg(feature = "azure")]
+        StorageBackend::Azure => Ok(Some(Arc::new(AzureBackend::new(
+            azure.unwrap_or_default(),
+        )?))),
+        #[cfg(not(feature = "azure"))]
+        StorageBackend::Azure => {
+            bail!("the `azure` backend requires the provider to be built with the `azure` feature")
+        }
+    }
+}
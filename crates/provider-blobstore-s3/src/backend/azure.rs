@@ -0,0 +1,424 @@
+//! Backend storing containers as Azure Blob Storage containers and objects as block blobs named
+//! by their keys, so that links to Azure behave like those to S3 rather than those of a separate
+//! provider
+//!
+//! Objects larger than a block are written as blocks committed by a block list, like multipart
+//! uploads to S3.
+
+use core::time::Duration;
+
+use std::io;
+
+use anyhow::{bail, Context as _};
+use azure_core::error::ErrorKind as AzureErrorKind;
+use azure_core::StatusCode;
+use azure_storage::{CloudLocation, ConnectionString, StorageCredentials};
+use azure_storage_blobs::blob::CopyStatus;
+use azure_storage_blobs::prelude::{
+    BlobBlockType, BlobClient, BlobServiceClient, BlockId, BlockList, ClientBuilder,
+    ContainerClient,
+};
+use bytes::Bytes;
+use futures::future::BoxFuture;
+use futures::stream::{self, BoxStream};
+use futures::{StreamExt as _, TryStreamExt as _};
+use tokio_util::io::{ReaderStream, StreamReader};
+use tracing::debug;
+
+use super::Backend;
+use crate::client::{ContainerInfo, ObjectInfo};
+use crate::config::AzureConfig;
+use crate::error::{BackendError, ErrorKind};
+
+/// Size of the blocks objects are written in, objects up to this size are written at once
+const BLOCK_SIZE: usize = 8 * 1024 * 1024;
+/// Maximum number of blocks of an object written concurrently
+const MAX_CONCURRENT_BLOCKS: usize = 4;
+/// Maximum number of blobs deleted concurrently
+const MAX_CONCURRENT_DELETES: usize = 16;
+/// Interval of checks of the status of pending copies
+const COPY_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Containers and objects stored as Azure Blob Storage containers and block blobs
+#[derive(Debug)]
+pub(crate) struct AzureBackend {
+    client: BlobServiceClient,
+}
+
+impl AzureBackend {
+    pub(crate) fn new(
+        AzureConfig {
+            connection_string,
+            account,
+            endpoint,
+        }: AzureConfig,
+    ) -> anyhow::Result<Self> {
+        let (account, credentials, endpoint) = match connection_string {
+            Some(connection_string) => {
+                let connection_string = ConnectionString::new(&connection_string)
+                    .context("invalid Azure connection string")?;
+                let account = connection_string
+                    .account_name
+                    .context("Azure connection string is missing `AccountName`")?
+                    .to_string();
+                let credentials = connection_string
+                    .storage_credentials()
+                    .context("failed to read credentials of Azure connection string")?;
+                let endpoint = endpoint.or(connection_string.blob_endpoint.map(str::to_string));
+                (account, credentials, endpoint)
+            }
+            None => {
+                let account = account.context(
+                    "the `azure` backend requires `azure.connection_string` or `azure.account`",
+                )?;
+                let credential = azure_identity::create_credential()
+                    .context("failed to load Azure credentials")?;
+                (
+                    account,
+                    StorageCredentials::token_credential(credential),
+                    endpoint,
+                )
+            }
+        };
+        let builder = match endpoint {
+            Some(uri) => {
+                ClientBuilder::with_location(CloudLocation::Custom { account, uri }, credentials)
+            }
+            None => ClientBuilder::new(account, credentials),
+        };
+        Ok(Self {
+            client: builder.blob_service_client(),
+        })
+    }
+
+    fn container(&self, container: &str) -> ContainerClient {
+        self.client.container_client(container)
+    }
+
+    fn blob(&self, container: &str, key: &str) -> BlobClient {
+        self.client.container_client(container).blob_client(key)
+    }
+}
+
+/// Kind of a failed request, by the status Azure responded with
+fn kind(err: &azure_core::Error) -> ErrorKind {
+    match err.kind() {
+        AzureErrorKind::HttpResponse { status, error_code } => match status {
+            StatusCode::NotFound => ErrorKind::NotFound,
+            StatusCode::Conflict
+                if error_code
+                    .as_deref()
+                    .is_some_and(|code| code.ends_with("AlreadyExists")) =>
+            {
+                ErrorKind::AlreadyExists
+            }
+            StatusCode::Conflict | StatusCode::PreconditionFailed => ErrorKind::PreconditionFailed,
+            StatusCode::Unauthorized | StatusCode::Forbidden => ErrorKind::PermissionDenied,
+            StatusCode::TooManyRequests => ErrorKind::Throttled,
+            StatusCode::ServiceUnavailable if error_code.as_deref() == Some("ServerBusy") => {
+                ErrorKind::Throttled
+            }
+            StatusCode::InternalServerError
+            | StatusCode::BadGateway
+            | StatusCode::ServiceUnavailable
+            | StatusCode::GatewayTimeout => ErrorKind::Unavailable,
+            _ => ErrorKind::Internal,
+        },
+        AzureErrorKind::Io => ErrorKind::Unavailable,
+        AzureErrorKind::Credential => ErrorKind::PermissionDenied,
+        _ => ErrorKind::Internal,
+    }
+}
+
+/// Classify a failed request
+fn error(err: azure_core::Error) -> BackendError {
+    BackendError::new(kind(&err), err)
+}
+
+impl Backend for AzureBackend {
+    fn container_exists<'a>(&'a self, container: &'a str) -> BoxFuture<'a, anyhow::Result<bool>> {
+        Box::pin(async move {
+            self.container(container)
+                .exists()
+                .await
+                .map_err(error)
+                .context("failed to check container existence")
+        })
+    }
+
+    fn create_container<'a>(&'a self, container: &'a str) -> BoxFuture<'a, anyhow::Result<()>> {
+        Box::pin(async move {
+            self.container(container)
+                .create()
+                .await
+                .map_err(error)
+                .context("failed to create container")?;
+            debug!(container, "container created");
+            Ok(())
+        })
+    }
+
+    fn delete_container<'a>(&'a self, container: &'a str) -> BoxFuture<'a, anyhow::Result<()>> {
+        Box::pin(async move {
+            // Azure deletes containers along with their blobs, S3 only deletes empty buckets
+            if !self
+                .list_container_objects(container, Some(1), None)
+                .await?
+                .is_empty()
+            {
+                bail!(BackendError::new(
+                    ErrorKind::PreconditionFailed,
+                    format!("container [{container}] is not empty")
+                ))
+            }
+            self.container(container)
+                .delete()
+                .await
+                .map_err(error)
+                .context("failed to delete container")?;
+            Ok(())
+        })
+    }
+
+    fn get_container_info<'a>(
+        &'a self,
+        container: &'a str,
+    ) -> BoxFuture<'a, anyhow::Result<ContainerInfo>> {
+        Box::pin(async move {
+            let properties = match self.container(container).get_properties().await {
+                Ok(properties) => properties,
+                Err(err) if kind(&err) == ErrorKind::NotFound => bail!(BackendError::new(
+                    ErrorKind::NotFound,
+                    format!("container [{container}] not found")
+                )),
+                Err(err) => return Err(error(err)).context("failed to get container properties"),
+            };
+            // Azure does not record the creation time of containers
+            Ok(ContainerInfo {
+                created_at: properties
+                    .container
+                    .last_modified
+                    .unix_timestamp()
+                    .try_into()
+                    .unwrap_or_default(),
+            })
+        })
+    }
+
+    fn list_container_objects<'a>(
+        &'a self,
+        container: &'a str,
+        limit: Option<u64>,
+        offset: Option<u64>,
+    ) -> BoxFuture<'a, anyhow::Result<Vec<String>>> {
+        Box::pin(async move {
+            let mut pages = self.container(container).list_blobs().into_stream();
+            let mut keys = Vec::new();
+            while let Some(page) = pages.next().await {
+                let page = match page {
+                    Ok(page) => page,
+                    Err(err) if kind(&err) == ErrorKind::NotFound => bail!(BackendError::new(
+                        ErrorKind::NotFound,
+                        format!("container [{container}] not found")
+                    )),
+                    Err(err) => return Err(error(err)).context("failed to list blobs"),
+                };
+                keys.extend(page.blobs.blobs().map(|blob| blob.name.clone()));
+            }
+            keys.sort_unstable();
+            Ok(keys
+                .into_iter()
+                .skip(offset.unwrap_or_default().try_into().unwrap_or(usize::MAX))
+                .take(limit.unwrap_or(u64::MAX).try_into().unwrap_or(usize::MAX))
+                .collect())
+        })
+    }
+
+    fn has_object<'a>(
+        &'a self,
+        container: &'a str,
+        key: &'a str,
+    ) -> BoxFuture<'a, anyhow::Result<bool>> {
+        Box::pin(async move {
+            self.blob(container, key)
+                .exists()
+                .await
+                .map_err(error)
+                .context("failed to check blob existence")
+        })
+    }
+
+    fn get_object_info<'a>(
+        &'a self,
+        container: &'a str,
+        key: &'a str,
+    ) -> BoxFuture<'a, anyhow::Result<ObjectInfo>> {
+        Box::pin(async move {
+            let properties = match self.blob(container, key).get_properties().await {
+                Ok(res) => res.blob.properties,
+                Err(err) if kind(&err) == ErrorKind::NotFound => bail!(BackendError::new(
+                    ErrorKind::NotFound,
+                    format!("object [{container}/{key}] not found")
+                )),
+                Err(err) => return Err(error(err)).context("failed to get blob properties"),
+            };
+            Ok(ObjectInfo {
+                created_at: properties
+                    .creation_time
+                    .unix_timestamp()
+                    .try_into()
+                    .unwrap_or_default(),
+                size: properties.content_length,
+            })
+        })
+    }
+
+    fn read_object<'a>(
+        &'a self,
+        container: &'a str,
+        key: &'a str,
+        start: u64,
+        end: u64,
+        chunk_size: usize,
+    ) -> BoxFuture<'a, anyhow::Result<BoxStream<'static, io::Result<Bytes>>>> {
+        Box::pin(async move {
+            if end <= start {
+                return Ok(stream::empty().boxed());
+            }
+            let mut responses = self
+                .blob(container, key)
+                .get()
+                .range(start..end)
+                .into_stream();
+            // fail on missing objects when reading starts, like S3, rather than in the stream
+            let first = match responses.next().await {
+                Some(Ok(res)) => res,
+                Some(Err(err)) if kind(&err) == ErrorKind::NotFound => {
+                    bail!(BackendError::new(
+                        ErrorKind::NotFound,
+                        format!("object [{container}/{key}] not found")
+                    ))
+                }
+                Some(Err(err)) => return Err(error(err)).context("failed to get blob"),
+                None => return Ok(stream::empty().boxed()),
+            };
+            let data = stream::once(async move { Ok(first) })
+                .chain(responses)
+                .map_err(io::Error::other)
+                .map_ok(|res| res.data.map_err(io::Error::other))
+                .try_flatten();
+            Ok(ReaderStream::with_capacity(StreamReader::new(data), chunk_size).boxed())
+        })
+    }
+
+    fn write_object<'a>(
+        &'a self,
+        container: &'a str,
+        key: &'a str,
+        data: Bytes,
+    ) -> BoxFuture<'a, anyhow::Result<()>> {
+        Box::pin(async move {
+            let blob = self.blob(container, key);
+            if data.len() <= BLOCK_SIZE {
+                blob.put_block_blob(data)
+                    .await
+                    .map_err(error)
+                    .context("failed to put block blob")?;
+                return Ok(());
+            }
+            let blob = &blob;
+            let blocks: Vec<_> = stream::iter((0..data.len()).step_by(BLOCK_SIZE).enumerate())
+                .map(|(i, offset)| {
+                    // IDs of the blocks of a blob must be of equal length
+                    let id = BlockId::new(format!("{i:08}"));
+                    let block = data.slice(offset..data.len().min(offset + BLOCK_SIZE));
+                    async move {
+                        blob.put_block(id.clone(), block)
+                            .await
+                            .map_err(error)
+                            .with_context(|| format!("failed to put block {i}"))?;
+                        anyhow::Ok(BlobBlockType::new_uncommitted(id))
+                    }
+                })
+                .buffered(MAX_CONCURRENT_BLOCKS)
+                .try_collect()
+                .await?;
+            debug!(container, key, blocks = blocks.len(), "blocks uploaded");
+            blob.put_block_list(BlockList { blocks })
+                .await
+                .map_err(error)
+                .context("failed to commit block list")?;
+            Ok(())
+        })
+    }
+
+    fn delete_objects<'a>(
+        &'a self,
+        container: &'a str,
+        keys: Vec<String>,
+    ) -> BoxFuture<'a, anyhow::Result<()>> {
+        Box::pin(async move {
+            stream::iter(keys)
+                .map(|key| async move {
+                    match self.blob(container, &key).delete().await {
+                        Ok(_) => Ok(()),
+                        Err(err) if kind(&err) == ErrorKind::NotFound => Ok(()),
+                        Err(err) => Err(error(err))
+                            .with_context(|| format!("failed to delete blob `{key}`")),
+                    }
+                })
+                .buffer_unordered(MAX_CONCURRENT_DELETES)
+                .try_collect()
+                .await
+        })
+    }
+
+    fn copy_object<'a>(
+        &'a self,
+        src_container: &'a str,
+        src_key: &'a str,
+        dest_container: &'a str,
+        dest_key: &'a str,
+    ) -> BoxFuture<'a, anyhow::Result<()>> {
+        Box::pin(async move {
+            if src_container == dest_container && src_key == dest_key {
+                return Ok(());
+            }
+            let src = self
+                .blob(src_container, src_key)
+                .url()
+                .map_err(error)
+                .context("failed to build URL of source blob")?;
+            let dest = self.blob(dest_container, dest_key);
+            let res = match dest.copy(src).await {
+                Ok(res) => res,
+                Err(err) if kind(&err) == ErrorKind::NotFound => {
+                    bail!(BackendError::new(
+                        ErrorKind::NotFound,
+                        format!("object [{src_container}/{src_key}] not found")
+                    ))
+                }
+                Err(err) => return Err(error(err)).context("failed to copy blob"),
+            };
+            // copies are asynchronous, wait for them to complete, so that the source can be
+            // deleted by moves and the copy read right away
+            let mut status = res.copy_status;
+            while status == CopyStatus::Pending {
+                tokio::time::sleep(COPY_POLL_INTERVAL).await;
+                status = dest
+                    .get_properties()
+                    .await
+                    .map_err(error)
+                    .context("failed to get properties of blob copy")?
+                    .blob
+                    .properties
+                    .copy_status
+                    .unwrap_or(CopyStatus::Success);
+            }
+            if status != CopyStatus::Success {
+                bail!("copy of blob [{src_container}/{src_key}] did not succeed: {status:?}")
+            }
+            Ok(())
+        })
+    }
+}
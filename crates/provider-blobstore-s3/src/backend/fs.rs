@@ -0,0 +1,461 @@
+//! Backend storing containers as directories and objects as files under a root directory, so
+//! that components can be developed without any external dependencies
+//!
+//! Objects are written to a temporary file under `<root>/.tmp` first and renamed into place,
+//! so that readers never observe partially written objects. Directories emptied by deletes are
+//! removed, so that containers can be deleted once all of their objects are.
+
+use std::io::{self, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use anyhow::{bail, ensure, Context as _};
+use bytes::Bytes;
+use futures::future::BoxFuture;
+use futures::stream::BoxStream;
+use futures::StreamExt as _;
+use tokio::fs;
+use tokio::io::{AsyncReadExt as _, AsyncSeekExt as _};
+use tokio_util::io::ReaderStream;
+use tracing::{debug, warn};
+use uuid::Uuid;
+
+use super::Backend;
+use crate::client::{ContainerInfo, ObjectInfo};
+use crate::error::{BackendError, ErrorKind};
+
+/// Directory under the root temporary files are written to, which is not a valid container name
+const TEMP_DIR: &str = ".tmp";
+
+/// Containers and objects stored as directories and files under a root directory
+#[derive(Debug)]
+pub(crate) struct FsBackend {
+    root: PathBuf,
+}
+
+impl FsBackend {
+    pub(crate) fn new(root: PathBuf) -> Self {
+        Self { root }
+    }
+
+    /// Directory of a container
+    fn container_dir(&self, container: &str) -> anyhow::Result<PathBuf> {
+        ensure!(
+            !container.is_empty()
+                && !container.starts_with('.')
+                && !container.contains(['/', '\\']),
+            "invalid container name `{container}`"
+        );
+        Ok(self.root.join(container))
+    }
+
+    /// Path of the file of an object, in which `/` of the key separate directories
+    fn object_path(&self, container: &str, key: &str) -> anyhow::Result<PathBuf> {
+        let mut path = self.container_dir(container)?;
+        for segment in key.split('/') {
+            ensure!(
+                !segment.is_empty() && segment != "." && segment != ".." && !segment.contains('\\'),
+                "object key `{key}` cannot be stored by the `fs` backend"
+            );
+            path.push(segment);
+        }
+        Ok(path)
+    }
+
+    /// Fail unless the directory of a container exists
+    async fn ensure_container(&self, container: &str) -> anyhow::Result<PathBuf> {
+        let dir = self.container_dir(container)?;
+        match fs::metadata(&dir).await {
+            Ok(metadata) if metadata.is_dir() => Ok(dir),
+            Ok(_) => bail!(BackendError::new(
+                ErrorKind::NotFound,
+                format!("container [{container}] not found")
+            )),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                bail!(BackendError::new(
+                    ErrorKind::NotFound,
+                    format!("container [{container}] not found")
+                ))
+            }
+            Err(err) => Err(err).context("failed to stat container directory"),
+        }
+    }
+
+    /// Metadata of the file of an object, failing if it does not exist
+    async fn object_metadata(
+        &self,
+        container: &str,
+        key: &str,
+    ) -> anyhow::Result<(PathBuf, std::fs::Metadata)> {
+        let path = self.object_path(container, key)?;
+        match fs::metadata(&path).await {
+            Ok(metadata) if metadata.is_file() => Ok((path, metadata)),
+            Ok(_) => bail!(BackendError::new(
+                ErrorKind::NotFound,
+                format!("object [{container}/{key}] not found")
+            )),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => {
+                self.ensure_container(container).await?;
+                bail!(BackendError::new(
+                    ErrorKind::NotFound,
+                    format!("object [{container}/{key}] not found")
+                ))
+            }
+            Err(err) => Err(err).context("failed to stat object file"),
+        }
+    }
+
+    /// Create the directories of the file of an object in an existing container
+    async fn prepare_object(&self, container: &str, key: &str) -> anyhow::Result<PathBuf> {
+        let path = self.object_path(container, key)?;
+        self.ensure_container(container).await?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .await
+                .with_context(|| format!("failed to create directory of object `{key}`"))?;
+        }
+        Ok(path)
+    }
+
+    /// Path of a new temporary file
+    async fn temp_path(&self) -> anyhow::Result<PathBuf> {
+        let dir = self.root.join(TEMP_DIR);
+        fs::create_dir_all(&dir)
+            .await
+            .context("failed to create directory of temporary files")?;
+        Ok(dir.join(Uuid::new_v4().to_string()))
+    }
+
+    /// Move a temporary file to its final path, removing it if that fails
+    async fn persist(tmp: &Path, path: &Path) -> anyhow::Result<()> {
+        if let Err(err) = fs::rename(tmp, path).await {
+            if let Err(err) = fs::remove_file(tmp).await {
+                warn!(?err, path = %tmp.display(), "failed to remove temporary file");
+            }
+            return Err(err).context("failed to move object into place");
+        }
+        Ok(())
+    }
+
+    /// Remove the directories of an object emptied by its deletion, up to its container
+    async fn prune(container_dir: &Path, path: &Path) {
+        let mut dir = path.parent();
+        while let Some(parent) = dir.filter(|dir| *dir != container_dir) {
+            if fs::remove_dir(parent).await.is_err() {
+                break;
+            }
+            dir = parent.parent();
+        }
+    }
+}
+
+/// Seconds since the Unix epoch of the creation, or else last modification of a file
+fn created_at(metadata: &std::fs::Metadata) -> u64 {
+    metadata
+        .created()
+        .or_else(|_| metadata.modified())
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map_or(0, |t| t.as_secs())
+}
+
+/// Keys of all files under `dir`, in lexicographical order
+async fn list_keys(dir: &Path) -> anyhow::Result<Vec<String>> {
+    let mut keys = Vec::new();
+    let mut dirs = vec![(dir.to_path_buf(), String::new())];
+    while let Some((dir, prefix)) = dirs.pop() {
+        let mut entries = fs::read_dir(&dir)
+            .await
+            .with_context(|| format!("failed to read directory `{}`", dir.display()))?;
+        while let Some(entry) = entries
+            .next_entry()
+            .await
+            .context("failed to read directory entry")?
+        {
+            let Ok(name) = entry.file_name().into_string() else {
+                warn!(path = %entry.path().display(), "skipping file with non-UTF-8 name");
+                continue;
+            };
+            let key = format!("{prefix}{name}");
+            if entry
+                .file_type()
+                .await
+                .context("failed to read file type")?
+                .is_dir()
+            {
+                dirs.push((entry.path(), format!("{key}/")));
+            } else {
+                keys.push(key);
+            }
+        }
+    }
+    keys.sort_unstable();
+    Ok(keys)
+}
+
+impl Backend for FsBackend {
+    fn container_exists<'a>(&'a self, container: &'a str) -> BoxFuture<'a, anyhow::Result<bool>> {
+        Box::pin(async move {
+            let dir = self.container_dir(container)?;
+            match fs::metadata(&dir).await {
+                Ok(metadata) => Ok(metadata.is_dir()),
+                Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(false),
+                Err(err) => Err(err).context("failed to stat container directory"),
+            }
+        })
+    }
+
+    fn create_container<'a>(&'a self, container: &'a str) -> BoxFuture<'a, anyhow::Result<()>> {
+        Box::pin(async move {
+            let dir = self.container_dir(container)?;
+            fs::create_dir_all(&dir)
+                .await
+                .with_context(|| format!("failed to create directory `{}`", dir.display()))?;
+            debug!(path = %dir.display(), "container directory created");
+            Ok(())
+        })
+    }
+
+    fn delete_container<'a>(&'a self, container: &'a str) -> BoxFuture<'a, anyhow::Result<()>> {
+        Box::pin(async move {
+            let dir = self.ensure_container(container).await?;
+            fs::remove_dir(&dir)
+                .await
+                .context("failed to delete container directory, which must be empty")
+        })
+    }
+
+    fn get_container_info<'a>(
+        &'a self,
+        container: &'a str,
+    ) -> BoxFuture<'a, anyhow::Result<ContainerInfo>> {
+        Box::pin(async move {
+            let dir = self.ensure_container(container).await?;
+            let metadata = fs::metadata(&dir)
+                .await
+                .context("failed to stat container directory")?;
+            Ok(ContainerInfo {
+                created_at: created_at(&metadata),
+            })
+        })
+    }
+
+    fn list_container_objects<'a>(
+        &'a self,
+        container: &'a str,
+        limit: Option<u64>,
+        offset: Option<u64>,
+    ) -> BoxFuture<'a, anyhow::Result<Vec<String>>> {
+        Box::pin(async move {
+            let dir = self.ensure_container(container).await?;
+            Ok(list_keys(&dir)
+                .await?
+                .into_iter()
+                .skip(offset.unwrap_or_default().try_into().unwrap_or(usize::MAX))
+                .take(limit.unwrap_or(u64::MAX).try_into().unwrap_or(usize::MAX))
+                .collect())
+        })
+    }
+
+    fn has_object<'a>(
+        &'a self,
+        container: &'a str,
+        key: &'a str,
+    ) -> BoxFuture<'a, anyhow::Result<bool>> {
+        Box::pin(async move {
+            let path = self.object_path(container, key)?;
+            match fs::metadata(&path).await {
+                Ok(metadata) => Ok(metadata.is_file()),
+                Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(false),
+                Err(err) => Err(err).context("failed to stat object file"),
+            }
+        })
+    }
+
+    fn get_object_info<'a>(
+        &'a self,
+        container: &'a str,
+        key: &'a str,
+    ) -> BoxFuture<'a, anyhow::Result<ObjectInfo>> {
+        Box::pin(async move {
+            let (_, metadata) = self.object_metadata(container, key).await?;
+            Ok(ObjectInfo {
+                created_at: created_at(&metadata),
+                size: metadata.len(),
+            })
+        })
+    }
+
+    fn read_object<'a>(
+        &'a self,
+        container: &'a str,
+        key: &'a str,
+        start: u64,
+        end: u64,
+        chunk_size: usize,
+    ) -> BoxFuture<'a, anyhow::Result<BoxStream<'static, io::Result<Bytes>>>> {
+        Box::pin(async move {
+            let limit = end
+                .checked_sub(start)
+                .context("`end` must be greater than `start`")?;
+            let (path, _) = self.object_metadata(container, key).await?;
+            let mut file = fs::File::open(&path)
+                .await
+                .context("failed to open object file")?;
+            file.seek(SeekFrom::Start(start))
+                .await
+                .context("failed to seek object file")?;
+            Ok(ReaderStream::with_capacity(file.take(limit), chunk_size).boxed())
+        })
+    }
+
+    fn write_object<'a>(
+        &'a self,
+        container: &'a str,
+        key: &'a str,
+        data: Bytes,
+    ) -> BoxFuture<'a, anyhow::Result<()>> {
+        Box::pin(async move {
+            let path = self.prepare_object(container, key).await?;
+            let tmp = self.temp_path().await?;
+            fs::write(&tmp, &data)
+                .await
+                .context("failed to write object file")?;
+            Self::persist(&tmp, &path).await
+        })
+    }
+
+    fn delete_objects<'a>(
+        &'a self,
+        container: &'a str,
+        keys: Vec<String>,
+    ) -> BoxFuture<'a, anyhow::Result<()>> {
+        Box::pin(async move {
+            let dir = self.ensure_container(container).await?;
+            for key in keys {
+                let path = self.object_path(container, &key)?;
+                match fs::remove_file(&path).await {
+                    Ok(()) => Self::prune(&dir, &path).await,
+                    Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+                    Err(err) => {
+                        return Err(err).with_context(|| format!("failed to delete object `{key}`"))
+                    }
+                }
+            }
+            Ok(())
+        })
+    }
+
+    fn copy_object<'a>(
+        &'a self,
+        src_container: &'a str,
+        src_key: &'a str,
+        dest_container: &'a str,
+        dest_key: &'a str,
+    ) -> BoxFuture<'a, anyhow::Result<()>> {
+        Box::pin(async move {
+            let (src, _) = self.object_metadata(src_container, src_key).await?;
+            let dest = self.prepare_object(dest_container, dest_key).await?;
+            let tmp = self.temp_path().await?;
+            fs::copy(&src, &tmp)
+                .await
+                .context("failed to copy object file")?;
+            Self::persist(&tmp, &dest).await
+        })
+    }
+
+    fn move_object<'a>(
+        &'a self,
+        src_container: &'a str,
+        src_key: &'a str,
+        dest_container: &'a str,
+        dest_key: &'a str,
+    ) -> BoxFuture<'a, anyhow::Result<()>> {
+        Box::pin(async move {
+            let (src, _) = self.object_metadata(src_container, src_key).await?;
+            if src_container == dest_container && src_key == dest_key {
+                return Ok(());
+            }
+            let dest = self.prepare_object(dest_container, dest_key).await?;
+            fs::rename(&src, &dest)
+                .await
+                .context("failed to move object file")?;
+            Self::prune(&self.container_dir(src_container)?, &src).await;
+            Ok(())
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use futures::TryStreamExt as _;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn fs_backend() {
+        let root = std::env::temp_dir().join(format!("blobstore-fs-{}", Uuid::new_v4()));
+        let fs = FsBackend::new(root.clone());
+
+        assert!(!fs.container_exists("a").await.unwrap());
+        assert!(fs.write_object("a", "x", Bytes::new()).await.is_err());
+        fs.create_container("a").await.unwrap();
+        fs.create_container("b").await.unwrap();
+        assert!(fs.container_exists("a").await.unwrap());
+        assert!(fs.create_container("../a").await.is_err());
+
+        fs.write_object("a", "dir/nested/obj", Bytes::from_static(b"hello world"))
+            .await
+            .unwrap();
+        fs.write_object("a", "top", Bytes::from_static(b"top"))
+            .await
+            .unwrap();
+        assert!(fs
+            .write_object("a", "../escape", Bytes::new())
+            .await
+            .is_err());
+        assert!(fs.write_object("a", "dir/", Bytes::new()).await.is_err());
+        assert!(!fs.has_object("a", "dir").await.unwrap());
+        assert_eq!(fs.get_object_info("a", "top").await.unwrap().size, 3);
+        assert_eq!(
+            fs.list_container_objects("a", None, None).await.unwrap(),
+            ["dir/nested/obj", "top"]
+        );
+        assert_eq!(
+            fs.list_container_objects("a", Some(1), Some(1))
+                .await
+                .unwrap(),
+            ["top"]
+        );
+
+        let data: Vec<Bytes> = fs
+            .read_object("a", "dir/nested/obj", 6, 11, 2)
+            .await
+            .unwrap()
+            .try_collect()
+            .await
+            .unwrap();
+        assert_eq!(data.concat(), b"world");
+
+        fs.copy_object("a", "top", "b", "copy").await.unwrap();
+        fs.move_object("a", "dir/nested/obj", "b", "moved")
+            .await
+            .unwrap();
+        assert!(!root.join("a/dir").exists(), "emptied directories remain");
+        assert_eq!(
+            fs.list_container_objects("b", None, None).await.unwrap(),
+            ["copy", "moved"]
+        );
+
+        assert!(fs.delete_container("b").await.is_err());
+        assert_eq!(fs.clear_container("b").await.unwrap(), ["copy", "moved"]);
+        fs.delete_objects("a", vec!["top".into(), "missing".into()])
+            .await
+            .unwrap();
+        fs.delete_container("a").await.unwrap();
+        fs.delete_container("b").await.unwrap();
+        assert!(!fs.container_exists("b").await.unwrap());
+
+        std::fs::remove_dir_all(root).unwrap();
+    }
+}
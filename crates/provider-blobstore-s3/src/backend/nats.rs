@@ -0,0 +1,338 @@
+//! Backend storing containers as NATS JetStream object stores, so that small deployments can
+//! store objects in the NATS servers they run anyway
+//!
+//! The connection is established on first use, so that links can be established while NATS is
+//! unavailable, like those to S3.
+
+use std::io;
+
+use anyhow::{bail, Context as _};
+use async_nats::jetstream::object_store::{
+    self, DeleteErrorKind, GetErrorKind, InfoErrorKind, ObjectStore,
+};
+use async_nats::jetstream::{self, Context as JetStream};
+use bytes::Bytes;
+use futures::future::BoxFuture;
+use futures::stream::BoxStream;
+use futures::{StreamExt as _, TryStreamExt as _};
+use tokio::io::AsyncReadExt as _;
+use tokio::sync::OnceCell;
+use tokio_util::io::ReaderStream;
+use tracing::debug;
+
+use super::Backend;
+use crate::client::{ContainerInfo, ObjectInfo};
+use crate::config::NatsObjectStoreConfig;
+use crate::error::{BackendError, ErrorKind};
+
+/// URL of the NATS server, unless configured
+const DEFAULT_URL: &str = "nats://127.0.0.1:4222";
+/// Prefix of the names of the streams backing object stores
+const STREAM_PREFIX: &str = "OBJ_";
+
+/// Containers and objects stored as NATS JetStream object stores and their objects
+#[derive(Debug)]
+pub(crate) struct NatsObjectStoreBackend {
+    config: NatsObjectStoreConfig,
+    jetstream: OnceCell<JetStream>,
+}
+
+impl NatsObjectStoreBackend {
+    pub(crate) fn new(config: NatsObjectStoreConfig) -> Self {
+        Self {
+            config,
+            jetstream: OnceCell::new(),
+        }
+    }
+
+    /// JetStream context of the connection, connecting to NATS if not connected yet
+    async fn jetstream(&self) -> anyhow::Result<&JetStream> {
+        self.jetstream
+            .get_or_try_init(|| async {
+                let NatsObjectStoreConfig {
+                    url,
+                    credentials_file,
+                    js_domain,
+                } = &self.config;
+                let opts = match credentials_file {
+                    Some(path) => async_nats::ConnectOptions::with_credentials_file(path)
+                        .await
+                        .context("failed to read NATS credentials file")?,
+                    None => async_nats::ConnectOptions::default(),
+                };
+                let url = url.as_deref().unwrap_or(DEFAULT_URL);
+                let client = opts
+                    .name("blobstore-s3-provider")
+                    .connect(url)
+                    .await
+                    .map_err(|err| BackendError::new(ErrorKind::Unavailable, err))
+                    .with_context(|| format!("failed to connect to NATS at `{url}`"))?;
+                debug!(url, "connected to NATS");
+                anyhow::Ok(match js_domain {
+                    Some(domain) => jetstream::with_domain(client, domain),
+                    None => jetstream::new(client),
+                })
+            })
+            .await
+    }
+
+    /// Object store of a container
+    async fn store(&self, container: &str) -> anyhow::Result<ObjectStore> {
+        self.jetstream()
+            .await?
+            .get_object_store(container)
+            .await
+            .with_context(|| format!("failed to open object store of container [{container}]"))
+    }
+
+    /// Metadata of an object, failing if it does not exist
+    async fn info(
+        store: &ObjectStore,
+        container: &str,
+        key: &str,
+    ) -> anyhow::Result<object_store::ObjectInfo> {
+        match store.info(key).await {
+            Ok(info) if !info.deleted => Ok(info),
+            Ok(_) => bail!(BackendError::new(
+                ErrorKind::NotFound,
+                format!("object [{container}/{key}] not found")
+            )),
+            Err(err) if err.kind() == InfoErrorKind::NotFound => {
+                bail!(BackendError::new(
+                    ErrorKind::NotFound,
+                    format!("object [{container}/{key}] not found")
+                ))
+            }
+            Err(err) => Err(err).context("failed to get object info"),
+        }
+    }
+
+    /// Open an object for reading, failing if it does not exist
+    async fn get(
+        store: &ObjectStore,
+        container: &str,
+        key: &str,
+    ) -> anyhow::Result<object_store::Object> {
+        match store.get(key).await {
+            Ok(object) => Ok(object),
+            Err(err) if err.kind() == GetErrorKind::NotFound => {
+                bail!(BackendError::new(
+                    ErrorKind::NotFound,
+                    format!("object [{container}/{key}] not found")
+                ))
+            }
+            Err(err) => Err(err).context("failed to get object"),
+        }
+    }
+}
+
+impl Backend for NatsObjectStoreBackend {
+    fn container_exists<'a>(&'a self, container: &'a str) -> BoxFuture<'a, anyhow::Result<bool>> {
+        Box::pin(async move {
+            let stream = format!("{STREAM_PREFIX}{container}");
+            let mut names = self.jetstream().await?.stream_names();
+            while let Some(name) = names
+                .try_next()
+                .await
+                .context("failed to list JetStream streams")?
+            {
+                if name == stream {
+                    return Ok(true);
+                }
+            }
+            Ok(false)
+        })
+    }
+
+    fn create_container<'a>(&'a self, container: &'a str) -> BoxFuture<'a, anyhow::Result<()>> {
+        Box::pin(async move {
+            self.jetstream()
+                .await?
+                .create_object_store(object_store::Config {
+                    bucket: container.to_string(),
+                    ..Default::default()
+                })
+                .await
+                .context("failed to create object store")?;
+            debug!(container, "object store created");
+            Ok(())
+        })
+    }
+
+    fn delete_container<'a>(&'a self, container: &'a str) -> BoxFuture<'a, anyhow::Result<()>> {
+        Box::pin(async move {
+            if !self
+                .list_container_objects(container, Some(1), None)
+                .await?
+                .is_empty()
+            {
+                bail!(BackendError::new(
+                    ErrorKind::PreconditionFailed,
+                    format!("container [{container}] is not empty")
+                ))
+            }
+            self.jetstream()
+                .await?
+                .delete_object_store(container)
+                .await
+                .context("failed to delete object store")
+        })
+    }
+
+    fn get_container_info<'a>(
+        &'a self,
+        container: &'a str,
+    ) -> BoxFuture<'a, anyhow::Result<ContainerInfo>> {
+        Box::pin(async move {
+            let stream = self
+                .jetstream()
+                .await?
+                .get_stream(format!("{STREAM_PREFIX}{container}"))
+                .await
+                .with_context(|| format!("failed to get stream of container [{container}]"))?;
+            Ok(ContainerInfo {
+                created_at: stream
+                    .cached_info()
+                    .created
+                    .unix_timestamp()
+                    .try_into()
+                    .unwrap_or_default(),
+            })
+        })
+    }
+
+    fn list_container_objects<'a>(
+        &'a self,
+        container: &'a str,
+        limit: Option<u64>,
+        offset: Option<u64>,
+    ) -> BoxFuture<'a, anyhow::Result<Vec<String>>> {
+        Box::pin(async move {
+            let mut keys: Vec<_> = self
+                .store(container)
+                .await?
+                .list()
+                .await
+                .context("failed to list objects")?
+                .try_filter_map(|info| async move { Ok((!info.deleted).then_some(info.name)) })
+                .try_collect()
+                .await
+                .context("failed to list objects")?;
+            keys.sort_unstable();
+            Ok(keys
+                .into_iter()
+                .skip(offset.unwrap_or_default().try_into().unwrap_or(usize::MAX))
+                .take(limit.unwrap_or(u64::MAX).try_into().unwrap_or(usize::MAX))
+                .collect())
+        })
+    }
+
+    fn has_object<'a>(
+        &'a self,
+        container: &'a str,
+        key: &'a str,
+    ) -> BoxFuture<'a, anyhow::Result<bool>> {
+        Box::pin(async move {
+            match self.store(container).await?.info(key).await {
+                Ok(info) => Ok(!info.deleted),
+                Err(err) if err.kind() == InfoErrorKind::NotFound => Ok(false),
+                Err(err) => Err(err).context("failed to get object info"),
+            }
+        })
+    }
+
+    fn get_object_info<'a>(
+        &'a self,
+        container: &'a str,
+        key: &'a str,
+    ) -> BoxFuture<'a, anyhow::Result<ObjectInfo>> {
+        Box::pin(async move {
+            let info = Self::info(&self.store(container).await?, container, key).await?;
+            Ok(ObjectInfo {
+                created_at: info
+                    .modified
+                    .and_then(|modified| modified.unix_timestamp().try_into().ok())
+                    .unwrap_or_default(),
+                size: info.size as u64,
+            })
+        })
+    }
+
+    fn read_object<'a>(
+        &'a self,
+        container: &'a str,
+        key: &'a str,
+        start: u64,
+        end: u64,
+        chunk_size: usize,
+    ) -> BoxFuture<'a, anyhow::Result<BoxStream<'static, io::Result<Bytes>>>> {
+        Box::pin(async move {
+            let limit = end
+                .checked_sub(start)
+                .context("`end` must be greater than `start`")?;
+            let mut object = Self::get(&self.store(container).await?, container, key).await?;
+            // objects are stored in chunks, which are read from the start
+            tokio::io::copy(&mut (&mut object).take(start), &mut tokio::io::sink())
+                .await
+                .context("failed to skip object data")?;
+            Ok(ReaderStream::with_capacity(object.take(limit), chunk_size).boxed())
+        })
+    }
+
+    fn write_object<'a>(
+        &'a self,
+        container: &'a str,
+        key: &'a str,
+        data: Bytes,
+    ) -> BoxFuture<'a, anyhow::Result<()>> {
+        Box::pin(async move {
+            self.store(container)
+                .await?
+                .put(key, &mut data.as_ref())
+                .await
+                .context("failed to put object")?;
+            Ok(())
+        })
+    }
+
+    fn delete_objects<'a>(
+        &'a self,
+        container: &'a str,
+        keys: Vec<String>,
+    ) -> BoxFuture<'a, anyhow::Result<()>> {
+        Box::pin(async move {
+            let store = self.store(container).await?;
+            for key in keys {
+                match store.delete(&key).await {
+                    Ok(()) => {}
+                    Err(err) if err.kind() == DeleteErrorKind::NotFound => {}
+                    Err(err) => {
+                        return Err(err).with_context(|| format!("failed to delete object `{key}`"))
+                    }
+                }
+            }
+            Ok(())
+        })
+    }
+
+    fn copy_object<'a>(
+        &'a self,
+        src_container: &'a str,
+        src_key: &'a str,
+        dest_container: &'a str,
+        dest_key: &'a str,
+    ) -> BoxFuture<'a, anyhow::Result<()>> {
+        Box::pin(async move {
+            if src_container == dest_container && src_key == dest_key {
+                return Ok(());
+            }
+            let src = self.store(src_container).await?;
+            let dest = self.store(dest_container).await?;
+            let mut object = Self::get(&src, src_container, src_key).await?;
+            dest.put(dest_key, &mut object)
+                .await
+                .context("failed to put copy of object")?;
+            Ok(())
+        })
+    }
+}
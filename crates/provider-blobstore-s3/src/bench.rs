@@ -0,0 +1,437 @@
+//! Load generator driving read and write workloads against a bucket through [`StorageClient`],
+//! reporting throughput and latency percentiles, used to size provider instances, and comparing
+//! streaming object data over wRPC byte by byte with streaming it in chunks
+//!
+//! Only built with the `bench` feature. The provider binary runs a benchmark instead of the
+//! provider when invoked with `--bench <file>`, see [`run_file`].
+
+use core::pin::{pin, Pin};
+use core::sync::atomic::{AtomicU64, Ordering};
+use core::task::{Context, Poll};
+use core::time::Duration;
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+
+use anyhow::{bail, ensure, Context as _};
+use bytes::{Bytes, BytesMut};
+use futures::{Stream, StreamExt as _, TryStreamExt as _};
+use serde::{Deserialize, Serialize};
+use tokio::io::AsyncWrite;
+use tokio::time::Instant;
+use tracing::warn;
+use uuid::Uuid;
+use wrpc_transport::Encode;
+
+use crate::{StorageClient, StorageConfig, DEFAULT_CHUNK_SIZE};
+
+/// Default number of operations of a benchmark
+const DEFAULT_OPERATIONS: u64 = 1000;
+/// Default number of operations in flight at the same time
+const DEFAULT_CONCURRENCY: usize = 16;
+/// Default size of objects written and read
+const DEFAULT_OBJECT_SIZE: u64 = 64 * 1024;
+/// Default number of distinct objects reads are spread across
+const DEFAULT_OBJECTS: u64 = 100;
+/// Default size of the object streamed by wire benchmarks
+const DEFAULT_WIRE_OBJECT_SIZE: u64 = 1024 * 1024;
+
+/// Benchmark file read by [`run_file`]
+#[derive(Clone, Debug, Deserialize)]
+pub struct BenchFile {
+    /// Configuration of the client, like the configuration of a link
+    #[serde(default)]
+    pub storage: StorageConfig,
+    /// Workload to run against S3, if any
+    #[serde(default)]
+    pub workload: Option<Workload>,
+    /// Wire benchmark to run, if any, which does not use S3
+    #[serde(default)]
+    pub wire: Option<WireWorkload>,
+}
+
+/// Workload of a benchmark
+#[derive(Clone, Debug, Deserialize)]
+pub struct Workload {
+    /// Bucket (or alias) objects are written to and read from, which should not be used otherwise
+    pub bucket: String,
+    /// Total number of operations, 1000 by default
+    #[serde(default)]
+    pub operations: Option<u64>,
+    /// Number of operations in flight at the same time, 16 by default
+    #[serde(default)]
+    pub concurrency: Option<usize>,
+    /// Size of objects in bytes, 64 KiB by default
+    #[serde(default)]
+    pub object_size: Option<u64>,
+    /// Number of distinct objects, which are written before reads start, 100 by default
+    #[serde(default)]
+    pub objects: Option<u64>,
+    /// Percentage of operations reading objects, the others write them, 50 by default
+    #[serde(default)]
+    pub read_percent: Option<u8>,
+}
+
+/// Comparison of streaming an object to a component over wRPC byte by byte, as a `stream<u8>` of
+/// single byte values, and in chunks of raw bytes, as the provider does
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct WireWorkload {
+    /// Size of the object in bytes, 1 MiB by default
+    #[serde(default)]
+    pub object_size: Option<u64>,
+    /// Sizes of the chunks to compare, the default chunk size of links (64 KiB) by default
+    #[serde(default)]
+    pub chunk_sizes: Option<Vec<usize>>,
+}
+
+/// Latencies of an operation
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize)]
+pub struct Latencies {
+    /// Number of successful operations
+    pub count: u64,
+    pub p50_ms: f64,
+    pub p90_ms: f64,
+    pub p99_ms: f64,
+    pub max_ms: f64,
+}
+
+/// Results of a benchmark
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct BenchReport {
+    /// Number of operations performed, including failed ones
+    pub operations: u64,
+    /// Number of failed operations
+    pub failures: u64,
+    /// Time all operations took, excluding writing objects before reads started
+    pub duration_ms: f64,
+    /// Successful operations per second
+    pub ops_per_sec: f64,
+    /// Bytes read and written per second
+    pub bytes_per_sec: f64,
+    /// Latencies of reads
+    pub reads: Latencies,
+    /// Latencies of writes
+    pub writes: Latencies,
+    /// First error operations failed with, if any
+    pub first_error: Option<String>,
+}
+
+/// Transmission of an object by a wire benchmark
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct WireReport {
+    /// Size of the chunks the object was streamed in, `None` if it was streamed byte by byte
+    pub chunk_size: Option<usize>,
+    /// Number of writes to the transport, each of which is a NATS message with the NATS transport
+    pub writes: u64,
+    /// Number of bytes written to the transport, including the encoding of the stream
+    pub bytes: u64,
+    /// Time encoding and writing the object took
+    pub duration_ms: f64,
+    /// Bytes of the object streamed per second
+    pub bytes_per_sec: f64,
+}
+
+/// Reports of the benchmarks of a benchmark file
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct BenchFileReport {
+    /// Results of the workload run against S3, if configured
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub workload: Option<BenchReport>,
+    /// Results of streaming the object byte by byte followed by those of streaming it in each
+    /// chunk size, if configured
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub wire: Option<Vec<WireReport>>,
+}
+
+/// Run the benchmarks configured in the JSON file at `path` and print their report as JSON to
+/// stdout
+pub async fn run_file(path: impl AsRef<Path>) -> anyhow::Result<()> {
+    let path = path.as_ref();
+    let file = std::fs::read(path)
+        .with_context(|| format!("failed to read benchmark file `{}`", path.display()))?;
+    let BenchFile {
+        storage,
+        workload,
+        wire,
+    } = serde_json::from_slice(&file).context("failed to parse benchmark file")?;
+    if workload.is_none() && wire.is_none() {
+        bail!("benchmark file configures neither `workload` nor `wire`")
+    }
+    let mut report = BenchFileReport::default();
+    if let Some(workload) = workload {
+        let client = StorageClient::new(storage, &HashMap::default())
+            .await
+            .context("failed to create storage client")?;
+        report.workload = Some(run(&client, &workload).await?);
+    }
+    if let Some(wire) = wire {
+        report.wire = Some(run_wire(&wire).await?);
+    }
+    println!(
+        "{}",
+        serde_json::to_string_pretty(&report).context("failed to encode report")?
+    );
+    Ok(())
+}
+
+/// Run `workload` using `client`, deleting the objects written afterwards
+pub async fn run(client: &StorageClient, workload: &Workload) -> anyhow::Result<BenchReport> {
+    let operations = workload.operations.unwrap_or(DEFAULT_OPERATIONS);
+    let concurrency = workload.concurrency.unwrap_or(DEFAULT_CONCURRENCY).max(1);
+    let object_size = workload.object_size.unwrap_or(DEFAULT_OBJECT_SIZE);
+    let objects = workload.objects.unwrap_or(DEFAULT_OBJECTS).max(1);
+    let read_percent = workload.read_percent.unwrap_or(50);
+    ensure!(read_percent <= 100, "`read_percent` must not exceed 100");
+
+    let bucket = client.unalias(&workload.bucket);
+    let prefix = format!("bench/{}/", Uuid::new_v4());
+    let keys: Vec<_> = (0..objects).map(|i| format!("{prefix}{i}")).collect();
+    let data = Bytes::from(vec![
+        0xa5;
+        object_size.try_into().context("object too large")?
+    ]);
+
+    if read_percent > 0 {
+        futures::stream::iter(&keys)
+            .map(|key| client.write_object(bucket, key, data.clone()))
+            .buffer_unordered(concurrency)
+            .try_collect::<Vec<_>>()
+            .await
+            .context("failed to write objects before reads")?;
+    }
+
+    let start = Instant::now();
+    let results: Vec<_> = futures::stream::iter(0..operations)
+        .map(|i| {
+            let key = &keys[(i % objects) as usize];
+            let read = i % 100 < u64::from(read_percent);
+            let data = data.clone();
+            async move {
+                let start = Instant::now();
+                let res = if read {
+                    read_all(client, bucket, key).await
+                } else {
+                    client.write_object(bucket, key, data).await
+                };
+                (read, start.elapsed(), res)
+            }
+        })
+        .buffer_unordered(concurrency)
+        .collect()
+        .await;
+    let duration = start.elapsed();
+
+    if let Err(err) = client.delete_objects(bucket, keys).await {
+        warn!(?err, "failed to delete objects written by benchmark");
+    }
+
+    let mut reads = Vec::new();
+    let mut writes = Vec::new();
+    let mut failures = 0;
+    let mut first_error = None;
+    for (read, latency, res) in results {
+        match res {
+            Ok(()) if read => reads.push(latency),
+            Ok(()) => writes.push(latency),
+            Err(err) => {
+                failures += 1;
+                first_error.get_or_insert_with(|| format!("{err:#}"));
+            }
+        }
+    }
+    let succeeded = (reads.len() + writes.len()) as f64;
+    let secs = duration.as_secs_f64().max(f64::EPSILON);
+    Ok(BenchReport {
+        operations,
+        failures,
+        duration_ms: duration.as_secs_f64() * 1000.0,
+        ops_per_sec: succeeded / secs,
+        bytes_per_sec: succeeded * object_size as f64 / secs,
+        reads: latencies(reads),
+        writes: latencies(writes),
+        first_error,
+    })
+}
+
+/// Stream an object over an in-memory transport byte by byte and in each chunk size of
+/// `workload`, measuring the writes to the transport
+pub async fn run_wire(workload: &WireWorkload) -> anyhow::Result<Vec<WireReport>> {
+    let object_size = workload
+        .object_size
+        .unwrap_or(DEFAULT_WIRE_OBJECT_SIZE)
+        .try_into()
+        .context("object too large")?;
+    let chunk_sizes = workload
+        .chunk_sizes
+        .clone()
+        .unwrap_or_else(|| vec![DEFAULT_CHUNK_SIZE]);
+    ensure!(
+        chunk_sizes.iter().all(|&size| size > 0),
+        "chunk sizes must not be zero"
+    );
+    let data = Bytes::from(vec![0xa5; object_size]);
+
+    let bytes = data.clone();
+    let per_byte: Pin<Box<dyn Stream<Item = Vec<u8>> + Send>> =
+        Box::pin(futures::stream::iter(bytes).map(|b| vec![b]));
+    let mut reports = vec![stream_wire(per_byte, None, object_size).await?];
+    for size in chunk_sizes {
+        let chunks: Vec<_> = (0..object_size)
+            .step_by(size)
+            .map(|start| data.slice(start..object_size.min(start + size)))
+            .collect();
+        let chunked: Pin<Box<dyn Stream<Item = Bytes> + Send>> =
+            Box::pin(futures::stream::iter(chunks));
+        reports.push(stream_wire(chunked, Some(size), object_size).await?);
+    }
+    Ok(reports)
+}
+
+/// Encode `stream` as wRPC does for the results of invocations and write it to a [`WireSink`]
+async fn stream_wire<T>(
+    stream: T,
+    chunk_size: Option<usize>,
+    object_size: usize,
+) -> anyhow::Result<WireReport>
+where
+    T: Encode<WireSink>,
+    T::Encoder: tokio_util::codec::Encoder<T, Error = std::io::Error>,
+{
+    let sink = WireSink::default();
+    let start = Instant::now();
+    let mut enc = T::Encoder::default();
+    let mut buf = BytesMut::default();
+    let deferred = stream
+        .encode(&mut enc, &mut buf)
+        .context("failed to encode stream")?;
+    sink.record(buf.len());
+    if let Some(deferred) = deferred {
+        deferred(Arc::new(sink.clone()), Vec::default())
+            .await
+            .context("failed to write stream")?;
+    }
+    let duration = start.elapsed();
+    Ok(WireReport {
+        chunk_size,
+        writes: sink.0.writes.load(Ordering::Relaxed),
+        bytes: sink.0.bytes.load(Ordering::Relaxed),
+        duration_ms: duration.as_secs_f64() * 1000.0,
+        bytes_per_sec: object_size as f64 / duration.as_secs_f64().max(f64::EPSILON),
+    })
+}
+
+/// Writes and bytes written to a [`WireSink`]
+#[derive(Debug, Default)]
+struct WireStats {
+    writes: AtomicU64,
+    bytes: AtomicU64,
+}
+
+/// In-memory wRPC transport, which discards data written to it, only counting writes and bytes
+#[derive(Clone, Debug, Default)]
+struct WireSink(Arc<WireStats>);
+
+impl WireSink {
+    fn record(&self, n: usize) {
+        self.0.writes.fetch_add(1, Ordering::Relaxed);
+        self.0.bytes.fetch_add(n as u64, Ordering::Relaxed);
+    }
+}
+
+impl wrpc_transport::Index<Self> for WireSink {
+    fn index(&self, _path: &[usize]) -> anyhow::Result<Self> {
+        Ok(self.clone())
+    }
+}
+
+impl AsyncWrite for WireSink {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        self.record(buf.len());
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+/// Read the object `key` of `bucket` to the end
+async fn read_all(client: &StorageClient, bucket: &str, key: &str) -> anyhow::Result<()> {
+    let mut data = pin!(client.read_object(bucket, key, 0, u64::MAX).await?);
+    while let Some(chunk) = data.next().await {
+        chunk.context("failed to read object")?;
+    }
+    Ok(())
+}
+
+/// Percentiles of `samples`
+fn latencies(mut samples: Vec<Duration>) -> Latencies {
+    if samples.is_empty() {
+        return Latencies::default();
+    }
+    samples.sort_unstable();
+    let percentile = |p: f64| {
+        let rank = ((p / 100.0) * samples.len() as f64).ceil() as usize;
+        samples[rank.clamp(1, samples.len()) - 1].as_micros() as f64 / 1000.0
+    };
+    Latencies {
+        count: samples.len() as u64,
+        p50_ms: percentile(50.0),
+        p90_ms: percentile(90.0),
+        p99_ms: percentile(99.0),
+        max_ms: percentile(100.0),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn percentiles() {
+        let samples = (1..=100).map(Duration::from_millis).collect();
+        let Latencies {
+            count,
+            p50_ms,
+            p90_ms,
+            p99_ms,
+            max_ms,
+        } = latencies(samples);
+        assert_eq!(count, 100);
+        assert_eq!((p50_ms, p90_ms, p99_ms, max_ms), (50.0, 90.0, 99.0, 100.0));
+
+        let single = latencies(vec![Duration::from_millis(7)]);
+        assert_eq!((single.p50_ms, single.max_ms), (7.0, 7.0));
+        assert_eq!(latencies(Vec::new()), Latencies::default());
+    }
+
+    #[tokio::test]
+    async fn wire() {
+        let reports = run_wire(&WireWorkload {
+            object_size: Some(64 * 1024),
+            chunk_sizes: Some(vec![4096, 64 * 1024]),
+        })
+        .await
+        .expect("failed to run wire benchmark");
+        let [per_byte, small, large] = &reports[..] else {
+            panic!("expected 3 reports, got {reports:?}");
+        };
+        assert_eq!(per_byte.chunk_size, None);
+        assert_eq!(small.chunk_size, Some(4096));
+        assert_eq!(large.chunk_size, Some(64 * 1024));
+        // chunks are streamed with a length prefix each, single bytes with one per byte
+        assert!(large.writes <= small.writes, "{reports:?}");
+        assert!(small.writes < per_byte.writes, "{reports:?}");
+        assert!(large.bytes < 64 * 1024 + 16, "{reports:?}");
+        assert!(per_byte.bytes >= 2 * 64 * 1024, "{reports:?}");
+    }
+}
@@ -0,0 +1,73 @@
+//! Package provider binaries built for several targets into a signed provider archive
+//!
+//! ```sh
+//! blobstore-s3-par blobstore-s3.par.gz \
+//!     x86_64-linux=target/x86_64-unknown-linux-musl/release/blobstore-s3-provider \
+//!     aarch64-linux=target/aarch64-unknown-linux-musl/release/blobstore-s3-provider
+//! ```
+//!
+//! The archive is signed with the account key in `WASH_ISSUER_KEY` and the service key in
+//! `WASH_SUBJECT_KEY`, like `wash par create`, each either a seed or a path to a file with one.
+
+use std::path::Path;
+
+use anyhow::{bail, Context as _};
+use nkeys::KeyPair;
+use provider_archive::ProviderArchive;
+
+/// Name claim of the provider, as in the `wasmcloud.toml` of the provider binary
+const NAME: &str = "Blobstore S3";
+/// Vendor claim of the provider, as in the `wasmcloud.toml` of the provider binary
+const VENDOR: &str = "wasmCloud";
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    let mut args = std::env::args().skip(1);
+    let Some(destination) = args.next() else {
+        bail!("usage: blobstore-s3-par <DESTINATION> <ARCH-OS>=<BINARY>...")
+    };
+    let issuer = key("WASH_ISSUER_KEY")?;
+    let subject = key("WASH_SUBJECT_KEY")?;
+    let mut par = ProviderArchive::new(
+        NAME,
+        VENDOR,
+        None,
+        Some(env!("CARGO_PKG_VERSION").to_string()),
+    );
+    let mut targets = 0;
+    for arg in args {
+        let Some((target, binary)) = arg.split_once('=') else {
+            bail!("binary `{arg}` must be given as `<ARCH-OS>=<BINARY>`, e.g. `x86_64-linux=...`")
+        };
+        let binary = std::fs::read(binary)
+            .with_context(|| format!("failed to read provider binary `{binary}`"))?;
+        par.add_library(target, &binary)
+            .map_err(|err| anyhow::anyhow!(err))
+            .with_context(|| format!("failed to add provider binary for `{target}`"))?;
+        targets += 1;
+    }
+    if targets == 0 {
+        bail!("at least one provider binary must be given as `<ARCH-OS>=<BINARY>`")
+    }
+    par.write(&destination, &issuer, &subject, true)
+        .await
+        .map_err(|err| anyhow::anyhow!(err))
+        .with_context(|| format!("failed to write provider archive `{destination}`"))?;
+    eprintln!(
+        "wrote provider archive `{destination}` for {targets} target(s), provider ID {}",
+        subject.public_key()
+    );
+    Ok(())
+}
+
+/// Key pair from the seed in the environment variable `var`, or in the file it refers to
+fn key(var: &str) -> anyhow::Result<KeyPair> {
+    let key = std::env::var(var)
+        .with_context(|| format!("`{var}` must be set to a seed or a path to one"))?;
+    let seed = if Path::new(&key).is_file() {
+        std::fs::read_to_string(&key).with_context(|| format!("failed to read `{key}`"))?
+    } else {
+        key
+    };
+    KeyPair::from_seed(seed.trim()).with_context(|| format!("`{var}` is not a valid seed"))
+}
@@ -0,0 +1,181 @@
+//! Invalidation of the paths of a CloudFront distribution serving objects of a link
+//!
+//! Paths of changed objects are collected for a short interval and invalidated in batches, since
+//! CloudFront limits the number of invalidation requests in progress.
+
+use core::fmt::Write as _;
+use core::future::Future;
+use core::time::Duration;
+
+use std::collections::BTreeSet;
+
+use anyhow::Context as _;
+use aws_sdk_cloudfront::types::{InvalidationBatch, Paths};
+use tokio::sync::mpsc;
+use tracing::{debug, error};
+use uuid::Uuid;
+
+use crate::config::{CdnInvalidationConfig, CdnPathMapping};
+
+/// Default time changes are collected for before invalidating their paths
+const DEFAULT_BATCH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Maximum number of paths invalidated by a single request
+const MAX_INVALIDATION_PATHS: usize = 1000;
+
+/// Handle of the background task invalidating paths of a distribution
+pub(crate) struct CdnInvalidator {
+    mappings: Vec<CdnPathMapping>,
+    paths: mpsc::UnboundedSender<String>,
+}
+
+impl CdnInvalidator {
+    /// Create an invalidator along with its background task, which must be spawned for paths
+    /// to be invalidated
+    pub(crate) fn new(
+        cloudfront: aws_sdk_cloudfront::Client,
+        CdnInvalidationConfig {
+            distribution_id,
+            paths: mappings,
+            batch_interval_ms,
+        }: CdnInvalidationConfig,
+    ) -> (Self, impl Future<Output = ()> + Send + 'static) {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let interval = batch_interval_ms.map_or(DEFAULT_BATCH_INTERVAL, Duration::from_millis);
+        let task = invalidate_batches(cloudfront, distribution_id, rx, interval);
+        (
+            Self {
+                mappings,
+                paths: tx,
+            },
+            task,
+        )
+    }
+
+    /// Containers and key prefixes served by the distribution
+    pub(crate) fn mappings(&self) -> &[CdnPathMapping] {
+        &self.mappings
+    }
+
+    /// Queue `path` for invalidation
+    pub(crate) fn invalidate(&self, path: String) {
+        if self.paths.send(path).is_err() {
+            debug!("CDN invalidation task stopped, dropping path");
+        }
+    }
+}
+
+/// Path of the distribution serving the object `key`, if it is served under `mapping`
+pub(crate) fn cdn_path(
+    CdnPathMapping { prefix, path, .. }: &CdnPathMapping,
+    key: &str,
+) -> Option<String> {
+    let rest = key.strip_prefix(prefix.as_str())?;
+    let mut out = String::with_capacity(path.len() + rest.len());
+    if !path.starts_with('/') {
+        out.push('/');
+    }
+    out.push_str(path);
+    for b in rest.bytes() {
+        if b.is_ascii_alphanumeric() || matches!(b, b'-' | b'.' | b'_' | b'~' | b'/') {
+            out.push(char::from(b));
+        } else {
+            let _ = write!(out, "%{b:02X}");
+        }
+    }
+    Some(out)
+}
+
+/// Collect paths for `interval` after the first one is received and invalidate them together
+async fn invalidate_batches(
+    cloudfront: aws_sdk_cloudfront::Client,
+    distribution_id: String,
+    mut rx: mpsc::UnboundedReceiver<String>,
+    interval: Duration,
+) {
+    while let Some(path) = rx.recv().await {
+        let mut paths = BTreeSet::from([path]);
+        let deadline = tokio::time::sleep(interval);
+        tokio::pin!(deadline);
+        while paths.len() < MAX_INVALIDATION_PATHS {
+            tokio::select! {
+                () = &mut deadline => break,
+                path = rx.recv() => match path {
+                    Some(path) => {
+                        paths.insert(path);
+                    }
+                    None => break,
+                },
+            }
+        }
+        let n = paths.len();
+        if let Err(err) = create_invalidation(&cloudfront, &distribution_id, paths).await {
+            error!(
+                ?err,
+                distribution_id,
+                paths = n,
+                "failed to invalidate CDN paths"
+            );
+        }
+    }
+}
+
+async fn create_invalidation(
+    cloudfront: &aws_sdk_cloudfront::Client,
+    distribution_id: &str,
+    paths: BTreeSet<String>,
+) -> anyhow::Result<()> {
+    let quantity = paths.len().try_into().context("too many paths")?;
+    let paths = Paths::builder()
+        .quantity(quantity)
+        .set_items(Some(paths.into_iter().collect()))
+        .build()
+        .context("failed to build paths")?;
+    let batch = InvalidationBatch::builder()
+        .caller_reference(Uuid::new_v4().to_string())
+        .paths(paths)
+        .build()
+        .context("failed to build invalidation batch")?;
+    cloudfront
+        .create_invalidation()
+        .distribution_id(distribution_id)
+        .invalidation_batch(batch)
+        .send()
+        .await
+        .context("failed to create invalidation")?;
+    debug!(distribution_id, paths = quantity, "invalidated CDN paths");
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn cdn_paths() {
+        let mapping = CdnPathMapping {
+            container: "site".into(),
+            prefix: "public/".into(),
+            path: "/assets/".into(),
+        };
+        assert_eq!(
+            cdn_path(&mapping, "public/img/logo.png").as_deref(),
+            Some("/assets/img/logo.png")
+        );
+        assert_eq!(
+            cdn_path(&mapping, "public/a b*.txt").as_deref(),
+            Some("/assets/a%20b%2A.txt")
+        );
+        assert_eq!(cdn_path(&mapping, "private/key"), None);
+
+        let root = CdnPathMapping {
+            container: "site".into(),
+            prefix: String::new(),
+            path: String::new(),
+        };
+        assert_eq!(
+            cdn_path(&root, "index.html").as_deref(),
+            Some("/index.html")
+        );
+    }
+}
@@ -0,0 +1,6926 @@
+//! S3 storage client, independent of the wRPC interfaces served by the provider
+
+use core::cmp::Reverse;
+use core::future::Future;
+use core::pin::Pin;
+use core::str::FromStr;
+use core::time::Duration;
+
+use std::borrow::Cow;
+use std::collections::hash_map::RandomState;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::hash::{BuildHasher as _, Hasher as _};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Weak};
+use std::time::SystemTime;
+
+use anyhow::{anyhow, bail, ensure, Context as _};
+use async_compression::tokio::bufread::GzipDecoder;
+use aws_config::default_provider::credentials::DefaultCredentialsChain;
+use aws_config::default_provider::region::DefaultRegionChain;
+use aws_config::imds;
+use aws_config::imds::credentials::ImdsCredentialsProvider;
+use aws_config::meta::region::ProvideRegion as _;
+use aws_config::profile::{ProfileFileCredentialsProvider, ProfileFileRegionProvider};
+use aws_config::retry::RetryConfig;
+use aws_config::sts::AssumeRoleProvider;
+use aws_runtime::env_config::file::{EnvConfigFileKind, EnvConfigFiles};
+use aws_sdk_s3::config::http::HttpResponse;
+use aws_sdk_s3::config::{
+    AppName, ProvideCredentials as _, Region, SharedCredentialsProvider, SharedHttpClient,
+};
+use aws_sdk_s3::error::{ProvideErrorMetadata, SdkError};
+use aws_sdk_s3::operation::create_bucket::{CreateBucketError, CreateBucketOutput};
+use aws_sdk_s3::operation::get_object::GetObjectOutput;
+use aws_sdk_s3::operation::head_bucket::HeadBucketError;
+use aws_sdk_s3::operation::head_object::{HeadObjectError, HeadObjectOutput};
+use aws_sdk_s3::operation::list_object_versions::ListObjectVersionsOutput;
+use aws_sdk_s3::operation::list_objects_v2::ListObjectsV2Output;
+use aws_sdk_s3::operation::{RequestId, RequestIdExt};
+#[cfg(feature = "presign")]
+use aws_sdk_s3::presigning::PresigningConfig;
+use aws_sdk_s3::primitives::{DateTime, DateTimeFormat};
+use aws_sdk_s3::types::{
+    BucketLifecycleConfiguration, BucketLocationConstraint, ChecksumAlgorithm, ChecksumMode,
+    CompletedMultipartUpload, CompletedPart, CreateBucketConfiguration, Delete, DeleteMarkerEntry,
+    ErrorDocument, ExpirationStatus, Grant, Grantee, IndexDocument, InventoryFormat,
+    LifecycleExpiration, LifecycleRule, LifecycleRuleFilter, MetadataDirective, Object,
+    ObjectIdentifier, ObjectLockLegalHold, ObjectLockLegalHoldStatus, Part, PolicyStatus,
+    PublicAccessBlockConfiguration, StorageClass, Tag, Tagging, TaggingDirective,
+    WebsiteConfiguration,
+};
+use aws_smithy_runtime::client::http::hyper_014::HyperClientBuilder;
+use bytes::{Bytes, BytesMut};
+use futures::future::Either;
+use futures::{Stream, StreamExt as _, TryStreamExt as _};
+use hyper::client::HttpConnector;
+use md5::Md5;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tokio::io::{AsyncBufRead, AsyncBufReadExt as _, AsyncReadExt as _, BufReader};
+use tokio::sync::{mpsc, Mutex};
+use tokio::time::{Instant, MissedTickBehavior};
+use tokio_stream::wrappers::ReceiverStream;
+use tokio_util::io::ReaderStream;
+use tokio_util::sync::CancellationToken;
+use tracing::instrument::WithSubscriber as _;
+use tracing::level_filters::LevelFilter;
+use tracing::{debug, error, instrument, warn, Dispatch};
+use uuid::Uuid;
+use wasmcloud_provider_sdk::core::tls;
+
+use crate::backend::{self, Backend};
+#[cfg(feature = "cdn-invalidation")]
+use crate::cdn::{cdn_path, CdnInvalidator};
+#[cfg(feature = "cdn-signing")]
+use crate::cloudfront::CdnSigner;
+use crate::config::{
+    BackendProfile, BatchOperationsConfig, ContainerPolicyConfig, ContentSniffingConfig,
+    ExpirationSweeperConfig, HookConfig, HttpClientConfig, HttpVersion, ImdsConfig,
+    InventoryConfig, JobsConfig, JournalConfig, ListConsistencyConfig, ListingStatsConfig,
+    PayloadSigning, PublicAccessBlockConfig, R2Config, SignatureVersion, StorageBackend,
+    StorageConfig, StsAssumeRoleConfig,
+};
+use crate::connections::{ConnectionCounters, ConnectionStats, CountConnections};
+use crate::consistency::RecentWrites;
+use crate::credentials::RefreshingCredentialsProvider;
+#[cfg(feature = "derivatives")]
+use crate::derivatives::Derivatives;
+use crate::error::{BackendError, ErrorKind, KeyError, RequestError};
+use crate::hooks::MAX_INSPECT_BYTES;
+use crate::interceptors::{
+    CustomHeaders, DebugLogger, RequestCounter, SigV2Signer, UnsignedPayload,
+};
+use crate::inventory::{self, InventoryManifest, DEFAULT_INVENTORY_MAX_AGE};
+use crate::jobs::{JobRecord, JOBS_PREFIX};
+use crate::journal::{self, JournalRecord, Mutation, JOURNAL_PREFIX};
+use crate::keys::{KeyPolicy, KeyTemplate};
+use crate::logging::{glob_matches, LinkLogging};
+use crate::partitions;
+use crate::quirks::{Quirks, DEFAULT_PART_SIZE, MAX_PART_SIZE, MIN_PART_SIZE};
+use crate::reports::Top;
+use crate::routing::{PrefixRouter, Router, Routers};
+use crate::scan::Scanner;
+use crate::shards::ShardRouter;
+use crate::throttle::TokenBucket;
+use crate::tiers::{tiered_key, SizeTiers, Tier};
+use crate::website;
+
+#[cfg(feature = "batch-operations")]
+mod batch;
+#[cfg(feature = "encryption")]
+mod encryption;
+#[cfg(feature = "storage-metrics")]
+mod metrics;
+
+/// Prefix of link configuration keys defining bucket aliases
+pub(crate) const ALIAS_PREFIX: &str = "alias_";
+const DEFAULT_STS_SESSION: &str = "blobstore_s3_provider";
+/// Region of the S3 clients of links with backends other than S3, unless configured
+const DEFAULT_REGION: &str = "us-east-1";
+
+/// Metadata of a container
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ContainerInfo {
+    /// Creation time in seconds since the Unix epoch, 0 if unknown
+    pub created_at: u64,
+}
+
+/// Statistics of the objects of a container, `None` if unknown
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ContainerStats {
+    pub object_count: Option<u64>,
+    /// Total size of all objects in bytes
+    pub total_bytes: Option<u64>,
+    /// Time the statistics were determined at in seconds since the Unix epoch
+    pub updated_at: Option<u64>,
+}
+
+/// Number and total size of the objects under a prefix
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PrefixStats {
+    pub prefix: String,
+    pub object_count: u64,
+    /// Total size of the objects in bytes
+    pub total_bytes: u64,
+}
+
+/// Kind of a difference between the objects under two prefixes
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ChangeKind {
+    /// The object only exists under the source prefix
+    Added,
+    /// The object only exists under the target prefix
+    Removed,
+    /// The object exists under both prefixes, with a different size or ETag
+    Changed,
+}
+
+/// A difference between the objects under two prefixes
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Change {
+    /// Key of the object, relative to the prefixes
+    pub key: String,
+    pub kind: ChangeKind,
+}
+
+/// Key, relative to the prefix listed, size and ETag of a listed object, which are compared by
+/// diffs
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct DiffEntry {
+    key: String,
+    size: i64,
+    etag: Option<String>,
+}
+
+/// Key, size and modification time of an object, as listed
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ObjectSummary {
+    pub key: String,
+    /// Size of the object in bytes
+    pub size: u64,
+    /// Time the object was last modified at in seconds since the Unix epoch
+    pub last_modified: u64,
+}
+
+impl ObjectSummary {
+    /// Summary of a listed object, `None` if the listing did not include its key
+    fn new(
+        Object {
+            key,
+            size,
+            last_modified,
+            ..
+        }: Object,
+    ) -> Option<Self> {
+        Some(Self {
+            key: key?,
+            size: size.unwrap_or_default().try_into().unwrap_or(0),
+            last_modified: last_modified
+                .map(|t| t.secs())
+                .unwrap_or_default()
+                .try_into()
+                .unwrap_or(0),
+        })
+    }
+}
+
+/// Statistics of a bucket computed by listing its objects
+#[cfg(feature = "caching")]
+#[derive(Clone, Copy, Debug, Default)]
+struct ListedStats {
+    /// Latest statistics and the time they were computed at
+    stats: Option<(ContainerStats, Instant)>,
+    /// Whether statistics are being computed
+    computing: bool,
+}
+
+/// Metadata of an object
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ObjectInfo {
+    /// Creation time in seconds since the Unix epoch, 0 if unknown
+    pub created_at: u64,
+    /// Size of the object in bytes
+    pub size: u64,
+}
+
+/// Conditions of a read, which is skipped if the object was not modified
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ReadConditions {
+    /// Only read the object if its ETag differs
+    pub if_none_match: Option<String>,
+    /// Only read the object if it was modified after this time
+    pub if_modified_since: Option<SystemTime>,
+}
+
+/// Validators of an object, which conditional reads compare against
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ObjectValidators {
+    /// ETag of the object
+    pub etag: Option<String>,
+    /// Time the object was last modified
+    pub last_modified: Option<SystemTime>,
+}
+
+/// Hashes of an object recorded by the store, which are returned without reading it
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct StoredHashes {
+    /// ETag of the object, which is the MD5 of its content for objects uploaded in a single part
+    /// without SSE-KMS or SSE-C
+    pub etag: Option<String>,
+    /// Base64-encoded SHA-256 checksum of the object, if it was uploaded with one
+    pub checksum_sha256: Option<String>,
+}
+
+/// Algorithm of hashes of the content of objects computed by the provider
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HashAlgorithm {
+    Sha256,
+    Md5,
+}
+
+/// Whether an object exists, distinguishing objects hidden by a delete marker in versioned buckets
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ObjectPresence {
+    /// The latest version of the object exists
+    Present,
+    /// The object does not exist
+    Absent,
+    /// The latest version of the object is a delete marker
+    Deleted,
+}
+
+/// An object, which could not be deleted
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DeleteFailure {
+    /// Key of the object
+    pub key: String,
+    /// Error code reported by S3, like `AccessDenied`
+    pub code: Option<String>,
+    /// Error message reported by S3
+    pub message: Option<String>,
+}
+
+/// An object removed by a verified delete, as it was found before being deleted
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DeletedObject {
+    /// Key of the object
+    pub key: String,
+    /// ETag of the object
+    pub etag: Option<String>,
+    /// Version of the object, which was deleted, if the bucket is versioned
+    pub version_id: Option<String>,
+    /// Size of the object in bytes
+    pub size: u64,
+}
+
+/// Outcome of a verified delete
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct VerifiedDelete {
+    /// Objects, which were deleted
+    pub deleted: Vec<DeletedObject>,
+    /// Keys of objects, which did not exist
+    pub missing: Vec<String>,
+    /// Objects, which could not be looked up or deleted
+    pub failures: Vec<DeleteFailure>,
+}
+
+/// An object in the trash of a bucket of a link soft-deleting objects
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TrashedObject {
+    /// Key of the object before it was deleted
+    pub key: String,
+    /// UTC time the object was deleted at, like `2024-03-05T07:00:00.042Z`
+    pub deleted_at: String,
+    /// Key of the object in the trash
+    pub trash_key: String,
+}
+
+/// Default time between sweeps of expired objects
+const DEFAULT_SWEEP_INTERVAL_SECS: u64 = 60 * 60;
+/// Separator of the keys of objects and the numbers of their versions, for links with write-once
+/// keys
+const KEY_VERSION_SEPARATOR: char = '@';
+/// Number of days soft-deleted objects are kept in the trash, unless configured otherwise
+const DEFAULT_TRASH_RETENTION_DAYS: u32 = 30;
+/// Time between purges of the trash of soft-deleted objects
+const TRASH_PURGE_INTERVAL: Duration = Duration::from_secs(60 * 60);
+/// Time buckets found not to be publicly accessible are written to without checking again
+const PUBLIC_ACCESS_CHECK_TTL: Duration = Duration::from_secs(5 * 60);
+/// URIs of the ACL grantee groups of anyone and of any AWS account
+const PUBLIC_ACL_GROUPS: [&str; 2] = [
+    "http://acs.amazonaws.com/groups/global/AllUsers",
+    "http://acs.amazonaws.com/groups/global/AuthenticatedUsers",
+];
+/// Maximum number of objects listed by a single `ListObjectsV2` request
+const MAX_LIST_OBJECTS: u64 = 1000;
+/// Maximum number of objects deleted by a single `DeleteObjects` request
+const MAX_DELETE_OBJECTS: usize = 1000;
+
+/// Number of concurrent single deletes emulating a multi-object delete, for stores not
+/// supporting them
+const EMULATED_DELETE_CONCURRENCY: usize = 16;
+/// Maximum number of concurrent `HeadObject` requests of a single verified delete
+const MAX_CONCURRENT_HEADS: usize = 16;
+/// Maximum number of concurrent `DeleteObjects` requests of a single bulk delete
+const MAX_CONCURRENT_DELETES: usize = 4;
+/// Number of batches deleted concurrently when clearing a bucket, unless configured otherwise
+const DEFAULT_CLEAR_WORKERS: usize = 4;
+/// Maximum number of concurrent `CopyObject` requests of a single snapshot
+const MAX_CONCURRENT_COPIES: usize = 16;
+/// Maximum number of byte ranges of a single scatter read
+const MAX_SCATTER_RANGES: usize = 1000;
+/// Maximum number of concurrent ranged `GetObject` requests of a single scatter read
+const MAX_CONCURRENT_RANGE_READS: usize = 8;
+/// Maximum number of objects of a single report of the largest or stale objects
+const MAX_REPORTED_OBJECTS: usize = 1000;
+/// Maximum number of concurrent `ListObjectsV2` listings of the prefixes of a single time window
+/// or prefix statistics
+const MAX_CONCURRENT_LISTINGS: usize = 8;
+/// Time download URLs are valid for, unless requested otherwise
+#[cfg(any(feature = "presign", feature = "cdn-signing"))]
+const DEFAULT_DOWNLOAD_URL_EXPIRY: Duration = Duration::from_secs(15 * 60);
+/// Maximum time download URLs are valid for, which SigV4 limits to 7 days
+#[cfg(feature = "presign")]
+const MAX_DOWNLOAD_URL_EXPIRY: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+/// Maximum number of failed objects listed in errors of bulk operations
+const MAX_REPORTED_FAILURES: usize = 100;
+/// Time statistics computed by listing objects are cached for, unless configured otherwise
+const DEFAULT_LISTING_STATS_TTL: Duration = Duration::from_secs(60 * 60);
+/// Default number of retries of objects failing with transient errors in batch operations
+const DEFAULT_BATCH_RETRY_ATTEMPTS: u32 = 3;
+/// Backoff period before the first retry of a batch operation
+const BATCH_RETRY_BASE_DELAY: Duration = Duration::from_millis(100);
+/// Maximum backoff period between retries of a batch operation
+const BATCH_RETRY_MAX_DELAY: Duration = Duration::from_secs(5);
+
+/// Tag marking objects for expiration by bucket lifecycle rules, with values like `7d`
+pub const TTL_TAG: &str = "ttl";
+/// Tag identifying the link, which created a bucket, as `<lattice>/<component>/<link>`, by which
+/// buckets are counted against the `max_containers` of the link
+pub const LINK_TAG: &str = "wasmcloud:link";
+/// Prefix of the temporary keys of objects being written atomically
+pub const TEMP_PREFIX: &str = ".wasmcloud-tmp/";
+/// Prefix of the keys of lock objects
+pub const LOCKS_PREFIX: &str = ".wasmcloud-locks/";
+/// Prefix of the keys of manifests, followed by the name of their set
+pub const MANIFESTS_PREFIX: &str = ".wasmcloud-manifests/";
+/// Key of the pointer to the current manifest of a set, relative to the prefix of the set
+const CURRENT_MANIFEST: &str = "current";
+/// Prefix of the keys of manifests of resumable uploads, which record their multipart upload IDs
+pub const UPLOADS_PREFIX: &str = ".wasmcloud-uploads/";
+/// Default prefix of the keys of snapshots, followed by the name of the snapshotted bucket
+pub const SNAPSHOTS_PREFIX: &str = ".wasmcloud-snapshots/";
+/// Prefix objects deleted by links soft-deleting them are moved to, followed by the time of their
+/// deletion
+pub const TRASH_PREFIX: &str = ".trash/";
+/// Key of the manifest of a snapshot, relative to its prefix
+const SNAPSHOT_MANIFEST: &str = "manifest.json";
+/// Prefix of the copies of objects in a snapshot, relative to its prefix
+const SNAPSHOT_OBJECTS: &str = "objects/";
+/// Default maximum size of the chunks object data is streamed to components in, each of which is
+/// sent as a single frame
+pub const DEFAULT_CHUNK_SIZE: usize = 64 * 1024;
+
+/// Contents of a lock object
+#[derive(Debug, Deserialize, Serialize)]
+struct Lock {
+    /// Expiry in seconds since the Unix epoch
+    expires_at: u64,
+}
+
+/// Contents of a manifest object
+#[derive(Debug, Deserialize, Serialize)]
+struct ManifestObject {
+    /// Keys of the objects listed by the manifest
+    members: Vec<String>,
+    /// Keys the members are stored under, resolved by the key policy of the link when the
+    /// manifest was created, so that dated key templates do not resolve them differently later
+    #[serde(default)]
+    object_keys: Vec<String>,
+    /// Creation time in seconds since the Unix epoch
+    created_at: u64,
+}
+
+/// Contents of the pointer to the current manifest of a set
+#[derive(Debug, Deserialize, Serialize)]
+struct ManifestPointer {
+    /// ID of the current manifest
+    id: String,
+}
+
+/// A manifest listing the keys of objects published together
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Manifest {
+    /// ID of the manifest
+    pub id: String,
+    /// Keys of the objects listed by the manifest
+    pub members: Vec<String>,
+    /// Time the manifest was created
+    pub created_at: SystemTime,
+}
+
+/// A lease of a lock, identified by the ETag of the lock object
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LockLease {
+    /// ETag of the lock object, which must be presented to renew or release the lock
+    pub etag: String,
+    /// Time the lease expires, unless renewed
+    pub expires_at: SystemTime,
+}
+
+/// Manifest of a resumable upload, stored under [`UPLOADS_PREFIX`] until the upload completes
+#[derive(Debug, Deserialize, Serialize)]
+struct UploadManifest {
+    key: String,
+    upload_id: String,
+    /// Size of all but the last part, which must not change when resuming, manifests without
+    /// it were written with parts of [`DEFAULT_PART_SIZE`]
+    #[serde(default)]
+    part_size: Option<usize>,
+}
+
+/// Options of a snapshot of a container
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SnapshotOptions {
+    /// Bucket (or alias) snapshots are stored in, the snapshotted bucket if `None`
+    pub destination: Option<String>,
+    /// Prefix of the keys of snapshots, [`SNAPSHOTS_PREFIX`] if `None`
+    pub prefix: Option<String>,
+    /// Only copy objects, which changed since the latest snapshot according to its manifest
+    pub incremental: bool,
+}
+
+/// A snapshot of a container
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SnapshotInfo {
+    /// ID of the snapshot, the UTC time it was taken at, like `2024-03-05T07:00:00.000Z`
+    pub id: String,
+    /// Bucket the snapshot is stored in
+    pub container: String,
+    /// Prefix of the snapshot, under which its manifest and copied objects are stored
+    pub prefix: String,
+    /// Number of objects in the snapshot
+    pub objects: u64,
+    /// Number of objects copied, which is less than `objects` for incremental snapshots
+    pub objects_copied: u64,
+    /// Number of bytes copied
+    pub bytes_copied: u64,
+}
+
+/// Static website hosting of a bucket
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct WebsiteOptions {
+    /// Suffix of requests for directories, which serve the object with it appended, e.g.
+    /// `index.html`
+    pub index_document: String,
+    /// Key of the object served for requests failing with a 4XX error, if any
+    pub error_document: Option<String>,
+}
+
+/// Options of a publish of the objects of a static website
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PublishOptions {
+    /// Prefix of the keys of the objects to publish, all objects of the bucket if `None`
+    pub prefix: Option<String>,
+    /// `Cache-Control` of HTML documents, `no-cache` if `None`
+    pub html_cache_control: Option<String>,
+    /// `Cache-Control` of other objects, `public, max-age=3600` if `None`
+    pub cache_control: Option<String>,
+    /// Invalidate the paths of the published objects in the CDN of the link, if configured
+    pub invalidate_cdn: bool,
+}
+
+/// Options of a restore of a snapshot
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RestoreOptions {
+    /// Bucket (or alias) the snapshot is stored in, the restored bucket if `None`
+    pub source: Option<String>,
+    /// Prefix of the keys of snapshots, [`SNAPSHOTS_PREFIX`] if `None`
+    pub prefix: Option<String>,
+    /// Only restore objects, which are missing or whose ETag differs from the snapshot
+    pub only_changed: bool,
+}
+
+/// Progress of a long-running operation on many objects
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Progress {
+    /// Number of objects processed so far, including skipped ones
+    pub objects: u64,
+    /// Number of objects to process, if known
+    pub total: Option<u64>,
+    /// Number of objects skipped so far, because they did not need to be processed
+    pub skipped: u64,
+    /// Number of bytes transferred so far
+    pub bytes: u64,
+    /// Number of objects, which failed to be processed so far
+    pub errors: u64,
+}
+
+/// Scope of a bulk operation estimated by a dry run, which does not change anything
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Estimate {
+    /// Number of objects, which would be changed
+    pub objects: u64,
+    /// Number of bytes, which would be copied or deleted
+    pub bytes: u64,
+    /// Number of requests to S3, including those listing objects
+    pub requests: u64,
+}
+
+/// Outcome of a step of a self-test
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SelfTestStep {
+    /// Name of the step, like `put`
+    pub name: String,
+    /// Time the step took
+    pub duration: Duration,
+    /// Error the step failed with, if any
+    pub error: Option<String>,
+}
+
+/// Report of a self-test, which wrote, read and deleted a scratch object
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SelfTestReport {
+    pub bucket: String,
+    /// Key of the scratch object
+    pub key: String,
+    /// Steps performed, in order, stopping at the first step the following ones depend on
+    pub steps: Vec<SelfTestStep>,
+}
+
+impl SelfTestReport {
+    /// Whether all steps succeeded
+    #[must_use]
+    pub fn passed(&self) -> bool {
+        self.steps.iter().all(|step| step.error.is_none())
+    }
+}
+
+/// Manifest of a snapshot, written once all objects have been copied
+#[derive(Debug, Default, Deserialize, Serialize)]
+struct SnapshotManifest {
+    /// Bucket the snapshot was taken of
+    container: String,
+    /// Objects of the bucket at the time of the snapshot, by key
+    objects: BTreeMap<String, SnapshotEntry>,
+}
+
+/// An object of a snapshot
+#[derive(Clone, Debug, Deserialize, Serialize)]
+struct SnapshotEntry {
+    etag: String,
+    size: u64,
+    /// ID of the snapshot holding the copy of the object, an earlier one if the object did not
+    /// change since
+    snapshot: String,
+}
+
+/// Status of an S3 Batch Operations job
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct BatchJobInfo {
+    pub id: String,
+    /// Status of the job, like `Active`, `Complete` or `Failed`
+    pub status: String,
+    /// Description of the job given when it was created
+    pub description: Option<String>,
+    /// Number of tasks of the job, once its manifest was generated
+    pub total: Option<u64>,
+    /// Number of tasks, which succeeded so far
+    pub succeeded: u64,
+    /// Number of tasks, which failed so far
+    pub failed: u64,
+    /// Reasons the job failed, if it did
+    pub failure_reasons: Vec<String>,
+}
+
+/// Options of deletes in buckets with MFA delete or object lock enabled
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DeleteOptions {
+    /// MFA device serial number and current code, separated by a space
+    pub mfa: Option<String>,
+    /// Whether to bypass governance-mode object lock retention
+    pub bypass_governance_retention: bool,
+}
+
+/// S3 clients for a connection configuration, which may be shared by multiple links
+#[derive(Debug)]
+struct S3Clients {
+    s3: aws_sdk_s3::Client,
+    /// Client used for access point ARNs, which cannot be addressed path-style
+    access_point: aws_sdk_s3::Client,
+    /// Client of the read replica, if configured
+    read_replica: Option<aws_sdk_s3::Client>,
+    /// Client of the S3 control API, used for S3 Batch Operations
+    #[cfg(feature = "batch-operations")]
+    s3control: aws_sdk_s3control::Client,
+    /// Client of CloudWatch, used for the storage metrics of buckets, if the clients connect to
+    /// AWS rather than a custom endpoint
+    #[cfg(feature = "storage-metrics")]
+    cloudwatch: Option<aws_sdk_cloudwatch::Client>,
+    /// Client of CloudFront, used to invalidate the paths of distributions serving objects
+    #[cfg(feature = "cdn-invalidation")]
+    cloudfront: aws_sdk_cloudfront::Client,
+    /// Credentials provider of all clients
+    credentials_provider: SharedCredentialsProvider,
+    /// Connection and request counters of all clients
+    connections: Arc<ConnectionCounters>,
+}
+
+/// Key identifying links with identical connection configuration
+#[derive(Debug, PartialEq, Eq, Hash)]
+struct ClientKey {
+    endpoint: Option<String>,
+    region: Option<String>,
+    /// Digest of credentials and all other connection settings,
+    /// so that secrets are not retained in the key
+    digest: [u8; 32],
+}
+
+impl ClientKey {
+    fn new(
+        StorageConfig {
+            access_key_id,
+            secret_access_key,
+            session_token,
+            region,
+            max_attempts,
+            sts_config,
+            endpoint,
+            aliases: _,
+            bucket_region: _,
+            public_access_block: _,
+            allow_public_buckets: _,
+            aws_profile,
+            aws_config_file,
+            aws_credentials_file,
+            imds,
+            custom_headers,
+            app_name,
+            identify_workload: _,
+            signature_version,
+            payload_signing,
+            http_client,
+            bypass_governance_retention: _,
+            expires_after_days: _,
+            expiration_sweeper: _,
+            request_ids_in_errors: _,
+            bandwidth: _,
+            atomic_writes: _,
+            batch_retry_attempts: _,
+            log_level: _,
+            log_sample_rate: _,
+            chunk_size: _,
+            warm_up: _,
+            lazy_init: _,
+            key_validation: _,
+            key_template: _,
+            key_hmac_secret: _,
+            journal: _,
+            jobs: _,
+            batch_operations: _,
+            inventory: _,
+            listing_stats: _,
+            clear_container: _,
+            size_tiers: _,
+            read_endpoint,
+            shards: _,
+            prefix_routes: _,
+            cdn_invalidation: _,
+            self_test_container: _,
+            debug_logging,
+            backend_profile: _,
+            r2,
+            part_size: _,
+            list_consistency: _,
+            backend,
+            fs: _,
+            nats_objstore: _,
+            azure: _,
+            post_write_hooks: _,
+            pre_write_hooks: _,
+            antivirus: _,
+            content_sniffing: _,
+            derivatives: _,
+            soft_delete: _,
+            trash_retention_days: _,
+            immutable: _,
+            write_once_keys: _,
+            cdn_signing: _,
+            container_policy: _,
+            auto_create_containers: _,
+        }: &StorageConfig,
+    ) -> Self {
+        let custom_headers: BTreeMap<_, _> = custom_headers.iter().collect();
+        let mut digest = Sha256::new();
+        for field in [
+            access_key_id as &dyn core::fmt::Debug,
+            secret_access_key,
+            session_token,
+            max_attempts,
+            sts_config,
+            aws_profile,
+            aws_config_file,
+            aws_credentials_file,
+            imds,
+            &custom_headers,
+            app_name,
+            signature_version,
+            payload_signing,
+            http_client,
+            read_endpoint,
+            debug_logging,
+            r2,
+            backend,
+        ] {
+            digest.update(format!("{field:?}\n"));
+        }
+        Self {
+            endpoint: endpoint.clone(),
+            region: region.clone(),
+            digest: digest.finalize().into(),
+        }
+    }
+}
+
+/// Registry of S3 clients, sharing clients (and their connection pools) between
+/// links with identical connection configuration
+#[derive(Clone, Default)]
+pub struct ClientRegistry(Arc<Mutex<HashMap<ClientKey, Weak<S3Clients>>>>);
+
+impl ClientRegistry {
+    /// Create a [`StorageClient`], reusing the S3 clients of other links with
+    /// identical connection configuration
+    pub async fn storage_client(
+        &self,
+        config: StorageConfig,
+        config_values: &HashMap<String, String>,
+    ) -> anyhow::Result<StorageClient> {
+        let key = ClientKey::new(&config);
+        let mut clients = self.0.lock().await;
+        clients.retain(|_, clients| clients.strong_count() > 0);
+        if let Some(shared) = clients.get(&key).and_then(Weak::upgrade) {
+            debug!(endpoint = ?key.endpoint, region = ?key.region, "reusing S3 client");
+            drop(clients);
+            return StorageClient::with_clients(shared, config, config_values);
+        }
+        // building clients may resolve credentials over the network, which must not block links
+        // with other configurations
+        drop(clients);
+        let built = Arc::new(S3Clients::new(config.clone()).await?);
+        let mut clients = self.0.lock().await;
+        let shared = match clients.get(&key).and_then(Weak::upgrade) {
+            // a link with identical configuration built clients concurrently
+            Some(shared) => shared,
+            None => {
+                clients.insert(key, Arc::downgrade(&built));
+                built
+            }
+        };
+        drop(clients);
+        StorageClient::with_clients(shared, config, config_values)
+    }
+}
+
+#[derive(Clone)]
+pub struct StorageClient {
+    clients: Arc<S3Clients>,
+    /// Backend the operations of `wrpc:blobstore` are delegated to, `None` for S3
+    backend: Option<Arc<dyn Backend>>,
+    aliases: Arc<HashMap<String, String>>,
+    /// Workarounds for the store the link connects to
+    quirks: Quirks,
+    /// Size of the parts of resumable uploads
+    part_size: usize,
+    /// Objects recently written by the link, which listings are retried until they include
+    recent_writes: Option<Arc<RecentWrites>>,
+    /// Preferred region for bucket creation
+    bucket_region: Option<BucketLocationConstraint>,
+    /// Public access block applied to created buckets
+    public_access_block: Option<PublicAccessBlockConfig>,
+    /// Whether writes to publicly accessible buckets are allowed
+    allow_public_buckets: bool,
+    /// Whether overwrites of existing objects and deletes are rejected
+    immutable: bool,
+    /// Whether overwrites of objects are written to new keys `<key>@<n>`
+    write_once_keys: bool,
+    /// Whether all deletes of the link bypass governance-mode object lock retention
+    bypass_governance_retention: bool,
+    /// Number of days after which objects written by the link expire
+    expires_after_days: Option<u32>,
+    /// Buckets and expiration periods, for which lifecycle rules are known to exist
+    expiration_rules: Arc<Mutex<HashSet<(String, u32)>>>,
+    /// Buckets and the times they were last found not to be publicly accessible
+    private_buckets: Arc<Mutex<HashMap<String, Instant>>>,
+    /// Whether request IDs are included in error messages
+    request_ids_in_errors: bool,
+    /// Bandwidth limit of data written to S3
+    upload_limit: Option<Arc<TokenBucket>>,
+    /// Bandwidth limit of data read from S3
+    download_limit: Option<Arc<TokenBucket>>,
+    /// Whether objects are written to a temporary key before being copied to their final key
+    atomic_writes: bool,
+    /// Number of retries of objects failing with transient errors in batch operations
+    batch_retry_attempts: u32,
+    /// Log level and sampling of requests of the link, if configured
+    logging: Option<LinkLogging>,
+    /// Maximum size of the chunks object data is streamed to components in
+    chunk_size: usize,
+    /// Validation and normalization of object keys supplied by components
+    keys: KeyPolicy,
+    /// Journal mutating operations of the link are recorded in, if configured
+    journal: Option<JournalConfig>,
+    /// Invalidator of the paths of the CloudFront distribution serving objects, if configured
+    #[cfg(feature = "cdn-invalidation")]
+    cdn: Option<Arc<CdnInvalidator>>,
+    /// Signer of CloudFront URLs and cookies, if configured
+    #[cfg(feature = "cdn-signing")]
+    cdn_signer: Option<CdnSigner>,
+    /// Policy on the containers the link creates, if configured
+    container_policy: Option<ContainerPolicyConfig>,
+    /// Whether components may create containers
+    auto_create_containers: bool,
+    /// Location the state of jobs of the link is persisted in, if configured
+    jobs: Option<JobsConfig>,
+    /// Account and role of S3 Batch Operations jobs, if configured
+    #[cfg_attr(not(feature = "batch-operations"), allow(dead_code))]
+    batch_operations: Option<BatchOperationsConfig>,
+    /// Use of S3 Inventory reports to list all objects, if configured
+    inventory: Option<InventoryConfig>,
+    /// Time statistics computed by listing objects are cached for, if they are computed
+    listing_stats_ttl: Option<Duration>,
+    /// Statistics computed by listing objects, by bucket
+    #[cfg(feature = "caching")]
+    listed_stats: Arc<Mutex<HashMap<String, ListedStats>>>,
+    /// Number of batches deleted concurrently when clearing a bucket
+    clear_workers: usize,
+    /// Rate limit of `DeleteObjects` requests clearing a bucket, if configured
+    clear_limit: Option<Arc<TokenBucket>>,
+    /// Buckets and storage classes written objects are routed to by their size
+    size_tiers: SizeTiers,
+    /// Policy choosing the buckets objects of containers are stored in
+    router: Arc<dyn Router>,
+    /// Components invoked after objects were written
+    post_write_hooks: Arc<[HookConfig]>,
+    /// Components validating objects before they are written
+    pre_write_hooks: Arc<[HookConfig]>,
+    /// Scanner of objects written by components, if configured
+    scanner: Option<Scanner>,
+    /// Sniffing of the content type of objects written by components, if configured
+    content_sniffing: Option<ContentSniffingConfig>,
+    /// Derivatives of images produced on read, if configured
+    #[cfg(feature = "derivatives")]
+    derivatives: Option<Derivatives>,
+    /// Number of days objects are kept in the trash, if the link soft-deletes objects
+    trash_retention_days: Option<u32>,
+    /// Buckets with objects in their trash when the link was established, and those the link moved
+    /// objects to the trash of since
+    trash_buckets: Arc<Mutex<HashSet<String>>>,
+    /// Cancelled once the link is deleted, aborting in-flight and background work
+    cancel: CancellationToken,
+}
+
+impl S3Clients {
+    async fn new(
+        StorageConfig {
+            access_key_id,
+            secret_access_key,
+            session_token,
+            region,
+            max_attempts,
+            sts_config,
+            endpoint,
+            aliases: _,
+            bucket_region: _,
+            public_access_block: _,
+            allow_public_buckets: _,
+            aws_profile,
+            aws_config_file,
+            aws_credentials_file,
+            imds,
+            custom_headers,
+            app_name,
+            identify_workload: _,
+            signature_version,
+            payload_signing,
+            http_client,
+            bypass_governance_retention: _,
+            expires_after_days: _,
+            expiration_sweeper: _,
+            request_ids_in_errors: _,
+            bandwidth: _,
+            atomic_writes: _,
+            batch_retry_attempts: _,
+            log_level: _,
+            log_sample_rate: _,
+            chunk_size: _,
+            warm_up: _,
+            lazy_init: _,
+            key_validation: _,
+            key_template: _,
+            key_hmac_secret: _,
+            journal: _,
+            jobs: _,
+            batch_operations: _,
+            inventory: _,
+            listing_stats: _,
+            clear_container: _,
+            size_tiers: _,
+            read_endpoint,
+            shards: _,
+            prefix_routes: _,
+            cdn_invalidation: _,
+            self_test_container: _,
+            debug_logging,
+            backend_profile: _,
+            r2,
+            part_size: _,
+            list_consistency: _,
+            backend,
+            fs: _,
+            nats_objstore: _,
+            azure: _,
+            post_write_hooks: _,
+            pre_write_hooks: _,
+            antivirus: _,
+            content_sniffing: _,
+            derivatives: _,
+            soft_delete: _,
+            trash_retention_days: _,
+            immutable: _,
+            write_once_keys: _,
+            cdn_signing: _,
+            container_policy: _,
+            auto_create_containers: _,
+        }: StorageConfig,
+    ) -> anyhow::Result<Self> {
+        let backend = backend.unwrap_or_default();
+        let signature_version = signature_version.unwrap_or_default();
+        let payload_signing = payload_signing.unwrap_or_default();
+        // R2 ignores the region, but the SDK requires one
+        let endpoint = endpoint.or_else(|| r2.as_ref().map(R2Config::endpoint));
+        let region = region.or_else(|| r2.is_some().then(|| "auto".to_string()));
+        // Links with other backends only use S3 for extensions, which must not delay their
+        // creation by looking up the region
+        let region =
+            region.or_else(|| (backend != StorageBackend::S3).then(|| DEFAULT_REGION.to_string()));
+        // Only load shared config files explicitly when asked to, the default chains
+        // already consult `~/.aws` using the `AWS_PROFILE` from the environment
+        let profile =
+            (aws_profile.is_some() || aws_config_file.is_some() || aws_credentials_file.is_some())
+                .then(|| {
+                    let mut files = EnvConfigFiles::builder()
+                        .include_default_config_file(aws_config_file.is_none())
+                        .include_default_credentials_file(aws_credentials_file.is_none());
+                    if let Some(path) = aws_config_file {
+                        files = files.with_file(EnvConfigFileKind::Config, path);
+                    }
+                    if let Some(path) = aws_credentials_file {
+                        files = files.with_file(EnvConfigFileKind::Credentials, path);
+                    }
+                    (aws_profile, files.build())
+                });
+
+        let region = match (region, &profile) {
+            (Some(region), _) => Some(Region::new(region)),
+            (None, Some((name, files))) => {
+                let mut provider =
+                    ProfileFileRegionProvider::builder().profile_files(files.clone());
+                if let Some(name) = name {
+                    provider = provider.profile_name(name);
+                }
+                provider.build().region().await
+            }
+            (None, None) => DefaultRegionChain::builder().build().region().await,
+        };
+
+        // use static credentials, a shared config profile, instance metadata or defaults from environment
+        let mut cred_provider = match (access_key_id, secret_access_key, profile) {
+            (Some(access_key_id), Some(secret_access_key), _) => {
+                SharedCredentialsProvider::new(aws_sdk_s3::config::Credentials::new(
+                    access_key_id,
+                    secret_access_key,
+                    session_token,
+                    None,
+                    "static",
+                ))
+            }
+            (_, _, Some((name, files))) => {
+                let mut provider = ProfileFileCredentialsProvider::builder().profile_files(files);
+                if let Some(name) = name {
+                    provider = provider.profile_name(name);
+                }
+                SharedCredentialsProvider::new(provider.build())
+            }
+            _ => match imds {
+                Some(imds) => {
+                    SharedCredentialsProvider::new(imds_credentials_provider(&imds).await?)
+                }
+                None => SharedCredentialsProvider::new(
+                    DefaultCredentialsChain::builder()
+                        .region(region.clone())
+                        .build()
+                        .await,
+                ),
+            },
+        };
+        if let Some(StsAssumeRoleConfig {
+            role,
+            region,
+            session,
+            external_id,
+        }) = sts_config
+        {
+            let mut role = AssumeRoleProvider::builder(role)
+                .session_name(session.unwrap_or_else(|| DEFAULT_STS_SESSION.to_string()));
+            if let Some(region) = region {
+                role = role.region(Region::new(region));
+            }
+            if let Some(external_id) = external_id {
+                role = role.external_id(external_id);
+            }
+            cred_provider = SharedCredentialsProvider::new(role.build().await);
+        }
+        let cred_provider = RefreshingCredentialsProvider::new(cred_provider);
+        let credentials = cred_provider.cache();
+        let cred_provider = SharedCredentialsProvider::new(cred_provider);
+        let credentials_provider = cred_provider.clone();
+
+        let mut retry_config = RetryConfig::standard();
+        if let Some(max_attempts) = max_attempts {
+            retry_config = retry_config.with_max_attempts(max_attempts);
+        }
+        let mut loader = aws_config::defaults(aws_config::BehaviorVersion::v2024_03_28())
+            .region(region)
+            .credentials_provider(cred_provider)
+            .retry_config(retry_config);
+        // S3 storage metrics are only available for buckets in AWS
+        #[cfg(feature = "storage-metrics")]
+        let aws = endpoint.is_none();
+        if let Some(endpoint) = endpoint {
+            loader = loader.endpoint_url(endpoint);
+        };
+        if let Some(app_name) = app_name {
+            let app_name = AppName::new(app_name).context("invalid app name")?;
+            loader = loader.app_name(app_name);
+        }
+        let sdk_config = loader.load().await;
+        let http_client = http_client.unwrap_or_default();
+        let connections = Arc::new(ConnectionCounters::default());
+        let mut s3_config = aws_sdk_s3::Config::from(&sdk_config)
+            .to_builder()
+            .interceptor(RequestCounter::new(Arc::clone(&connections)));
+        if let Some(debug_logging) = debug_logging {
+            s3_config = s3_config.interceptor(DebugLogger::new(
+                debug_logging,
+                custom_headers.keys().cloned(),
+            ));
+        }
+        if !custom_headers.is_empty() {
+            s3_config = s3_config.interceptor(CustomHeaders::new(custom_headers)?);
+        }
+        if payload_signing == PayloadSigning::Unsigned {
+            s3_config = s3_config.interceptor(UnsignedPayload);
+        }
+        if signature_version == SignatureVersion::V2 {
+            s3_config = s3_config.interceptor(SigV2Signer::new(credentials));
+        }
+        let s3 = aws_sdk_s3::Client::from_conf(
+            s3_config
+                // Since minio requires force path style,
+                // turn it on since it's disabled by default
+                // due to deprecation by AWS.
+                // https://github.com/awslabs/aws-sdk-rust/issues/390
+                .force_path_style(true)
+                .http_client(build_http_client(&http_client, &connections))
+                .build(),
+        );
+        let access_point =
+            aws_sdk_s3::Client::from_conf(s3.config().to_builder().force_path_style(false).build());
+        let read_replica = read_endpoint.map(|endpoint| {
+            aws_sdk_s3::Client::from_conf(s3.config().to_builder().endpoint_url(endpoint).build())
+        });
+        #[cfg(feature = "batch-operations")]
+        let s3control = aws_sdk_s3control::Client::from_conf(
+            aws_sdk_s3control::Config::from(&sdk_config)
+                .to_builder()
+                .http_client(build_http_client(&http_client, &connections))
+                .build(),
+        );
+        #[cfg(feature = "storage-metrics")]
+        let cloudwatch = aws.then(|| {
+            aws_sdk_cloudwatch::Client::from_conf(
+                aws_sdk_cloudwatch::Config::from(&sdk_config)
+                    .to_builder()
+                    .http_client(build_http_client(&http_client, &connections))
+                    .build(),
+            )
+        });
+        #[cfg(feature = "cdn-invalidation")]
+        let cloudfront = aws_sdk_cloudfront::Client::from_conf(
+            aws_sdk_cloudfront::Config::from(&sdk_config)
+                .to_builder()
+                .http_client(build_http_client(&http_client, &connections))
+                .build(),
+        );
+        Ok(Self {
+            s3,
+            access_point,
+            read_replica,
+            #[cfg(feature = "batch-operations")]
+            s3control,
+            #[cfg(feature = "storage-metrics")]
+            cloudwatch,
+            #[cfg(feature = "cdn-invalidation")]
+            cloudfront,
+            credentials_provider,
+            connections,
+        })
+    }
+}
+
+impl StorageClient {
+    pub async fn new(
+        config: StorageConfig,
+        config_values: &HashMap<String, String>,
+    ) -> anyhow::Result<Self> {
+        let clients = S3Clients::new(config.clone()).await?;
+        Self::with_clients(Arc::new(clients), config, config_values)
+    }
+
+    /// Validate the settings of a configuration, which would otherwise only fail once a client
+    /// is created from it
+    pub fn validate_config(
+        StorageConfig {
+            endpoint,
+            read_endpoint,
+            custom_headers,
+            app_name,
+            key_template,
+            size_tiers,
+            shards,
+            prefix_routes,
+            backend_profile,
+            public_access_block,
+            expires_after_days,
+            expiration_sweeper,
+            bypass_governance_retention,
+            r2,
+            part_size,
+            backend,
+            fs,
+            nats_objstore,
+            azure,
+            post_write_hooks,
+            pre_write_hooks,
+            antivirus,
+            derivatives,
+            journal,
+            cdn_invalidation,
+            #[cfg(feature = "cdn-signing")]
+            cdn_signing,
+            container_policy,
+            ..
+        }: &StorageConfig,
+    ) -> anyhow::Result<()> {
+        let backend_profile = backend_profile.unwrap_or_default();
+        let backend = backend.unwrap_or_default();
+        let bypass_governance_retention = bypass_governance_retention.unwrap_or_default();
+        if let Some(endpoint) = endpoint {
+            url::Url::parse(endpoint).context("invalid endpoint")?;
+        }
+        if let Some(endpoint) = read_endpoint {
+            url::Url::parse(endpoint).context("invalid read endpoint")?;
+        }
+        if let Some(app_name) = app_name {
+            AppName::new(app_name.clone()).context("invalid app name")?;
+        }
+        CustomHeaders::new(custom_headers.clone())?;
+        if let Some(template) = key_template {
+            KeyTemplate::parse(template).context("invalid key template")?;
+        }
+        if let Some(tiers) = size_tiers {
+            SizeTiers::new(tiers.clone())?;
+        }
+        ShardRouter::new(shards.clone())?;
+        PrefixRouter::new(prefix_routes.clone())?;
+        let quirks = Quirks::new(backend_profile);
+        ensure!(
+            quirks.public_access_block || public_access_block.is_none(),
+            "`public_access_block` is not supported by backend profile `{backend_profile:?}`"
+        );
+        ensure!(
+            quirks.object_tagging || expires_after_days.is_none(),
+            "`expires_after_days` is not supported by backend profile `{backend_profile:?}`, \
+             since it relies on object tagging"
+        );
+        ensure!(
+            quirks.object_tagging
+                || !expiration_sweeper
+                    .as_ref()
+                    .is_some_and(|sweeper| sweeper.ttl_tag),
+            "`ttl_tag` of the `expiration_sweeper` is not supported by backend profile \
+             `{backend_profile:?}`, since it relies on object tagging"
+        );
+        ensure!(
+            quirks.object_lock || !bypass_governance_retention,
+            "`bypass_governance_retention` is not supported by backend profile \
+             `{backend_profile:?}`, since it does not support object lock"
+        );
+        if r2.is_some() {
+            ensure!(
+                backend_profile == BackendProfile::R2,
+                "`r2` requires the `r2` backend profile"
+            );
+            ensure!(
+                endpoint.is_none(),
+                "`r2` and `endpoint` must not both be set"
+            );
+        }
+        if let Some(part_size) = part_size {
+            ensure!(
+                (MIN_PART_SIZE..=MAX_PART_SIZE).contains(part_size),
+                "`part_size` must be between {MIN_PART_SIZE} and {MAX_PART_SIZE} bytes"
+            );
+        }
+        ensure!(
+            fs.is_some() || backend != StorageBackend::Fs,
+            "the `fs` backend requires `fs.root`"
+        );
+        if let Some(ContainerPolicyConfig {
+            max_containers,
+            tags,
+            ..
+        }) = container_policy
+        {
+            ensure!(
+                backend == StorageBackend::S3 || (max_containers.is_none() && tags.is_empty()),
+                "`max_containers` and `tags` of the `container_policy` require the `s3` backend"
+            );
+        }
+        if let Some(url) = nats_objstore.as_ref().and_then(|nats| nats.url.as_ref()) {
+            url::Url::parse(url).context("invalid NATS URL")?;
+        }
+        #[cfg(not(feature = "azure"))]
+        ensure!(
+            backend != StorageBackend::Azure,
+            "the `azure` backend requires the provider to be built with the `azure` feature"
+        );
+        if backend == StorageBackend::Azure {
+            ensure!(
+                azure.as_ref().is_some_and(
+                    |azure| azure.connection_string.is_some() || azure.account.is_some()
+                ),
+                "the `azure` backend requires `azure.connection_string` or `azure.account`"
+            );
+        }
+        if let Some(endpoint) = azure.as_ref().and_then(|azure| azure.endpoint.as_ref()) {
+            url::Url::parse(endpoint).context("invalid Azure Blob Storage endpoint")?;
+        }
+        ensure!(
+            post_write_hooks
+                .iter()
+                .chain(pre_write_hooks)
+                .all(|hook| !hook.component_id.is_empty()),
+            "hooks must name components by `component_id`"
+        );
+        ensure!(
+            pre_write_hooks
+                .iter()
+                .filter_map(|hook| hook.inspect_bytes)
+                .all(|n| n <= MAX_INSPECT_BYTES),
+            "`inspect_bytes` of hooks must be at most {MAX_INSPECT_BYTES} bytes"
+        );
+        ensure!(
+            antivirus
+                .as_ref()
+                .is_none_or(|antivirus| !antivirus.address.is_empty()),
+            "`antivirus.address` must not be empty"
+        );
+        #[cfg(not(feature = "derivatives"))]
+        ensure!(
+            derivatives.is_none(),
+            "`derivatives` requires the provider to be built with the `derivatives` feature"
+        );
+        if let Some(derivatives) = derivatives {
+            ensure!(
+                derivatives.prefix.as_deref() != Some(""),
+                "`derivatives.prefix` must not be empty"
+            );
+            ensure!(
+                derivatives.max_dimension != Some(0),
+                "`derivatives.max_dimension` must be positive"
+            );
+        }
+        #[cfg(feature = "cdn-signing")]
+        if let Some(signing) = cdn_signing {
+            CdnSigner::new(signing)?;
+        }
+        #[cfg(not(feature = "cdn-invalidation"))]
+        ensure!(
+            cdn_invalidation.is_none(),
+            "`cdn_invalidation` requires the provider to be built with the `cdn-invalidation` feature"
+        );
+        if backend != StorageBackend::S3 {
+            for (name, set) in [
+                ("public_access_block", public_access_block.is_some()),
+                ("expires_after_days", expires_after_days.is_some()),
+                ("expiration_sweeper", expiration_sweeper.is_some()),
+                ("journal", journal.is_some()),
+                ("size_tiers", size_tiers.is_some()),
+                ("shards", !shards.is_empty()),
+                ("prefix_routes", !prefix_routes.is_empty()),
+                ("cdn_invalidation", cdn_invalidation.is_some()),
+            ] {
+                ensure!(
+                    !set,
+                    "`{name}` is only supported by the S3 backend, not by `{backend:?}`"
+                );
+            }
+        }
+        Ok(())
+    }
+
+    fn with_clients(
+        clients: Arc<S3Clients>,
+        StorageConfig {
+            mut aliases,
+            bucket_region,
+            public_access_block,
+            allow_public_buckets,
+            immutable,
+            write_once_keys,
+            bypass_governance_retention,
+            expires_after_days,
+            request_ids_in_errors,
+            bandwidth,
+            atomic_writes,
+            batch_retry_attempts,
+            log_level,
+            log_sample_rate,
+            chunk_size,
+            key_validation,
+            key_template,
+            key_hmac_secret,
+            journal,
+            jobs,
+            batch_operations,
+            inventory,
+            listing_stats,
+            clear_container,
+            size_tiers,
+            shards,
+            prefix_routes,
+            #[cfg(feature = "cdn-invalidation")]
+            cdn_invalidation,
+            backend_profile,
+            part_size,
+            list_consistency,
+            backend,
+            fs,
+            nats_objstore,
+            azure,
+            post_write_hooks,
+            pre_write_hooks,
+            antivirus,
+            content_sniffing,
+            #[cfg(feature = "derivatives")]
+            derivatives,
+            soft_delete,
+            trash_retention_days,
+            #[cfg(feature = "cdn-signing")]
+            cdn_signing,
+            container_policy,
+            auto_create_containers,
+            ..
+        }: StorageConfig,
+        config_values: &HashMap<String, String>,
+    ) -> anyhow::Result<Self> {
+        // Process aliases
+        for (k, v) in config_values {
+            if let Some(alias) = k.strip_prefix(ALIAS_PREFIX) {
+                if alias.is_empty() || v.is_empty() {
+                    error!("invalid bucket alias_ key and value must not be empty");
+                } else {
+                    aliases.insert(alias.to_string(), v.to_string());
+                }
+            }
+        }
+
+        let logging = (log_level.is_some() || log_sample_rate.is_some()).then(|| {
+            let level = log_level
+                .as_deref()
+                .and_then(|level| {
+                    level
+                        .parse()
+                        .map_err(|err| warn!(?err, level, "ignoring invalid log level"))
+                        .ok()
+                })
+                .unwrap_or(LevelFilter::TRACE);
+            LinkLogging::new(level, log_sample_rate.unwrap_or(1.0))
+        });
+
+        let keys = KeyPolicy::new(
+            key_validation,
+            key_template.as_deref(),
+            key_hmac_secret.as_deref(),
+        )
+        .context("invalid key template")?;
+        let size_tiers = SizeTiers::new(size_tiers.unwrap_or_default())?;
+        let router = Routers::default()
+            .with(PrefixRouter::new(prefix_routes)?)
+            .with(ShardRouter::new(shards)?);
+        #[cfg(feature = "cdn-invalidation")]
+        let (cdn, cdn_task) = cdn_invalidation
+            .map(|config| CdnInvalidator::new(clients.cloudfront.clone(), config))
+            .unzip();
+        #[cfg(feature = "cdn-signing")]
+        let cdn_signer = cdn_signing.as_ref().map(CdnSigner::new).transpose()?;
+
+        let quirks = Quirks::new(backend_profile.unwrap_or_default());
+        let client = StorageClient {
+            clients,
+            backend: backend::new(backend.unwrap_or_default(), fs, nats_objstore, azure)?,
+            aliases: Arc::new(aliases),
+            quirks,
+            part_size: part_size.unwrap_or(quirks.part_size),
+            recent_writes: list_consistency
+                .or_else(|| {
+                    quirks
+                        .eventually_consistent_listings
+                        .then(ListConsistencyConfig::default)
+                })
+                .map(|config| Arc::new(RecentWrites::new(config))),
+            bucket_region: bucket_region.and_then(|v| BucketLocationConstraint::from_str(&v).ok()),
+            public_access_block,
+            allow_public_buckets: allow_public_buckets.unwrap_or_default(),
+            immutable: immutable.unwrap_or_default(),
+            write_once_keys: write_once_keys.unwrap_or_default(),
+            bypass_governance_retention: bypass_governance_retention.unwrap_or_default(),
+            expires_after_days,
+            expiration_rules: Arc::default(),
+            private_buckets: Arc::default(),
+            request_ids_in_errors: request_ids_in_errors.unwrap_or_default(),
+            upload_limit: bandwidth
+                .as_ref()
+                .and_then(|bandwidth| bandwidth.upload_bytes_per_sec)
+                .map(|rate| Arc::new(TokenBucket::new(rate))),
+            download_limit: bandwidth
+                .as_ref()
+                .and_then(|bandwidth| bandwidth.download_bytes_per_sec)
+                .map(|rate| Arc::new(TokenBucket::new(rate))),
+            atomic_writes: atomic_writes.unwrap_or_default(),
+            batch_retry_attempts: batch_retry_attempts.unwrap_or(DEFAULT_BATCH_RETRY_ATTEMPTS),
+            logging,
+            chunk_size: chunk_size.unwrap_or(DEFAULT_CHUNK_SIZE).max(1),
+            keys,
+            journal,
+            jobs,
+            batch_operations,
+            inventory,
+            listing_stats_ttl: listing_stats.map(|ListingStatsConfig { ttl_secs }| {
+                ttl_secs.map_or(DEFAULT_LISTING_STATS_TTL, Duration::from_secs)
+            }),
+            #[cfg(feature = "caching")]
+            listed_stats: Arc::default(),
+            clear_workers: clear_container
+                .as_ref()
+                .and_then(|config| config.workers)
+                .unwrap_or(DEFAULT_CLEAR_WORKERS)
+                .max(1),
+            clear_limit: clear_container
+                .and_then(|config| config.max_batches_per_sec)
+                .map(|rate| Arc::new(TokenBucket::new(rate))),
+            size_tiers,
+            router: Arc::new(router),
+            post_write_hooks: post_write_hooks.into(),
+            pre_write_hooks: pre_write_hooks.into(),
+            scanner: antivirus.map(Scanner::new),
+            content_sniffing,
+            #[cfg(feature = "derivatives")]
+            derivatives: derivatives.map(Derivatives::new),
+            trash_retention_days: soft_delete
+                .unwrap_or_default()
+                .then(|| trash_retention_days.unwrap_or(DEFAULT_TRASH_RETENTION_DAYS)),
+            trash_buckets: Arc::default(),
+            #[cfg(feature = "cdn-invalidation")]
+            cdn: cdn.map(Arc::new),
+            #[cfg(feature = "cdn-signing")]
+            cdn_signer,
+            container_policy,
+            auto_create_containers: auto_create_containers.unwrap_or(true),
+            cancel: CancellationToken::new(),
+        };
+        #[cfg(feature = "cdn-invalidation")]
+        if let Some(task) = cdn_task {
+            client.spawn(task);
+        }
+        Ok(client)
+    }
+
+    /// Wrap the error of an S3 request, including its request IDs in the message if configured
+    fn request_error<E>(&self, err: E) -> RequestError
+    where
+        E: ProvideErrorMetadata
+            + RequestId
+            + RequestIdExt
+            + std::error::Error
+            + Send
+            + Sync
+            + 'static,
+    {
+        RequestError::new(err, self.request_ids_in_errors)
+    }
+
+    /// Wait until `n` bytes may be written to S3 within the bandwidth limit of the link
+    pub async fn throttle_upload(&self, n: usize) {
+        if let Some(limit) = &self.upload_limit {
+            limit.take(n).await;
+        }
+    }
+
+    /// Wait until `n` bytes may be read from S3 within the bandwidth limit of the link
+    pub async fn throttle_download(&self, n: usize) {
+        if let Some(limit) = &self.download_limit {
+            limit.take(n).await;
+        }
+    }
+
+    /// Spawn a background task for the link, which is aborted once the link is deleted
+    pub fn spawn(&self, task: impl Future<Output = ()> + Send + 'static) {
+        let cancel = self.cancel.clone();
+        tokio::spawn(async move {
+            tokio::select! {
+                () = task => {}
+                () = cancel.cancelled() => {}
+            }
+        });
+    }
+
+    /// Wrap in-flight work of the link, so that it fails once the link is deleted and logs with
+    /// the log level and sampling configured for the link
+    pub fn abort_on_delete<T: Send + 'static>(
+        &self,
+        work: impl Future<Output = Result<T, String>> + Send + 'static,
+    ) -> impl Future<Output = Result<T, String>> + Send + 'static {
+        let cancel = self.cancel.clone();
+        let work = async move {
+            tokio::select! {
+                res = work => res,
+                () = cancel.cancelled() => Err("link was deleted".to_string()),
+            }
+        };
+        match self.log_dispatch() {
+            Some(dispatch) => Either::Left(work.with_subscriber(dispatch)),
+            None => Either::Right(work),
+        }
+    }
+
+    /// Abort all in-flight and background work of the link
+    pub fn cancel(&self) {
+        self.cancel.cancel();
+    }
+
+    /// Connection and request statistics of the clients of the link, which are shared with other
+    /// links with identical connection configuration
+    pub fn connection_stats(&self) -> ConnectionStats {
+        self.clients.connections.snapshot()
+    }
+
+    /// Dispatcher for the logs of a request of the link, if the link configures its logging
+    pub fn log_dispatch(&self) -> Option<Dispatch> {
+        self.logging.as_ref().map(LinkLogging::dispatch)
+    }
+
+    /// Scanner of objects written by components, if configured
+    pub(crate) fn scanner(&self) -> Option<&Scanner> {
+        self.scanner.as_ref()
+    }
+
+    /// Sniffing of the content type of objects written by components, if configured
+    pub(crate) fn content_sniffing(&self) -> Option<&ContentSniffingConfig> {
+        self.content_sniffing.as_ref()
+    }
+
+    /// Derivatives of images produced on read, if configured
+    #[cfg(feature = "derivatives")]
+    pub(crate) fn derivatives(&self) -> Option<&Derivatives> {
+        self.derivatives.as_ref()
+    }
+
+    /// Whether objects deleted by components are moved to the trash instead
+    pub fn soft_deletes(&self) -> bool {
+        self.trash_retention_days.is_some()
+    }
+
+    /// Number of days after which objects written by the link expire, if configured
+    pub(crate) fn expires_after_days(&self) -> Option<u32> {
+        self.expires_after_days
+    }
+
+    /// Components validating the object `key` before it is written by the link
+    pub fn pre_write_hooks<'a>(&'a self, key: &'a str) -> impl Iterator<Item = &'a HookConfig> {
+        self.pre_write_hooks
+            .iter()
+            .filter(move |hook| hook.matches(key))
+    }
+
+    /// Components invoked after the object `key` was written by the link
+    pub fn post_write_hooks<'a>(&'a self, key: &'a str) -> impl Iterator<Item = &'a HookConfig> {
+        self.post_write_hooks
+            .iter()
+            .filter(move |hook| hook.matches(key))
+    }
+
+    /// Fetch credentials and open connections to the endpoint, so that the first request of the
+    /// link does not pay for credential fetches, DNS resolution and TLS handshakes
+    ///
+    /// The buckets the link has aliases for are `head`ed, or buckets are listed if it has none.
+    /// Failed requests are only logged, since any response leaves an open connection behind.
+    #[instrument(level = "debug", skip(self))]
+    pub async fn warm_up(&self) {
+        if self.backend.is_some() {
+            return;
+        }
+        if let Err(err) = self
+            .clients
+            .credentials_provider
+            .provide_credentials()
+            .await
+        {
+            warn!(?err, "failed to fetch credentials during warm-up");
+        }
+        let buckets: HashSet<&String> = self.aliases.values().collect();
+        if buckets.is_empty() {
+            if let Err(err) = self.clients.s3.list_buckets().send().await {
+                debug!(%err, "warm-up request failed");
+            }
+            return;
+        }
+        futures::future::join_all(buckets.into_iter().map(|bucket| async move {
+            if let Err(err) = self.s3(bucket).head_bucket().bucket(bucket).send().await {
+                debug!(%err, %bucket, "warm-up request failed");
+            }
+        }))
+        .await;
+    }
+
+    /// Write, read and delete a scratch object in `bucket`, timing each step, to diagnose the
+    /// connectivity, credentials and permissions of the link
+    ///
+    /// Failing steps are reported rather than returned as errors. The scratch object is deleted
+    /// even if reading it fails.
+    #[instrument(level = "debug", skip(self))]
+    pub async fn self_test(&self, bucket: &str) -> SelfTestReport {
+        let key = format!("{TEMP_PREFIX}self-test/{}", Uuid::new_v4());
+        let data = Bytes::from(format!("wasmCloud blobstore-s3 self-test {key}"));
+        let mut steps = Vec::with_capacity(3);
+        let mut step = |name: &str, start: Instant, res: anyhow::Result<()>| {
+            let error = res.err().map(|err| format!("{err:#}"));
+            let failed = error.is_some();
+            steps.push(SelfTestStep {
+                name: name.into(),
+                duration: start.elapsed(),
+                error,
+            });
+            !failed
+        };
+
+        let start = Instant::now();
+        let res = self
+            .write_object_with_expiry(bucket, &key, data.clone(), None, None)
+            .await;
+        if step("put", start, res) {
+            let start = Instant::now();
+            let res = async {
+                let read: Vec<_> = self
+                    .read_object(bucket, &key, 0, u64::MAX)
+                    .await?
+                    .collect()
+                    .await;
+                let read = read
+                    .into_iter()
+                    .collect::<std::io::Result<Vec<_>>>()
+                    .context("failed to read object")?
+                    .concat();
+                ensure!(
+                    data == read,
+                    "read {} bytes differing from those written",
+                    read.len()
+                );
+                Ok(())
+            }
+            .await;
+            step("get", start, res);
+            let start = Instant::now();
+            let res = self.delete_object(bucket, key.clone()).await;
+            step("delete", start, res);
+        }
+        SelfTestReport {
+            bucket: bucket.to_string(),
+            key,
+            steps,
+        }
+    }
+
+    /// Replace the router built from the prefix routes and sharded aliases of the link
+    #[must_use]
+    pub fn with_router(mut self, router: impl Router + 'static) -> Self {
+        self.router = Arc::new(router);
+        self
+    }
+
+    /// Bucket the object `key` of `bucket` is stored in, as chosen by the router of the link
+    fn route<'a>(&'a self, bucket: &'a str, key: &str) -> &'a str {
+        self.router.route(bucket, key).unwrap_or(bucket)
+    }
+
+    /// perform alias lookup on bucket name
+    /// This can be used either for giving shortcuts to actors in the linkdefs, for example:
+    /// - component could use bucket names `alias_today`, `alias_images`, etc. and the linkdef aliases
+    ///   will remap them to the real bucket name
+    ///
+    /// The `'alias_'` prefix is not required, so this also works as a general redirect capability
+    pub fn unalias<'n, 's: 'n>(&'s self, bucket_or_alias: &'n str) -> &'n str {
+        debug!(%bucket_or_alias, aliases = ?self.aliases);
+        let name = bucket_or_alias
+            .strip_prefix(ALIAS_PREFIX)
+            .unwrap_or(bucket_or_alias);
+        if let Some(name) = self.aliases.get(name) {
+            name.as_ref()
+        } else {
+            name
+        }
+    }
+
+    /// Validate an object key supplied by a component, normalizing it if configured for the link
+    pub fn object_key<'k>(&self, key: &'k str) -> Result<Cow<'k, str>, KeyError> {
+        self.keys.apply(key)
+    }
+
+    /// Validate object keys supplied by a component, normalizing them if configured for the link
+    pub fn object_keys(
+        &self,
+        keys: impl IntoIterator<Item = String>,
+    ) -> Result<Vec<String>, KeyError> {
+        keys.into_iter()
+            .map(|key| self.object_key(&key).map(Cow::into_owned))
+            .collect()
+    }
+
+    /// Append a record of a mutating operation performed on behalf of `component_id` to the
+    /// journal, if one is configured for the link, and invalidate the CDN paths of the changed
+    /// objects, if the link configures CDN invalidation
+    ///
+    /// Failures to write the record are only logged, since the operation itself succeeded.
+    #[instrument(level = "debug", skip(self))]
+    pub async fn journal(&self, component_id: &str, mutation: Mutation) {
+        #[cfg(feature = "cdn-invalidation")]
+        self.invalidate_cdn(&mutation);
+        let Some(JournalConfig { bucket, prefix }) = &self.journal else {
+            return;
+        };
+        let bucket = self.unalias(bucket);
+        let record = JournalRecord::new(component_id, mutation, SystemTime::now());
+        let key = journal::record_key(prefix.as_deref().unwrap_or(JOURNAL_PREFIX), record.at_ms);
+        let body = match serde_json::to_vec(&record) {
+            Ok(body) => body,
+            Err(err) => {
+                error!(?err, "failed to encode journal record");
+                return;
+            }
+        };
+        if let Err(err) = self
+            .s3(bucket)
+            .put_object()
+            .bucket(bucket)
+            .key(&key)
+            .content_type("application/json")
+            .body(body.into())
+            .send()
+            .await
+        {
+            error!(%err, bucket, key, "failed to write journal record");
+        }
+    }
+
+    /// Queue the CDN paths of the objects changed by `mutation` for invalidation
+    #[cfg(feature = "cdn-invalidation")]
+    fn invalidate_cdn(&self, mutation: &Mutation) {
+        let Some(cdn) = &self.cdn else {
+            return;
+        };
+        let changed: Vec<(&str, &str)> = match mutation {
+            Mutation::Put { bucket, key, .. }
+            | Mutation::DeleteVersion { bucket, key, .. }
+            | Mutation::Undelete { bucket, key } => vec![(bucket.as_str(), key.as_str())],
+            Mutation::Copy {
+                dest_bucket,
+                dest_key,
+                ..
+            } => vec![(dest_bucket.as_str(), dest_key.as_str())],
+            Mutation::Move {
+                src_bucket,
+                src_key,
+                dest_bucket,
+                dest_key,
+            } => vec![
+                (src_bucket.as_str(), src_key.as_str()),
+                (dest_bucket.as_str(), dest_key.as_str()),
+            ],
+            Mutation::Delete { bucket, keys } => keys
+                .iter()
+                .map(|key| (bucket.as_str(), key.as_str()))
+                .collect(),
+        };
+        for (bucket, key) in changed {
+            for mapping in cdn.mappings() {
+                if self.unalias(&mapping.container) != bucket {
+                    continue;
+                }
+                if let Some(path) = cdn_path(mapping, key) {
+                    cdn.invalidate(path);
+                }
+            }
+        }
+    }
+
+    /// Queue the CDN paths of all objects of `bucket` with keys starting with `prefix` for
+    /// invalidation, as wildcard paths
+    #[cfg(feature = "cdn-invalidation")]
+    fn invalidate_cdn_prefix(&self, bucket: &str, prefix: &str) {
+        let Some(cdn) = &self.cdn else {
+            return;
+        };
+        for mapping in cdn.mappings() {
+            if self.unalias(&mapping.container) != bucket {
+                continue;
+            }
+            // the mapping serves a part of the prefix, or the prefix is a part of the mapping
+            let prefix = if mapping.prefix.starts_with(prefix) {
+                mapping.prefix.as_str()
+            } else {
+                prefix
+            };
+            if let Some(path) = cdn_path(mapping, prefix) {
+                cdn.invalidate(format!("{path}*"));
+            }
+        }
+    }
+
+    /// Signer of CloudFront URLs and cookies of the link
+    #[cfg(feature = "cdn-signing")]
+    fn cdn_signer(&self) -> anyhow::Result<&CdnSigner> {
+        self.cdn_signer
+            .as_ref()
+            .context("no CloudFront key pair is configured for the link")
+    }
+
+    /// Sign a CloudFront `url`, granting access to it for `expires_in`, 15 minutes by default
+    #[cfg(feature = "cdn-signing")]
+    pub fn sign_cdn_url(&self, url: &str, expires_in: Option<Duration>) -> anyhow::Result<String> {
+        self.cdn_signer()?
+            .sign_url(url, expires_in.unwrap_or(DEFAULT_DOWNLOAD_URL_EXPIRY))
+    }
+
+    /// Sign CloudFront cookies granting access to the URLs matching `resource`, which may
+    /// contain wildcards, for `expires_in`, 15 minutes by default, returning the names and
+    /// values of the cookies
+    #[cfg(feature = "cdn-signing")]
+    pub fn sign_cdn_cookies(
+        &self,
+        resource: &str,
+        expires_in: Option<Duration>,
+    ) -> anyhow::Result<Vec<(String, String)>> {
+        self.cdn_signer()?
+            .sign_cookies(resource, expires_in.unwrap_or(DEFAULT_DOWNLOAD_URL_EXPIRY))
+    }
+
+    /// Sign a CloudFront URL, which requires the `cdn-signing` feature
+    #[cfg(not(feature = "cdn-signing"))]
+    pub fn sign_cdn_url(
+        &self,
+        _url: &str,
+        _expires_in: Option<Duration>,
+    ) -> anyhow::Result<String> {
+        bail!(cdn_signing_disabled())
+    }
+
+    /// Sign CloudFront cookies, which requires the `cdn-signing` feature
+    #[cfg(not(feature = "cdn-signing"))]
+    pub fn sign_cdn_cookies(
+        &self,
+        _resource: &str,
+        _expires_in: Option<Duration>,
+    ) -> anyhow::Result<Vec<(String, String)>> {
+        bail!(cdn_signing_disabled())
+    }
+
+    /// Read the journal records of operations performed between `since` and `until`, in the
+    /// order they were performed
+    #[instrument(level = "debug", skip(self))]
+    pub async fn journal_records(
+        &self,
+        since: SystemTime,
+        until: SystemTime,
+    ) -> anyhow::Result<Vec<JournalRecord>> {
+        let JournalConfig { bucket, prefix } = self
+            .journal
+            .as_ref()
+            .context("no journal is configured for the link")?;
+        let bucket = self.unalias(bucket);
+        let prefix = prefix.as_deref().unwrap_or(JOURNAL_PREFIX);
+        let (since, until) = (journal::unix_millis(since), journal::unix_millis(until));
+        let mut records = Vec::new();
+        let mut pages = self
+            .s3(bucket)
+            .list_objects_v2()
+            .bucket(bucket)
+            .prefix(prefix)
+            // keys of records at `since` sort after their time without suffix
+            .start_after(format!("{prefix}{}", journal::time_key(since)))
+            .into_paginator()
+            .send();
+        'pages: while let Some(page) = pages.next().await {
+            let page = page
+                .map_err(|err| self.request_error(err))
+                .context("failed to list journal records")?;
+            for Object { key, .. } in page.contents.unwrap_or_default() {
+                let Some(key) = key else {
+                    continue;
+                };
+                let Some(at_ms) = key
+                    .strip_prefix(prefix)
+                    .and_then(|name| name.get(..20))
+                    .and_then(|at_ms| at_ms.parse::<u64>().ok())
+                else {
+                    warn!(key, "ignoring object without a time in journal");
+                    continue;
+                };
+                if at_ms > until {
+                    break 'pages;
+                }
+                let record = self
+                    .s3(bucket)
+                    .get_object()
+                    .bucket(bucket)
+                    .key(&key)
+                    .send()
+                    .await
+                    .map_err(|err| self.request_error(err))
+                    .context("failed to get journal record")?
+                    .body
+                    .collect()
+                    .await
+                    .context("failed to read journal record")?
+                    .into_bytes();
+                match serde_json::from_slice(&record) {
+                    Ok(record) => records.push(record),
+                    Err(err) => warn!(?err, key, "ignoring invalid journal record"),
+                }
+            }
+        }
+        Ok(records)
+    }
+
+    /// Persist the state of a job, if the link persists jobs, only logging failures
+    #[instrument(level = "trace", skip(self))]
+    pub async fn save_job(&self, record: &JobRecord) {
+        let Some(JobsConfig { bucket, prefix }) = &self.jobs else {
+            return;
+        };
+        let bucket = self.unalias(bucket);
+        let key = format!(
+            "{}{}.json",
+            prefix.as_deref().unwrap_or(JOBS_PREFIX),
+            record.id
+        );
+        let body = match serde_json::to_vec(record) {
+            Ok(body) => body,
+            Err(err) => {
+                error!(?err, "failed to encode job state");
+                return;
+            }
+        };
+        if let Err(err) = self
+            .s3(bucket)
+            .put_object()
+            .bucket(bucket)
+            .key(&key)
+            .content_type("application/json")
+            .body(body.into())
+            .send()
+            .await
+        {
+            error!(%err, bucket, key, "failed to write job state");
+        }
+    }
+
+    /// Load the persisted state of the job `id`, `None` if it does not exist or the link does
+    /// not persist jobs
+    #[instrument(level = "debug", skip(self))]
+    pub async fn load_job(&self, id: &str) -> anyhow::Result<Option<JobRecord>> {
+        let Some(JobsConfig { bucket, prefix }) = &self.jobs else {
+            return Ok(None);
+        };
+        let bucket = self.unalias(bucket);
+        let key = format!("{}{id}.json", prefix.as_deref().unwrap_or(JOBS_PREFIX));
+        let out = match self
+            .s3(bucket)
+            .get_object()
+            .bucket(bucket)
+            .key(&key)
+            .send()
+            .await
+        {
+            Ok(out) => out,
+            Err(se) if se.code() == Some("NoSuchKey") => return Ok(None),
+            Err(se) => bail!(anyhow!(self.request_error(se)).context("failed to get job state")),
+        };
+        let record = out
+            .body
+            .collect()
+            .await
+            .context("failed to read job state")?
+            .into_bytes();
+        serde_json::from_slice(&record)
+            .map(Some)
+            .context("failed to decode job state")
+    }
+
+    /// Create an S3 Batch Operations job, which requires the `batch-operations` feature
+    #[cfg(not(feature = "batch-operations"))]
+    pub async fn start_batch_copy(
+        &self,
+        _src_bucket: &str,
+        _dest_bucket: &str,
+        _prefix: Option<&str>,
+        _description: &str,
+    ) -> anyhow::Result<String> {
+        bail!(batch_operations_disabled())
+    }
+
+    /// Status of an S3 Batch Operations job, which requires the `batch-operations` feature
+    #[cfg(not(feature = "batch-operations"))]
+    pub async fn batch_job(&self, _id: &str) -> anyhow::Result<BatchJobInfo> {
+        bail!(batch_operations_disabled())
+    }
+
+    /// Cancel an S3 Batch Operations job, which requires the `batch-operations` feature
+    #[cfg(not(feature = "batch-operations"))]
+    pub async fn cancel_batch_job(&self, _id: &str) -> anyhow::Result<()> {
+        bail!(batch_operations_disabled())
+    }
+
+    /// Select the S3 client to use for a bucket name or access point ARN
+    fn s3(&self, bucket: &str) -> &aws_sdk_s3::Client {
+        if is_access_point_arn(bucket) {
+            &self.clients.access_point
+        } else {
+            &self.clients.s3
+        }
+    }
+
+    /// Send a read request to the read replica, if configured, falling back to the primary
+    /// endpoint if the replica fails
+    ///
+    /// Failures include objects missing from the replica, which may not have been replicated yet.
+    /// Access points are always read through the primary endpoint.
+    async fn read<T, E, Fut>(
+        &self,
+        bucket: &str,
+        request: impl Fn(aws_sdk_s3::Client) -> Fut,
+    ) -> Result<T, E>
+    where
+        Fut: Future<Output = Result<T, E>>,
+        E: core::fmt::Debug,
+    {
+        match &self.clients.read_replica {
+            Some(replica) if !is_access_point_arn(bucket) => match request(replica.clone()).await {
+                Ok(out) => Ok(out),
+                Err(err) => {
+                    debug!(
+                        ?err,
+                        "read from replica failed, falling back to primary endpoint"
+                    );
+                    request(self.clients.s3.clone()).await
+                }
+            },
+            _ => request(self.s3(bucket).clone()).await,
+        }
+    }
+
+    /// Check whether a container exists
+    #[instrument(level = "debug", skip(self))]
+    pub async fn container_exists(&self, bucket: &str) -> anyhow::Result<bool> {
+        if let Some(backend) = &self.backend {
+            return backend.container_exists(bucket).await;
+        }
+        let routed = self.router.buckets(bucket);
+        if routed.is_empty() {
+            return self.bucket_exists(bucket).await;
+        }
+        for bucket in routed {
+            if !self.bucket_exists(bucket).await? {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    async fn bucket_exists(&self, bucket: &str) -> anyhow::Result<bool> {
+        match self.s3(bucket).head_bucket().bucket(bucket).send().await {
+            Ok(_) => Ok(true),
+            Err(se) => match se.into_service_error() {
+                HeadBucketError::NotFound(_) => Ok(false),
+                err => {
+                    error!(?err, code = err.code(), "Unable to head bucket");
+                    bail!(anyhow!(self.request_error(err)).context("failed to `head` bucket"))
+                }
+            },
+        }
+    }
+
+    /// Create a bucket
+    #[instrument(level = "debug", skip(self))]
+    pub async fn create_container(&self, bucket: &str) -> anyhow::Result<()> {
+        if !self.auto_create_containers {
+            bail!(BackendError::new(
+                ErrorKind::PermissionDenied,
+                format!("link does not allow creating containers, refusing to create [{bucket}]")
+            ))
+        }
+        if !self.container_name_allowed(bucket) {
+            bail!(BackendError::new(
+                ErrorKind::PermissionDenied,
+                format!(
+                    "container name `{bucket}` is not allowed by the container policy of the link"
+                )
+            ))
+        }
+        if let Some(backend) = &self.backend {
+            return backend.create_container(bucket).await;
+        }
+        let routed = self.router.buckets(bucket);
+        if routed.is_empty() {
+            return self.create_bucket(bucket).await;
+        }
+        for bucket in routed {
+            self.create_bucket(bucket).await?;
+        }
+        Ok(())
+    }
+
+    async fn create_bucket(&self, bucket: &str) -> anyhow::Result<()> {
+        if is_access_point_arn(bucket) {
+            bail!("access points cannot be created as containers, create the bucket instead")
+        }
+        let mut builder = self.clients.s3.create_bucket();
+
+        // Only add BucketLocationConstraint if bucket_region was set.
+        if let Some(bucket_region) = &self.bucket_region {
+            // Build bucket config, using location constraint if necessary
+            let bucket_config = CreateBucketConfiguration::builder()
+                .set_location_constraint(Some(bucket_region.clone()))
+                .build();
+
+            builder = builder.create_bucket_configuration(bucket_config);
+        }
+
+        if let Some(max) = self
+            .container_policy
+            .as_ref()
+            .and_then(|policy| policy.max_containers)
+        {
+            self.ensure_container_quota(bucket, max).await?;
+        }
+
+        let created = match builder.bucket(bucket).send().await {
+            Ok(CreateBucketOutput { location, .. }) => {
+                debug!(?location, "bucket created");
+                true
+            }
+            Err(se) => match se.into_service_error() {
+                CreateBucketError::BucketAlreadyOwnedByYou(..) => false,
+                err => {
+                    error!(?err, code = err.code(), "failed to create bucket");
+                    bail!(anyhow!(self.request_error(err)).context("failed to create bucket"))
+                }
+            },
+        };
+
+        if let Some(PublicAccessBlockConfig {
+            block_public_acls,
+            ignore_public_acls,
+            block_public_policy,
+            restrict_public_buckets,
+        }) = &self.public_access_block
+        {
+            if let Err(err) = self
+                .clients
+                .s3
+                .put_public_access_block()
+                .bucket(bucket)
+                .public_access_block_configuration(
+                    PublicAccessBlockConfiguration::builder()
+                        .block_public_acls(*block_public_acls)
+                        .ignore_public_acls(*ignore_public_acls)
+                        .block_public_policy(*block_public_policy)
+                        .restrict_public_buckets(*restrict_public_buckets)
+                        .build(),
+                )
+                .send()
+                .await
+            {
+                // a bucket created by the link must not be left without its public access block
+                if created {
+                    self.delete_created_bucket(bucket, "bucket without public access block")
+                        .await;
+                }
+                bail!(anyhow!(self.request_error(err)).context("failed to put public access block"))
+            }
+            debug!("public access block applied");
+        }
+
+        // tags of existing buckets, e.g. for cost allocation, are never replaced
+        match &self.container_policy {
+            Some(ContainerPolicyConfig { tags, .. }) if created && !tags.is_empty() => {
+                let tags = tags
+                    .iter()
+                    .map(|(key, value)| Tag::builder().key(key).value(value).build())
+                    .collect::<Result<_, _>>()
+                    .context("failed to build tag")?;
+                if let Err(err) = self
+                    .clients
+                    .s3
+                    .put_bucket_tagging()
+                    .bucket(bucket)
+                    .tagging(
+                        Tagging::builder()
+                            .set_tag_set(Some(tags))
+                            .build()
+                            .context("failed to build tagging")?,
+                    )
+                    .send()
+                    .await
+                {
+                    // untagged buckets would not count against the quota of the link
+                    self.delete_created_bucket(bucket, "bucket without tags")
+                        .await;
+                    bail!(anyhow!(self.request_error(err)).context("failed to put bucket tagging"))
+                }
+                debug!("bucket tagged");
+            }
+            _ => {}
+        }
+
+        Ok(())
+    }
+
+    /// Delete the bucket `bucket` the link just created, which was not set up completely, as
+    /// described by `what` in logs
+    async fn delete_created_bucket(&self, bucket: &str, what: &str) {
+        match self.clients.s3.delete_bucket().bucket(bucket).send().await {
+            Ok(_) => debug!(bucket, "{what} deleted"),
+            Err(err) => error!(%err, bucket, "failed to delete {what}"),
+        }
+    }
+
+    /// Whether the container policy of the link allows creating the container `bucket`
+    fn container_name_allowed(&self, bucket: &str) -> bool {
+        self.container_policy
+            .as_ref()
+            .and_then(|policy| policy.name_pattern.as_deref())
+            .is_none_or(|pattern| glob_matches(pattern, bucket))
+    }
+
+    /// Refuse to create the bucket `bucket`, if the link created `max` buckets already, which are
+    /// those tagged with its identity ([`LINK_TAG`]) among the buckets allowed by its container
+    /// policy
+    ///
+    /// Buckets are counted before they are created, so concurrent creates may exceed the limit.
+    async fn ensure_container_quota(&self, bucket: &str, max: usize) -> anyhow::Result<()> {
+        let link = self
+            .container_policy
+            .as_ref()
+            .and_then(|policy| policy.tags.get(LINK_TAG))
+            .context("buckets created by the link are not tagged with its identity")?;
+        let buckets = self.allowed_buckets().await?;
+        // creating an existing bucket is a no-op
+        if buckets.iter().any(|name| name == bucket) {
+            return Ok(());
+        }
+        let created = futures::stream::iter(buckets)
+            .map(|name| async move {
+                let tag = self.bucket_tag(&name, LINK_TAG).await?;
+                anyhow::Ok(tag.as_ref() == Some(link))
+            })
+            .buffer_unordered(MAX_CONCURRENT_HEADS)
+            .try_fold(0, |n, created| {
+                futures::future::ready(Ok(n + usize::from(created)))
+            })
+            .await?;
+        if created >= max {
+            bail!(BackendError::new(
+                ErrorKind::PreconditionFailed,
+                format!(
+                    "link may create at most {max} container(s), refusing to create [{bucket}]"
+                )
+            ))
+        }
+        Ok(())
+    }
+
+    /// List the buckets allowed by the container policy of the link
+    async fn allowed_buckets(&self) -> anyhow::Result<Vec<String>> {
+        let mut buckets = Vec::new();
+        let mut pages = self.clients.s3.list_buckets().into_paginator().send();
+        while let Some(page) = pages.next().await {
+            let page = page
+                .map_err(|err| self.request_error(err))
+                .context("failed to list buckets")?;
+            buckets.extend(
+                page.buckets
+                    .unwrap_or_default()
+                    .into_iter()
+                    .filter_map(|bucket| bucket.name)
+                    .filter(|name| self.container_name_allowed(name)),
+            );
+        }
+        Ok(buckets)
+    }
+
+    /// Look up the value of the tag `key` of the bucket `bucket`, if any
+    async fn bucket_tag(&self, bucket: &str, key: &str) -> anyhow::Result<Option<String>> {
+        match self
+            .clients
+            .s3
+            .get_bucket_tagging()
+            .bucket(bucket)
+            .send()
+            .await
+        {
+            Ok(out) => Ok(out
+                .tag_set
+                .into_iter()
+                .find(|tag| tag.key == key)
+                .map(|tag| tag.value)),
+            Err(se) if se.code() == Some("NoSuchTagSet") => Ok(None),
+            Err(se) => bail!(anyhow!(self.request_error(se))
+                .context(format!("failed to get tagging of bucket [{bucket}]"))),
+        }
+    }
+
+    /// Refuse writes to publicly accessible buckets, unless allowed for this link
+    ///
+    /// The check is only performed when a public access block was configured for the link.
+    /// Buckets are public if their policy makes them so or their ACL grants access to anyone or
+    /// any AWS account, even if the public access block of the bucket ignores public ACLs.
+    /// Buckets found not to be public are not checked again for [`PUBLIC_ACCESS_CHECK_TTL`].
+    #[instrument(level = "debug", skip(self))]
+    pub async fn ensure_bucket_not_public(&self, bucket: &str) -> anyhow::Result<()> {
+        if self.public_access_block.is_none() || self.allow_public_buckets {
+            return Ok(());
+        }
+        if self
+            .private_buckets
+            .lock()
+            .await
+            .get(bucket)
+            .is_some_and(|checked_at| checked_at.elapsed() < PUBLIC_ACCESS_CHECK_TTL)
+        {
+            return Ok(());
+        }
+        match self
+            .s3(bucket)
+            .get_bucket_policy_status()
+            .bucket(bucket)
+            .send()
+            .await
+        {
+            Ok(out) => {
+                if out
+                    .policy_status()
+                    .and_then(PolicyStatus::is_public)
+                    .unwrap_or_default()
+                {
+                    bail!("bucket [{bucket}] is publicly accessible, refusing to write")
+                }
+            }
+            // buckets without a policy cannot be made public by one
+            Err(se) if se.code() == Some("NoSuchBucketPolicy") => {}
+            Err(se) => {
+                error!(%se, code = se.code(), "failed to get bucket policy status");
+                bail!(anyhow!(self.request_error(se)).context("failed to get bucket policy status"))
+            }
+        }
+        let acl = self
+            .s3(bucket)
+            .get_bucket_acl()
+            .bucket(bucket)
+            .send()
+            .await
+            .map_err(|err| self.request_error(err))
+            .context("failed to get bucket ACL")?;
+        if acl.grants().iter().any(is_public_grant) {
+            bail!("bucket [{bucket}] is publicly accessible by its ACL, refusing to write")
+        }
+        self.private_buckets
+            .lock()
+            .await
+            .insert(bucket.to_string(), Instant::now());
+        Ok(())
+    }
+
+    /// Refuse `operation`, which deletes or overwrites objects, if the link is immutable
+    pub fn ensure_mutable(&self, operation: &str) -> anyhow::Result<()> {
+        if self.immutable {
+            bail!(BackendError::new(
+                ErrorKind::PermissionDenied,
+                format!("link is immutable, refusing to {operation}")
+            ))
+        }
+        Ok(())
+    }
+
+    /// Refuse writes to the object `key` of `bucket`, if it exists and the link is immutable
+    ///
+    /// The object is looked up before it is written, so concurrent writes of a new object may
+    /// still overwrite each other.
+    #[instrument(level = "debug", skip(self))]
+    pub async fn ensure_new_object(&self, bucket: &str, key: &str) -> anyhow::Result<()> {
+        if self.immutable && self.has_object(bucket, key).await? {
+            bail!(BackendError::new(
+                ErrorKind::PermissionDenied,
+                format!("link is immutable, refusing to overwrite object [{bucket}/{key}]")
+            ))
+        }
+        Ok(())
+    }
+
+    /// List the versions `<key>@<n>` of the object `key` of `bucket` written by a link with
+    /// write-once keys, as their numbers and keys ordered by number
+    async fn key_versions(&self, bucket: &str, key: &str) -> anyhow::Result<Vec<(u64, String)>> {
+        let prefix = format!("{key}{KEY_VERSION_SEPARATOR}");
+        let mut versions: Vec<_> = self
+            .list_prefixes(bucket, &[prefix])
+            .await?
+            .into_iter()
+            .filter_map(|candidate| Some((key_version(key, &candidate)?, candidate)))
+            .collect();
+        versions.sort_unstable();
+        Ok(versions)
+    }
+
+    /// List the keys of the objects of `bucket` starting with any of `prefixes`, in order
+    ///
+    /// Stores other than S3 list all objects of the container, which are filtered by prefix.
+    async fn list_prefixes(
+        &self,
+        bucket: &str,
+        prefixes: &[String],
+    ) -> anyhow::Result<Vec<String>> {
+        if self.backend.is_some() {
+            let mut keys: Vec<_> = self
+                .list_container_objects(bucket, None, None)
+                .await?
+                .filter(|key| prefixes.iter().any(|prefix| key.starts_with(prefix)))
+                .collect();
+            keys.sort_unstable();
+            return Ok(keys);
+        }
+        let routed = self.router.buckets(bucket);
+        let routed = if routed.is_empty() {
+            vec![bucket]
+        } else {
+            routed
+        };
+        // listings are owned by the futures, which borrowing them would keep from being `Send`
+        let listings: Vec<_> = prefixes
+            .iter()
+            .flat_map(|prefix| {
+                routed
+                    .iter()
+                    .map(|routed| (routed.to_string(), prefix.clone()))
+            })
+            .collect();
+        let keys: Vec<_> = futures::stream::iter(listings)
+            .map(|(routed, prefix)| async move {
+                let objects = self.list_objects(&routed, Some(&prefix)).await?;
+                anyhow::Ok(
+                    objects
+                        .into_iter()
+                        .filter_map(|Object { key, .. }| key)
+                        .filter(|key| self.route(bucket, key) == routed)
+                        .collect::<Vec<_>>(),
+                )
+            })
+            .buffered(MAX_CONCURRENT_LISTINGS)
+            .try_collect()
+            .await?;
+        let mut keys: Vec<_> = keys.into_iter().flatten().collect();
+        keys.sort_unstable();
+        Ok(keys)
+    }
+
+    /// List the keys of the objects of `bucket` in the time partitions under `prefix`, which are
+    /// laid out by UTC date like `<prefix>yyyy/mm/dd/<key>`, of the days from `since` to `until`
+    ///
+    /// Only the prefixes of the days within the window are listed, whole months and years by
+    /// their prefix, rather than the whole container.
+    #[instrument(level = "debug", skip(self))]
+    pub async fn list_time_window(
+        &self,
+        bucket: &str,
+        prefix: &str,
+        since: SystemTime,
+        until: SystemTime,
+    ) -> anyhow::Result<Vec<String>> {
+        ensure!(since <= until, "time window must not end before it starts");
+        let prefixes: Vec<_> = partitions::window_prefixes(since, until)
+            .into_iter()
+            .map(|partition| format!("{prefix}{partition}"))
+            .collect();
+        debug!(bucket, prefixes = prefixes.len(), "listing time partitions");
+        self.list_prefixes(bucket, &prefixes).await
+    }
+
+    /// Key the latest version of the object `key` of `bucket` is stored at, which is `key` itself
+    /// unless the link has write-once keys and the object was overwritten
+    #[instrument(level = "debug", skip(self))]
+    pub async fn latest_key<'a>(&self, bucket: &str, key: &'a str) -> anyhow::Result<Cow<'a, str>> {
+        if !self.write_once_keys {
+            return Ok(Cow::Borrowed(key));
+        }
+        Ok(match self.key_versions(bucket, key).await?.pop() {
+            Some((_, latest)) => Cow::Owned(latest),
+            None => Cow::Borrowed(key),
+        })
+    }
+
+    /// Key a write of the object `key` of `bucket` is stored at, which is the next version
+    /// `<key>@<n>` of the object if the link has write-once keys and the object exists
+    ///
+    /// Versions are numbered by looking up the latest one, so concurrent writes of an object may
+    /// still overwrite each other.
+    #[instrument(level = "debug", skip(self))]
+    pub async fn write_once_key<'a>(
+        &self,
+        bucket: &str,
+        key: &'a str,
+    ) -> anyhow::Result<Cow<'a, str>> {
+        if !self.write_once_keys {
+            return Ok(Cow::Borrowed(key));
+        }
+        let next = match self.key_versions(bucket, key).await?.pop() {
+            Some((n, _)) => n + 1,
+            None if self.has_object(bucket, key).await? => 1,
+            None => return Ok(Cow::Borrowed(key)),
+        };
+        Ok(Cow::Owned(format!("{key}{KEY_VERSION_SEPARATOR}{next}")))
+    }
+
+    /// Add the keys of all versions of objects to their keys, if the link has write-once keys, so
+    /// that deletes of objects delete all of their versions
+    pub async fn with_key_versions(
+        &self,
+        bucket: &str,
+        keys: Vec<String>,
+    ) -> anyhow::Result<Vec<String>> {
+        if !self.write_once_keys {
+            return Ok(keys);
+        }
+        let mut all = Vec::with_capacity(keys.len());
+        for key in keys {
+            let versions = self.key_versions(bucket, &key).await?;
+            all.push(key);
+            all.extend(versions.into_iter().map(|(_, version)| version));
+        }
+        Ok(all)
+    }
+
+    #[instrument(level = "debug", skip(self))]
+    pub async fn get_container_info(&self, bucket: &str) -> anyhow::Result<ContainerInfo> {
+        if let Some(backend) = &self.backend {
+            return backend.get_container_info(bucket).await;
+        }
+        match self.s3(bucket).head_bucket().bucket(bucket).send().await {
+            Ok(_) => Ok(ContainerInfo {
+                // unfortunately, HeadBucketOut doesn't include any information
+                // so we can't fill in creation date
+                created_at: 0,
+            }),
+            Err(se) => match se.into_service_error() {
+                HeadBucketError::NotFound(_) => {
+                    error!("bucket [{bucket}] not found");
+                    bail!(BackendError::new(
+                        ErrorKind::NotFound,
+                        format!("bucket [{bucket}] not found")
+                    ))
+                }
+                err => {
+                    error!(?err, code = err.code(), "unexpected error");
+                    bail!(anyhow!(self.request_error(err)).context("unexpected error"));
+                }
+            },
+        }
+    }
+
+    /// Statistics of the objects of a bucket from its daily S3 storage metrics in CloudWatch,
+    /// which are unknown for buckets outside of AWS or if its metrics cannot be read
+    ///
+    /// Unknown statistics are computed by listing the objects of the bucket instead, if the link
+    /// is configured to.
+    #[instrument(level = "debug", skip(self))]
+    pub async fn get_container_stats(&self, bucket: &str) -> anyhow::Result<ContainerStats> {
+        self.get_container_info(bucket).await?;
+        #[cfg(feature = "storage-metrics")]
+        let stats = match &self.clients.cloudwatch {
+            Some(cloudwatch) => match metrics::storage_metrics(cloudwatch, bucket).await {
+                Ok(stats) => stats,
+                Err(err) => {
+                    warn!(?err, "failed to get storage metrics of bucket");
+                    ContainerStats::default()
+                }
+            },
+            None => ContainerStats::default(),
+        };
+        #[cfg(not(feature = "storage-metrics"))]
+        let stats = ContainerStats::default();
+        match self.listing_stats_ttl {
+            Some(ttl) if stats.object_count.is_none() || stats.total_bytes.is_none() => {
+                Ok(self.listed_stats(bucket, ttl).await)
+            }
+            _ => Ok(stats),
+        }
+    }
+
+    /// Statistics of a bucket computed by listing all its objects, which are cached for `ttl`
+    ///
+    /// Statistics are computed in the background, so that listing large buckets does not delay
+    /// requests: they are unknown until they were computed once, after which stale ones are
+    /// returned while they are recomputed.
+    #[cfg(feature = "caching")]
+    async fn listed_stats(&self, bucket: &str, ttl: Duration) -> ContainerStats {
+        let mut cache = self.listed_stats.lock().await;
+        let entry = cache.entry(bucket.to_string()).or_default();
+        let fresh = entry.stats.is_some_and(|(_, at)| at.elapsed() < ttl);
+        if !fresh && !entry.computing {
+            entry.computing = true;
+            let client = self.clone();
+            let bucket = bucket.to_string();
+            self.spawn(async move {
+                let stats = client.count_objects(&bucket).await;
+                let mut cache = client.listed_stats.lock().await;
+                let entry = cache.entry(bucket).or_default();
+                entry.computing = false;
+                match stats {
+                    Ok(stats) => entry.stats = Some((stats, Instant::now())),
+                    Err(err) => warn!(?err, "failed to compute statistics of bucket"),
+                }
+            });
+        }
+        entry.stats.map(|(stats, _)| stats).unwrap_or_default()
+    }
+
+    /// Statistics of a bucket computed by listing all its objects on every request, since the
+    /// provider was built without the `caching` feature
+    #[cfg(not(feature = "caching"))]
+    async fn listed_stats(&self, bucket: &str, _ttl: Duration) -> ContainerStats {
+        self.count_objects(bucket).await.unwrap_or_else(|err| {
+            warn!(?err, "failed to compute statistics of bucket");
+            ContainerStats::default()
+        })
+    }
+
+    /// Count the objects of a bucket and their total size by listing them
+    #[instrument(level = "debug", skip(self))]
+    async fn count_objects(&self, bucket: &str) -> anyhow::Result<ContainerStats> {
+        let (count, bytes) = self.sum_objects(bucket, None).await?;
+        Ok(ContainerStats {
+            object_count: Some(count),
+            total_bytes: Some(bytes),
+            updated_at: Some(
+                SystemTime::now()
+                    .duration_since(SystemTime::UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_secs(),
+            ),
+        })
+    }
+
+    /// Count the objects of a bucket with keys starting with `prefix`, if set, and their total
+    /// size by listing them
+    async fn sum_objects(&self, bucket: &str, prefix: Option<&str>) -> anyhow::Result<(u64, u64)> {
+        let (mut count, mut bytes) = (0u64, 0u64);
+        self.for_each_object(bucket, prefix, |Object { size, .. }| {
+            count += 1;
+            bytes = bytes.saturating_add(size.unwrap_or_default().try_into().unwrap_or(0));
+        })
+        .await?;
+        Ok((count, bytes))
+    }
+
+    /// Call `f` with each object of a bucket with a key starting with `prefix`, if set, as the
+    /// pages of its listing are received
+    async fn for_each_object(
+        &self,
+        bucket: &str,
+        prefix: Option<&str>,
+        mut f: impl FnMut(Object),
+    ) -> anyhow::Result<()> {
+        let mut pages = self
+            .s3(bucket)
+            .list_objects_v2()
+            .bucket(bucket)
+            .set_prefix(prefix.map(Into::into))
+            .into_paginator()
+            .send();
+        while let Some(page) = pages.next().await {
+            let page = page
+                .map_err(|err| self.request_error(err))
+                .context("failed to list objects")?;
+            page.contents
+                .unwrap_or_default()
+                .into_iter()
+                .for_each(&mut f);
+        }
+        Ok(())
+    }
+
+    /// Count the objects of a bucket and their total size per first-level prefix under `prefix`,
+    /// i.e. the prefixes up to the next `/`, like `du -s <prefix>*/`
+    ///
+    /// The first-level prefixes are found by a listing with a delimiter and then listed
+    /// concurrently. Objects directly under `prefix` are counted as `prefix` itself, which is
+    /// listed first if there are any.
+    #[instrument(level = "debug", skip(self))]
+    pub async fn get_prefix_stats(
+        &self,
+        bucket: &str,
+        prefix: &str,
+    ) -> anyhow::Result<Vec<PrefixStats>> {
+        ensure!(
+            self.backend.is_none(),
+            "prefix statistics are only supported by the S3 backend"
+        );
+        let mut direct = PrefixStats {
+            prefix: prefix.to_string(),
+            ..PrefixStats::default()
+        };
+        let mut prefixes = Vec::new();
+        let mut pages = self
+            .s3(bucket)
+            .list_objects_v2()
+            .bucket(bucket)
+            .prefix(prefix)
+            .delimiter("/")
+            .into_paginator()
+            .send();
+        while let Some(page) = pages.next().await {
+            let page = page
+                .map_err(|err| self.request_error(err))
+                .context("failed to list objects")?;
+            for Object { size, .. } in page.contents.unwrap_or_default() {
+                direct.object_count += 1;
+                direct.total_bytes = direct
+                    .total_bytes
+                    .saturating_add(size.unwrap_or_default().try_into().unwrap_or(0));
+            }
+            prefixes.extend(
+                page.common_prefixes
+                    .unwrap_or_default()
+                    .into_iter()
+                    .filter_map(|common| common.prefix),
+            );
+        }
+        let mut stats: Vec<_> = futures::stream::iter(prefixes)
+            .map(|prefix| async move {
+                let (object_count, total_bytes) = self.sum_objects(bucket, Some(&prefix)).await?;
+                anyhow::Ok(PrefixStats {
+                    prefix,
+                    object_count,
+                    total_bytes,
+                })
+            })
+            .buffered(MAX_CONCURRENT_LISTINGS)
+            .try_collect()
+            .await?;
+        if direct.object_count > 0 {
+            stats.insert(0, direct);
+        }
+        Ok(stats)
+    }
+
+    /// The `n` largest objects of a bucket with keys starting with `prefix`, largest first
+    #[instrument(level = "debug", skip(self))]
+    pub async fn get_largest_objects(
+        &self,
+        bucket: &str,
+        prefix: &str,
+        n: usize,
+    ) -> anyhow::Result<Vec<ObjectSummary>> {
+        ensure!(
+            self.backend.is_none(),
+            "object reports are only supported by the S3 backend"
+        );
+        ensure!(
+            n <= MAX_REPORTED_OBJECTS,
+            "at most {MAX_REPORTED_OBJECTS} objects can be reported"
+        );
+        let mut top = Top::new(n);
+        self.for_each_object(bucket, Some(prefix), |object| {
+            if let Some(summary) = ObjectSummary::new(object) {
+                top.push((summary.size, Reverse(summary.key), summary.last_modified));
+            }
+        })
+        .await?;
+        Ok(top
+            .into_sorted_vec()
+            .into_iter()
+            .map(|(size, Reverse(key), last_modified)| ObjectSummary {
+                key,
+                size,
+                last_modified,
+            })
+            .collect())
+    }
+
+    /// The objects of a bucket with keys starting with `prefix`, which were last modified before
+    /// `before`, least recently modified first, at most `limit` of them
+    #[instrument(level = "debug", skip(self))]
+    pub async fn get_stale_objects(
+        &self,
+        bucket: &str,
+        prefix: &str,
+        before: SystemTime,
+        limit: usize,
+    ) -> anyhow::Result<Vec<ObjectSummary>> {
+        ensure!(
+            self.backend.is_none(),
+            "object reports are only supported by the S3 backend"
+        );
+        ensure!(
+            limit <= MAX_REPORTED_OBJECTS,
+            "at most {MAX_REPORTED_OBJECTS} objects can be reported"
+        );
+        let before = before
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let mut top = Top::new(limit);
+        self.for_each_object(bucket, Some(prefix), |object| {
+            match ObjectSummary::new(object) {
+                Some(summary) if summary.last_modified < before => {
+                    top.push(Reverse((summary.last_modified, summary.key, summary.size)))
+                }
+                _ => {}
+            }
+        })
+        .await?;
+        Ok(top
+            .into_sorted_vec()
+            .into_iter()
+            .map(|Reverse((last_modified, key, size))| ObjectSummary {
+                key,
+                size,
+                last_modified,
+            })
+            .collect())
+    }
+
+    /// Compare the objects of `source_bucket` under `source_prefix` to those of `target_bucket`
+    /// under `target_prefix` by their keys relative to the prefixes, sizes and ETags, returning
+    /// the changes, which would make the target match the source, ordered by key
+    #[instrument(level = "debug", skip(self))]
+    pub async fn diff_prefixes(
+        &self,
+        source_bucket: &str,
+        source_prefix: &str,
+        target_bucket: &str,
+        target_prefix: &str,
+    ) -> anyhow::Result<Vec<Change>> {
+        ensure!(
+            self.backend.is_none(),
+            "diffs are only supported by the S3 backend"
+        );
+        let (source, target) = futures::try_join!(
+            self.list_objects(source_bucket, Some(source_prefix)),
+            self.list_objects(target_bucket, Some(target_prefix)),
+        )?;
+        Ok(diff_changes(
+            diff_entries(source_prefix, source),
+            diff_entries(target_prefix, target),
+        ))
+    }
+
+    /// List the keys of objects of a bucket
+    ///
+    /// Listings of all objects use the latest S3 Inventory report of the bucket, if the link is
+    /// configured to, which may not include objects written since the report was created.
+    #[instrument(level = "debug", skip(self))]
+    pub async fn list_container_objects(
+        &self,
+        bucket: &str,
+        limit: Option<u64>,
+        offset: Option<u64>,
+    ) -> anyhow::Result<impl Iterator<Item = String>> {
+        if let Some(backend) = &self.backend {
+            return Ok(backend
+                .list_container_objects(bucket, limit, offset)
+                .await?
+                .into_iter());
+        }
+        let routed = self.router.buckets(bucket);
+        if !routed.is_empty() {
+            let mut keys = Vec::new();
+            for routed in routed {
+                keys.extend(
+                    self.list_objects(routed, None)
+                        .await?
+                        .into_iter()
+                        .filter_map(|Object { key, .. }| key)
+                        .filter(|key| self.route(bucket, key) == routed),
+                );
+            }
+            keys.sort_unstable();
+            return Ok(keys
+                .into_iter()
+                .skip(offset.unwrap_or_default().try_into().unwrap_or(usize::MAX))
+                .take(limit.unwrap_or(u64::MAX).try_into().unwrap_or(usize::MAX))
+                .collect::<Vec<_>>()
+                .into_iter());
+        }
+        if limit.is_none() {
+            match self.inventory_keys(bucket).await {
+                Ok(Some(keys)) => {
+                    return Ok(keys
+                        .into_iter()
+                        .skip(offset.unwrap_or_default().try_into().unwrap_or(usize::MAX))
+                        .collect::<Vec<_>>()
+                        .into_iter())
+                }
+                Ok(None) => {}
+                Err(err) => warn!(?err, "failed to list objects from inventory, listing them"),
+            }
+        }
+        // TODO: Stream names
+        let mut retries = 0;
+        loop {
+            let (keys, truncated) = match self
+                .read(bucket, |s3| {
+                    s3.list_objects_v2()
+                        .bucket(bucket)
+                        .set_max_keys(limit.map(|limit| limit.try_into().unwrap_or(i32::MAX)))
+                        .send()
+                })
+                .await
+            {
+                Ok(ListObjectsV2Output {
+                    contents,
+                    is_truncated,
+                    ..
+                }) => (
+                    contents
+                        .into_iter()
+                        .flatten()
+                        .filter_map(|Object { key, .. }| key)
+                        .collect::<Vec<_>>(),
+                    is_truncated.unwrap_or_default(),
+                ),
+                Err(SdkError::ServiceError(err)) => {
+                    error!(?err, "service error");
+                    bail!(anyhow!("{err:?}").context("service error"))
+                }
+                Err(err) => {
+                    error!(%err, code = err.code(), "unexpected error");
+                    bail!(anyhow!("{err:?}").context("unexpected error"))
+                }
+            };
+            // only complete listings are expected to include all recently written objects
+            if let (Some(recent_writes), None, false) = (&self.recent_writes, limit, truncated) {
+                let missing = recent_writes.missing(bucket, &keys);
+                if missing > 0 && retries < recent_writes.retries {
+                    retries += 1;
+                    debug!(
+                        missing,
+                        retries, "listing is missing recently written objects, retrying"
+                    );
+                    tokio::time::sleep(recent_writes.delay).await;
+                    continue;
+                }
+            }
+            return Ok(keys
+                .into_iter()
+                .skip(offset.unwrap_or_default().try_into().unwrap_or(usize::MAX))
+                .take(limit.unwrap_or(u64::MAX).try_into().unwrap_or(usize::MAX))
+                .collect::<Vec<_>>()
+                .into_iter());
+        }
+    }
+
+    /// Copy an object, which is a no-op if the source and destination are identical
+    #[instrument(level = "debug", skip(self))]
+    pub async fn copy_object(
+        &self,
+        src_bucket: &str,
+        src_key: &str,
+        dest_bucket: &str,
+        dest_key: &str,
+    ) -> anyhow::Result<()> {
+        if let Some(backend) = &self.backend {
+            return backend
+                .copy_object(src_bucket, src_key, dest_bucket, dest_key)
+                .await;
+        }
+        self.copy_object_with_class(src_bucket, src_key, dest_bucket, dest_key, None)
+            .await
+    }
+
+    /// Copy an object, storing the copy with `storage_class` or the default storage class of
+    /// the destination bucket, if `None`
+    async fn copy_object_with_class(
+        &self,
+        src_bucket: &str,
+        src_key: &str,
+        dest_bucket: &str,
+        dest_key: &str,
+        storage_class: Option<StorageClass>,
+    ) -> anyhow::Result<()> {
+        let src_bucket = self.route(src_bucket, src_key);
+        let dest_bucket = self.route(dest_bucket, dest_key);
+        // S3 rejects copying an object onto itself without changing its metadata
+        if src_bucket == dest_bucket && src_key == dest_key {
+            debug!("source and destination are identical, skipping copy");
+            return Ok(());
+        }
+        let copy_source = if is_access_point_arn(src_bucket) {
+            format!("{src_bucket}/object/{src_key}")
+        } else {
+            format!("{src_bucket}/{src_key}")
+        };
+        self.s3(dest_bucket)
+            .copy_object()
+            .copy_source(copy_source)
+            .bucket(dest_bucket)
+            .key(dest_key)
+            .set_storage_class(storage_class)
+            .send()
+            .await
+            .map_err(|err| self.request_error(err))
+            .context("failed to copy object")?;
+        Ok(())
+    }
+
+    /// Move an object by copying it and deleting the source, which is a no-op if the source and
+    /// destination are identical
+    ///
+    /// S3 has no rename, so this is the case even within a bucket. The copy is only made if the
+    /// source does not change in the meantime, and its size and ETag are verified before the
+    /// source is deleted. A copy failing verification is deleted again. If the source cannot be
+    /// deleted, the error reports that both objects exist.
+    #[instrument(level = "debug", skip(self))]
+    pub async fn move_object(
+        &self,
+        src_bucket: &str,
+        src_key: &str,
+        dest_bucket: &str,
+        dest_key: &str,
+    ) -> anyhow::Result<()> {
+        if let Some(backend) = &self.backend {
+            return backend
+                .move_object(src_bucket, src_key, dest_bucket, dest_key)
+                .await;
+        }
+        let src_bucket = self.route(src_bucket, src_key);
+        let dest_bucket = self.route(dest_bucket, dest_key);
+        if src_bucket == dest_bucket && src_key == dest_key {
+            debug!("source and destination are identical, skipping move");
+            return Ok(());
+        }
+        let src = self
+            .s3(src_bucket)
+            .head_object()
+            .bucket(src_bucket)
+            .key(src_key)
+            .send()
+            .await
+            .map_err(|err| self.request_error(err))
+            .context("failed to head source object")?;
+        let copy_source = if is_access_point_arn(src_bucket) {
+            format!("{src_bucket}/object/{src_key}")
+        } else {
+            format!("{src_bucket}/{src_key}")
+        };
+        self.s3(dest_bucket)
+            .copy_object()
+            .copy_source(copy_source)
+            .set_copy_source_if_match(src.e_tag.clone())
+            .bucket(dest_bucket)
+            .key(dest_key)
+            .send()
+            .await
+            .map_err(|err| self.request_error(err))
+            .context("failed to copy object")?;
+        let verified = match self
+            .s3(dest_bucket)
+            .head_object()
+            .bucket(dest_bucket)
+            .key(dest_key)
+            .send()
+            .await
+        {
+            Ok(dest) => verify_copy(&src, &dest, self.quirks.md5_etags),
+            Err(err) => Err(anyhow!(self.request_error(err)).context("failed to head copy")),
+        };
+        if let Err(err) = verified {
+            if let Err(err) = self.delete_object(dest_bucket, dest_key.to_string()).await {
+                error!(?err, "failed to delete unverified copy");
+            }
+            return Err(err.context("failed to verify copy, source object was kept"));
+        }
+        self.delete_object(src_bucket, src_key.to_string())
+            .await
+            .with_context(|| {
+                format!(
+                    "object was copied to `{dest_bucket}/{dest_key}`, but the source object \
+                     `{src_bucket}/{src_key}` could not be deleted, both objects exist"
+                )
+            })
+    }
+
+    /// Delete an object, along with its copies in the buckets of size tiers, if any
+    #[instrument(level = "debug", skip(self, object))]
+    pub async fn delete_object(&self, container: &str, object: String) -> anyhow::Result<()> {
+        if let Some(backend) = &self.backend {
+            return backend.delete_objects(container, vec![object]).await;
+        }
+        let container = self.route(container, &object);
+        let stale = self.tier_locations(container, &object);
+        self.delete_key(container, object).await?;
+        self.delete_stale_copies(stale).await;
+        Ok(())
+    }
+
+    /// Delete the object `key` of `bucket` only
+    async fn delete_key(&self, bucket: &str, key: String) -> anyhow::Result<()> {
+        self.forget_writes(bucket, [key.as_str()]);
+        self.s3(bucket)
+            .delete_object()
+            .bucket(bucket)
+            .key(key)
+            .set_bypass_governance_retention(self.bypass_governance_retention.then_some(true))
+            .send()
+            .await
+            .map_err(|err| self.request_error(err))
+            .context("failed to delete object")?;
+        Ok(())
+    }
+
+    /// Permanently delete a specific version of an object. `mfa` in `options` is sent with the
+    /// delete, `bypass_governance_retention` is combined with the one configured for the link.
+    #[instrument(level = "debug", skip(self, options))]
+    pub async fn delete_object_version(
+        &self,
+        bucket: &str,
+        key: &str,
+        version_id: &str,
+        options: DeleteOptions,
+    ) -> anyhow::Result<()> {
+        let bucket = self.route(bucket, key);
+        let bypass_governance_retention =
+            options.bypass_governance_retention || self.bypass_governance_retention;
+        self.s3(bucket)
+            .delete_object()
+            .bucket(bucket)
+            .key(key)
+            .version_id(version_id)
+            .set_mfa(options.mfa)
+            .set_bypass_governance_retention(bypass_governance_retention.then_some(true))
+            .send()
+            .await
+            .map_err(|err| self.request_error(err))
+            .context("failed to delete object version")?;
+        Ok(())
+    }
+
+    /// Delete objects, failing if any of them could not be deleted
+    #[instrument(level = "debug", skip(self, objects))]
+    pub async fn delete_objects(
+        &self,
+        container: &str,
+        objects: impl IntoIterator<Item = String>,
+    ) -> anyhow::Result<()> {
+        if let Some(backend) = &self.backend {
+            return backend
+                .delete_objects(container, objects.into_iter().collect())
+                .await;
+        }
+        let failures = self.delete_objects_detailed(container, objects).await?;
+        if let Some(DeleteFailure { key, code, message }) = failures.first() {
+            bail!(
+                "failed to delete {} object(s), first failure: `{key}` ({}: {})",
+                failures.len(),
+                code.as_deref().unwrap_or("unknown"),
+                message.as_deref().unwrap_or("no message"),
+            )
+        }
+        Ok(())
+    }
+
+    /// Delete objects in batches sent concurrently, returning the objects, which could not be
+    /// deleted, along with the reasons reported by S3
+    ///
+    /// Objects failing with transient errors, like throttling, are retried with jittered
+    /// exponential backoff up to `batch_retry_attempts` times before being reported.
+    #[instrument(level = "debug", skip(self, objects))]
+    pub async fn delete_objects_detailed(
+        &self,
+        container: &str,
+        objects: impl IntoIterator<Item = String>,
+    ) -> anyhow::Result<Vec<DeleteFailure>> {
+        let mut objects: Vec<_> = objects.into_iter().collect();
+        if objects.is_empty() {
+            debug!("no objects to delete, return");
+            return Ok(Vec::default());
+        }
+        let mut routed = HashMap::<_, Vec<_>>::new();
+        objects.retain(|key| match self.router.route(container, key) {
+            Some(bucket) if bucket != container => {
+                routed.entry(bucket).or_default().push(key.clone());
+                false
+            }
+            _ => true,
+        });
+        let mut failures = Vec::default();
+        for (bucket, keys) in routed {
+            failures.extend(Box::pin(self.delete_objects_detailed(bucket, keys)).await?);
+        }
+        let tiered: Vec<_> = if self.size_tiers.is_empty() {
+            Vec::default()
+        } else {
+            objects
+                .iter()
+                .map(|key| tiered_key(container, key))
+                .collect()
+        };
+        for attempt in 0.. {
+            let batches: Vec<_> = objects
+                .chunks(MAX_DELETE_OBJECTS)
+                .map(|objects| self.delete_batch(container, objects))
+                .collect();
+            let batches: Vec<_> = futures::stream::iter(batches)
+                .buffer_unordered(MAX_CONCURRENT_DELETES)
+                .collect()
+                .await;
+            let mut transient = Vec::default();
+            for batch in batches {
+                for failure in batch? {
+                    if attempt < self.batch_retry_attempts
+                        && failure.code.as_deref().is_some_and(is_transient_error)
+                    {
+                        transient.push(failure.key);
+                    } else {
+                        failures.push(failure);
+                    }
+                }
+            }
+            if transient.is_empty() {
+                break;
+            }
+            let delay = retry_delay(attempt, RandomState::new().build_hasher().finish());
+            debug!(
+                attempt,
+                ?delay,
+                objects = transient.len(),
+                "retrying objects failing with transient errors"
+            );
+            tokio::time::sleep(delay).await;
+            objects = transient;
+        }
+        for bucket in self.tier_buckets(container) {
+            for batch in tiered.chunks(MAX_DELETE_OBJECTS) {
+                match self.delete_batch(bucket, batch).await {
+                    Ok(stale) if stale.is_empty() => {}
+                    Ok(stale) => warn!(
+                        bucket,
+                        objects = stale.len(),
+                        "failed to delete copies of objects in size tier"
+                    ),
+                    Err(err) => {
+                        warn!(
+                            ?err,
+                            bucket, "failed to delete copies of objects in size tier"
+                        );
+                    }
+                }
+            }
+        }
+        Ok(failures)
+    }
+
+    /// Buckets of size tiers other than `bucket`, which objects of `bucket` may be stored in
+    fn tier_buckets(&self, bucket: &str) -> Vec<&str> {
+        let mut buckets: Vec<_> = self
+            .size_tiers
+            .buckets()
+            .into_iter()
+            .map(|tier| self.unalias(tier))
+            .filter(|tier| *tier != bucket)
+            .collect();
+        buckets.dedup();
+        buckets
+    }
+
+    /// Locations of the copies of the object `key` of `bucket`, which may exist in the buckets of
+    /// size tiers other than `bucket`
+    fn tier_locations(&self, bucket: &str, key: &str) -> Vec<(String, String)> {
+        self.tier_buckets(bucket)
+            .into_iter()
+            .map(|tier| (tier.to_string(), tiered_key(bucket, key)))
+            .collect()
+    }
+
+    /// Look up the copy of the object `key` of `bucket` in the bucket of a size tier other than
+    /// `bucket`, returning its location and metadata
+    async fn tiered_object(
+        &self,
+        bucket: &str,
+        key: &str,
+    ) -> anyhow::Result<Option<(String, String, HeadObjectOutput)>> {
+        for (tier, tier_key) in self.tier_locations(bucket, key) {
+            match self
+                .s3(&tier)
+                .head_object()
+                .bucket(&tier)
+                .key(&tier_key)
+                .send()
+                .await
+            {
+                Ok(head) => return Ok(Some((tier, tier_key, head))),
+                Err(se) => match se.into_service_error() {
+                    HeadObjectError::NotFound(_) => {}
+                    err => bail!(anyhow!(self.request_error(err))
+                        .context(format!("failed to look up object in size tier `{tier}`"))),
+                },
+            }
+        }
+        Ok(None)
+    }
+
+    /// Delete copies of an object, which are stale, since it was written to or deleted from
+    /// another location, logging failures
+    async fn delete_stale_copies(&self, locations: Vec<(String, String)>) {
+        for (bucket, key) in locations {
+            if let Err(err) = self.delete_key(&bucket, key).await {
+                warn!(?err, %bucket, "failed to delete stale copy of object in size tier");
+            }
+        }
+    }
+
+    /// Delete up to [`MAX_DELETE_OBJECTS`] objects in a single request
+    async fn delete_batch(
+        &self,
+        container: &str,
+        objects: &[String],
+    ) -> anyhow::Result<Vec<DeleteFailure>> {
+        self.forget_writes(container, objects.iter().map(String::as_str));
+        if !self.quirks.batch_delete {
+            return Ok(self.delete_each(container, objects).await);
+        }
+        let objects = objects
+            .iter()
+            .map(|key| ObjectIdentifier::builder().key(key).build())
+            .collect::<Result<_, _>>()
+            .context("failed to build object identifier list")?;
+        let delete = Delete::builder()
+            .set_objects(Some(objects))
+            .quiet(true)
+            .build()
+            .context("failed to build `delete_objects` command")?;
+        let out = self
+            .s3(container)
+            .delete_objects()
+            .bucket(container)
+            .delete(delete)
+            .set_bypass_governance_retention(self.bypass_governance_retention.then_some(true))
+            .send()
+            .await
+            .map_err(|err| self.request_error(err))
+            .context("failed to delete objects")?;
+        Ok(out
+            .errors
+            .unwrap_or_default()
+            .into_iter()
+            .map(|err| DeleteFailure {
+                key: err.key.unwrap_or_default(),
+                code: err.code,
+                message: err.message,
+            })
+            .collect())
+    }
+
+    /// Delete objects by concurrent single deletes, for stores not supporting multi-object deletes
+    async fn delete_each(&self, container: &str, objects: &[String]) -> Vec<DeleteFailure> {
+        // keys are owned by the futures, which borrowing them would keep from being `Send`
+        futures::stream::iter(objects.to_vec())
+            .map(|key| async move {
+                let err = self
+                    .s3(container)
+                    .delete_object()
+                    .bucket(container)
+                    .key(&key)
+                    .set_bypass_governance_retention(
+                        self.bypass_governance_retention.then_some(true),
+                    )
+                    .send()
+                    .await
+                    .err()?
+                    .into_service_error();
+                Some(DeleteFailure {
+                    key,
+                    code: err.code().map(str::to_string),
+                    message: Some(
+                        err.message()
+                            .map_or_else(|| err.to_string(), str::to_string),
+                    ),
+                })
+            })
+            .buffer_unordered(EMULATED_DELETE_CONCURRENCY)
+            .filter_map(futures::future::ready)
+            .collect()
+            .await
+    }
+
+    /// Delete objects, recording the ETag and version of each object before deleting exactly that
+    /// version, and return the objects, which were deleted according to S3
+    ///
+    /// In versioned buckets, the recorded versions are deleted permanently, rather than hidden by a
+    /// delete marker.
+    #[instrument(level = "debug", skip(self, objects))]
+    pub async fn delete_objects_verified(
+        &self,
+        container: &str,
+        objects: impl IntoIterator<Item = String>,
+    ) -> anyhow::Result<VerifiedDelete> {
+        ensure!(
+            self.backend.is_none(),
+            "verified deletes are only supported by the S3 backend"
+        );
+        let mut verified = VerifiedDelete::default();
+        let heads: Vec<_> = futures::stream::iter(objects)
+            .map(|key| async move {
+                let bucket = self.route(container, &key);
+                let res = self
+                    .s3(bucket)
+                    .head_object()
+                    .bucket(bucket)
+                    .key(&key)
+                    .send()
+                    .await;
+                (bucket, key, res)
+            })
+            .buffer_unordered(MAX_CONCURRENT_HEADS)
+            .collect()
+            .await;
+        let mut found = HashMap::<_, Vec<_>>::new();
+        for (bucket, key, res) in heads {
+            match res.map_err(SdkError::into_service_error) {
+                Ok(HeadObjectOutput {
+                    e_tag,
+                    version_id,
+                    content_length,
+                    ..
+                }) => found.entry(bucket).or_default().push(DeletedObject {
+                    key,
+                    etag: e_tag,
+                    version_id,
+                    size: content_length
+                        .and_then(|v| v.try_into().ok())
+                        .unwrap_or_default(),
+                }),
+                Err(HeadObjectError::NotFound(_)) => verified.missing.push(key),
+                Err(err) => verified.failures.push(DeleteFailure {
+                    key,
+                    code: err.code().map(str::to_string),
+                    message: Some(
+                        err.message()
+                            .map_or_else(|| err.to_string(), str::to_string),
+                    ),
+                }),
+            }
+        }
+        for (bucket, objects) in found {
+            self.forget_writes(bucket, objects.iter().map(|object| object.key.as_str()));
+            for batch in objects.chunks(MAX_DELETE_OBJECTS) {
+                let (deleted, failures) = self.delete_versions(bucket, batch).await?;
+                verified.deleted.extend(deleted);
+                verified.failures.extend(failures);
+            }
+        }
+        Ok(verified)
+    }
+
+    /// Delete the recorded versions of objects, returning those S3 reports as deleted and the
+    /// failures
+    async fn delete_versions(
+        &self,
+        bucket: &str,
+        objects: &[DeletedObject],
+    ) -> anyhow::Result<(Vec<DeletedObject>, Vec<DeleteFailure>)> {
+        let mut failures = Vec::default();
+        let deleted: HashSet<_> = if self.quirks.batch_delete {
+            let identifiers = objects
+                .iter()
+                .map(|object| {
+                    ObjectIdentifier::builder()
+                        .key(&object.key)
+                        .set_version_id(object.version_id.clone())
+                        .build()
+                })
+                .collect::<Result<_, _>>()
+                .context("failed to build object identifier list")?;
+            let delete = Delete::builder()
+                .set_objects(Some(identifiers))
+                .quiet(false)
+                .build()
+                .context("failed to build `delete_objects` command")?;
+            let out = self
+                .s3(bucket)
+                .delete_objects()
+                .bucket(bucket)
+                .delete(delete)
+                .set_bypass_governance_retention(self.bypass_governance_retention.then_some(true))
+                .send()
+                .await
+                .map_err(|err| self.request_error(err))
+                .context("failed to delete objects")?;
+            failures.extend(
+                out.errors
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(|err| DeleteFailure {
+                        key: err.key.unwrap_or_default(),
+                        code: err.code,
+                        message: err.message,
+                    }),
+            );
+            out.deleted
+                .unwrap_or_default()
+                .into_iter()
+                .filter_map(|deleted| deleted.key)
+                .collect()
+        } else {
+            let results: Vec<_> = futures::stream::iter(objects.to_vec())
+                .map(|object| async move {
+                    let res = self
+                        .s3(bucket)
+                        .delete_object()
+                        .bucket(bucket)
+                        .key(&object.key)
+                        .set_version_id(object.version_id.clone())
+                        .set_bypass_governance_retention(
+                            self.bypass_governance_retention.then_some(true),
+                        )
+                        .send()
+                        .await;
+                    (object, res)
+                })
+                .buffer_unordered(EMULATED_DELETE_CONCURRENCY)
+                .collect()
+                .await;
+            let mut deleted = HashSet::new();
+            for (object, res) in results {
+                match res.map_err(SdkError::into_service_error) {
+                    Ok(_) => {
+                        deleted.insert(object.key);
+                    }
+                    Err(err) => failures.push(DeleteFailure {
+                        key: object.key,
+                        code: err.code().map(str::to_string),
+                        message: Some(
+                            err.message()
+                                .map_or_else(|| err.to_string(), str::to_string),
+                        ),
+                    }),
+                }
+            }
+            deleted
+        };
+        let deleted = objects
+            .iter()
+            .filter(|object| deleted.contains(&object.key))
+            .cloned()
+            .collect();
+        Ok((deleted, failures))
+    }
+
+    #[instrument(level = "debug", skip(self))]
+    pub async fn delete_container(&self, bucket: &str) -> anyhow::Result<()> {
+        if let Some(backend) = &self.backend {
+            return backend.delete_container(bucket).await;
+        }
+        if is_access_point_arn(bucket) {
+            bail!("access points cannot be deleted as containers, delete the bucket instead")
+        }
+        match self.clients.s3.delete_bucket().bucket(bucket).send().await {
+            Ok(_) => Ok(()),
+            Err(SdkError::ServiceError(err)) => {
+                bail!("{err:?}")
+            }
+            Err(err) => {
+                error!(%err, code = err.code(), "unexpected error");
+                bail!(self.request_error(err))
+            }
+        }
+    }
+
+    /// Find out whether object exists
+    #[instrument(level = "debug", skip(self))]
+    pub async fn has_object(&self, bucket: &str, key: &str) -> anyhow::Result<bool> {
+        if let Some(backend) = &self.backend {
+            return backend.has_object(bucket, key).await;
+        }
+        let bucket = self.route(bucket, key);
+        match self
+            .read(bucket, |s3| s3.head_object().bucket(bucket).key(key).send())
+            .await
+        {
+            Ok(_) => Ok(true),
+            Err(se) => match se.into_service_error() {
+                HeadObjectError::NotFound(_) => {
+                    Ok(self.tiered_object(bucket, key).await?.is_some())
+                }
+                err => {
+                    error!(
+                        %err,
+                        code = err.code(),
+                        "unexpected error for object_exists"
+                    );
+                    bail!(anyhow!(self.request_error(err))
+                        .context("unexpected error for object_exists"))
+                }
+            },
+        }
+    }
+
+    /// Check whether an object exists, reporting whether a missing object is hidden by a delete marker
+    #[instrument(level = "debug", skip(self))]
+    pub async fn object_presence(&self, bucket: &str, key: &str) -> anyhow::Result<ObjectPresence> {
+        if self.has_object(bucket, key).await? {
+            Ok(ObjectPresence::Present)
+        } else if self.latest_delete_marker(bucket, key).await?.is_some() {
+            Ok(ObjectPresence::Deleted)
+        } else {
+            Ok(ObjectPresence::Absent)
+        }
+    }
+
+    /// Look up the version ID of the delete marker, which is the latest version of an object, if any
+    #[instrument(level = "debug", skip(self))]
+    pub async fn latest_delete_marker(
+        &self,
+        bucket: &str,
+        key: &str,
+    ) -> anyhow::Result<Option<String>> {
+        let bucket = self.route(bucket, key);
+        let mut key_marker = None;
+        let mut version_id_marker = None;
+        loop {
+            let ListObjectVersionsOutput {
+                delete_markers,
+                versions,
+                is_truncated,
+                next_key_marker,
+                next_version_id_marker,
+                ..
+            } = self
+                .s3(bucket)
+                .list_object_versions()
+                .bucket(bucket)
+                .prefix(key)
+                .set_key_marker(key_marker)
+                .set_version_id_marker(version_id_marker)
+                .send()
+                .await
+                .map_err(|err| self.request_error(err))
+                .context("failed to list object versions")?;
+            if let Some(DeleteMarkerEntry { version_id, .. }) = delete_markers
+                .unwrap_or_default()
+                .into_iter()
+                .find(|m| m.key.as_deref() == Some(key) && m.is_latest == Some(true))
+            {
+                return version_id
+                    .context("delete marker is missing a version ID")
+                    .map(Some);
+            }
+            if versions
+                .unwrap_or_default()
+                .iter()
+                .any(|v| v.key.as_deref() == Some(key) && v.is_latest == Some(true))
+                || !is_truncated.unwrap_or_default()
+            {
+                return Ok(None);
+            }
+            key_marker = next_key_marker;
+            version_id_marker = next_version_id_marker;
+        }
+    }
+
+    /// Restore an object in a versioned bucket by removing the delete marker, which is its latest
+    /// version. Returns `false` if the latest version of the object is not a delete marker.
+    #[instrument(level = "debug", skip(self))]
+    pub async fn undelete_object(&self, bucket: &str, key: &str) -> anyhow::Result<bool> {
+        let bucket = self.route(bucket, key);
+        let Some(version_id) = self.latest_delete_marker(bucket, key).await? else {
+            return Ok(false);
+        };
+        self.s3(bucket)
+            .delete_object()
+            .bucket(bucket)
+            .key(key)
+            .version_id(version_id)
+            .send()
+            .await
+            .map_err(|err| self.request_error(err))
+            .context("failed to remove delete marker")?;
+        Ok(true)
+    }
+
+    /// Read the byte range from `start` to `end` of an object
+    pub async fn read_object(
+        &self,
+        bucket: &str,
+        key: &str,
+        start: u64,
+        end: u64,
+    ) -> anyhow::Result<impl Stream<Item = std::io::Result<Bytes>> + Send + 'static> {
+        self.read_object_version(bucket, key, None, start, end)
+            .await
+    }
+
+    /// Read the byte range from `start` to `end` of a specific version of an object,
+    /// or of the latest version if `version_id` is `None`
+    #[instrument(level = "debug", skip(self))]
+    pub async fn read_object_version(
+        &self,
+        bucket: &str,
+        key: &str,
+        version_id: Option<&str>,
+        start: u64,
+        end: u64,
+    ) -> anyhow::Result<impl Stream<Item = std::io::Result<Bytes>> + Send + 'static> {
+        if let Some(backend) = &self.backend {
+            ensure!(
+                version_id.is_none(),
+                "object versions are only supported by the S3 backend"
+            );
+            return backend
+                .read_object(bucket, key, start, end, self.chunk_size)
+                .await
+                .map(Either::Left);
+        }
+        self.get_object_range(
+            bucket,
+            key,
+            version_id,
+            start,
+            end,
+            &ReadConditions::default(),
+        )
+        .await?
+        .context("unconditional read reported the object as not modified")
+        .map(Either::Right)
+    }
+
+    /// Presign a URL, which downloads an object without credentials until it expires after
+    /// `expires_in`, 15 minutes by default
+    #[cfg(feature = "presign")]
+    #[instrument(level = "debug", skip(self))]
+    pub async fn download_url(
+        &self,
+        bucket: &str,
+        key: &str,
+        expires_in: Option<Duration>,
+    ) -> anyhow::Result<String> {
+        ensure!(
+            self.backend.is_none(),
+            "download URLs are only supported by the S3 backend"
+        );
+        let expires_in = expires_in.unwrap_or(DEFAULT_DOWNLOAD_URL_EXPIRY);
+        ensure!(
+            expires_in <= MAX_DOWNLOAD_URL_EXPIRY,
+            "download URLs expire after 7 days at most"
+        );
+        let bucket = self.route(bucket, key);
+        let config =
+            PresigningConfig::expires_in(expires_in).context("invalid expiry of download URL")?;
+        let req = self
+            .s3(bucket)
+            .get_object()
+            .bucket(bucket)
+            .key(key)
+            .presigned(config)
+            .await
+            .map_err(|err| self.request_error(err))
+            .context("failed to presign download URL")?;
+        Ok(req.uri().to_string())
+    }
+
+    /// Presign a download URL, which requires the `presign` feature
+    #[cfg(not(feature = "presign"))]
+    pub async fn download_url(
+        &self,
+        _bucket: &str,
+        _key: &str,
+        _expires_in: Option<Duration>,
+    ) -> anyhow::Result<String> {
+        bail!(presign_disabled())
+    }
+
+    /// Read disjoint byte ranges of an object concurrently, returning the data of each range along
+    /// with its offset in the order of `ranges`
+    pub fn read_ranges<'a>(
+        &'a self,
+        bucket: &'a str,
+        key: &'a str,
+        ranges: Vec<(u64, u64)>,
+    ) -> anyhow::Result<impl Stream<Item = anyhow::Result<(u64, Bytes)>> + Send + 'a> {
+        validate_ranges(&ranges)?;
+        Ok(futures::stream::iter(ranges)
+            .map(move |(start, end)| async move {
+                let data = self
+                    .read_object(bucket, key, start, end)
+                    .await?
+                    .try_fold(BytesMut::new(), |mut data, chunk| async move {
+                        data.extend_from_slice(&chunk);
+                        Ok(data)
+                    })
+                    .await
+                    .with_context(|| format!("failed to read bytes {start}-{end}"))?;
+                Ok((start, data.freeze()))
+            })
+            .buffered(MAX_CONCURRENT_RANGE_READS))
+    }
+
+    /// Read the byte range from `start` to `end` of an object, unless `conditions` report it as
+    /// not modified, in which case `None` is returned
+    #[instrument(level = "debug", skip(self))]
+    pub async fn read_object_if(
+        &self,
+        bucket: &str,
+        key: &str,
+        start: u64,
+        end: u64,
+        conditions: &ReadConditions,
+    ) -> anyhow::Result<Option<impl Stream<Item = std::io::Result<Bytes>> + Send + 'static>> {
+        self.get_object_range(bucket, key, None, start, end, conditions)
+            .await
+    }
+
+    async fn get_object_range(
+        &self,
+        bucket: &str,
+        key: &str,
+        version_id: Option<&str>,
+        start: u64,
+        end: u64,
+        ReadConditions {
+            if_none_match,
+            if_modified_since,
+        }: &ReadConditions,
+    ) -> anyhow::Result<Option<impl Stream<Item = std::io::Result<Bytes>> + Send + 'static>> {
+        let bucket = self.route(bucket, key);
+        let limit = end
+            .checked_sub(start)
+            .context("`end` must be greater than `start`")?;
+        let get = |bucket: String, key: String| async move {
+            self.read(&bucket, |s3| {
+                s3.get_object()
+                    .bucket(&bucket)
+                    .key(&key)
+                    .set_version_id(version_id.map(Into::into))
+                    .set_if_none_match(if_none_match.clone())
+                    .set_if_modified_since(if_modified_since.map(DateTime::from))
+                    .range(format!("bytes={start}-{end}"))
+                    .send()
+            })
+            .await
+        };
+        // objects missing from the bucket may have been routed to the bucket of a size tier
+        let res = match get(bucket.to_string(), key.to_string()).await {
+            Err(SdkError::ServiceError(err))
+                if version_id.is_none() && err.err().is_no_such_key() =>
+            {
+                match self.tiered_object(bucket, key).await? {
+                    Some((tier, tier_key, _)) => get(tier, tier_key).await,
+                    None => Err(SdkError::ServiceError(err)),
+                }
+            }
+            res => res,
+        };
+        let GetObjectOutput { body, .. } = match res {
+            Ok(out) => out,
+            Err(SdkError::ServiceError(err)) if err.raw().status().as_u16() == 304 => {
+                return Ok(None)
+            }
+            Err(err) => bail!(anyhow!(self.request_error(err)).context("failed to get object")),
+        };
+        Ok(Some(ReaderStream::with_capacity(
+            body.into_async_read().take(limit),
+            self.chunk_size,
+        )))
+    }
+
+    /// Retrieves the validators of an object, which conditional reads compare against
+    #[instrument(level = "debug", skip(self))]
+    pub async fn get_object_validators(
+        &self,
+        bucket: &str,
+        key: &str,
+    ) -> anyhow::Result<ObjectValidators> {
+        let bucket = self.route(bucket, key);
+        let HeadObjectOutput {
+            e_tag,
+            last_modified,
+            ..
+        } = self
+            .read(bucket, |s3| s3.head_object().bucket(bucket).key(key).send())
+            .await
+            .map_err(|err| self.request_error(err))
+            .context("failed to head object")?;
+        Ok(ObjectValidators {
+            etag: e_tag,
+            last_modified: last_modified.and_then(|t| SystemTime::try_from(t).ok()),
+        })
+    }
+
+    /// Hashes of an object recorded by S3
+    #[instrument(level = "debug", skip(self))]
+    pub async fn get_stored_hashes(&self, bucket: &str, key: &str) -> anyhow::Result<StoredHashes> {
+        ensure!(
+            self.backend.is_none(),
+            "stored hashes are only supported by the S3 backend"
+        );
+        let bucket = self.route(bucket, key);
+        let HeadObjectOutput {
+            e_tag,
+            checksum_sha256,
+            ..
+        } = self
+            .read(bucket, |s3| {
+                s3.head_object()
+                    .bucket(bucket)
+                    .key(key)
+                    .checksum_mode(ChecksumMode::Enabled)
+                    .send()
+            })
+            .await
+            .map_err(|err| self.request_error(err))
+            .context("failed to head object")?;
+        Ok(StoredHashes {
+            etag: e_tag,
+            checksum_sha256,
+        })
+    }
+
+    /// Hash of the content of an object, hex-encoded, computed by reading it within the provider
+    #[instrument(level = "debug", skip(self))]
+    pub async fn compute_hash(
+        &self,
+        bucket: &str,
+        key: &str,
+        algorithm: HashAlgorithm,
+    ) -> anyhow::Result<String> {
+        let data = self.read_object(bucket, key, 0, u64::MAX).await?;
+        match algorithm {
+            HashAlgorithm::Sha256 => hash::<Sha256>(data).await,
+            HashAlgorithm::Md5 => hash::<Md5>(data).await,
+        }
+    }
+
+    /// Write an object, which expires if an expiration period is configured for the link
+    pub async fn write_object(&self, bucket: &str, key: &str, data: Bytes) -> anyhow::Result<()> {
+        self.write_object_with_expiry(bucket, key, data, self.expires_after_days, None)
+            .await
+    }
+
+    /// Write an object, which expires `expires_after_days` days later, if set
+    ///
+    /// `content_type` is recorded as `Content-Type` of the object by S3 and ignored by other
+    /// backends.
+    #[instrument(level = "debug", skip(self, data))]
+    pub async fn write_object_with_expiry(
+        &self,
+        bucket: &str,
+        key: &str,
+        data: Bytes,
+        expires_after_days: Option<u32>,
+        content_type: Option<&str>,
+    ) -> anyhow::Result<()> {
+        if let Some(backend) = &self.backend {
+            ensure!(
+                expires_after_days.is_none(),
+                "expiring objects is only supported by the S3 backend"
+            );
+            return backend.write_object(bucket, key, data).await;
+        }
+        let bucket = self.route(bucket, key);
+        let (dest_bucket, dest_key, storage_class) = match self.size_tiers.route(data.len() as u64)
+        {
+            Some(Tier {
+                bucket: Some(tier),
+                storage_class,
+                ..
+            }) if self.unalias(tier) != bucket => (
+                self.unalias(tier),
+                Cow::Owned(tiered_key(bucket, key)),
+                storage_class.clone(),
+            ),
+            tier => (
+                bucket,
+                Cow::Borrowed(key),
+                tier.and_then(|tier| tier.storage_class.clone()),
+            ),
+        };
+        if let Some(days) = expires_after_days {
+            self.ensure_expiration_rule(dest_bucket, days).await?;
+        }
+        let tmp = self
+            .atomic_writes
+            .then(|| format!("{TEMP_PREFIX}{}", Uuid::new_v4()));
+        self.s3(dest_bucket)
+            .put_object()
+            .bucket(dest_bucket)
+            .key(tmp.as_deref().unwrap_or(&dest_key))
+            .set_tagging(expires_after_days.map(|days| format!("{TTL_TAG}={days}d")))
+            .set_content_type(content_type.map(str::to_string))
+            .set_storage_class(storage_class.clone())
+            .set_checksum_algorithm(
+                self.quirks
+                    .sha1_checksums
+                    .then_some(ChecksumAlgorithm::Sha1),
+            )
+            .body(data.into())
+            .send()
+            .await
+            .map_err(|err| self.request_error(err))
+            .context("failed to put object")?;
+        if let Some(tmp) = tmp {
+            self.publish_object(dest_bucket, tmp, &dest_key, storage_class)
+                .await?;
+        }
+        if dest_bucket == bucket {
+            self.record_write(bucket, key);
+        }
+        if !self.size_tiers.is_empty() {
+            // copies of the object in other tiers, which it was routed to before, are stale
+            let stale = std::iter::once((bucket.to_string(), key.to_string()))
+                .chain(self.tier_locations(bucket, key))
+                .filter(|(stale, _)| stale != dest_bucket)
+                .collect();
+            self.delete_stale_copies(stale).await;
+        }
+        Ok(())
+    }
+
+    /// Copy a completely written object from its temporary key `tmp` to `key` with
+    /// `storage_class`, deleting the temporary object afterwards
+    async fn publish_object(
+        &self,
+        bucket: &str,
+        tmp: String,
+        key: &str,
+        storage_class: Option<StorageClass>,
+    ) -> anyhow::Result<()> {
+        let res = self
+            .copy_object_with_class(bucket, &tmp, bucket, key, storage_class)
+            .await
+            .context("failed to publish object");
+        if let Err(err) = self.delete_key(bucket, tmp).await {
+            warn!(?err, "failed to delete temporary object");
+        }
+        res
+    }
+
+    /// Write an object using a multipart upload, which a write with the same idempotency key
+    /// resumes if this one fails midway. `data` must be identical for all attempts, bytes that
+    /// were uploaded by previous attempts are skipped. Returns the number of bytes uploaded.
+    ///
+    /// `content_type` is recorded as `Content-Type` of the object by S3 and ignored by other
+    /// backends.
+    #[instrument(level = "debug", skip(self, data))]
+    pub async fn write_object_resumable(
+        &self,
+        bucket: &str,
+        key: &str,
+        idempotency_key: &str,
+        mut data: impl Stream<Item = Bytes> + Unpin,
+        content_type: Option<&str>,
+    ) -> anyhow::Result<u64> {
+        if let Some(backend) = &self.backend {
+            // writes of other backends cannot be resumed, so they are not made in parts
+            let data: BytesMut = data.collect().await;
+            let n = data.len() as u64;
+            backend.write_object(bucket, key, data.freeze()).await?;
+            return Ok(n);
+        }
+        let bucket = self.route(bucket, key);
+        let manifest_key = upload_manifest_key(key, idempotency_key);
+        let resumed = match self.upload_manifest(bucket, &manifest_key).await? {
+            Some(manifest) if manifest.key == key => self
+                .uploaded_parts(bucket, key, &manifest.upload_id)
+                .await?
+                .map(|parts| {
+                    let part_size = manifest.part_size.unwrap_or(DEFAULT_PART_SIZE);
+                    (manifest.upload_id, parts, part_size)
+                }),
+            _ => None,
+        };
+        let part_size = resumed
+            .as_ref()
+            .map_or(self.part_size, |(_, _, part_size)| *part_size);
+        let (upload_id, mut parts, mut skip) = if let Some((upload_id, parts, _)) = resumed {
+            let skip = parts.iter().map(|(_, size)| size).sum();
+            debug!(upload_id, skip, "resuming upload");
+            (
+                upload_id,
+                parts.into_iter().map(|(part, _)| part).collect(),
+                skip,
+            )
+        } else {
+            let upload_id = self
+                .s3(bucket)
+                .create_multipart_upload()
+                .bucket(bucket)
+                .key(key)
+                .set_content_type(content_type.map(str::to_string))
+                .send()
+                .await
+                .map_err(|err| self.request_error(err))
+                .context("failed to create multipart upload")?
+                .upload_id
+                .context("multipart upload is missing an upload ID")?;
+            let manifest = serde_json::to_vec(&UploadManifest {
+                key: key.into(),
+                upload_id: upload_id.clone(),
+                part_size: Some(part_size),
+            })
+            .context("failed to encode upload manifest")?;
+            self.s3(bucket)
+                .put_object()
+                .bucket(bucket)
+                .key(&manifest_key)
+                .body(manifest.into())
+                .send()
+                .await
+                .map_err(|err| self.request_error(err))
+                .context("failed to put upload manifest")?;
+            (upload_id, Vec::new(), 0)
+        };
+        let mut uploaded = 0;
+        let mut buf = BytesMut::new();
+        while let Some(mut chunk) = data.next().await {
+            if skip > 0 {
+                let n = skip.min(chunk.len() as u64);
+                chunk = chunk.slice(n as usize..);
+                skip -= n;
+            }
+            buf.extend_from_slice(&chunk);
+            while buf.len() >= part_size {
+                let body = buf.split_to(part_size).freeze();
+                uploaded += body.len() as u64;
+                let part = self
+                    .upload_part(bucket, key, &upload_id, parts.len() + 1, body)
+                    .await?;
+                parts.push(part);
+            }
+        }
+        if skip > 0 {
+            bail!("data is shorter than the parts uploaded by previous attempts")
+        }
+        if !buf.is_empty() || parts.is_empty() {
+            uploaded += buf.len() as u64;
+            let part = self
+                .upload_part(bucket, key, &upload_id, parts.len() + 1, buf.freeze())
+                .await?;
+            parts.push(part);
+        }
+        self.s3(bucket)
+            .complete_multipart_upload()
+            .bucket(bucket)
+            .key(key)
+            .upload_id(upload_id)
+            .multipart_upload(
+                CompletedMultipartUpload::builder()
+                    .set_parts(Some(parts))
+                    .build(),
+            )
+            .send()
+            .await
+            .map_err(|err| self.request_error(err))
+            .context("failed to complete multipart upload")?;
+        if let Err(err) = self.delete_key(bucket, manifest_key).await {
+            warn!(?err, "failed to delete upload manifest");
+        }
+        self.record_write(bucket, key);
+        Ok(uploaded)
+    }
+
+    /// Record a write of an object, which listings are expected to include
+    fn record_write(&self, bucket: &str, key: &str) {
+        if let Some(recent_writes) = &self.recent_writes {
+            recent_writes.record(bucket, key);
+        }
+    }
+
+    /// Forget writes of objects, which were deleted since
+    fn forget_writes<'a>(&self, bucket: &str, keys: impl IntoIterator<Item = &'a str>) {
+        if let Some(recent_writes) = &self.recent_writes {
+            recent_writes.forget(bucket, keys);
+        }
+    }
+
+    /// Look up the manifest of a resumable upload
+    async fn upload_manifest(
+        &self,
+        bucket: &str,
+        manifest_key: &str,
+    ) -> anyhow::Result<Option<UploadManifest>> {
+        let out = match self
+            .s3(bucket)
+            .get_object()
+            .bucket(bucket)
+            .key(manifest_key)
+            .send()
+            .await
+        {
+            Ok(out) => out,
+            Err(se) if se.code() == Some("NoSuchKey") => return Ok(None),
+            Err(se) => {
+                bail!(anyhow!(self.request_error(se)).context("failed to get upload manifest"))
+            }
+        };
+        let manifest = out
+            .body
+            .collect()
+            .await
+            .context("failed to read upload manifest")?
+            .into_bytes();
+        match serde_json::from_slice(&manifest) {
+            Ok(manifest) => Ok(Some(manifest)),
+            Err(err) => {
+                warn!(?err, manifest_key, "ignoring invalid upload manifest");
+                Ok(None)
+            }
+        }
+    }
+
+    /// List the leading parts of a multipart upload and their sizes, or return `None` if the
+    /// upload no longer exists
+    async fn uploaded_parts(
+        &self,
+        bucket: &str,
+        key: &str,
+        upload_id: &str,
+    ) -> anyhow::Result<Option<Vec<(CompletedPart, u64)>>> {
+        let mut parts = Vec::new();
+        let mut pages = self
+            .s3(bucket)
+            .list_parts()
+            .bucket(bucket)
+            .key(key)
+            .upload_id(upload_id)
+            .into_paginator()
+            .send();
+        while let Some(page) = pages.next().await {
+            let page = match page {
+                Ok(page) => page,
+                Err(se) if se.code() == Some("NoSuchUpload") => return Ok(None),
+                Err(se) => {
+                    bail!(anyhow!(self.request_error(se)).context("failed to list parts"))
+                }
+            };
+            for Part {
+                part_number,
+                e_tag,
+                size,
+                ..
+            } in page.parts.unwrap_or_default()
+            {
+                let part = CompletedPart::builder()
+                    .set_part_number(part_number)
+                    .set_e_tag(e_tag)
+                    .build();
+                parts.push((
+                    part,
+                    size.unwrap_or_default().try_into().unwrap_or_default(),
+                ));
+            }
+        }
+        // parts following a missing one are uploaded again
+        parts.sort_by_key(|(part, _)| part.part_number());
+        let contiguous = parts
+            .iter()
+            .zip(1..)
+            .take_while(|((part, _), i)| part.part_number() == Some(*i))
+            .count();
+        parts.truncate(contiguous);
+        Ok(Some(parts))
+    }
+
+    /// Upload a part of a multipart upload
+    async fn upload_part(
+        &self,
+        bucket: &str,
+        key: &str,
+        upload_id: &str,
+        part_number: usize,
+        body: Bytes,
+    ) -> anyhow::Result<CompletedPart> {
+        let part_number = part_number.try_into().context("too many parts")?;
+        let out = self
+            .s3(bucket)
+            .upload_part()
+            .bucket(bucket)
+            .key(key)
+            .upload_id(upload_id)
+            .part_number(part_number)
+            .body(body.into())
+            .send()
+            .await
+            .map_err(|err| self.request_error(err))
+            .context("failed to upload part")?;
+        Ok(CompletedPart::builder()
+            .part_number(part_number)
+            .set_e_tag(out.e_tag)
+            .build())
+    }
+
+    /// Try to acquire the lock `name` for `ttl`, returning `None` if it is held by someone else.
+    /// Locks, which have not been renewed before they expire, can be acquired by anyone.
+    #[instrument(level = "debug", skip(self))]
+    pub async fn acquire_lock(
+        &self,
+        bucket: &str,
+        name: &str,
+        ttl: Duration,
+    ) -> anyhow::Result<Option<LockLease>> {
+        let key = format!("{LOCKS_PREFIX}{name}");
+        if let Some(lease) = self.put_lock(bucket, &key, ttl, None).await? {
+            return Ok(Some(lease));
+        }
+        let out = match self
+            .s3(bucket)
+            .get_object()
+            .bucket(bucket)
+            .key(&key)
+            .send()
+            .await
+        {
+            Ok(out) => out,
+            // the lock was released in the meantime
+            Err(se) if se.code() == Some("NoSuchKey") => return Ok(None),
+            Err(se) => bail!(anyhow!(self.request_error(se)).context("failed to get lock")),
+        };
+        let etag = out.e_tag.clone().context("lock is missing an ETag")?;
+        let lock = out
+            .body
+            .collect()
+            .await
+            .context("failed to read lock")?
+            .into_bytes();
+        let expired = match serde_json::from_slice::<Lock>(&lock) {
+            Ok(Lock { expires_at }) => {
+                SystemTime::UNIX_EPOCH + Duration::from_secs(expires_at) < SystemTime::now()
+            }
+            Err(err) => {
+                warn!(?err, key, "taking over invalid lock");
+                true
+            }
+        };
+        if !expired {
+            return Ok(None);
+        }
+        self.put_lock(bucket, &key, ttl, Some(etag)).await
+    }
+
+    /// Extend a lease of the lock `name` by `ttl`, failing if the lock was lost
+    #[instrument(level = "debug", skip(self))]
+    pub async fn renew_lock(
+        &self,
+        bucket: &str,
+        name: &str,
+        etag: &str,
+        ttl: Duration,
+    ) -> anyhow::Result<LockLease> {
+        let key = format!("{LOCKS_PREFIX}{name}");
+        self.put_lock(bucket, &key, ttl, Some(etag.into()))
+            .await?
+            .context("lock is no longer held")
+    }
+
+    /// Release a lease of the lock `name`, failing if the lock was lost
+    #[instrument(level = "debug", skip(self))]
+    pub async fn release_lock(&self, bucket: &str, name: &str, etag: &str) -> anyhow::Result<()> {
+        let etag = etag.to_string();
+        match self
+            .s3(bucket)
+            .delete_object()
+            .bucket(bucket)
+            .key(format!("{LOCKS_PREFIX}{name}"))
+            .customize()
+            .mutate_request(move |req| {
+                req.headers_mut().insert("if-match", etag.clone());
+            })
+            .send()
+            .await
+        {
+            Ok(_) => Ok(()),
+            Err(SdkError::ServiceError(err)) if is_precondition_failure(err.raw()) => {
+                bail!("lock is no longer held")
+            }
+            Err(err) => bail!(anyhow!(self.request_error(err)).context("failed to delete lock")),
+        }
+    }
+
+    /// Write a lock object, if it does not exist yet or matches the ETag `if_match`
+    async fn put_lock(
+        &self,
+        bucket: &str,
+        key: &str,
+        ttl: Duration,
+        if_match: Option<String>,
+    ) -> anyhow::Result<Option<LockLease>> {
+        let expires_at = SystemTime::now() + ttl;
+        let lock = serde_json::to_vec(&Lock {
+            expires_at: expires_at
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+        })
+        .context("failed to encode lock")?;
+        let req = self
+            .s3(bucket)
+            .put_object()
+            .bucket(bucket)
+            .key(key)
+            .body(lock.into());
+        let res = if let Some(etag) = if_match {
+            req.customize()
+                .mutate_request(move |req| {
+                    req.headers_mut().insert("if-match", etag.clone());
+                })
+                .send()
+                .await
+        } else {
+            req.if_none_match("*").send().await
+        };
+        match res {
+            Ok(out) => Ok(Some(LockLease {
+                etag: out.e_tag.context("lock is missing an ETag")?,
+                expires_at,
+            })),
+            Err(SdkError::ServiceError(err)) if is_precondition_failure(err.raw()) => Ok(None),
+            Err(err) => bail!(anyhow!(self.request_error(err)).context("failed to put lock")),
+        }
+    }
+
+    /// Create a manifest of the set `name`, listing `members`, which must all exist, and return
+    /// its ID. The manifest does not become current until it is swapped in.
+    #[instrument(level = "debug", skip(self, members))]
+    pub async fn create_manifest(
+        &self,
+        bucket: &str,
+        name: &str,
+        members: Vec<String>,
+    ) -> anyhow::Result<String> {
+        ensure!(
+            self.backend.is_none(),
+            "manifests are only supported by the S3 backend"
+        );
+        validate_manifest_part("name", name)?;
+        let object_keys = self.object_keys(members.clone())?;
+        let checks = members.clone().into_iter().zip(object_keys.clone());
+        let missing: Vec<_> = futures::stream::iter(checks)
+            .map(|(member, key)| async move {
+                let exists = self.has_object(bucket, &key).await?;
+                anyhow::Ok((!exists).then_some(member))
+            })
+            .buffer_unordered(MAX_CONCURRENT_HEADS)
+            .try_filter_map(|member| futures::future::ready(Ok(member)))
+            .try_collect()
+            .await?;
+        if let Some(member) = missing.first() {
+            bail!(BackendError::new(
+                ErrorKind::PreconditionFailed,
+                format!(
+                    "{} member(s) of manifest do not exist, first missing: `{member}`",
+                    missing.len()
+                )
+            ))
+        }
+        let id = Uuid::new_v4().to_string();
+        let manifest = serde_json::to_vec(&ManifestObject {
+            members,
+            object_keys,
+            created_at: SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+        })
+        .context("failed to encode manifest")?;
+        self.s3(bucket)
+            .put_object()
+            .bucket(bucket)
+            .key(format!("{MANIFESTS_PREFIX}{name}/{id}.json"))
+            .content_type("application/json")
+            .body(manifest.into())
+            .send()
+            .await
+            .map_err(|err| self.request_error(err))
+            .context("failed to put manifest")?;
+        Ok(id)
+    }
+
+    /// Look up the manifest `id` of the set `name`, or its current manifest if `None`
+    #[instrument(level = "debug", skip(self))]
+    pub async fn get_manifest(
+        &self,
+        bucket: &str,
+        name: &str,
+        id: Option<&str>,
+    ) -> anyhow::Result<Option<Manifest>> {
+        let manifest = self.manifest_object(bucket, name, id).await?;
+        Ok(manifest.map(|(id, manifest)| Manifest {
+            id,
+            members: manifest.members,
+            created_at: SystemTime::UNIX_EPOCH + Duration::from_secs(manifest.created_at),
+        }))
+    }
+
+    /// Read the manifest `id` of the set `name`, or its current manifest if `None`, along with
+    /// its ID
+    async fn manifest_object(
+        &self,
+        bucket: &str,
+        name: &str,
+        id: Option<&str>,
+    ) -> anyhow::Result<Option<(String, ManifestObject)>> {
+        ensure!(
+            self.backend.is_none(),
+            "manifests are only supported by the S3 backend"
+        );
+        validate_manifest_part("name", name)?;
+        let id = match id {
+            Some(id) => {
+                validate_manifest_part("ID", id)?;
+                id.to_string()
+            }
+            None => match self.current_manifest(bucket, name).await? {
+                Some((ManifestPointer { id }, _)) => id,
+                None => return Ok(None),
+            },
+        };
+        let manifest: Option<(ManifestObject, _)> = self
+            .get_json(bucket, &format!("{MANIFESTS_PREFIX}{name}/{id}.json"))
+            .await
+            .context("failed to get manifest")?;
+        Ok(manifest.map(|(manifest, _)| (id, manifest)))
+    }
+
+    /// Make the manifest `id` of the set `name` current, if its current manifest is `expected`,
+    /// `None` meaning that the set has no current manifest yet
+    ///
+    /// The pointer to the current manifest is written conditionally on its ETag, so that
+    /// concurrent swaps fail rather than overwrite each other.
+    #[instrument(level = "debug", skip(self))]
+    pub async fn swap_manifest(
+        &self,
+        bucket: &str,
+        name: &str,
+        id: &str,
+        expected: Option<&str>,
+    ) -> anyhow::Result<()> {
+        if self.get_manifest(bucket, name, Some(id)).await?.is_none() {
+            bail!(BackendError::new(
+                ErrorKind::NotFound,
+                format!("manifest `{id}` of [{bucket}/{name}] not found")
+            ))
+        }
+        let current = self.current_manifest(bucket, name).await?;
+        let current_id = current
+            .as_ref()
+            .map(|(ManifestPointer { id }, _)| id.as_str());
+        if current_id != expected {
+            bail!(BackendError::new(
+                ErrorKind::PreconditionFailed,
+                format!(
+                    "current manifest of [{bucket}/{name}] is `{}`, not `{}`",
+                    current_id.unwrap_or("none"),
+                    expected.unwrap_or("none")
+                )
+            ))
+        }
+        let pointer = serde_json::to_vec(&ManifestPointer { id: id.to_string() })
+            .context("failed to encode manifest pointer")?;
+        let req = self
+            .s3(bucket)
+            .put_object()
+            .bucket(bucket)
+            .key(format!("{MANIFESTS_PREFIX}{name}/{CURRENT_MANIFEST}"))
+            .content_type("application/json")
+            .body(pointer.into());
+        let res = if let Some((_, etag)) = current {
+            req.customize()
+                .mutate_request(move |req| {
+                    req.headers_mut().insert("if-match", etag.clone());
+                })
+                .send()
+                .await
+        } else {
+            req.if_none_match("*").send().await
+        };
+        match res {
+            Ok(_) => Ok(()),
+            Err(SdkError::ServiceError(err)) if is_precondition_failure(err.raw()) => {
+                bail!(BackendError::new(
+                    ErrorKind::PreconditionFailed,
+                    format!("current manifest of [{bucket}/{name}] was swapped concurrently")
+                ))
+            }
+            Err(err) => {
+                bail!(anyhow!(self.request_error(err)).context("failed to put manifest pointer"))
+            }
+        }
+    }
+
+    /// Look up the pointer to the current manifest of the set `name` and its ETag
+    async fn current_manifest(
+        &self,
+        bucket: &str,
+        name: &str,
+    ) -> anyhow::Result<Option<(ManifestPointer, String)>> {
+        self.get_json(
+            bucket,
+            &format!("{MANIFESTS_PREFIX}{name}/{CURRENT_MANIFEST}"),
+        )
+        .await
+        .context("failed to get manifest pointer")?
+        .map(|(pointer, etag)| {
+            Ok((
+                pointer,
+                etag.context("manifest pointer is missing an ETag")?,
+            ))
+        })
+        .transpose()
+    }
+
+    /// Look up the objects under `prefix`, which none of the `live` manifests list and which were
+    /// last modified more than `grace_period` ago, i.e. garbage
+    ///
+    /// Live manifests are given by the name of their set and their ID, `None` referring to the
+    /// current manifest of the set. Objects modified within the grace period are kept, since they
+    /// may belong to a manifest, which was not created yet. Objects of the provider itself, like
+    /// manifests or locks, and objects in the trash are never garbage.
+    #[instrument(level = "debug", skip(self, live))]
+    pub async fn find_garbage(
+        &self,
+        bucket: &str,
+        prefix: &str,
+        live: &[(String, Option<String>)],
+        grace_period: Duration,
+    ) -> anyhow::Result<Vec<ObjectSummary>> {
+        ensure!(
+            self.backend.is_none(),
+            "garbage collection is only supported by the S3 backend"
+        );
+        ensure!(
+            !live.is_empty(),
+            "at least one live manifest is required, refusing to collect all objects"
+        );
+        let manifests: Vec<_> = futures::stream::iter(live.to_vec())
+            .map(|(name, id)| async move {
+                match self.manifest_object(bucket, &name, id.as_deref()).await? {
+                    Some((_, manifest)) => Ok(manifest),
+                    None => bail!(BackendError::new(
+                        ErrorKind::NotFound,
+                        format!(
+                            "live manifest `{}` of [{bucket}/{name}] not found",
+                            id.as_deref().unwrap_or("current")
+                        )
+                    )),
+                }
+            })
+            .buffer_unordered(MAX_CONCURRENT_HEADS)
+            .try_collect()
+            .await?;
+        let members = self.manifest_object_keys(manifests)?;
+        let before = SystemTime::now()
+            .checked_sub(grace_period)
+            .unwrap_or(SystemTime::UNIX_EPOCH)
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let mut garbage = Vec::new();
+        self.for_each_object(bucket, Some(prefix), |object| {
+            if let Some(summary) = ObjectSummary::new(object) {
+                if is_garbage(&summary, &members, before) {
+                    garbage.push(summary);
+                }
+            }
+        })
+        .await?;
+        Ok(garbage)
+    }
+
+    /// Keys of the objects listed by `manifests`
+    ///
+    /// Members of manifests, which were created before their object keys were recorded, are
+    /// resolved by the key policy of the link now.
+    fn manifest_object_keys(
+        &self,
+        manifests: Vec<ManifestObject>,
+    ) -> anyhow::Result<HashSet<String>> {
+        let mut keys = HashSet::new();
+        for ManifestObject {
+            members,
+            object_keys,
+            ..
+        } in manifests
+        {
+            if object_keys.is_empty() {
+                keys.extend(self.object_keys(members)?);
+            } else {
+                keys.extend(object_keys);
+            }
+        }
+        Ok(keys)
+    }
+
+    /// Read and decode a JSON object along with its ETag, `None` if it does not exist
+    async fn get_json<T: DeserializeOwned>(
+        &self,
+        bucket: &str,
+        key: &str,
+    ) -> anyhow::Result<Option<(T, Option<String>)>> {
+        let out = match self
+            .s3(bucket)
+            .get_object()
+            .bucket(bucket)
+            .key(key)
+            .send()
+            .await
+        {
+            Ok(out) => out,
+            Err(se) if se.code() == Some("NoSuchKey") => return Ok(None),
+            Err(se) => bail!(anyhow!(self.request_error(se)).context("failed to get object")),
+        };
+        let etag = out.e_tag.clone();
+        let data = out
+            .body
+            .collect()
+            .await
+            .context("failed to read object")?
+            .into_bytes();
+        let value = serde_json::from_slice(&data).with_context(|| format!("invalid `{key}`"))?;
+        Ok(Some((value, etag)))
+    }
+
+    /// Ensure that the bucket has a lifecycle rule expiring objects tagged with `ttl=<days>d`
+    /// after `days` days, adding it to the existing lifecycle configuration if necessary
+    #[instrument(level = "debug", skip(self))]
+    pub async fn ensure_expiration_rule(&self, bucket: &str, days: u32) -> anyhow::Result<()> {
+        let key = (bucket.to_string(), days);
+        if self.expiration_rules.lock().await.contains(&key) {
+            return Ok(());
+        }
+        let mut rules = match self
+            .s3(bucket)
+            .get_bucket_lifecycle_configuration()
+            .bucket(bucket)
+            .send()
+            .await
+        {
+            Ok(out) => out.rules.unwrap_or_default(),
+            Err(se) if se.code() == Some("NoSuchLifecycleConfiguration") => Vec::default(),
+            Err(se) => {
+                error!(%se, code = se.code(), "failed to get bucket lifecycle configuration");
+                bail!(anyhow!(self.request_error(se))
+                    .context("failed to get bucket lifecycle configuration"))
+            }
+        };
+        let id = format!("wasmcloud-{TTL_TAG}-{days}d");
+        if !rules.iter().any(|rule| rule.id() == Some(id.as_str())) {
+            let tag = Tag::builder()
+                .key(TTL_TAG)
+                .value(format!("{days}d"))
+                .build()
+                .context("failed to build tag")?;
+            let days = days.try_into().context("expiration period is too long")?;
+            rules.push(
+                LifecycleRule::builder()
+                    .id(id)
+                    .status(ExpirationStatus::Enabled)
+                    .filter(LifecycleRuleFilter::Tag(tag))
+                    .expiration(LifecycleExpiration::builder().days(days).build())
+                    .build()
+                    .context("failed to build lifecycle rule")?,
+            );
+            let config = BucketLifecycleConfiguration::builder()
+                .set_rules(Some(rules))
+                .build()
+                .context("failed to build lifecycle configuration")?;
+            self.s3(bucket)
+                .put_bucket_lifecycle_configuration()
+                .bucket(bucket)
+                .lifecycle_configuration(config)
+                .send()
+                .await
+                .map_err(|err| self.request_error(err))
+                .context("failed to put bucket lifecycle configuration")?;
+        }
+        self.expiration_rules.lock().await.insert(key);
+        Ok(())
+    }
+
+    /// Periodically delete expired objects as configured, until the link is deleted
+    pub async fn sweep_expired(self, config: ExpirationSweeperConfig) {
+        let mut interval = tokio::time::interval(Duration::from_secs(
+            config.interval_secs.unwrap_or(DEFAULT_SWEEP_INTERVAL_SECS),
+        ));
+        interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        loop {
+            interval.tick().await;
+            match self
+                .sweep_expired_once(config.clone(), SystemTime::now())
+                .await
+            {
+                Ok(n) => debug!(n, bucket = config.bucket, "swept expired objects"),
+                Err(err) => warn!(
+                    ?err,
+                    bucket = config.bucket,
+                    "failed to sweep expired objects"
+                ),
+            }
+        }
+    }
+
+    /// Delete the objects, which have expired at `now`, returning their number
+    #[instrument(level = "debug", skip(self))]
+    pub async fn sweep_expired_once(
+        &self,
+        config: ExpirationSweeperConfig,
+        now: SystemTime,
+    ) -> anyhow::Result<usize> {
+        let bucket = self.unalias(&config.bucket);
+        let max_age = config.max_age_secs.map(Duration::from_secs);
+        let mut expired = Vec::new();
+        let mut pages = self
+            .s3(bucket)
+            .list_objects_v2()
+            .bucket(bucket)
+            .set_prefix(config.prefix.clone())
+            .into_paginator()
+            .send();
+        while let Some(page) = pages.next().await {
+            let page = page
+                .map_err(|err| self.request_error(err))
+                .context("failed to list objects")?;
+            for Object {
+                key, last_modified, ..
+            } in page.contents.unwrap_or_default()
+            {
+                let (Some(key), Some(last_modified)) = (key, last_modified) else {
+                    continue;
+                };
+                let Ok(last_modified) = SystemTime::try_from(last_modified) else {
+                    continue;
+                };
+                let age = now.duration_since(last_modified).unwrap_or_default();
+                if max_age.is_some_and(|max_age| age > max_age)
+                    || config.ttl_tag && self.ttl(bucket, &key).await?.is_some_and(|ttl| age > ttl)
+                {
+                    expired.push(key);
+                }
+            }
+        }
+        let n = expired.len();
+        self.delete_objects(bucket, expired).await?;
+        Ok(n)
+    }
+
+    /// Move an object to the trash directory `dir` of its bucket, returning whether it existed
+    async fn trash_object(&self, bucket: &str, key: &str, dir: &str) -> anyhow::Result<bool> {
+        match self
+            .move_object(bucket, key, bucket, &format!("{dir}{key}"))
+            .await
+        {
+            Ok(()) => Ok(true),
+            Err(err) if ErrorKind::of(&err) == ErrorKind::NotFound => Ok(false),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Move objects to the trash of a bucket instead of deleting them, returning the objects,
+    /// which could not be moved
+    ///
+    /// Objects are moved to `.trash/<timestamp>/<key>`. Objects, which do not exist or are in the
+    /// trash already, are skipped.
+    #[instrument(level = "debug", skip(self, objects))]
+    pub async fn trash_objects(
+        &self,
+        bucket: &str,
+        objects: impl IntoIterator<Item = String>,
+    ) -> Vec<DeleteFailure> {
+        let dir = trash_dir(SystemTime::now());
+        self.trash_buckets.lock().await.insert(bucket.to_string());
+        futures::stream::iter(
+            objects
+                .into_iter()
+                .filter(|key| !key.starts_with(TRASH_PREFIX)),
+        )
+        .map(|key| {
+            let dir = &dir;
+            async move {
+                let err = self.trash_object(bucket, &key, dir).await.err()?;
+                Some(DeleteFailure {
+                    key,
+                    code: None,
+                    message: Some(format!("{err:#}")),
+                })
+            }
+        })
+        .buffer_unordered(MAX_CONCURRENT_COPIES)
+        .filter_map(futures::future::ready)
+        .collect()
+        .await
+    }
+
+    /// Move all objects of a bucket, which are not in the trash already, to the trash, returning
+    /// their keys
+    async fn trash_container(
+        &self,
+        bucket: &str,
+        progress: Option<&mpsc::Sender<Progress>>,
+    ) -> anyhow::Result<Vec<String>> {
+        let keys: Vec<_> = self
+            .list_container_objects(bucket, None, None)
+            .await?
+            .filter(|key| !key.starts_with(TRASH_PREFIX))
+            .collect();
+        let dir = trash_dir(SystemTime::now());
+        self.trash_buckets.lock().await.insert(bucket.to_string());
+        let mut state = Progress {
+            total: Some(keys.len() as u64),
+            ..Progress::default()
+        };
+        let trashed = futures::stream::iter(keys.clone())
+            .map(|key| {
+                let dir = &dir;
+                async move {
+                    let existed = self
+                        .trash_object(bucket, &key, dir)
+                        .await
+                        .with_context(|| format!("failed to move `{key}` to the trash"))?;
+                    anyhow::Ok(existed.then_some(0))
+                }
+            })
+            .buffer_unordered(MAX_CONCURRENT_COPIES);
+        track_progress(trashed, &mut state, progress).await?;
+        Ok(keys)
+    }
+
+    /// Periodically delete objects kept in the trash for longer than configured, until the link
+    /// is deleted
+    ///
+    /// The trash of the buckets aliases of the link refer to, of those the link moved objects to
+    /// since it was established and of those allowed for the link, which had objects in their
+    /// trash when it was established, is purged. The latter keeps purging the trash left by
+    /// earlier runs of the provider.
+    pub async fn purge_trash(self) {
+        let Some(days) = self.trash_retention_days else {
+            return;
+        };
+        if let Err(err) = self.discover_trash().await {
+            warn!(
+                ?err,
+                "failed to discover buckets with objects in their trash"
+            );
+        }
+        let retention = Duration::from_secs(u64::from(days) * 24 * 60 * 60);
+        let mut interval = tokio::time::interval(TRASH_PURGE_INTERVAL);
+        interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        loop {
+            interval.tick().await;
+            let before = SystemTime::now() - retention;
+            let mut buckets: HashSet<_> = self.aliases.values().cloned().collect();
+            buckets.extend(self.trash_buckets.lock().await.iter().cloned());
+            for bucket in buckets {
+                match self.purge_trash_once(&bucket, before).await {
+                    Ok(n) => debug!(n, bucket, "purged trash"),
+                    Err(err) => warn!(?err, bucket, "failed to purge trash"),
+                }
+            }
+        }
+    }
+
+    /// Remember the buckets allowed for the link, which have objects in their trash
+    async fn discover_trash(&self) -> anyhow::Result<()> {
+        let buckets: Vec<_> = futures::stream::iter(self.allowed_buckets().await?)
+            .map(|bucket| async move {
+                let trashed = self.has_trash(&bucket).await?;
+                anyhow::Ok(trashed.then_some(bucket))
+            })
+            .buffer_unordered(MAX_CONCURRENT_HEADS)
+            .try_filter_map(|bucket| futures::future::ready(Ok(bucket)))
+            .try_collect()
+            .await?;
+        self.trash_buckets.lock().await.extend(buckets);
+        Ok(())
+    }
+
+    /// Whether there are objects in the trash of a bucket
+    async fn has_trash(&self, bucket: &str) -> anyhow::Result<bool> {
+        let res = self
+            .clients
+            .s3
+            .list_objects_v2()
+            .bucket(bucket)
+            .prefix(TRASH_PREFIX)
+            .max_keys(1)
+            .send()
+            .await
+            .map_err(|err| self.request_error(err))
+            .with_context(|| format!("failed to list trash of bucket [{bucket}]"))?;
+        Ok(!res.contents().is_empty())
+    }
+
+    /// Delete the objects, which were moved to the trash of a bucket before `before`, returning
+    /// their number
+    #[instrument(level = "debug", skip(self))]
+    pub async fn purge_trash_once(
+        &self,
+        bucket: &str,
+        before: SystemTime,
+    ) -> anyhow::Result<usize> {
+        let cutoff = trash_dir(before);
+        let expired: Vec<_> = self
+            .trash_keys(bucket)
+            .await?
+            .into_iter()
+            .filter(|key| *key < cutoff)
+            .collect();
+        let n = expired.len();
+        if n > 0 {
+            self.delete_objects(bucket, expired).await?;
+        }
+        Ok(n)
+    }
+
+    /// List the keys of the objects in the trash of a bucket, in order
+    async fn trash_keys(&self, bucket: &str) -> anyhow::Result<Vec<String>> {
+        self.list_prefixes(bucket, &[TRASH_PREFIX.to_string()])
+            .await
+    }
+
+    /// List the objects in the trash of a bucket, in the order they were deleted
+    #[instrument(level = "debug", skip(self))]
+    pub async fn list_trash(&self, bucket: &str) -> anyhow::Result<Vec<TrashedObject>> {
+        let keys = self.trash_keys(bucket).await?;
+        Ok(keys.into_iter().filter_map(trashed_object).collect())
+    }
+
+    /// Move an object back from the trash of a bucket to its key, the copy deleted at
+    /// `deleted_at` or the one deleted last if `None`, returning the restored object
+    ///
+    /// Fails if an object exists at the key, so that restores do not overwrite objects written
+    /// since the deletion.
+    #[instrument(level = "debug", skip(self))]
+    pub async fn restore_object(
+        &self,
+        bucket: &str,
+        key: &str,
+        deleted_at: Option<&str>,
+    ) -> anyhow::Result<TrashedObject> {
+        let trashed = match deleted_at {
+            Some(deleted_at) => TrashedObject {
+                key: key.to_string(),
+                deleted_at: deleted_at.to_string(),
+                trash_key: format!("{TRASH_PREFIX}{deleted_at}/{key}"),
+            },
+            None => {
+                let Some(trashed) = self
+                    .list_trash(bucket)
+                    .await?
+                    .into_iter()
+                    .rfind(|trashed| trashed.key == key)
+                else {
+                    bail!(BackendError::new(
+                        ErrorKind::NotFound,
+                        format!("object [{bucket}/{key}] is not in the trash")
+                    ))
+                };
+                trashed
+            }
+        };
+        if self.has_object(bucket, key).await? {
+            bail!(BackendError::new(
+                ErrorKind::AlreadyExists,
+                format!("object [{bucket}/{key}] exists, remove it before restoring it")
+            ))
+        }
+        self.move_object(bucket, &trashed.trash_key, bucket, key)
+            .await
+            .with_context(|| format!("failed to restore `{key}` from the trash"))?;
+        Ok(trashed)
+    }
+
+    /// Look up the lifetime of an object from its `ttl` tag, if any
+    async fn ttl(&self, bucket: &str, key: &str) -> anyhow::Result<Option<Duration>> {
+        let tags = self
+            .s3(bucket)
+            .get_object_tagging()
+            .bucket(bucket)
+            .key(key)
+            .send()
+            .await
+            .map_err(|err| self.request_error(err))
+            .context("failed to get object tagging")?;
+        Ok(tags
+            .tag_set()
+            .iter()
+            .find(|tag| tag.key() == TTL_TAG)
+            .and_then(|tag| parse_ttl(tag.value())))
+    }
+
+    /// List all objects with keys starting with `prefix`
+    async fn list_objects(
+        &self,
+        bucket: &str,
+        prefix: Option<&str>,
+    ) -> anyhow::Result<Vec<Object>> {
+        let mut objects = Vec::new();
+        let mut pages = self
+            .s3(bucket)
+            .list_objects_v2()
+            .bucket(bucket)
+            .set_prefix(prefix.map(Into::into))
+            .into_paginator()
+            .send();
+        while let Some(page) = pages.next().await {
+            let page = page
+                .map_err(|err| self.request_error(err))
+                .context("failed to list objects")?;
+            objects.extend(page.contents.unwrap_or_default());
+        }
+        Ok(objects)
+    }
+
+    /// Delete all objects of a bucket in batches, returning the keys of the deleted objects
+    ///
+    /// Each page of the listing of the bucket is deleted as a batch by one of `clear_workers`
+    /// concurrent workers, while the following pages are listed, at most at the configured rate.
+    ///
+    /// Progress is sent to `progress`, if any, after each batch. The total number of objects is
+    /// known once all of them were listed. Objects, which could not be deleted, fail the
+    /// operation once all batches were processed.
+    #[instrument(level = "debug", skip(self, progress))]
+    pub async fn clear_container(
+        &self,
+        bucket: &str,
+        progress: Option<&mpsc::Sender<Progress>>,
+    ) -> anyhow::Result<Vec<String>> {
+        if self.soft_deletes() {
+            return self.trash_container(bucket, progress).await;
+        }
+        if let Some(backend) = &self.backend {
+            return backend.clear_container(bucket).await;
+        }
+        let listed = AtomicU64::new(0);
+        let listing_done = AtomicBool::new(false);
+        let (tx, rx) = mpsc::channel::<Vec<String>>(self.clear_workers);
+        let list = {
+            let (listed, listing_done) = (&listed, &listing_done);
+            async move {
+                let mut pages = self
+                    .s3(bucket)
+                    .list_objects_v2()
+                    .bucket(bucket)
+                    .into_paginator()
+                    .send();
+                while let Some(page) = pages.next().await {
+                    let page = page
+                        .map_err(|err| self.request_error(err))
+                        .context("failed to list objects")?;
+                    let keys: Vec<_> = page
+                        .contents
+                        .unwrap_or_default()
+                        .into_iter()
+                        .filter_map(|Object { key, .. }| key)
+                        .collect();
+                    listed.fetch_add(keys.len() as u64, Ordering::Relaxed);
+                    // the workers stopped on an error
+                    if !keys.is_empty() && tx.send(keys).await.is_err() {
+                        break;
+                    }
+                }
+                listing_done.store(true, Ordering::Relaxed);
+                anyhow::Ok(())
+            }
+        };
+        let delete = async {
+            let mut state = Progress::default();
+            report_progress(progress, state).await;
+            let mut deleted = Vec::new();
+            let mut first_failure = None;
+            let mut batches = ReceiverStream::new(rx)
+                .map(|batch| async move {
+                    if let Some(limit) = &self.clear_limit {
+                        limit.take(1).await;
+                    }
+                    let failures = self
+                        .delete_objects_detailed(bucket, batch.iter().cloned())
+                        .await?;
+                    anyhow::Ok((batch, failures))
+                })
+                .buffer_unordered(self.clear_workers);
+            while let Some(res) = batches.next().await {
+                let (batch, failures) = res?;
+                let failed: HashSet<_> = failures.iter().map(|failure| &failure.key).collect();
+                state.objects += batch.len() as u64;
+                state.errors += failures.len() as u64;
+                deleted.extend(batch.into_iter().filter(|key| !failed.contains(key)));
+                if first_failure.is_none() {
+                    first_failure = failures.into_iter().next();
+                }
+                state.total = listing_done
+                    .load(Ordering::Relaxed)
+                    .then(|| listed.load(Ordering::Relaxed));
+                report_progress(progress, state).await;
+            }
+            anyhow::Ok((deleted, state, first_failure))
+        };
+        let (listing, deletion) = tokio::join!(list, delete);
+        let (deleted, state, first_failure) = deletion?;
+        listing?;
+        if let Some(DeleteFailure { key, code, message }) = first_failure {
+            bail!(
+                "failed to delete {} object(s), first failure: `{key}` ({}: {})",
+                state.errors,
+                code.as_deref().unwrap_or("unknown"),
+                message.as_deref().unwrap_or("no message"),
+            )
+        }
+        Ok(deleted)
+    }
+
+    /// Estimate the scope of clearing `bucket` without deleting anything, like
+    /// [`Self::clear_container`] would
+    #[instrument(level = "debug", skip(self))]
+    pub async fn estimate_clear_container(&self, bucket: &str) -> anyhow::Result<Estimate> {
+        let objects = self.list_objects(bucket, None).await?;
+        let count = objects.len() as u64;
+        Ok(Estimate {
+            objects: count,
+            bytes: object_bytes(&objects),
+            // each page of the listing is deleted as a batch
+            requests: 2 * listing_requests(count),
+        })
+    }
+
+    /// Copy all objects of a bucket into a new snapshot, or only those, which changed since the
+    /// latest snapshot, if `incremental` is set
+    ///
+    /// Snapshots are stored under `<prefix><bucket>/<id>/`, with copies of objects under
+    /// `objects/` and a manifest, which is written last, listing all objects of the snapshot and
+    /// the snapshots holding their copies. Objects are copied with single `CopyObject` requests,
+    /// which S3 limits to objects of up to 5 GiB.
+    ///
+    /// Progress is sent to `progress`, if any, after each object. Objects failing to be copied
+    /// fail the snapshot once all others were copied, without writing its manifest.
+    #[instrument(level = "debug", skip(self, progress))]
+    pub async fn snapshot_container(
+        &self,
+        bucket: &str,
+        SnapshotOptions {
+            destination,
+            prefix,
+            incremental,
+        }: SnapshotOptions,
+        progress: Option<&mpsc::Sender<Progress>>,
+    ) -> anyhow::Result<SnapshotInfo> {
+        let destination = destination
+            .as_deref()
+            .map_or(bucket, |dest| self.unalias(dest));
+        let prefix = prefix.as_deref().unwrap_or(SNAPSHOTS_PREFIX);
+        let id = snapshot_id(SystemTime::now());
+        let dir = format!("{prefix}{bucket}/{id}/");
+        let (manifest, copies) = self
+            .plan_snapshot(bucket, destination, prefix, incremental, &id)
+            .await?;
+        let unchanged = (manifest.objects.len() - copies.len()) as u64;
+        let mut state = Progress {
+            objects: unchanged,
+            total: Some(manifest.objects.len() as u64),
+            skipped: unchanged,
+            ..Progress::default()
+        };
+        let copied = futures::stream::iter(copies.into_iter().map(|(key, size)| {
+            let dest_key = format!("{dir}{SNAPSHOT_OBJECTS}{key}");
+            async move {
+                self.copy_object(bucket, &key, destination, &dest_key)
+                    .await
+                    .with_context(|| format!("failed to copy `{key}` into snapshot"))?;
+                anyhow::Ok(Some(size))
+            }
+        }))
+        .buffer_unordered(MAX_CONCURRENT_COPIES);
+        track_progress(copied, &mut state, progress)
+            .await
+            .context("snapshot is incomplete")?;
+        let body = serde_json::to_vec(&manifest).context("failed to encode snapshot manifest")?;
+        self.s3(destination)
+            .put_object()
+            .bucket(destination)
+            .key(format!("{dir}{SNAPSHOT_MANIFEST}"))
+            .content_type("application/json")
+            .body(body.into())
+            .send()
+            .await
+            .map_err(|err| self.request_error(err))
+            .context("failed to put snapshot manifest")?;
+        Ok(SnapshotInfo {
+            id,
+            container: destination.into(),
+            prefix: dir,
+            objects: manifest.objects.len() as u64,
+            objects_copied: state.objects - state.skipped,
+            bytes_copied: state.bytes,
+        })
+    }
+
+    /// Plan a snapshot `id` of `bucket` stored in `destination` under `prefix`, returning its
+    /// manifest and the keys and sizes of the objects to copy into it
+    async fn plan_snapshot(
+        &self,
+        bucket: &str,
+        destination: &str,
+        prefix: &str,
+        incremental: bool,
+        id: &str,
+    ) -> anyhow::Result<(SnapshotManifest, Vec<(String, u64)>)> {
+        let previous = if incremental {
+            self.latest_snapshot_manifest(destination, &format!("{prefix}{bucket}/"))
+                .await?
+        } else {
+            None
+        };
+        let mut manifest = SnapshotManifest {
+            container: bucket.into(),
+            objects: BTreeMap::new(),
+        };
+        let mut copies = Vec::new();
+        for Object {
+            key, e_tag, size, ..
+        } in self.list_objects(bucket, None).await?
+        {
+            let Some(key) = key else {
+                continue;
+            };
+            // snapshots stored in the snapshotted bucket are not part of it
+            if destination == bucket && key.starts_with(prefix) {
+                continue;
+            }
+            let etag = e_tag.unwrap_or_default();
+            let size = size.unwrap_or_default().try_into().unwrap_or_default();
+            let entry = match previous.as_ref().and_then(|prev| prev.objects.get(&key)) {
+                Some(prev) if !etag.is_empty() && prev.etag == etag => prev.clone(),
+                _ => {
+                    copies.push((key.clone(), size));
+                    SnapshotEntry {
+                        etag,
+                        size,
+                        snapshot: id.to_string(),
+                    }
+                }
+            };
+            manifest.objects.insert(key, entry);
+        }
+        Ok((manifest, copies))
+    }
+
+    /// Estimate the scope of a snapshot of `bucket` without copying anything, like
+    /// [`Self::snapshot_container`] would
+    #[instrument(level = "debug", skip(self))]
+    pub async fn estimate_snapshot(
+        &self,
+        bucket: &str,
+        SnapshotOptions {
+            destination,
+            prefix,
+            incremental,
+        }: SnapshotOptions,
+    ) -> anyhow::Result<Estimate> {
+        let destination = destination
+            .as_deref()
+            .map_or(bucket, |dest| self.unalias(dest));
+        let prefix = prefix.as_deref().unwrap_or(SNAPSHOTS_PREFIX);
+        let id = snapshot_id(SystemTime::now());
+        let (manifest, copies) = self
+            .plan_snapshot(bucket, destination, prefix, incremental, &id)
+            .await?;
+        let objects = copies.len() as u64;
+        // the latest snapshot is looked up by listing snapshots and reading its manifest
+        let lookups = if incremental { 2 } else { 0 };
+        Ok(Estimate {
+            objects,
+            bytes: copies.iter().map(|(_, size)| size).sum(),
+            // the manifest is written last
+            requests: lookups + listing_requests(manifest.objects.len() as u64) + objects + 1,
+        })
+    }
+
+    /// Copy the objects of the snapshot `id` of a bucket back into it, or only those, which are
+    /// missing or changed, if `only_changed` is set, returning the final progress
+    ///
+    /// Progress is sent to `progress`, if any, after each object. Objects are restored as they
+    /// were when the snapshot was taken, objects created since are kept. Objects failing to be
+    /// restored fail the restore once all others were restored.
+    #[instrument(level = "debug", skip(self, progress))]
+    pub async fn restore_snapshot(
+        &self,
+        bucket: &str,
+        id: &str,
+        RestoreOptions {
+            source,
+            prefix,
+            only_changed,
+        }: RestoreOptions,
+        progress: Option<&mpsc::Sender<Progress>>,
+    ) -> anyhow::Result<Progress> {
+        let source = source.as_deref().map_or(bucket, |src| self.unalias(src));
+        let root = format!("{}{bucket}/", prefix.as_deref().unwrap_or(SNAPSHOTS_PREFIX));
+        let (total, copies) = self
+            .plan_restore(bucket, id, source, &root, only_changed)
+            .await?;
+        let unchanged = total - copies.len() as u64;
+        let mut state = Progress {
+            objects: unchanged,
+            total: Some(total),
+            skipped: unchanged,
+            ..Progress::default()
+        };
+        let restored = futures::stream::iter(copies.into_iter().map(
+            |(key, SnapshotEntry { size, snapshot, .. })| {
+                let src_key = format!("{root}{snapshot}/{SNAPSHOT_OBJECTS}{key}");
+                async move {
+                    self.copy_object(source, &src_key, bucket, &key)
+                        .await
+                        .with_context(|| format!("failed to restore `{key}`"))?;
+                    anyhow::Ok(Some(size))
+                }
+            },
+        ))
+        .buffer_unordered(MAX_CONCURRENT_COPIES);
+        track_progress(restored, &mut state, progress).await?;
+        Ok(state)
+    }
+
+    /// Plan restoring the snapshot `id` of `bucket` stored in `source` under `root`, returning
+    /// the number of objects in the snapshot and the keys and entries of the objects to copy
+    async fn plan_restore(
+        &self,
+        bucket: &str,
+        id: &str,
+        source: &str,
+        root: &str,
+        only_changed: bool,
+    ) -> anyhow::Result<(u64, Vec<(String, SnapshotEntry)>)> {
+        let SnapshotManifest { objects, .. } = self
+            .snapshot_manifest(source, &format!("{root}{id}/"))
+            .await?
+            .with_context(|| format!("snapshot `{id}` of `{bucket}` not found"))?;
+        let current: HashMap<_, _> = if only_changed {
+            self.list_objects(bucket, None)
+                .await?
+                .into_iter()
+                .filter_map(|Object { key, e_tag, .. }| key.zip(e_tag))
+                .collect()
+        } else {
+            HashMap::default()
+        };
+        let total = objects.len() as u64;
+        let copies = objects
+            .into_iter()
+            .filter(|(key, entry)| current.get(key) != Some(&entry.etag))
+            .collect();
+        Ok((total, copies))
+    }
+
+    /// Estimate the scope of restoring the snapshot `id` of `bucket` without copying anything,
+    /// like [`Self::restore_snapshot`] would
+    #[instrument(level = "debug", skip(self))]
+    pub async fn estimate_restore(
+        &self,
+        bucket: &str,
+        id: &str,
+        RestoreOptions {
+            source,
+            prefix,
+            only_changed,
+        }: RestoreOptions,
+    ) -> anyhow::Result<Estimate> {
+        let source = source.as_deref().map_or(bucket, |src| self.unalias(src));
+        let root = format!("{}{bucket}/", prefix.as_deref().unwrap_or(SNAPSHOTS_PREFIX));
+        let (total, copies) = self
+            .plan_restore(bucket, id, source, &root, only_changed)
+            .await?;
+        let objects = copies.len() as u64;
+        // the manifest is read and the bucket is listed to find changed objects
+        let lookups = if only_changed {
+            1 + listing_requests(total)
+        } else {
+            1
+        };
+        Ok(Estimate {
+            objects,
+            bytes: copies.iter().map(|(_, entry)| entry.size).sum(),
+            requests: lookups + objects,
+        })
+    }
+
+    /// Enable static website hosting of a bucket, serving `index_document` for requests of
+    /// directories and `error_document`, if set, for requests failing with a 4XX error
+    #[instrument(level = "debug", skip(self))]
+    pub async fn configure_website(
+        &self,
+        bucket: &str,
+        WebsiteOptions {
+            index_document,
+            error_document,
+        }: WebsiteOptions,
+    ) -> anyhow::Result<()> {
+        ensure!(
+            self.backend.is_none(),
+            "website hosting is only supported by the S3 backend"
+        );
+        let config = WebsiteConfiguration::builder()
+            .index_document(
+                IndexDocument::builder()
+                    .suffix(index_document)
+                    .build()
+                    .context("failed to build index document")?,
+            )
+            .set_error_document(
+                error_document
+                    .map(|key| ErrorDocument::builder().key(key).build())
+                    .transpose()
+                    .context("failed to build error document")?,
+            )
+            .build();
+        self.s3(bucket)
+            .put_bucket_website()
+            .bucket(bucket)
+            .website_configuration(config)
+            .send()
+            .await
+            .map_err(|err| self.request_error(err))
+            .context("failed to put bucket website configuration")?;
+        Ok(())
+    }
+
+    /// Set the `Content-Type` and `Cache-Control` of all objects of the website in `bucket`, or
+    /// of those with keys starting with `options.prefix`, by copying each object onto itself,
+    /// reporting the progress to `progress`, if set
+    ///
+    /// Content types are derived from the extensions of keys, objects with unknown extensions
+    /// keep theirs. Objects with the headers set already are skipped, and objects changed while
+    /// they are being copied are not overwritten and fail instead. Once all objects were
+    /// published, their paths are invalidated in the CDN of the link, if requested.
+    #[instrument(level = "debug", skip(self, progress))]
+    pub async fn publish_site(
+        &self,
+        bucket: &str,
+        options: &PublishOptions,
+        progress: Option<&mpsc::Sender<Progress>>,
+    ) -> anyhow::Result<Progress> {
+        ensure!(
+            self.backend.is_none(),
+            "website hosting is only supported by the S3 backend"
+        );
+        let prefix = options.prefix.as_deref();
+        let keys: Vec<_> = self
+            .list_objects(bucket, prefix)
+            .await?
+            .into_iter()
+            .filter_map(|Object { key, .. }| key)
+            .collect();
+        let mut state = Progress {
+            total: Some(keys.len() as u64),
+            ..Progress::default()
+        };
+        let published = futures::stream::iter(keys.into_iter().map(|key| async move {
+            self.set_website_headers(bucket, &key, options)
+                .await
+                .with_context(|| format!("failed to publish `{key}`"))
+        }))
+        .buffer_unordered(MAX_CONCURRENT_COPIES);
+        track_progress(published, &mut state, progress).await?;
+        #[cfg(feature = "cdn-invalidation")]
+        if options.invalidate_cdn {
+            self.invalidate_cdn_prefix(bucket, prefix.unwrap_or_default());
+        }
+        Ok(state)
+    }
+
+    /// Set the website headers of an object, returning its size, or `None` if they were set
+    /// already
+    async fn set_website_headers(
+        &self,
+        bucket: &str,
+        key: &str,
+        options: &PublishOptions,
+    ) -> anyhow::Result<Option<u64>> {
+        let head = self
+            .s3(bucket)
+            .head_object()
+            .bucket(bucket)
+            .key(key)
+            .send()
+            .await
+            .map_err(|err| self.request_error(err))
+            .context("failed to get object metadata")?;
+        let content_type = website::content_type(key).or(head.content_type.as_deref());
+        let cache_control = website::cache_control(
+            content_type,
+            options.html_cache_control.as_deref(),
+            options.cache_control.as_deref(),
+        );
+        if head.content_type.as_deref() == content_type
+            && head.cache_control.as_deref() == Some(cache_control)
+        {
+            return Ok(None);
+        }
+        let copy_source = if is_access_point_arn(bucket) {
+            format!("{bucket}/object/{key}")
+        } else {
+            format!("{bucket}/{key}")
+        };
+        // replacing metadata drops all headers, which are not set again
+        self.s3(bucket)
+            .copy_object()
+            .copy_source(copy_source)
+            .set_copy_source_if_match(head.e_tag.clone())
+            .bucket(bucket)
+            .key(key)
+            .metadata_directive(MetadataDirective::Replace)
+            .set_metadata(head.metadata.clone())
+            .set_content_type(content_type.map(str::to_string))
+            .cache_control(cache_control)
+            .set_content_encoding(head.content_encoding.clone())
+            .set_content_disposition(head.content_disposition.clone())
+            .set_content_language(head.content_language.clone())
+            .tagging_directive(TaggingDirective::Copy)
+            .set_storage_class(head.storage_class.clone())
+            .set_server_side_encryption(head.server_side_encryption.clone())
+            .set_ssekms_key_id(head.ssekms_key_id.clone())
+            .send()
+            .await
+            .map_err(|err| self.request_error(err))
+            .context("failed to copy object")?;
+        Ok(Some(
+            head.content_length
+                .unwrap_or_default()
+                .try_into()
+                .unwrap_or_default(),
+        ))
+    }
+
+    /// Re-encrypt objects of a bucket, which requires the `encryption` feature
+    #[cfg(not(feature = "encryption"))]
+    pub async fn reencrypt_container(
+        &self,
+        _bucket: &str,
+        _kms_key_id: &str,
+        _prefix: Option<&str>,
+        _progress: Option<&mpsc::Sender<Progress>>,
+    ) -> anyhow::Result<Progress> {
+        bail!(encryption_disabled())
+    }
+
+    /// Estimate the scope of re-encrypting objects of a bucket, which requires the `encryption`
+    /// feature
+    #[cfg(not(feature = "encryption"))]
+    pub async fn estimate_reencrypt(
+        &self,
+        _bucket: &str,
+        _prefix: Option<&str>,
+    ) -> anyhow::Result<Estimate> {
+        bail!(encryption_disabled())
+    }
+
+    /// Place or, if `on` is `false`, remove a legal hold on all objects of `bucket` with keys
+    /// starting with `prefix`, reporting the progress to `progress`, if set
+    ///
+    /// Holds are set on the current versions of objects and require object lock to be enabled on
+    /// the bucket. Failures of individual objects do not stop the sweep, the error lists the keys
+    /// of up to 100 failed objects.
+    #[instrument(level = "debug", skip(self, progress))]
+    pub async fn set_legal_holds(
+        &self,
+        bucket: &str,
+        prefix: Option<&str>,
+        on: bool,
+        progress: Option<&mpsc::Sender<Progress>>,
+    ) -> anyhow::Result<Progress> {
+        ensure!(
+            self.quirks.object_lock,
+            "legal holds are not supported by the backend of the link, since it does not \
+             support object lock"
+        );
+        let keys: Vec<_> = self
+            .list_objects(bucket, prefix)
+            .await?
+            .into_iter()
+            .filter_map(|Object { key, .. }| key)
+            .collect();
+        let mut state = Progress {
+            total: Some(keys.len() as u64),
+            ..Progress::default()
+        };
+        let status = if on {
+            ObjectLockLegalHoldStatus::On
+        } else {
+            ObjectLockLegalHoldStatus::Off
+        };
+        let failures = Mutex::new(Vec::new());
+        let held = futures::stream::iter(keys.into_iter().map(|key| {
+            let hold = ObjectLockLegalHold::builder()
+                .status(status.clone())
+                .build();
+            let failures = &failures;
+            async move {
+                match self
+                    .s3(bucket)
+                    .put_object_legal_hold()
+                    .bucket(bucket)
+                    .key(&key)
+                    .legal_hold(hold)
+                    .send()
+                    .await
+                {
+                    Ok(_) => anyhow::Ok(Some(0)),
+                    Err(err) => {
+                        let err =
+                            anyhow!(self.request_error(err)).context("failed to set legal hold");
+                        let mut failures = failures.lock().await;
+                        if failures.len() < MAX_REPORTED_FAILURES {
+                            failures.push(format!("{key}: {err:#}"));
+                        }
+                        Err(err)
+                    }
+                }
+            }
+        }))
+        .buffer_unordered(MAX_CONCURRENT_COPIES);
+        if track_progress(held, &mut state, progress).await.is_err() {
+            let failures = failures.into_inner();
+            let more = state.errors.saturating_sub(failures.len() as u64);
+            let mut msg = format!(
+                "failed to set legal hold on {} object(s):\n{}",
+                state.errors,
+                failures.join("\n")
+            );
+            if more > 0 {
+                msg.push_str(&format!("\n... and {more} more"));
+            }
+            bail!(msg);
+        }
+        Ok(state)
+    }
+
+    /// Look up the manifest of the latest complete snapshot under `root`, if any
+    async fn latest_snapshot_manifest(
+        &self,
+        bucket: &str,
+        root: &str,
+    ) -> anyhow::Result<Option<SnapshotManifest>> {
+        let mut dirs = Vec::new();
+        let mut pages = self
+            .s3(bucket)
+            .list_objects_v2()
+            .bucket(bucket)
+            .prefix(root)
+            .delimiter("/")
+            .into_paginator()
+            .send();
+        while let Some(page) = pages.next().await {
+            let page = page
+                .map_err(|err| self.request_error(err))
+                .context("failed to list snapshots")?;
+            dirs.extend(
+                page.common_prefixes
+                    .unwrap_or_default()
+                    .into_iter()
+                    .filter_map(|dir| dir.prefix),
+            );
+        }
+        // IDs sort in the order snapshots were taken, incomplete ones have no manifest
+        dirs.sort();
+        for dir in dirs.iter().rev() {
+            if let Some(manifest) = self.snapshot_manifest(bucket, dir).await? {
+                return Ok(Some(manifest));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Look up the manifest of the snapshot stored under `dir`
+    async fn snapshot_manifest(
+        &self,
+        bucket: &str,
+        dir: &str,
+    ) -> anyhow::Result<Option<SnapshotManifest>> {
+        let out = match self
+            .s3(bucket)
+            .get_object()
+            .bucket(bucket)
+            .key(format!("{dir}{SNAPSHOT_MANIFEST}"))
+            .send()
+            .await
+        {
+            Ok(out) => out,
+            Err(se) if se.code() == Some("NoSuchKey") => return Ok(None),
+            Err(se) => {
+                bail!(anyhow!(self.request_error(se)).context("failed to get snapshot manifest"))
+            }
+        };
+        let manifest = out
+            .body
+            .collect()
+            .await
+            .context("failed to read snapshot manifest")?
+            .into_bytes();
+        serde_json::from_slice(&manifest)
+            .with_context(|| format!("invalid snapshot manifest under `{dir}`"))
+            .map(Some)
+    }
+
+    /// Keys of the current objects of a bucket according to its latest usable S3 Inventory
+    /// report, `None` if the link does not use inventories or there is no usable report
+    #[instrument(level = "debug", skip(self))]
+    pub async fn inventory_keys(&self, bucket: &str) -> anyhow::Result<Option<Vec<String>>> {
+        let Some(InventoryConfig { id, max_age_hours }) = &self.inventory else {
+            return Ok(None);
+        };
+        let max_age = max_age_hours.map_or(DEFAULT_INVENTORY_MAX_AGE, |hours| {
+            Duration::from_secs(hours.saturating_mul(60 * 60))
+        });
+        let Some((destination, root)) = self.inventory_destination(bucket, id.as_deref()).await?
+        else {
+            debug!(bucket, "no inventory configuration in CSV format found");
+            return Ok(None);
+        };
+        let mut dirs = Vec::new();
+        let mut pages = self
+            .s3(&destination)
+            .list_objects_v2()
+            .bucket(&destination)
+            .prefix(&root)
+            .delimiter("/")
+            .into_paginator()
+            .send();
+        while let Some(page) = pages.next().await {
+            let page = page
+                .map_err(|err| self.request_error(err))
+                .context("failed to list inventory reports")?;
+            dirs.extend(
+                page.common_prefixes
+                    .unwrap_or_default()
+                    .into_iter()
+                    .filter_map(|dir| dir.prefix)
+                    // reports are stored under their creation time, data files in `data/`
+                    .filter(|dir| {
+                        dir[root.len()..]
+                            .bytes()
+                            .next()
+                            .is_some_and(|b| b.is_ascii_digit())
+                    }),
+            );
+        }
+        dirs.sort();
+        // reports being written have no manifest yet
+        let mut manifest = None;
+        for dir in dirs.iter().rev() {
+            if let Some(m) = self.inventory_manifest(&destination, dir).await? {
+                manifest = Some(m);
+                break;
+            }
+        }
+        let Some(manifest) = manifest else {
+            debug!(bucket, "no inventory report found");
+            return Ok(None);
+        };
+        if !manifest.is_usable(max_age, SystemTime::now()) {
+            debug!(bucket, "latest inventory report is outdated");
+            return Ok(None);
+        }
+        let columns = manifest.columns();
+        let mut keys = Vec::new();
+        for file in &manifest.files {
+            let out = self
+                .s3(&destination)
+                .get_object()
+                .bucket(&destination)
+                .key(&file.key)
+                .send()
+                .await
+                .map_err(|err| self.request_error(err))
+                .context("failed to get inventory data file")?;
+            let data = out.body.into_async_read();
+            let mut lines = if file.key.ends_with(".gz") {
+                Box::pin(BufReader::new(GzipDecoder::new(data)))
+                    as Pin<Box<dyn AsyncBufRead + Send>>
+            } else {
+                Box::pin(data)
+            }
+            .lines();
+            while let Some(line) = lines
+                .next_line()
+                .await
+                .context("failed to read inventory data file")?
+            {
+                keys.extend(columns.key(&line));
+            }
+        }
+        keys.sort();
+        Ok(Some(keys))
+    }
+
+    /// Bucket and prefix the reports of the inventory configuration `id` of `bucket` are stored
+    /// under, or of its first enabled configuration in CSV format if `id` is `None`
+    async fn inventory_destination(
+        &self,
+        bucket: &str,
+        id: Option<&str>,
+    ) -> anyhow::Result<Option<(String, String)>> {
+        let mut token = None;
+        loop {
+            let out = self
+                .s3(bucket)
+                .list_bucket_inventory_configurations()
+                .bucket(bucket)
+                .set_continuation_token(token)
+                .send()
+                .await
+                .map_err(|err| self.request_error(err))
+                .context("failed to list inventory configurations")?;
+            for config in out.inventory_configuration_list() {
+                if !config.is_enabled() || id.is_some_and(|id| id != config.id()) {
+                    continue;
+                }
+                let Some(dest) = config
+                    .destination()
+                    .and_then(|dest| dest.s3_bucket_destination())
+                else {
+                    continue;
+                };
+                if *dest.format() != InventoryFormat::Csv {
+                    continue;
+                }
+                let destination = dest.bucket();
+                let destination = destination
+                    .strip_prefix("arn:aws:s3:::")
+                    .unwrap_or(destination);
+                return Ok(Some((
+                    destination.to_string(),
+                    inventory::report_prefix(dest.prefix(), bucket, config.id()),
+                )));
+            }
+            if out.is_truncated != Some(true) {
+                return Ok(None);
+            }
+            token = out.next_continuation_token;
+        }
+    }
+
+    /// Look up the manifest of the inventory report stored under `dir`
+    async fn inventory_manifest(
+        &self,
+        bucket: &str,
+        dir: &str,
+    ) -> anyhow::Result<Option<InventoryManifest>> {
+        let out = match self
+            .s3(bucket)
+            .get_object()
+            .bucket(bucket)
+            .key(format!("{dir}manifest.json"))
+            .send()
+            .await
+        {
+            Ok(out) => out,
+            Err(se) if se.code() == Some("NoSuchKey") => return Ok(None),
+            Err(se) => {
+                bail!(anyhow!(self.request_error(se)).context("failed to get inventory manifest"))
+            }
+        };
+        let manifest = out
+            .body
+            .collect()
+            .await
+            .context("failed to read inventory manifest")?
+            .into_bytes();
+        serde_json::from_slice(&manifest)
+            .with_context(|| format!("invalid inventory manifest under `{dir}`"))
+            .map(Some)
+    }
+
+    /// Retrieves metadata about the object
+    pub async fn get_object_info(&self, bucket: &str, key: &str) -> anyhow::Result<ObjectInfo> {
+        if let Some(backend) = &self.backend {
+            return backend.get_object_info(bucket, key).await;
+        }
+        self.get_object_version_info(bucket, key, None).await
+    }
+
+    /// Retrieves metadata about a specific version of an object,
+    /// or about the latest version if `version_id` is `None`
+    #[instrument(level = "debug", skip(self))]
+    pub async fn get_object_version_info(
+        &self,
+        bucket: &str,
+        key: &str,
+        version_id: Option<&str>,
+    ) -> anyhow::Result<ObjectInfo> {
+        let bucket = self.route(bucket, key);
+        match self
+            .read(bucket, |s3| {
+                s3.head_object()
+                    .bucket(bucket)
+                    .key(key)
+                    .set_version_id(version_id.map(Into::into))
+                    .send()
+            })
+            .await
+        {
+            Ok(HeadObjectOutput { content_length, .. }) => {
+                Ok(ObjectInfo {
+                    // NOTE: The `created_at` value is not reported by S3
+                    created_at: 0,
+                    size: content_length
+                        .and_then(|v| v.try_into().ok())
+                        .unwrap_or_default(),
+                })
+            }
+            Err(se) => match se.into_service_error() {
+                HeadObjectError::NotFound(_) => {
+                    if let (None, Some((.., head))) =
+                        (version_id, self.tiered_object(bucket, key).await?)
+                    {
+                        return Ok(ObjectInfo {
+                            created_at: 0,
+                            size: head
+                                .content_length
+                                .and_then(|v| v.try_into().ok())
+                                .unwrap_or_default(),
+                        });
+                    }
+                    error!("object [{bucket}/{key}] not found");
+                    bail!(BackendError::new(
+                        ErrorKind::NotFound,
+                        format!("object [{bucket}/{key}] not found")
+                    ))
+                }
+                err => {
+                    error!(
+                        ?err,
+                        code = err.code(),
+                        "get_object_metadata failed for object [{bucket}/{key}]"
+                    );
+                    bail!(anyhow!(self.request_error(err)).context(format!(
+                        "get_object_metadata failed for object [{bucket}/{key}]"
+                    )))
+                }
+            },
+        }
+    }
+}
+
+/// Send the progress of an operation, if requested
+async fn report_progress(progress: Option<&mpsc::Sender<Progress>>, state: Progress) {
+    if let Some(progress) = progress {
+        // the receiver may have gone away, which does not abort the operation
+        let _ = progress.send(state).await;
+    }
+}
+
+/// Drive the work on objects yielding the bytes transferred for each object, or `None` for
+/// skipped objects, to completion, counting failures and reporting the progress after each
+/// object, and fail with the first error once all objects were processed
+async fn track_progress(
+    mut work: impl Stream<Item = anyhow::Result<Option<u64>>> + Unpin,
+    state: &mut Progress,
+    progress: Option<&mpsc::Sender<Progress>>,
+) -> anyhow::Result<()> {
+    report_progress(progress, *state).await;
+    let mut first_error = None;
+    while let Some(res) = work.next().await {
+        state.objects += 1;
+        match res {
+            Ok(Some(bytes)) => state.bytes += bytes,
+            Ok(None) => state.skipped += 1,
+            Err(err) => {
+                warn!(?err, "failed to process object");
+                state.errors += 1;
+                first_error.get_or_insert(err);
+            }
+        }
+        report_progress(progress, *state).await;
+    }
+    match first_error {
+        Some(err) => Err(err.context(format!("failed to process {} object(s)", state.errors))),
+        None => Ok(()),
+    }
+}
+
+/// Number of `ListObjectsV2` requests listing `objects` objects
+fn listing_requests(objects: u64) -> u64 {
+    objects.div_ceil(MAX_LIST_OBJECTS).max(1)
+}
+
+/// Total size of `objects`
+fn object_bytes(objects: &[Object]) -> u64 {
+    objects
+        .iter()
+        .filter_map(|Object { size, .. }| u64::try_from((*size)?).ok())
+        .sum()
+}
+
+/// Verify that a copy has the size of the source and, unless the source was uploaded in parts
+/// or `compare_etags` is unset, its ETag. Copies of multipart uploads are stored in a single part
+/// and have a different ETag.
+fn verify_copy(
+    src: &HeadObjectOutput,
+    dest: &HeadObjectOutput,
+    compare_etags: bool,
+) -> anyhow::Result<()> {
+    if src.content_length != dest.content_length {
+        bail!(
+            "size of copy ({:?}) does not match the source ({:?})",
+            dest.content_length,
+            src.content_length
+        );
+    }
+    match (&src.e_tag, &dest.e_tag) {
+        _ if !compare_etags => Ok(()),
+        (Some(src), _) if src.contains('-') => Ok(()),
+        (Some(src), Some(dest)) if src != dest => {
+            bail!("ETag of copy ({dest}) does not match the source ({src})")
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Whether S3 reports an object as having failed with a transient error, which may succeed
+/// if retried
+fn is_transient_error(code: &str) -> bool {
+    matches!(
+        code,
+        "InternalError" | "ServiceUnavailable" | "SlowDown" | "RequestTimeout" | "Throttling"
+    )
+}
+
+/// Whether the ACL grant `grant` grants access to anyone or any AWS account
+fn is_public_grant(grant: &Grant) -> bool {
+    grant
+        .grantee()
+        .and_then(Grantee::uri)
+        .is_some_and(|uri| PUBLIC_ACL_GROUPS.contains(&uri))
+}
+
+/// Delay before retry `attempt` of a batch operation, chosen from the exponentially growing
+/// backoff period using the random value `jitter`
+fn retry_delay(attempt: u32, jitter: u64) -> Duration {
+    let backoff = BATCH_RETRY_BASE_DELAY
+        .saturating_mul(2u32.saturating_pow(attempt))
+        .min(BATCH_RETRY_MAX_DELAY);
+    backoff.mul_f64((jitter % 1000) as f64 / 1000.0 / 2.0 + 0.5)
+}
+
+/// Whether a conditional request failed, because its precondition did not hold or a
+/// concurrent conditional request was in progress
+fn is_precondition_failure(res: &HttpResponse) -> bool {
+    matches!(res.status().as_u16(), 409 | 412)
+}
+
+/// Key of the manifest of a resumable upload of `key` with the given idempotency key
+/// Validate the byte ranges of a scatter read, which must not overlap
+pub(crate) fn validate_ranges(ranges: &[(u64, u64)]) -> anyhow::Result<()> {
+    ensure!(!ranges.is_empty(), "no byte ranges to read");
+    ensure!(
+        ranges.len() <= MAX_SCATTER_RANGES,
+        "at most {MAX_SCATTER_RANGES} byte ranges can be read at once"
+    );
+    let mut sorted = ranges.to_vec();
+    sorted.sort_unstable();
+    for (start, end) in &sorted {
+        ensure!(start < end, "byte range {start}-{end} is empty");
+    }
+    if let Some(pair) = sorted.windows(2).find(|pair| pair[1].0 < pair[0].1) {
+        bail!(
+            "byte ranges {}-{} and {}-{} overlap",
+            pair[0].0,
+            pair[0].1,
+            pair[1].0,
+            pair[1].1
+        )
+    }
+    Ok(())
+}
+
+fn upload_manifest_key(key: &str, idempotency_key: &str) -> String {
+    let mut digest = Sha256::new();
+    digest.update(key);
+    digest.update([0]);
+    digest.update(idempotency_key);
+    format!("{UPLOADS_PREFIX}{:x}", digest.finalize())
+}
+
+/// ID of a snapshot taken at `now`, its UTC time with millisecond precision, which sort in the
+/// order snapshots are taken
+fn snapshot_id(now: SystemTime) -> String {
+    let since_epoch = now
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default();
+    let secs = DateTime::from_secs(since_epoch.as_secs().try_into().unwrap_or(i64::MAX))
+        .fmt(DateTimeFormat::DateTime)
+        .unwrap_or_else(|_| "1970-01-01T00:00:00Z".into());
+    format!(
+        "{}.{:03}Z",
+        secs.trim_end_matches('Z'),
+        since_epoch.subsec_millis()
+    )
+}
+
+/// Directory of the trash objects deleted at `now` are moved to, which sort in the order objects
+/// are deleted
+fn trash_dir(now: SystemTime) -> String {
+    format!("{TRASH_PREFIX}{}/", snapshot_id(now))
+}
+
+/// Number of the version `candidate` of the object `key`, written by a link with write-once keys,
+/// `None` if it is not a version of the object
+fn key_version(key: &str, candidate: &str) -> Option<u64> {
+    let n = candidate
+        .strip_prefix(key)?
+        .strip_prefix(KEY_VERSION_SEPARATOR)?;
+    if n.is_empty() || !n.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    n.parse().ok()
+}
+
+/// Hash data as it is read, hex-encoding the digest
+async fn hash<D: Digest>(
+    data: impl Stream<Item = std::io::Result<Bytes>>,
+) -> anyhow::Result<String> {
+    let digest = data
+        .try_fold(D::new(), |mut digest, chunk| async move {
+            digest.update(&chunk);
+            Ok(digest)
+        })
+        .await
+        .context("failed to read object")?;
+    Ok(digest
+        .finalize()
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect())
+}
+
+/// Whether the listed object is garbage, i.e. not one of the `members` of live manifests, last
+/// modified before `before`, in seconds since the Unix epoch, and not internal to the provider
+fn is_garbage(
+    ObjectSummary {
+        key, last_modified, ..
+    }: &ObjectSummary,
+    members: &HashSet<String>,
+    before: u64,
+) -> bool {
+    // manifests, locks, uploads, snapshots and the like all share the `.wasmcloud-` prefix
+    *last_modified < before
+        && !members.contains(key)
+        && !key.starts_with(".wasmcloud-")
+        && !key.starts_with(TRASH_PREFIX)
+}
+
+/// Entries of the objects listed under `prefix`, ordered by key
+fn diff_entries(prefix: &str, objects: Vec<Object>) -> Vec<DiffEntry> {
+    let mut entries: Vec<_> = objects
+        .into_iter()
+        .filter_map(
+            |Object {
+                 key, size, e_tag, ..
+             }| {
+                Some(DiffEntry {
+                    key: key?.strip_prefix(prefix)?.to_string(),
+                    size: size.unwrap_or_default(),
+                    etag: e_tag,
+                })
+            },
+        )
+        .collect();
+    entries.sort_unstable_by(|a, b| a.key.cmp(&b.key));
+    entries
+}
+
+/// Changes, which make the `target` entries match the `source` entries, both ordered by key
+fn diff_changes(source: Vec<DiffEntry>, target: Vec<DiffEntry>) -> Vec<Change> {
+    let mut changes = Vec::new();
+    let mut source = source.into_iter().peekable();
+    let mut target = target.into_iter().peekable();
+    loop {
+        let kind = match (source.peek(), target.peek()) {
+            (None, None) => return changes,
+            (Some(_), None) => ChangeKind::Added,
+            (None, Some(_)) => ChangeKind::Removed,
+            (Some(src), Some(dest)) if src.key < dest.key => ChangeKind::Added,
+            (Some(src), Some(dest)) if src.key > dest.key => ChangeKind::Removed,
+            (Some(_), Some(_)) => ChangeKind::Changed,
+        };
+        let entry = match kind {
+            ChangeKind::Added => source.next(),
+            ChangeKind::Removed => target.next(),
+            ChangeKind::Changed => {
+                let (src, dest) = (source.next(), target.next());
+                if src == dest {
+                    continue;
+                }
+                src
+            }
+        };
+        if let Some(DiffEntry { key, .. }) = entry {
+            changes.push(Change { key, kind });
+        }
+    }
+}
+
+/// Ensure that the name or ID of a manifest is a single, non-empty segment of a key
+fn validate_manifest_part(what: &str, part: &str) -> anyhow::Result<()> {
+    ensure!(
+        !part.is_empty() && part != "." && part != ".." && !part.contains('/'),
+        "invalid manifest {what} `{part}`"
+    );
+    Ok(())
+}
+
+/// Parse the key of an object in the trash, `None` if it is not in a trash directory
+fn trashed_object(trash_key: String) -> Option<TrashedObject> {
+    let (deleted_at, key) = trash_key.strip_prefix(TRASH_PREFIX)?.split_once('/')?;
+    Some(TrashedObject {
+        key: key.to_string(),
+        deleted_at: deleted_at.to_string(),
+        trash_key,
+    })
+}
+
+/// Parse a `ttl` tag value, consisting of a number and one of the units `d`, `h`, `m` or `s`
+fn parse_ttl(ttl: &str) -> Option<Duration> {
+    let unit = match ttl.chars().last()? {
+        'd' => 24 * 60 * 60,
+        'h' => 60 * 60,
+        'm' => 60,
+        's' => 1,
+        _ => return None,
+    };
+    let n: u64 = ttl[..ttl.len() - 1].parse().ok()?;
+    n.checked_mul(unit).map(Duration::from_secs)
+}
+
+/// Build an IMDSv2 credentials provider, verifying that instance metadata is reachable
+async fn imds_credentials_provider(
+    ImdsConfig {
+        endpoint,
+        max_attempts,
+        connect_timeout_ms,
+        read_timeout_ms,
+    }: &ImdsConfig,
+) -> anyhow::Result<ImdsCredentialsProvider> {
+    let mut client = imds::Client::builder();
+    if let Some(endpoint) = endpoint {
+        client = client
+            .endpoint(endpoint)
+            .map_err(|err| anyhow!(err).context("invalid IMDS endpoint"))?;
+    }
+    if let Some(max_attempts) = max_attempts {
+        client = client.max_attempts(*max_attempts);
+    }
+    if let Some(timeout) = connect_timeout_ms {
+        client = client.connect_timeout(Duration::from_millis(*timeout));
+    }
+    if let Some(timeout) = read_timeout_ms {
+        client = client.read_timeout(Duration::from_millis(*timeout));
+    }
+    let provider = ImdsCredentialsProvider::builder()
+        .imds_client(client.build())
+        .build();
+    // Fail early rather than on the first request if IMDS cannot provide credentials
+    provider.provide_credentials().await.context(
+        "failed to load credentials from instance metadata (IMDSv2), if the provider runs in a \
+         container ensure the instance metadata hop limit is at least 2",
+    )?;
+    Ok(provider)
+}
+
+/// Error of S3 Batch Operations, if the provider was built without the `batch-operations` feature
+#[cfg(not(feature = "batch-operations"))]
+fn batch_operations_disabled() -> BackendError {
+    BackendError::new(
+        ErrorKind::PreconditionFailed,
+        "S3 Batch Operations require the provider to be built with the `batch-operations` feature",
+    )
+}
+
+/// Error of CloudFront signing, if the provider was built without the `cdn-signing` feature
+#[cfg(not(feature = "cdn-signing"))]
+fn cdn_signing_disabled() -> BackendError {
+    BackendError::new(
+        ErrorKind::PreconditionFailed,
+        "CloudFront signing requires the provider to be built with the `cdn-signing` feature",
+    )
+}
+
+/// Error of presigning URLs, if the provider was built without the `presign` feature
+#[cfg(not(feature = "presign"))]
+fn presign_disabled() -> BackendError {
+    BackendError::new(
+        ErrorKind::PreconditionFailed,
+        "download URLs require the provider to be built with the `presign` feature",
+    )
+}
+
+/// Error of re-encrypting objects, if the provider was built without the `encryption` feature
+#[cfg(not(feature = "encryption"))]
+fn encryption_disabled() -> BackendError {
+    BackendError::new(
+        ErrorKind::PreconditionFailed,
+        "re-encrypting objects requires the provider to be built with the `encryption` feature",
+    )
+}
+
+/// Whether a container name is an S3 (or Multi-Region) Access Point ARN, e.g.
+/// `arn:aws:s3:us-west-2:123456789012:accesspoint/example` or
+/// `arn:aws:s3::123456789012:accesspoint/mfzwi23gnjvgw.mrap`
+///
+/// Multi-Region Access Points have no region and are signed with SigV4A.
+pub fn is_access_point_arn(name: &str) -> bool {
+    let mut parts = name.splitn(6, ':');
+    matches!(
+        (
+            parts.next(),
+            parts.next(),
+            parts.next(),
+            parts.next(),
+            parts.next(),
+            parts.next(),
+        ),
+        (Some("arn"), Some(partition), Some("s3"), Some(_), Some(account), Some(resource))
+            if !partition.is_empty() && !account.is_empty() && resource.starts_with("accesspoint/")
+    )
+}
+
+/// Build the HTTP client used for S3 requests
+fn build_http_client(
+    HttpClientConfig {
+        pool_max_idle_per_host,
+        pool_idle_timeout_ms,
+        tcp_keepalive_ms,
+        http_version,
+    }: &HttpClientConfig,
+    connections: &Arc<ConnectionCounters>,
+) -> SharedHttpClient {
+    let mut http = HttpConnector::new();
+    http.enforce_http(false);
+    http.set_keepalive(tcp_keepalive_ms.map(Duration::from_millis));
+    let https = hyper_rustls::HttpsConnectorBuilder::new()
+        .with_tls_config(
+            // use `tls::DEFAULT_CLIENT_CONFIG` directly once `rustls` versions
+            // are in sync
+            rustls::ClientConfig::builder()
+                .with_root_certificates(rustls::RootCertStore {
+                    roots: tls::DEFAULT_ROOTS.roots.clone(),
+                })
+                .with_no_client_auth(),
+        )
+        .https_or_http();
+    let https = match http_version {
+        HttpVersion::Auto => https.enable_all_versions().wrap_connector(http),
+        HttpVersion::Http1 => https.enable_http1().wrap_connector(http),
+        HttpVersion::Http2 => https.enable_http2().wrap_connector(http),
+    };
+
+    let mut builder = hyper::Client::builder();
+    if let Some(max_idle) = pool_max_idle_per_host {
+        builder.pool_max_idle_per_host(*max_idle);
+    }
+    if let Some(timeout) = pool_idle_timeout_ms {
+        builder.pool_idle_timeout(Duration::from_millis(*timeout));
+    }
+    if *http_version == HttpVersion::Http2 {
+        builder.http2_only(true);
+    }
+    HyperClientBuilder::new()
+        .hyper_builder(builder)
+        .build(CountConnections::new(https, Arc::clone(connections)))
+}
+
+#[cfg(test)]
+mod test {
+    use aws_sdk_s3::types::Type;
+    use tokio::sync::mpsc;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn aliases() {
+        let client = StorageClient::new(
+            StorageConfig::default(),
+            &HashMap::from([(format!("{ALIAS_PREFIX}foo"), "bar".into())]),
+        )
+        .await
+        .expect("failed to create client");
+
+        // no alias
+        assert_eq!(client.unalias("boo"), "boo");
+        // alias without prefix
+        assert_eq!(client.unalias("foo"), "bar");
+        // alias with prefix
+        assert_eq!(client.unalias(&format!("{ALIAS_PREFIX}foo")), "bar");
+        // undefined alias
+        assert_eq!(client.unalias(&format!("{ALIAS_PREFIX}baz")), "baz");
+    }
+
+    #[tokio::test]
+    async fn cancel_link_work() {
+        let client = StorageClient::new(StorageConfig::default(), &HashMap::new())
+            .await
+            .expect("failed to create client");
+        let (tx, mut rx) = mpsc::channel::<()>(1);
+        client.spawn(async move {
+            let _tx = tx;
+            futures::future::pending::<()>().await;
+        });
+        let work = client.abort_on_delete(futures::future::pending::<Result<(), String>>());
+        client.cancel();
+        assert!(work.await.is_err());
+        // the background task drops its sender once aborted
+        assert!(rx.recv().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn shared_clients() {
+        let registry = ClientRegistry::default();
+        let config = StorageConfig {
+            region: Some("us-east-1".into()),
+            access_key_id: Some("foo".into()),
+            secret_access_key: Some("bar".into()),
+            ..Default::default()
+        };
+        let a = registry
+            .storage_client(config.clone(), &HashMap::new())
+            .await
+            .expect("failed to create client");
+        let b = registry
+            .storage_client(
+                config.clone(),
+                &HashMap::from([(format!("{ALIAS_PREFIX}foo"), "bar".into())]),
+            )
+            .await
+            .expect("failed to create client");
+        let c = registry
+            .storage_client(
+                StorageConfig {
+                    secret_access_key: Some("baz".into()),
+                    ..config
+                },
+                &HashMap::new(),
+            )
+            .await
+            .expect("failed to create client");
+        assert!(Arc::ptr_eq(&a.clients, &b.clients));
+        assert!(!Arc::ptr_eq(&a.clients, &c.clients));
+        assert_eq!(b.unalias("foo"), "bar");
+        assert_eq!(a.unalias("foo"), "foo");
+    }
+
+    #[test]
+    fn access_point_arns() {
+        assert!(is_access_point_arn(
+            "arn:aws:s3:us-west-2:123456789012:accesspoint/example"
+        ));
+        assert!(is_access_point_arn(
+            "arn:aws:s3::123456789012:accesspoint/mfzwi23gnjvgw.mrap"
+        ));
+        assert!(!is_access_point_arn("example"));
+        assert!(!is_access_point_arn("arn:aws:s3:::example"));
+        assert!(!is_access_point_arn(
+            "arn:aws:iam::123456789012:role/example"
+        ));
+    }
+
+    #[test]
+    fn ttls() {
+        assert_eq!(parse_ttl("7d"), Some(Duration::from_secs(7 * 24 * 60 * 60)));
+        assert_eq!(parse_ttl("12h"), Some(Duration::from_secs(12 * 60 * 60)));
+        assert_eq!(parse_ttl("30m"), Some(Duration::from_secs(30 * 60)));
+        assert_eq!(parse_ttl("45s"), Some(Duration::from_secs(45)));
+        assert_eq!(parse_ttl("7"), None);
+        assert_eq!(parse_ttl("d"), None);
+        assert_eq!(parse_ttl(""), None);
+        assert_eq!(parse_ttl("-1d"), None);
+    }
+
+    #[test]
+    fn upload_manifest_keys() {
+        let key = upload_manifest_key("foo", "1");
+        assert!(key.starts_with(UPLOADS_PREFIX));
+        assert_eq!(key, upload_manifest_key("foo", "1"));
+        assert_ne!(key, upload_manifest_key("foo", "2"));
+        assert_ne!(
+            upload_manifest_key("foo1", ""),
+            upload_manifest_key("foo", "1")
+        );
+    }
+
+    #[test]
+    fn snapshot_ids() {
+        let at = SystemTime::UNIX_EPOCH + Duration::from_millis(1_709_622_000_042);
+        assert_eq!(snapshot_id(at), "2024-03-05T07:00:00.042Z");
+        assert!(snapshot_id(at) < snapshot_id(at + Duration::from_millis(1)));
+        assert!(snapshot_id(at) < snapshot_id(at + Duration::from_secs(1)));
+    }
+
+    #[test]
+    fn trash_dirs() {
+        let at = SystemTime::UNIX_EPOCH + Duration::from_millis(1_709_622_000_042);
+        assert_eq!(trash_dir(at), ".trash/2024-03-05T07:00:00.042Z/");
+        // objects deleted before a cutoff sort before its directory
+        let key = format!("{}photos/cat.jpg", trash_dir(at));
+        assert!(key < trash_dir(at + Duration::from_millis(1)));
+        assert!(key > trash_dir(at));
+    }
+
+    #[test]
+    fn key_versions() {
+        assert_eq!(key_version("log.txt", "log.txt@1"), Some(1));
+        assert_eq!(key_version("log.txt", "log.txt@42"), Some(42));
+        assert_eq!(key_version("log.txt", "log.txt"), None);
+        assert_eq!(key_version("log.txt", "log.txt@"), None);
+        assert_eq!(key_version("log.txt", "log.txt@1/nested"), None);
+        assert_eq!(key_version("log.txt", "log.txt@+1"), None);
+        assert_eq!(key_version("log", "log.txt@1"), None);
+        // versions of versions are not versions of the object
+        assert_eq!(key_version("log.txt", "log.txt@1@2"), None);
+    }
+
+    #[test]
+    fn manifest_parts() {
+        assert!(validate_manifest_part("name", "site").is_ok());
+        assert!(validate_manifest_part("ID", "0f8fad5b-d9cb-469f-a165-70867728950e").is_ok());
+        assert!(validate_manifest_part("name", "").is_err());
+        assert!(validate_manifest_part("name", "..").is_err());
+        assert!(validate_manifest_part("name", "site/v2").is_err());
+    }
+
+    #[tokio::test]
+    async fn hashes() {
+        let data =
+            || futures::stream::iter(["hello, ", "world"].map(|chunk| Ok(Bytes::from(chunk))));
+        assert_eq!(
+            hash::<Sha256>(data()).await.unwrap(),
+            "09ca7e4eaa6e8ae9c7d261167129184883644d07dfba7cbfbc4c8a2e08360d5b"
+        );
+        assert_eq!(
+            hash::<Md5>(data()).await.unwrap(),
+            "e4d7f1b4ed2e42d15898f4b27b019da4"
+        );
+        let failing = futures::stream::iter([Err(std::io::Error::other("connection reset"))]);
+        assert!(hash::<Sha256>(failing).await.is_err());
+    }
+
+    #[test]
+    fn garbage() {
+        let summary = |key: &str, last_modified| ObjectSummary {
+            key: key.into(),
+            size: 1,
+            last_modified,
+        };
+        let members = HashSet::from(["blobs/sha256/aa".to_string()]);
+        assert!(is_garbage(&summary("blobs/sha256/bb", 10), &members, 100));
+        assert!(!is_garbage(&summary("blobs/sha256/aa", 10), &members, 100));
+        // within the grace period
+        assert!(!is_garbage(&summary("blobs/sha256/bb", 100), &members, 100));
+        assert!(!is_garbage(
+            &summary(".wasmcloud-manifests/site/current", 10),
+            &members,
+            100
+        ));
+        assert!(!is_garbage(
+            &summary(".trash/1700000000/blobs/sha256/bb", 10),
+            &members,
+            100
+        ));
+    }
+
+    #[tokio::test]
+    async fn garbage_with_dated_keys() {
+        let client = StorageClient::new(
+            StorageConfig {
+                key_template: Some("{yyyy}/{mm}/{key}".into()),
+                ..Default::default()
+            },
+            &HashMap::new(),
+        )
+        .await
+        .expect("failed to create client");
+        let summary = |key: &str| ObjectSummary {
+            key: key.into(),
+            size: 1,
+            last_modified: 10,
+        };
+        let now = client
+            .object_key("app.js")
+            .expect("failed to resolve key")
+            .into_owned();
+
+        // members of manifests created in an earlier month resolve to other keys now
+        let members = client
+            .manifest_object_keys(vec![ManifestObject {
+                members: vec!["app.js".into()],
+                object_keys: vec!["2024/03/app.js".into()],
+                created_at: 0,
+            }])
+            .expect("failed to resolve manifest keys");
+        assert!(!is_garbage(&summary("2024/03/app.js"), &members, 100));
+        assert!(is_garbage(&summary(&now), &members, 100));
+
+        // manifests without recorded object keys resolve their members now
+        let members = client
+            .manifest_object_keys(vec![ManifestObject {
+                members: vec!["app.js".into()],
+                object_keys: Vec::new(),
+                created_at: 0,
+            }])
+            .expect("failed to resolve manifest keys");
+        assert!(!is_garbage(&summary(&now), &members, 100));
+    }
+
+    #[test]
+    fn diffs() {
+        let object =
+            |key: &str, size, etag: &str| Object::builder().key(key).size(size).e_tag(etag).build();
+        let source = diff_entries(
+            "release/",
+            vec![
+                object("release/index.html", 10, "\"a\""),
+                object("release/app.js", 20, "\"b\""),
+                object("release/new.css", 30, "\"c\""),
+            ],
+        );
+        let target = diff_entries(
+            "live/",
+            vec![
+                object("live/app.js", 20, "\"b2\""),
+                object("live/index.html", 10, "\"a\""),
+                object("live/old.css", 30, "\"d\""),
+            ],
+        );
+        let change = |key: &str, kind| Change {
+            key: key.into(),
+            kind,
+        };
+        assert_eq!(
+            diff_changes(source.clone(), target),
+            [
+                change("app.js", ChangeKind::Changed),
+                change("new.css", ChangeKind::Added),
+                change("old.css", ChangeKind::Removed),
+            ]
+        );
+        assert!(diff_changes(source.clone(), source).is_empty());
+    }
+
+    #[test]
+    fn trash_keys() {
+        assert_eq!(
+            trashed_object(".trash/2024-03-05T07:00:00.042Z/photos/cat.jpg".into()),
+            Some(TrashedObject {
+                key: "photos/cat.jpg".into(),
+                deleted_at: "2024-03-05T07:00:00.042Z".into(),
+                trash_key: ".trash/2024-03-05T07:00:00.042Z/photos/cat.jpg".into(),
+            })
+        );
+        assert_eq!(trashed_object(".trash/stray".into()), None);
+        assert_eq!(trashed_object("photos/cat.jpg".into()), None);
+    }
+
+    #[test]
+    fn copy_verification() {
+        let head = |size, etag: &str| {
+            HeadObjectOutput::builder()
+                .content_length(size)
+                .e_tag(etag)
+                .build()
+        };
+        verify_copy(&head(3, "\"a\""), &head(3, "\"a\""), true).expect("copy should be verified");
+        verify_copy(&head(3, "\"a\""), &head(2, "\"a\""), true)
+            .expect_err("size should be verified");
+        verify_copy(&head(3, "\"a\""), &head(3, "\"b\""), true)
+            .expect_err("ETag should be verified");
+        verify_copy(&head(3, "\"a\""), &head(3, "\"b\""), false)
+            .expect("ETag should not be verified");
+        verify_copy(&head(3, "\"a\""), &head(2, "\"a\""), false)
+            .expect_err("size should be verified");
+        // copies of multipart uploads have a different ETag
+        verify_copy(&head(3, "\"a-2\""), &head(3, "\"b\""), true).expect("copy should be verified");
+    }
+
+    #[test]
+    fn request_estimates() {
+        assert_eq!(listing_requests(0), 1);
+        assert_eq!(listing_requests(1000), 1);
+        assert_eq!(listing_requests(1001), 2);
+        let object = |size| Object::builder().key("key").size(size).build();
+        assert_eq!(object_bytes(&[object(3), object(4)]), 7);
+    }
+
+    #[test]
+    fn scatter_ranges() {
+        assert!(validate_ranges(&[(100, 200), (0, 8), (500, 1000)]).is_ok());
+        assert!(validate_ranges(&[(0, 8), (8, 16)]).is_ok());
+        assert!(validate_ranges(&[]).is_err());
+        assert!(validate_ranges(&[(8, 8)]).is_err());
+        assert!(validate_ranges(&[(0, 100), (50, 150)]).is_err());
+        assert!(validate_ranges(&vec![(0, 1); MAX_SCATTER_RANGES + 1]).is_err());
+    }
+
+    #[test]
+    fn retry_delays() {
+        assert_eq!(retry_delay(0, 0), Duration::from_millis(50));
+        assert_eq!(retry_delay(0, 999), Duration::from_micros(99_950));
+        assert_eq!(retry_delay(2, 500), Duration::from_millis(300));
+        assert_eq!(retry_delay(10, 0), Duration::from_millis(2500));
+        assert_eq!(retry_delay(u32::MAX, 0), Duration::from_millis(2500));
+        assert!(is_transient_error("SlowDown"));
+        assert!(!is_transient_error("AccessDenied"));
+    }
+
+    #[test]
+    fn public_grants() {
+        let grant = |grantee: Grantee| Grant::builder().grantee(grantee).build();
+        let group = |uri: &str| {
+            Grantee::builder()
+                .r#type(Type::Group)
+                .uri(uri)
+                .build()
+                .expect("failed to build grantee")
+        };
+        assert!(is_public_grant(&grant(group(
+            "http://acs.amazonaws.com/groups/global/AllUsers"
+        ))));
+        assert!(is_public_grant(&grant(group(
+            "http://acs.amazonaws.com/groups/global/AuthenticatedUsers"
+        ))));
+        assert!(!is_public_grant(&grant(group(
+            "http://acs.amazonaws.com/groups/s3/LogDelivery"
+        ))));
+        let owner = Grantee::builder()
+            .r#type(Type::CanonicalUser)
+            .id("owner")
+            .build()
+            .expect("failed to build grantee");
+        assert!(!is_public_grant(&grant(owner)));
+    }
+}
@@ -0,0 +1,168 @@
+//! S3 Batch Operations jobs, which copy huge numbers of objects using the managed machinery of
+//! AWS rather than the provider, only built with the `batch-operations` feature
+
+use anyhow::{bail, Context as _};
+use aws_sdk_s3control::types as control;
+use tracing::instrument;
+use uuid::Uuid;
+
+use super::{is_access_point_arn, BatchJobInfo, StorageClient};
+use crate::config::BatchOperationsConfig;
+
+/// Priority of S3 Batch Operations jobs, unless configured otherwise
+const DEFAULT_BATCH_JOB_PRIORITY: i32 = 10;
+/// Prefix of the completion reports of S3 Batch Operations jobs
+const BATCH_REPORTS_PREFIX: &str = ".wasmcloud-batch-reports";
+
+impl StorageClient {
+    /// Account and role of S3 Batch Operations jobs of the link
+    fn batch_operations(&self) -> anyhow::Result<&BatchOperationsConfig> {
+        self.batch_operations
+            .as_ref()
+            .context("S3 Batch Operations are not configured for the link")
+    }
+
+    /// Create an S3 Batch Operations job copying the objects of `src_bucket`, whose keys start
+    /// with `prefix`, to `dest_bucket`, returning its ID
+    ///
+    /// S3 generates the manifest of the job from the objects of `src_bucket` and copies the
+    /// objects, so that huge numbers of objects can be copied without the provider listing or
+    /// copying any of them.
+    #[instrument(level = "debug", skip(self))]
+    pub async fn start_batch_copy(
+        &self,
+        src_bucket: &str,
+        dest_bucket: &str,
+        prefix: Option<&str>,
+        description: &str,
+    ) -> anyhow::Result<String> {
+        let BatchOperationsConfig {
+            account_id,
+            role_arn,
+            report_bucket,
+            priority,
+        } = self.batch_operations()?;
+        if is_access_point_arn(src_bucket) || is_access_point_arn(dest_bucket) {
+            bail!("S3 Batch Operations jobs cannot be created for access points");
+        }
+        let report = match report_bucket {
+            Some(bucket) => control::JobReport::builder()
+                .enabled(true)
+                .bucket(format!("arn:aws:s3:::{}", self.unalias(bucket)))
+                .prefix(BATCH_REPORTS_PREFIX)
+                .format(control::JobReportFormat::ReportCsv20180820)
+                .report_scope(control::JobReportScope::FailedTasksOnly),
+            None => control::JobReport::builder().enabled(false),
+        }
+        .build();
+        let mut generator = control::S3JobManifestGenerator::builder()
+            .source_bucket(format!("arn:aws:s3:::{src_bucket}"))
+            .enable_manifest_output(false);
+        if let Some(prefix) = prefix {
+            generator = generator.filter(
+                control::JobManifestGeneratorFilter::builder()
+                    .key_name_constraint(
+                        control::KeyNameConstraint::builder()
+                            .match_any_prefix(prefix)
+                            .build(),
+                    )
+                    .build(),
+            );
+        }
+        let generator = generator
+            .build()
+            .context("failed to build job manifest generator")?;
+        let out = self
+            .clients
+            .s3control
+            .create_job()
+            .account_id(account_id)
+            .role_arn(role_arn)
+            .priority(priority.unwrap_or(DEFAULT_BATCH_JOB_PRIORITY))
+            .confirmation_required(false)
+            .client_request_token(Uuid::new_v4().to_string())
+            .description(description)
+            .operation(
+                control::JobOperation::builder()
+                    .s3_put_object_copy(
+                        control::S3CopyObjectOperation::builder()
+                            .target_resource(format!("arn:aws:s3:::{dest_bucket}"))
+                            .build(),
+                    )
+                    .build(),
+            )
+            .manifest_generator(control::JobManifestGenerator::S3JobManifestGenerator(
+                generator,
+            ))
+            .report(report)
+            .send()
+            .await
+            .map_err(|err| self.request_error(err))
+            .context("failed to create S3 Batch Operations job")?;
+        out.job_id
+            .context("S3 Batch Operations job was created without an ID")
+    }
+
+    /// Status of the S3 Batch Operations job `id`
+    #[instrument(level = "debug", skip(self))]
+    pub async fn batch_job(&self, id: &str) -> anyhow::Result<BatchJobInfo> {
+        let BatchOperationsConfig { account_id, .. } = self.batch_operations()?;
+        let job = self
+            .clients
+            .s3control
+            .describe_job()
+            .account_id(account_id)
+            .job_id(id)
+            .send()
+            .await
+            .map_err(|err| self.request_error(err))
+            .context("failed to describe S3 Batch Operations job")?
+            .job
+            .context("S3 Batch Operations job not found")?;
+        let tasks = |n: Option<i64>| n.and_then(|n| n.try_into().ok());
+        let progress = job.progress_summary.as_ref();
+        Ok(BatchJobInfo {
+            id: id.to_string(),
+            status: job
+                .status
+                .as_ref()
+                .map(|status| status.as_str().to_string())
+                .unwrap_or_default(),
+            description: job.description.clone(),
+            total: tasks(progress.and_then(|p| p.total_number_of_tasks)),
+            succeeded: tasks(progress.and_then(|p| p.number_of_tasks_succeeded)).unwrap_or(0),
+            failed: tasks(progress.and_then(|p| p.number_of_tasks_failed)).unwrap_or(0),
+            failure_reasons: job
+                .failure_reasons
+                .unwrap_or_default()
+                .into_iter()
+                .map(|failure| {
+                    format!(
+                        "{}: {}",
+                        failure.failure_code.unwrap_or_default(),
+                        failure.failure_reason.unwrap_or_default()
+                    )
+                })
+                .collect(),
+        })
+    }
+
+    /// Cancel the S3 Batch Operations job `id`
+    ///
+    /// Objects already copied by the job are not deleted.
+    #[instrument(level = "debug", skip(self))]
+    pub async fn cancel_batch_job(&self, id: &str) -> anyhow::Result<()> {
+        let BatchOperationsConfig { account_id, .. } = self.batch_operations()?;
+        self.clients
+            .s3control
+            .update_job_status()
+            .account_id(account_id)
+            .job_id(id)
+            .requested_job_status(control::RequestedJobStatus::Cancelled)
+            .send()
+            .await
+            .map_err(|err| self.request_error(err))
+            .context("failed to cancel S3 Batch Operations job")?;
+        Ok(())
+    }
+}
@@ -0,0 +1,148 @@
+//! Re-encryption of objects with KMS keys, by copying them onto themselves, only built with the
+//! `encryption` feature
+
+use anyhow::Context as _;
+use aws_sdk_s3::types::{MetadataDirective, Object, ServerSideEncryption, TaggingDirective};
+use futures::StreamExt as _;
+use tokio::sync::mpsc;
+use tracing::instrument;
+
+use super::{
+    is_access_point_arn, listing_requests, object_bytes, track_progress, Estimate, Progress,
+    StorageClient, MAX_CONCURRENT_COPIES,
+};
+
+impl StorageClient {
+    /// Re-encrypt all objects of `bucket`, or of those with keys starting with `prefix`, with the
+    /// KMS key `kms_key_id` by copying each object onto itself, reporting the progress to
+    /// `progress`, if set
+    ///
+    /// Metadata, tags and storage classes are kept. Objects already encrypted with the key are
+    /// skipped, which can only be determined if `kms_key_id` is a key ID or ARN rather than an
+    /// alias, and objects changed while they are being copied are not overwritten and fail
+    /// instead. In versioned buckets, noncurrent versions stay encrypted with their original key.
+    #[instrument(level = "debug", skip(self, progress))]
+    pub async fn reencrypt_container(
+        &self,
+        bucket: &str,
+        kms_key_id: &str,
+        prefix: Option<&str>,
+        progress: Option<&mpsc::Sender<Progress>>,
+    ) -> anyhow::Result<Progress> {
+        let keys: Vec<_> = self
+            .list_objects(bucket, prefix)
+            .await?
+            .into_iter()
+            .filter_map(|Object { key, .. }| key)
+            .collect();
+        let mut state = Progress {
+            total: Some(keys.len() as u64),
+            ..Progress::default()
+        };
+        let reencrypted = futures::stream::iter(keys.into_iter().map(|key| async move {
+            self.reencrypt_object(bucket, &key, kms_key_id)
+                .await
+                .with_context(|| format!("failed to re-encrypt `{key}`"))
+        }))
+        .buffer_unordered(MAX_CONCURRENT_COPIES);
+        track_progress(reencrypted, &mut state, progress).await?;
+        Ok(state)
+    }
+
+    /// Estimate the scope of re-encrypting objects of `bucket` without copying anything, like
+    /// [`Self::reencrypt_container`] would
+    ///
+    /// Objects already encrypted with the key are included, since finding them takes a request
+    /// per object, so the estimate is an upper bound.
+    #[instrument(level = "debug", skip(self))]
+    pub async fn estimate_reencrypt(
+        &self,
+        bucket: &str,
+        prefix: Option<&str>,
+    ) -> anyhow::Result<Estimate> {
+        let objects = self.list_objects(bucket, prefix).await?;
+        let count = objects.len() as u64;
+        Ok(Estimate {
+            objects: count,
+            bytes: object_bytes(&objects),
+            // each object is looked up and copied
+            requests: listing_requests(count) + 2 * count,
+        })
+    }
+
+    /// Copy the object `key` onto itself encrypted with the KMS key `kms_key_id`, returning its
+    /// size or `None` if it is already encrypted with the key
+    async fn reencrypt_object(
+        &self,
+        bucket: &str,
+        key: &str,
+        kms_key_id: &str,
+    ) -> anyhow::Result<Option<u64>> {
+        let head = self
+            .s3(bucket)
+            .head_object()
+            .bucket(bucket)
+            .key(key)
+            .send()
+            .await
+            .map_err(|err| self.request_error(err))
+            .context("failed to get object metadata")?;
+        if head.server_side_encryption == Some(ServerSideEncryption::AwsKms)
+            && head
+                .ssekms_key_id
+                .as_deref()
+                .is_some_and(|current| is_kms_key(current, kms_key_id))
+        {
+            return Ok(None);
+        }
+        let copy_source = if is_access_point_arn(bucket) {
+            format!("{bucket}/object/{key}")
+        } else {
+            format!("{bucket}/{key}")
+        };
+        self.s3(bucket)
+            .copy_object()
+            .copy_source(copy_source)
+            .set_copy_source_if_match(head.e_tag)
+            .bucket(bucket)
+            .key(key)
+            .metadata_directive(MetadataDirective::Copy)
+            .tagging_directive(TaggingDirective::Copy)
+            .set_storage_class(head.storage_class)
+            .server_side_encryption(ServerSideEncryption::AwsKms)
+            .ssekms_key_id(kms_key_id)
+            .send()
+            .await
+            .map_err(|err| self.request_error(err))
+            .context("failed to copy object")?;
+        Ok(Some(
+            head.content_length
+                .unwrap_or_default()
+                .try_into()
+                .unwrap_or_default(),
+        ))
+    }
+}
+
+/// Whether the KMS key ARN `current`, as reported for encrypted objects, identifies the key
+/// `kms_key_id`, given as key ID or ARN
+fn is_kms_key(current: &str, kms_key_id: &str) -> bool {
+    current == kms_key_id
+        || current
+            .strip_suffix(kms_key_id)
+            .is_some_and(|arn| arn.ends_with(":key/"))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn kms_keys() {
+        let arn = "arn:aws:kms:us-east-1:123456789012:key/1234abcd-12ab-34cd-56ef-1234567890ab";
+        assert!(is_kms_key(arn, arn));
+        assert!(is_kms_key(arn, "1234abcd-12ab-34cd-56ef-1234567890ab"));
+        assert!(!is_kms_key(arn, "34cd-56ef-1234567890ab"));
+        assert!(!is_kms_key(arn, "alias/rotated"));
+    }
+}
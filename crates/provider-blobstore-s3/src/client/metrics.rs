@@ -0,0 +1,110 @@
+//! Statistics of buckets from their daily S3 storage metrics in CloudWatch, only built with the
+//! `storage-metrics` feature
+
+use core::time::Duration;
+
+use std::time::SystemTime;
+
+use anyhow::Context as _;
+use aws_sdk_cloudwatch::types as cw;
+use aws_sdk_s3::primitives::DateTime;
+
+use super::ContainerStats;
+
+/// Period of the S3 storage metrics in CloudWatch in seconds
+const STORAGE_METRICS_PERIOD: u64 = 24 * 60 * 60;
+
+/// Statistics of the objects of a bucket from its daily S3 storage metrics in CloudWatch
+pub(super) async fn storage_metrics(
+    cloudwatch: &aws_sdk_cloudwatch::Client,
+    bucket: &str,
+) -> anyhow::Result<ContainerStats> {
+    let bucket_dimension = |name: &str| {
+        cw::Dimension::builder()
+            .name("BucketName")
+            .value(name)
+            .build()
+    };
+    // the size of objects is reported per storage class, their number for all of them
+    let mut metrics = vec![cw::Metric::builder()
+        .namespace("AWS/S3")
+        .metric_name("NumberOfObjects")
+        .dimensions(bucket_dimension(bucket))
+        .dimensions(
+            cw::Dimension::builder()
+                .name("StorageType")
+                .value("AllStorageTypes")
+                .build(),
+        )
+        .build()];
+    let mut pages = cloudwatch
+        .list_metrics()
+        .namespace("AWS/S3")
+        .metric_name("BucketSizeBytes")
+        .dimensions(
+            cw::DimensionFilter::builder()
+                .name("BucketName")
+                .value(bucket)
+                .build(),
+        )
+        .into_paginator()
+        .send();
+    while let Some(page) = pages.next().await {
+        let page = page.context("failed to list bucket metrics")?;
+        metrics.extend(page.metrics.unwrap_or_default());
+    }
+    let now = SystemTime::now();
+    let mut queries = cloudwatch
+        .get_metric_data()
+        // metrics are reported once a day, with a delay of up to a day
+        .start_time(DateTime::from(
+            now - Duration::from_secs(3 * STORAGE_METRICS_PERIOD),
+        ))
+        .end_time(DateTime::from(now))
+        .scan_by(cw::ScanBy::TimestampDescending);
+    for (i, metric) in metrics.into_iter().enumerate() {
+        queries = queries.metric_data_queries(
+            cw::MetricDataQuery::builder()
+                .id(format!("m{i}"))
+                .metric_stat(
+                    cw::MetricStat::builder()
+                        .metric(metric)
+                        .period(STORAGE_METRICS_PERIOD as i32)
+                        .stat("Average")
+                        .build(),
+                )
+                .build(),
+        );
+    }
+    let results = queries
+        .send()
+        .await
+        .context("failed to get bucket metrics")?
+        .metric_data_results
+        .unwrap_or_default();
+    let mut stats = ContainerStats::default();
+    for cw::MetricDataResult {
+        id,
+        timestamps,
+        values,
+        ..
+    } in results
+    {
+        // results are ordered by descending time
+        let (Some(at), Some(value)) = (
+            timestamps.unwrap_or_default().first().copied(),
+            values.unwrap_or_default().first().copied(),
+        ) else {
+            continue;
+        };
+        let value = value.max(0.0) as u64;
+        if id.as_deref() == Some("m0") {
+            stats.object_count = Some(value);
+        } else {
+            stats.total_bytes = Some(stats.total_bytes.unwrap_or(0).saturating_add(value));
+        }
+        let at = at.secs().try_into().unwrap_or_default();
+        stats.updated_at = Some(stats.updated_at.map_or(at, |updated_at| updated_at.max(at)));
+    }
+    Ok(stats)
+}
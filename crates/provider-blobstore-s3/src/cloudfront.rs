@@ -0,0 +1,240 @@
+//! Signed URLs and cookies of CloudFront distributions, granting access to private content
+//! served by them, e.g. from buckets of the link, until they expire
+//!
+//! URLs are signed with canned policies, which CloudFront reconstructs from the URL and its
+//! `Expires` parameter, and cookies with custom policies, so that their resources may contain
+//! wildcards, e.g. `https://d111111abcdef8.cloudfront.net/private/*`.
+
+use core::fmt;
+use core::time::Duration;
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{ensure, Context as _};
+use base64::Engine as _;
+use rsa::pkcs1::DecodeRsaPrivateKey as _;
+use rsa::pkcs1v15::SigningKey;
+use rsa::pkcs8::DecodePrivateKey as _;
+use rsa::signature::{SignatureEncoding as _, Signer as _};
+use rsa::RsaPrivateKey;
+use sha1::Sha1;
+
+use crate::config::CdnSigningConfig;
+
+/// Name of the cookie containing the policy
+const POLICY_COOKIE: &str = "CloudFront-Policy";
+/// Name of the cookie containing the signature of the policy
+const SIGNATURE_COOKIE: &str = "CloudFront-Signature";
+/// Name of the cookie containing the ID of the key pair the policy was signed with
+const KEY_PAIR_ID_COOKIE: &str = "CloudFront-Key-Pair-Id";
+
+/// Signer of the URLs and cookies of a link
+#[derive(Clone)]
+pub(crate) struct CdnSigner {
+    key_pair_id: String,
+    key: SigningKey<Sha1>,
+}
+
+// the private key must never be logged
+impl fmt::Debug for CdnSigner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CdnSigner")
+            .field("key_pair_id", &self.key_pair_id)
+            .finish_non_exhaustive()
+    }
+}
+
+impl CdnSigner {
+    pub(crate) fn new(
+        CdnSigningConfig {
+            key_pair_id,
+            private_key,
+        }: &CdnSigningConfig,
+    ) -> anyhow::Result<Self> {
+        ensure!(
+            !key_pair_id.is_empty(),
+            "`cdn_signing.key_pair_id` must not be empty"
+        );
+        let key = RsaPrivateKey::from_pkcs1_pem(private_key)
+            .or_else(|_| RsaPrivateKey::from_pkcs8_pem(private_key))
+            .context("`cdn_signing.private_key` is not an RSA private key in PEM format")?;
+        Ok(Self {
+            key_pair_id: key_pair_id.clone(),
+            key: SigningKey::new(key),
+        })
+    }
+
+    /// Sign `url`, returning it with the `Expires`, `Signature` and `Key-Pair-Id` query
+    /// parameters appended, which grant access to it for `expires_in`
+    pub(crate) fn sign_url(&self, url: &str, expires_in: Duration) -> anyhow::Result<String> {
+        ensure!(
+            !url.contains('*'),
+            "signed URLs must not contain wildcards, which only signed cookies support"
+        );
+        let expires = expiry(expires_in)?;
+        let signature = self.sign(&policy(url, expires));
+        let separator = if url.contains('?') { '&' } else { '?' };
+        Ok(format!(
+            "{url}{separator}Expires={expires}&Signature={signature}&Key-Pair-Id={}",
+            self.key_pair_id
+        ))
+    }
+
+    /// Sign cookies granting access to the URLs matching `resource`, which may contain
+    /// wildcards (`*`), for `expires_in`, returning the names and values of the cookies
+    pub(crate) fn sign_cookies(
+        &self,
+        resource: &str,
+        expires_in: Duration,
+    ) -> anyhow::Result<Vec<(String, String)>> {
+        let policy = policy(resource, expiry(expires_in)?);
+        Ok(vec![
+            (POLICY_COOKIE.to_string(), encode(policy.as_bytes())),
+            (SIGNATURE_COOKIE.to_string(), self.sign(&policy)),
+            (KEY_PAIR_ID_COOKIE.to_string(), self.key_pair_id.clone()),
+        ])
+    }
+
+    /// Signature of `policy` (RSA-SHA1), encoded for URLs and cookies
+    fn sign(&self, policy: &str) -> String {
+        encode(&self.key.sign(policy.as_bytes()).to_bytes())
+    }
+}
+
+/// Time `expires_in` from now in seconds since the epoch
+fn expiry(expires_in: Duration) -> anyhow::Result<u64> {
+    ensure!(!expires_in.is_zero(), "expiry must be in the future");
+    let expires = SystemTime::now()
+        .checked_add(expires_in)
+        .context("expiry is too far in the future")?;
+    Ok(expires
+        .duration_since(UNIX_EPOCH)
+        .context("system time is before the epoch")?
+        .as_secs())
+}
+
+/// Policy granting access to `resource` until `expires`, in the exact format of canned policies,
+/// without whitespace
+fn policy(resource: &str, expires: u64) -> String {
+    // serializing a string cannot fail
+    let resource = serde_json::to_string(resource).unwrap_or_default();
+    format!(
+        r#"{{"Statement":[{{"Resource":{resource},"Condition":{{"DateLessThan":{{"AWS:EpochTime":{expires}}}}}}}]}}"#
+    )
+}
+
+/// Encode data as base64, replacing the characters invalid in query parameters and cookies as
+/// CloudFront expects (`+` with `-`, `=` with `_` and `/` with `~`)
+fn encode(data: &[u8]) -> String {
+    base64::engine::general_purpose::STANDARD
+        .encode(data)
+        .chars()
+        .map(|c| match c {
+            '+' => '-',
+            '=' => '_',
+            '/' => '~',
+            c => c,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use rsa::pkcs1v15::{Signature, VerifyingKey};
+    use rsa::pkcs8::{EncodePrivateKey as _, LineEnding};
+    use rsa::signature::Verifier as _;
+
+    /// Decode data encoded by [`encode`]
+    fn decode(data: &str) -> Vec<u8> {
+        let data: String = data
+            .chars()
+            .map(|c| match c {
+                '-' => '+',
+                '_' => '=',
+                '~' => '/',
+                c => c,
+            })
+            .collect();
+        base64::engine::general_purpose::STANDARD
+            .decode(data)
+            .unwrap()
+    }
+
+    #[test]
+    fn policies() {
+        assert_eq!(
+            policy(
+                "https://d111111abcdef8.cloudfront.net/image.jpg",
+                1357034400
+            ),
+            r#"{"Statement":[{"Resource":"https://d111111abcdef8.cloudfront.net/image.jpg","Condition":{"DateLessThan":{"AWS:EpochTime":1357034400}}}]}"#
+        );
+        assert_eq!(encode(&[0xfb, 0xff]), "-~8_");
+    }
+
+    #[test]
+    fn signatures() {
+        let key = RsaPrivateKey::new(&mut rand::thread_rng(), 1024).unwrap();
+        let verifier = VerifyingKey::<Sha1>::new(key.to_public_key());
+        let signer = CdnSigner::new(&CdnSigningConfig {
+            key_pair_id: "K2JCJMDEHXQW5F".into(),
+            private_key: key.to_pkcs8_pem(LineEnding::LF).unwrap().to_string(),
+        })
+        .unwrap();
+
+        let url = signer
+            .sign_url(
+                "https://d111111abcdef8.cloudfront.net/image.jpg?size=large",
+                Duration::from_secs(60),
+            )
+            .unwrap();
+        let (resource, params) = url.split_once("&Expires=").unwrap();
+        let params: Vec<_> = params.split('&').collect();
+        let [expires, signature, key_pair_id] = params[..] else {
+            panic!("unexpected parameters of signed URL `{url}`");
+        };
+        assert_eq!(key_pair_id, "Key-Pair-Id=K2JCJMDEHXQW5F");
+        let signature = decode(signature.strip_prefix("Signature=").unwrap());
+        let policy = policy(resource, expires.parse().unwrap());
+        verifier
+            .verify(
+                policy.as_bytes(),
+                &Signature::try_from(signature.as_slice()).unwrap(),
+            )
+            .unwrap();
+        assert!(signer
+            .sign_url(
+                "https://d111111abcdef8.cloudfront.net/*",
+                Duration::from_secs(60)
+            )
+            .is_err());
+
+        let cookies = signer
+            .sign_cookies(
+                "https://d111111abcdef8.cloudfront.net/private/*",
+                Duration::from_secs(60),
+            )
+            .unwrap();
+        let [(_, policy), (_, signature), (_, key_pair_id)] = &cookies[..] else {
+            panic!("unexpected signed cookies {cookies:?}");
+        };
+        assert_eq!(key_pair_id, "K2JCJMDEHXQW5F");
+        let policy = decode(policy);
+        assert!(String::from_utf8_lossy(&policy)
+            .contains(r#""Resource":"https://d111111abcdef8.cloudfront.net/private/*""#));
+        verifier
+            .verify(
+                &policy,
+                &Signature::try_from(decode(signature).as_slice()).unwrap(),
+            )
+            .unwrap();
+
+        assert!(CdnSigner::new(&CdnSigningConfig {
+            key_pair_id: "K2JCJMDEHXQW5F".into(),
+            private_key: "not a key".into(),
+        })
+        .is_err());
+    }
+}
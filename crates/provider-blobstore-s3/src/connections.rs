@@ -0,0 +1,252 @@
+//! Statistics of the connection pool and requests of the clients shared by links with identical
+//! connection configuration, to correlate `503 Slow Down` responses with pool exhaustion
+//!
+//! Connections are counted by wrapping the connector of the HTTP clients, requests by the
+//! [`RequestCounter`](crate::interceptors::RequestCounter) interceptor of the S3 clients.
+
+use core::future::Future;
+use core::pin::Pin;
+use core::task::{Context, Poll};
+
+use std::io;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+
+use hyper::client::connect::{Connected, Connection};
+use hyper::service::Service;
+use hyper::Uri;
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+/// Connection and request statistics of the clients of a link, since they were created
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ConnectionStats {
+    /// Connections currently open, idle ones kept in the pool included
+    pub open_connections: u64,
+    /// Connections opened in total
+    pub connections_opened: u64,
+    /// S3 requests currently in flight, including those waiting for a connection or a retry
+    pub in_flight_requests: u64,
+    /// S3 requests made in total
+    pub requests: u64,
+    /// Attempts retrying an S3 request
+    pub retries: u64,
+    /// Responses indicating throttling, `503 Slow Down` or `429 Too Many Requests`
+    pub throttle_events: u64,
+}
+
+/// Counters backing [`ConnectionStats`], shared by the clients of a link
+#[derive(Debug, Default)]
+pub(crate) struct ConnectionCounters {
+    open_connections: AtomicU64,
+    connections_opened: AtomicU64,
+    in_flight_requests: AtomicU64,
+    requests: AtomicU64,
+    retries: AtomicU64,
+    throttle_events: AtomicU64,
+}
+
+impl ConnectionCounters {
+    pub(crate) fn snapshot(&self) -> ConnectionStats {
+        ConnectionStats {
+            open_connections: self.open_connections.load(Ordering::Relaxed),
+            connections_opened: self.connections_opened.load(Ordering::Relaxed),
+            in_flight_requests: self.in_flight_requests.load(Ordering::Relaxed),
+            requests: self.requests.load(Ordering::Relaxed),
+            retries: self.retries.load(Ordering::Relaxed),
+            throttle_events: self.throttle_events.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Record the start of a request, which remains in flight until the returned guard is dropped
+    pub(crate) fn start_request(self: &Arc<Self>) -> InFlightRequest {
+        self.requests.fetch_add(1, Ordering::Relaxed);
+        self.in_flight_requests.fetch_add(1, Ordering::Relaxed);
+        InFlightRequest {
+            counters: Arc::clone(self),
+            attempted: AtomicBool::default(),
+        }
+    }
+
+    pub(crate) fn record_throttle(&self) {
+        self.throttle_events.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// Request in flight, decrementing the number of requests in flight when dropped
+#[derive(Debug)]
+pub(crate) struct InFlightRequest {
+    counters: Arc<ConnectionCounters>,
+    attempted: AtomicBool,
+}
+
+impl InFlightRequest {
+    /// Record an attempt of the request, all but the first of which are retries
+    pub(crate) fn attempt(&self) {
+        if self.attempted.swap(true, Ordering::Relaxed) {
+            self.counters.retries.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+impl Drop for InFlightRequest {
+    fn drop(&mut self) {
+        self.counters
+            .in_flight_requests
+            .fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// Connector counting the connections established by another one
+#[derive(Clone, Debug)]
+pub(crate) struct CountConnections<C> {
+    inner: C,
+    counters: Arc<ConnectionCounters>,
+}
+
+impl<C> CountConnections<C> {
+    pub(crate) fn new(inner: C, counters: Arc<ConnectionCounters>) -> Self {
+        Self { inner, counters }
+    }
+}
+
+impl<C> Service<Uri> for CountConnections<C>
+where
+    C: Service<Uri>,
+    C::Future: Send + 'static,
+{
+    type Response = CountedConnection<C::Response>;
+    type Error = C::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, uri: Uri) -> Self::Future {
+        let counters = Arc::clone(&self.counters);
+        let connect = self.inner.call(uri);
+        Box::pin(async move {
+            let io = connect.await?;
+            counters.connections_opened.fetch_add(1, Ordering::Relaxed);
+            counters.open_connections.fetch_add(1, Ordering::Relaxed);
+            Ok(CountedConnection { io, counters })
+        })
+    }
+}
+
+/// Connection established by [`CountConnections`], which is counted as open until dropped
+#[derive(Debug)]
+pub(crate) struct CountedConnection<T> {
+    io: T,
+    counters: Arc<ConnectionCounters>,
+}
+
+impl<T> Drop for CountedConnection<T> {
+    fn drop(&mut self) {
+        self.counters
+            .open_connections
+            .fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+impl<T: Connection> Connection for CountedConnection<T> {
+    fn connected(&self) -> Connected {
+        self.io.connected()
+    }
+}
+
+impl<T: AsyncRead + Unpin> AsyncRead for CountedConnection<T> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.io).poll_read(cx, buf)
+    }
+}
+
+impl<T: AsyncWrite + Unpin> AsyncWrite for CountedConnection<T> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.io).poll_write(cx, buf)
+    }
+
+    fn poll_write_vectored(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[io::IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.io).poll_write_vectored(cx, bufs)
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        self.io.is_write_vectored()
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.io).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.io).poll_shutdown(cx)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Connector establishing connections without any I/O
+    struct Connector;
+
+    impl Service<Uri> for Connector {
+        type Response = ();
+        type Error = io::Error;
+        type Future = futures::future::Ready<io::Result<()>>;
+
+        fn poll_ready(&mut self, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, _: Uri) -> Self::Future {
+            futures::future::ready(Ok(()))
+        }
+    }
+
+    #[tokio::test]
+    async fn connection_counters() {
+        let counters = Arc::new(ConnectionCounters::default());
+        let mut connector = CountConnections::new(Connector, Arc::clone(&counters));
+        let a = connector
+            .call(Uri::from_static("http://localhost"))
+            .await
+            .expect("failed to connect");
+        let b = connector
+            .call(Uri::from_static("http://localhost"))
+            .await
+            .expect("failed to connect");
+        drop(a);
+
+        let request = counters.start_request();
+        request.attempt();
+        request.attempt();
+        counters.record_throttle();
+        assert_eq!(
+            counters.snapshot(),
+            ConnectionStats {
+                open_connections: 1,
+                connections_opened: 2,
+                in_flight_requests: 1,
+                requests: 1,
+                retries: 1,
+                throttle_events: 1,
+            }
+        );
+        drop((b, request));
+        let stats = counters.snapshot();
+        assert_eq!((stats.open_connections, stats.in_flight_requests), (0, 0));
+    }
+}
@@ -0,0 +1,114 @@
+//! Tracking of objects recently written by a link, so that listings of stores with eventually
+//! consistent listings can be retried until they include them, rather than components reading
+//! their own writes observing them missing
+
+use core::time::Duration;
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use tokio::time::Instant;
+
+use crate::config::ListConsistencyConfig;
+
+/// Default number of retries of listings missing recently written objects
+const DEFAULT_RETRIES: u32 = 3;
+/// Default delay between retries of listings
+const DEFAULT_DELAY: Duration = Duration::from_millis(500);
+/// Default time after a write, during which listings are expected to include the object
+const DEFAULT_WINDOW: Duration = Duration::from_secs(60);
+
+/// Objects written by a link within the last [`ListConsistencyConfig::window_secs`], by bucket
+#[derive(Debug)]
+pub(crate) struct RecentWrites {
+    /// Number of retries of listings missing recently written objects
+    pub(crate) retries: u32,
+    /// Delay between retries of listings
+    pub(crate) delay: Duration,
+    window: Duration,
+    writes: Mutex<HashMap<String, HashMap<String, Instant>>>,
+}
+
+impl RecentWrites {
+    pub(crate) fn new(
+        ListConsistencyConfig {
+            retries,
+            delay_ms,
+            window_secs,
+        }: ListConsistencyConfig,
+    ) -> Self {
+        Self {
+            retries: retries.unwrap_or(DEFAULT_RETRIES),
+            delay: delay_ms.map_or(DEFAULT_DELAY, Duration::from_millis),
+            window: window_secs.map_or(DEFAULT_WINDOW, Duration::from_secs),
+            writes: Mutex::default(),
+        }
+    }
+
+    /// Record a write of the object `key` of `bucket`
+    pub(crate) fn record(&self, bucket: &str, key: &str) {
+        let mut writes = self.writes.lock().unwrap_or_else(|err| err.into_inner());
+        writes
+            .entry(bucket.to_string())
+            .or_default()
+            .insert(key.to_string(), Instant::now());
+    }
+
+    /// Forget the writes of objects of `bucket`, which were deleted since
+    pub(crate) fn forget<'a>(&self, bucket: &str, keys: impl IntoIterator<Item = &'a str>) {
+        let mut writes = self.writes.lock().unwrap_or_else(|err| err.into_inner());
+        if let Some(bucket_writes) = writes.get_mut(bucket) {
+            for key in keys {
+                bucket_writes.remove(key);
+            }
+        }
+    }
+
+    /// Number of objects written to `bucket` within the window, which are missing from `listed`,
+    /// which must be sorted like listings are
+    pub(crate) fn missing(&self, bucket: &str, listed: &[String]) -> usize {
+        let mut writes = self.writes.lock().unwrap_or_else(|err| err.into_inner());
+        let Some(bucket_writes) = writes.get_mut(bucket) else {
+            return 0;
+        };
+        bucket_writes.retain(|_, written| written.elapsed() < self.window);
+        let missing = bucket_writes
+            .keys()
+            .filter(|key| listed.binary_search(key).is_err())
+            .count();
+        if bucket_writes.is_empty() {
+            writes.remove(bucket);
+        }
+        missing
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn recent_writes() {
+        let writes = RecentWrites::new(ListConsistencyConfig {
+            window_secs: Some(10),
+            ..Default::default()
+        });
+        writes.record("bucket", "a");
+        writes.record("bucket", "b");
+        writes.record("other", "c");
+        assert_eq!(writes.missing("bucket", &["a".into()]), 1);
+        assert_eq!(writes.missing("bucket", &["a".into(), "b".into()]), 0);
+        assert_eq!(writes.missing("empty", &[]), 0);
+
+        writes.forget("bucket", ["b"]);
+        assert_eq!(writes.missing("bucket", &[]), 1);
+
+        // writes older than the window are no longer expected to be listed
+        let expired = RecentWrites::new(ListConsistencyConfig {
+            window_secs: Some(0),
+            ..Default::default()
+        });
+        expired.record("bucket", "a");
+        assert_eq!(expired.missing("bucket", &[]), 0);
+    }
+}
@@ -0,0 +1,166 @@
+//! Monitoring and proactive refresh of expiring credentials
+
+use core::time::Duration;
+
+use std::sync::{Arc, PoisonError, RwLock, Weak};
+use std::time::SystemTime;
+
+use aws_credential_types::provider::{future, ProvideCredentials, SharedCredentialsProvider};
+use aws_credential_types::Credentials;
+use tracing::{debug, error};
+use wasmcloud_provider_sdk::wasmcloud_tracing::global;
+
+/// How long before their expiry credentials are refreshed
+const REFRESH_AHEAD: Duration = Duration::from_secs(5 * 60);
+/// Minimum interval between two refreshes
+const MIN_REFRESH_INTERVAL: Duration = Duration::from_secs(5);
+/// How long to wait before retrying a failed refresh
+const RETRY_INTERVAL: Duration = Duration::from_secs(30);
+/// Cached credentials closer than this to their expiry are never handed out
+const EXPIRY_BUFFER: Duration = Duration::from_secs(10);
+
+/// Most recently loaded credentials, shared between a provider and its refresh task
+#[derive(Debug, Default)]
+pub(crate) struct CredentialsCache(RwLock<Option<Credentials>>);
+
+impl CredentialsCache {
+    /// Cached credentials, unless they are about to expire
+    pub(crate) fn get(&self) -> Option<Credentials> {
+        self.0
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+            .filter(|creds| {
+                creds
+                    .expiry()
+                    .is_none_or(|expiry| expiry > SystemTime::now() + EXPIRY_BUFFER)
+            })
+    }
+
+    fn set(&self, creds: Credentials) {
+        *self.0.write().unwrap_or_else(PoisonError::into_inner) = Some(creds);
+    }
+
+    fn is_expired(&self) -> bool {
+        self.0
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .as_ref()
+            .and_then(Credentials::expiry)
+            .is_some_and(|expiry| expiry <= SystemTime::now())
+    }
+}
+
+/// Credentials provider which refreshes expiring credentials (e.g. from STS or web identity)
+/// in the background, ahead of their expiry
+///
+/// Refresh failures are logged and counted in the `blobstore_s3.credentials.refresh_failures`
+/// metric, and retried until the credentials can be refreshed. The background task stops once
+/// the provider is dropped, or credentials without expiry were loaded.
+#[derive(Debug)]
+pub(crate) struct RefreshingCredentialsProvider {
+    inner: SharedCredentialsProvider,
+    current: Arc<CredentialsCache>,
+}
+
+impl RefreshingCredentialsProvider {
+    /// Wrap a credentials provider and start monitoring its credentials
+    pub(crate) fn new(inner: SharedCredentialsProvider) -> Self {
+        let current = Arc::default();
+        tokio::spawn(refresh(inner.clone(), Arc::downgrade(&current)));
+        Self { inner, current }
+    }
+
+    /// Cache holding the credentials last provided
+    pub(crate) fn cache(&self) -> Arc<CredentialsCache> {
+        Arc::clone(&self.current)
+    }
+}
+
+impl ProvideCredentials for RefreshingCredentialsProvider {
+    fn provide_credentials<'a>(&'a self) -> future::ProvideCredentials<'a>
+    where
+        Self: 'a,
+    {
+        match self.current.get() {
+            Some(creds) => future::ProvideCredentials::ready(Ok(creds)),
+            None => future::ProvideCredentials::new(async move {
+                let creds = self.inner.provide_credentials().await?;
+                self.current.set(creds.clone());
+                Ok(creds)
+            }),
+        }
+    }
+}
+
+/// Time to wait before refreshing credentials expiring at `expiry`
+fn refresh_delay(expiry: SystemTime, now: SystemTime) -> Duration {
+    let remaining = expiry.duration_since(now).unwrap_or_default();
+    let delay = if remaining > REFRESH_AHEAD * 2 {
+        remaining - REFRESH_AHEAD
+    } else {
+        // short-lived credentials are refreshed halfway through their lifetime
+        remaining / 2
+    };
+    delay.max(MIN_REFRESH_INTERVAL)
+}
+
+async fn refresh(inner: SharedCredentialsProvider, current: Weak<CredentialsCache>) {
+    let failures = global::meter("blobstore-s3-provider")
+        .u64_counter("blobstore_s3.credentials.refresh_failures")
+        .with_description("Number of failed attempts to refresh expiring credentials")
+        .init();
+    loop {
+        let delay = match inner.provide_credentials().await {
+            Ok(creds) => {
+                let expiry = creds.expiry();
+                let Some(current) = current.upgrade() else {
+                    return;
+                };
+                current.set(creds);
+                let Some(expiry) = expiry else {
+                    debug!("credentials do not expire, stop monitoring");
+                    return;
+                };
+                debug!(?expiry, "credentials refreshed");
+                refresh_delay(expiry, SystemTime::now())
+            }
+            Err(err) => {
+                let Some(current) = current.upgrade() else {
+                    return;
+                };
+                let expired = current.is_expired();
+                failures.add(1, &[]);
+                error!(%err, expired, "failed to refresh credentials, retrying in {RETRY_INTERVAL:?}");
+                RETRY_INTERVAL
+            }
+        };
+        tokio::time::sleep(delay).await;
+        if current.strong_count() == 0 {
+            return;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn refresh_delays() {
+        let now = SystemTime::now();
+        assert_eq!(
+            refresh_delay(now + Duration::from_secs(3600), now),
+            Duration::from_secs(3600) - REFRESH_AHEAD
+        );
+        assert_eq!(
+            refresh_delay(now + Duration::from_secs(300), now),
+            Duration::from_secs(150)
+        );
+        assert_eq!(refresh_delay(now, now), MIN_REFRESH_INTERVAL);
+        assert_eq!(
+            refresh_delay(now - Duration::from_secs(60), now),
+            MIN_REFRESH_INTERVAL
+        );
+    }
+}
@@ -0,0 +1,248 @@
+//! Derivatives of images, e.g. thumbnails, which reads of keys with a transformation suffix like
+//! `photo.jpg?w=200` produce from the source object, so that components do not decode images
+//! within their memory limits
+//!
+//! Derivatives are cached under a prefix of the container of their source and produced again
+//! once the source is replaced.
+
+use core::fmt;
+
+use std::io::{self, Cursor};
+
+use anyhow::{bail, ensure, Context as _};
+use bytes::{Bytes, BytesMut};
+use futures::stream::BoxStream;
+use futures::{StreamExt as _, TryStreamExt as _};
+use image::imageops::FilterType;
+use image::{DynamicImage, ImageFormat, ImageReader};
+use tracing::debug;
+
+use crate::config::DerivativesConfig;
+use crate::error::{BackendError, ErrorKind};
+use crate::StorageClient;
+
+/// Prefix derivatives are cached under, unless configured
+const DEFAULT_PREFIX: &str = ".derivatives/";
+/// Maximum width and height of derivatives, unless configured
+const DEFAULT_MAX_DIMENSION: u32 = 4096;
+/// Maximum size of images derivatives are produced from, unless configured
+const DEFAULT_MAX_SOURCE_SIZE: u64 = 64 * 1024 * 1024;
+
+/// Transformation of an image, which is resized to fit within the width and height, preserving
+/// its aspect ratio and never enlarging it
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub(crate) struct Transform {
+    width: Option<u32>,
+    height: Option<u32>,
+}
+
+impl fmt::Display for Transform {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.width, self.height) {
+            (Some(w), Some(h)) => write!(f, "w={w},h={h}"),
+            (Some(w), None) => write!(f, "w={w}"),
+            (None, Some(h)) => write!(f, "h={h}"),
+            (None, None) => f.write_str("original"),
+        }
+    }
+}
+
+/// Split the transformation suffix off a key, e.g. `photo.jpg?w=200&h=100`, returning the key of
+/// the source and the transformation, `None` if the key has no suffix of known parameters
+pub(crate) fn parse(key: &str) -> anyhow::Result<Option<(&str, Transform)>> {
+    let Some((source, query)) = key.rsplit_once('?') else {
+        return Ok(None);
+    };
+    if source.is_empty() {
+        return Ok(None);
+    }
+    let mut transform = Transform::default();
+    for param in query.split('&') {
+        let (name, value) = param.split_once('=').unwrap_or((param, ""));
+        let dimension = match name {
+            "w" => &mut transform.width,
+            "h" => &mut transform.height,
+            // keys may contain `?`
+            _ => return Ok(None),
+        };
+        let value = value
+            .parse()
+            .ok()
+            .filter(|value| *value > 0)
+            .with_context(|| format!("invalid `{name}` of derivative: `{value}`"))?;
+        *dimension = Some(value);
+    }
+    Ok(Some((source, transform)))
+}
+
+/// Derivatives of the images of a link
+#[derive(Clone, Debug)]
+pub(crate) struct Derivatives {
+    prefix: String,
+    max_dimension: u32,
+    max_source_size: u64,
+}
+
+impl Derivatives {
+    pub(crate) fn new(
+        DerivativesConfig {
+            prefix,
+            max_dimension,
+            max_source_size,
+        }: DerivativesConfig,
+    ) -> Self {
+        Self {
+            prefix: prefix.unwrap_or_else(|| DEFAULT_PREFIX.to_string()),
+            max_dimension: max_dimension.unwrap_or(DEFAULT_MAX_DIMENSION),
+            max_source_size: max_source_size.unwrap_or(DEFAULT_MAX_SOURCE_SIZE),
+        }
+    }
+
+    /// Key the derivative of `source` is cached at
+    fn key(&self, source: &str, transform: Transform) -> String {
+        format!("{}{source}/{transform}", self.prefix)
+    }
+
+    /// Read the byte range from `start` to `end` of a derivative, producing it if it is not
+    /// cached or older than its source
+    pub(crate) async fn read(
+        &self,
+        client: &StorageClient,
+        bucket: &str,
+        source: &str,
+        transform: Transform,
+        start: u64,
+        end: u64,
+    ) -> anyhow::Result<BoxStream<'static, io::Result<Bytes>>> {
+        ensure!(
+            transform.width.unwrap_or_default() <= self.max_dimension
+                && transform.height.unwrap_or_default() <= self.max_dimension,
+            "dimensions of derivatives must be at most {}",
+            self.max_dimension
+        );
+        let key = self.key(source, transform);
+        let info = client.get_object_info(bucket, source).await?;
+        let cached = match client.get_object_info(bucket, &key).await {
+            Ok(cached) => cached.created_at >= info.created_at,
+            Err(err) if ErrorKind::of(&err) == ErrorKind::NotFound => false,
+            Err(err) => return Err(err.context("failed to get info of cached derivative")),
+        };
+        if !cached {
+            if info.size > self.max_source_size {
+                bail!(BackendError::new(
+                    ErrorKind::PreconditionFailed,
+                    format!(
+                        "object [{bucket}/{source}] is larger than {} bytes, \
+                         derivatives of which are produced",
+                        self.max_source_size
+                    )
+                ))
+            }
+            let data = client
+                .read_object(bucket, source, 0, u64::MAX)
+                .await?
+                .try_fold(BytesMut::new(), |mut data, chunk| async move {
+                    data.extend_from_slice(&chunk);
+                    Ok(data)
+                })
+                .await
+                .context("failed to read source of derivative")?;
+            let (derivative, format) =
+                tokio::task::spawn_blocking(move || produce(&data, transform))
+                    .await
+                    .context("derivative task panicked")??;
+            debug!(bucket, key, size = derivative.len(), "derivative produced");
+            client
+                .write_object_with_expiry(
+                    bucket,
+                    &key,
+                    derivative,
+                    client.expires_after_days(),
+                    Some(format.to_mime_type()),
+                )
+                .await
+                .context("failed to cache derivative")?;
+        }
+        Ok(client.read_object(bucket, &key, start, end).await?.boxed())
+    }
+}
+
+/// Produce the derivative of an image, encoded in the format of the image
+fn produce(data: &[u8], transform: Transform) -> anyhow::Result<(Bytes, ImageFormat)> {
+    let reader = ImageReader::new(Cursor::new(data))
+        .with_guessed_format()
+        .context("failed to read image")?;
+    let format = reader
+        .format()
+        .context("source of derivative is not a supported image")?;
+    let image = reader.decode().context("failed to decode image")?;
+    let width = transform
+        .width
+        .map_or(image.width(), |w| w.min(image.width()));
+    let height = transform
+        .height
+        .map_or(image.height(), |h| h.min(image.height()));
+    let image = image.resize(width, height, FilterType::Lanczos3);
+    let image = match format {
+        // JPEG has no alpha channel
+        ImageFormat::Jpeg => DynamicImage::ImageRgb8(image.to_rgb8()),
+        _ => image,
+    };
+    let mut buf = Vec::new();
+    image
+        .write_to(&mut Cursor::new(&mut buf), format)
+        .context("failed to encode derivative")?;
+    Ok((buf.into(), format))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn transforms() {
+        assert_eq!(
+            parse("photos/cat.jpg?w=200").unwrap(),
+            Some((
+                "photos/cat.jpg",
+                Transform {
+                    width: Some(200),
+                    height: None
+                }
+            ))
+        );
+        assert_eq!(
+            parse("cat.png?w=200&h=100").unwrap().unwrap().1.to_string(),
+            "w=200,h=100"
+        );
+        assert_eq!(parse("cat.jpg").unwrap(), None);
+        assert_eq!(parse("what?.txt").unwrap(), None);
+        assert_eq!(parse("?w=200").unwrap(), None);
+        assert!(parse("cat.jpg?w=0").is_err());
+        assert!(parse("cat.jpg?w=wide").is_err());
+    }
+
+    #[test]
+    fn resize() {
+        let mut png = Vec::new();
+        DynamicImage::new_rgba8(400, 200)
+            .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+            .unwrap();
+        let transform = Transform {
+            width: Some(100),
+            height: None,
+        };
+        let (derivative, format) = produce(&png, transform).unwrap();
+        assert_eq!(format, ImageFormat::Png);
+        let image = image::load_from_memory(&derivative).unwrap();
+        assert_eq!((image.width(), image.height()), (100, 50));
+
+        // images are never enlarged
+        let transform = Transform {
+            width: Some(1000),
+            height: Some(1000),
+        };
+        let image = image::load_from_memory(&produce(&png, transform).unwrap().0).unwrap();
+        assert_eq!((image.width(), image.height()), (400, 200));
+    }
+}
@@ -0,0 +1,369 @@
+//! Errors of S3 requests, of other backends and of object keys rejected before making them,
+//! classified by [`ErrorKind`]
+
+use core::fmt;
+
+use std::io;
+
+use aws_sdk_s3::error::{BoxError, ProvideErrorMetadata};
+use aws_sdk_s3::operation::{RequestId, RequestIdExt};
+
+/// Kind of a failure, shared by all backends, so that components can retry or compensate
+/// failed operations the same way whichever store a link uses
+///
+/// Errors returned to components are prefixed with the kind, e.g. `not-found: ...`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum ErrorKind {
+    /// The container or object does not exist
+    NotFound,
+    /// The container or object already exists
+    AlreadyExists,
+    /// The credentials of the link do not permit the operation
+    PermissionDenied,
+    /// The store rejected the request because of the request rate, retrying later may succeed
+    Throttled,
+    /// A condition of the operation does not hold, e.g. the container is not empty or the
+    /// object is locked
+    PreconditionFailed,
+    /// The store could not be reached or failed temporarily, retrying may succeed
+    Unavailable,
+    /// Any other failure, which retrying does not resolve
+    Internal,
+}
+
+impl ErrorKind {
+    /// Kebab-case name of the kind, which prefixes errors returned to components
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::NotFound => "not-found",
+            Self::AlreadyExists => "already-exists",
+            Self::PermissionDenied => "permission-denied",
+            Self::Throttled => "throttled",
+            Self::PreconditionFailed => "precondition-failed",
+            Self::Unavailable => "unavailable",
+            Self::Internal => "internal",
+        }
+    }
+
+    /// Whether retrying the failed operation may succeed
+    pub fn is_retryable(self) -> bool {
+        matches!(self, Self::Throttled | Self::Unavailable)
+    }
+
+    /// Kind of an error, the kind of the outermost classified error of its chain or
+    /// [`ErrorKind::Internal`]
+    pub fn of(err: &anyhow::Error) -> Self {
+        err.chain()
+            .find_map(|err| {
+                if let Some(err) = err.downcast_ref::<BackendError>() {
+                    Some(err.kind)
+                } else if let Some(err) = err.downcast_ref::<RequestError>() {
+                    Some(err.kind)
+                } else {
+                    err.downcast_ref::<io::Error>()
+                        .and_then(|err| Self::from_io(err.kind()))
+                }
+            })
+            .unwrap_or(Self::Internal)
+    }
+
+    /// Kind of an error code S3, or an S3-compatible store, responded with
+    fn from_s3_code(code: &str) -> Self {
+        match code {
+            "NoSuchBucket" | "NoSuchKey" | "NoSuchUpload" | "NoSuchVersion" | "NotFound" => {
+                Self::NotFound
+            }
+            "BucketAlreadyExists" | "BucketAlreadyOwnedByYou" => Self::AlreadyExists,
+            "AccessDenied"
+            | "AllAccessDisabled"
+            | "AccountProblem"
+            | "ExpiredToken"
+            | "InvalidAccessKeyId"
+            | "InvalidToken"
+            | "SignatureDoesNotMatch"
+            | "Forbidden" => Self::PermissionDenied,
+            "SlowDown"
+            | "Throttling"
+            | "ThrottlingException"
+            | "RequestLimitExceeded"
+            | "TooManyRequests" => Self::Throttled,
+            "PreconditionFailed"
+            | "ConditionalRequestConflict"
+            | "BucketNotEmpty"
+            | "InvalidObjectState"
+            | "OperationAborted" => Self::PreconditionFailed,
+            "InternalError" | "ServiceUnavailable" | "RequestTimeout" => Self::Unavailable,
+            _ => Self::Internal,
+        }
+    }
+
+    /// Kind of an I/O error, `None` if it is not specific
+    fn from_io(kind: io::ErrorKind) -> Option<Self> {
+        match kind {
+            io::ErrorKind::NotFound => Some(Self::NotFound),
+            io::ErrorKind::AlreadyExists => Some(Self::AlreadyExists),
+            io::ErrorKind::PermissionDenied => Some(Self::PermissionDenied),
+            io::ErrorKind::DirectoryNotEmpty => Some(Self::PreconditionFailed),
+            io::ErrorKind::ConnectionRefused
+            | io::ErrorKind::ConnectionReset
+            | io::ErrorKind::ConnectionAborted
+            | io::ErrorKind::NotConnected
+            | io::ErrorKind::BrokenPipe
+            | io::ErrorKind::TimedOut
+            | io::ErrorKind::Interrupted
+            | io::ErrorKind::UnexpectedEof => Some(Self::Unavailable),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A failure classified by the provider or by a backend other than S3
+#[derive(Debug)]
+pub struct BackendError {
+    kind: ErrorKind,
+    source: BoxError,
+}
+
+impl BackendError {
+    pub(crate) fn new(kind: ErrorKind, source: impl Into<BoxError>) -> Self {
+        Self {
+            kind,
+            source: source.into(),
+        }
+    }
+
+    /// The kind of the failure
+    pub fn kind(&self) -> ErrorKind {
+        self.kind
+    }
+}
+
+impl fmt::Display for BackendError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.source)
+    }
+}
+
+impl std::error::Error for BackendError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.source.source()
+    }
+}
+
+/// A failed S3 request, carrying the IDs S3 assigned to it
+///
+/// The request IDs are what AWS support asks for when investigating failed requests.
+/// They are only included in the message of the error if the link enables
+/// `request_ids_in_errors`, but are always available to callers downcasting errors.
+#[derive(Debug)]
+pub struct RequestError {
+    kind: ErrorKind,
+    code: Option<String>,
+    request_id: Option<String>,
+    extended_request_id: Option<String>,
+    display_ids: bool,
+    source: BoxError,
+}
+
+impl RequestError {
+    pub(crate) fn new<E>(err: E, display_ids: bool) -> Self
+    where
+        E: ProvideErrorMetadata
+            + RequestId
+            + RequestIdExt
+            + std::error::Error
+            + Send
+            + Sync
+            + 'static,
+    {
+        let code = err.code();
+        let kind = match code {
+            Some(code) => ErrorKind::from_s3_code(code),
+            // requests without an error response, like those timing out or failing to connect
+            None => ErrorKind::Unavailable,
+        };
+        Self {
+            kind,
+            code: code.map(Into::into),
+            request_id: err.request_id().map(Into::into),
+            extended_request_id: err.extended_request_id().map(Into::into),
+            display_ids,
+            source: err.into(),
+        }
+    }
+
+    /// The kind of the failure, derived from the error code
+    pub fn kind(&self) -> ErrorKind {
+        self.kind
+    }
+
+    /// The error code S3 responded with, like `TooManyBuckets`, if any
+    pub fn code(&self) -> Option<&str> {
+        self.code.as_deref()
+    }
+
+    /// The `x-amz-request-id` of the request, if S3 responded
+    pub fn request_id(&self) -> Option<&str> {
+        self.request_id.as_deref()
+    }
+
+    /// The `x-amz-id-2` of the request, if S3 responded
+    pub fn extended_request_id(&self) -> Option<&str> {
+        self.extended_request_id.as_deref()
+    }
+}
+
+impl fmt::Display for RequestError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.source)?;
+        if !self.display_ids {
+            return Ok(());
+        }
+        match (&self.request_id, &self.extended_request_id) {
+            (Some(id), Some(ext)) => write!(f, " (request ID: {id}, extended request ID: {ext})"),
+            (Some(id), None) => write!(f, " (request ID: {id})"),
+            (None, Some(ext)) => write!(f, " (extended request ID: {ext})"),
+            (None, None) => Ok(()),
+        }
+    }
+}
+
+impl std::error::Error for RequestError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        // `Display` already includes the message of the wrapped error
+        self.source.source()
+    }
+}
+
+/// An object key, which was rejected before making any request
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum KeyError {
+    /// The key is empty
+    Empty,
+    /// The key is longer than `max_length` bytes
+    TooLong { length: usize, max_length: usize },
+    /// The key contains a control character
+    ControlCharacter(char),
+    /// The key contains a character disallowed by the configuration of the link
+    DisallowedCharacter(char),
+}
+
+impl fmt::Display for KeyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Empty => write!(f, "object key must not be empty"),
+            Self::TooLong { length, max_length } => write!(
+                f,
+                "object key is {length} bytes long, longer than the maximum of {max_length} bytes"
+            ),
+            Self::ControlCharacter(c) => {
+                write!(f, "object key contains control character {c:?}")
+            }
+            Self::DisallowedCharacter(c) => {
+                write!(f, "object key contains disallowed character {c:?}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for KeyError {}
+
+#[cfg(test)]
+mod test {
+    use aws_sdk_s3::error::ErrorMetadata;
+
+    use super::*;
+
+    #[derive(Debug)]
+    struct TestError(ErrorMetadata);
+
+    impl fmt::Display for TestError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "access denied")
+        }
+    }
+
+    impl std::error::Error for TestError {}
+
+    impl ProvideErrorMetadata for TestError {
+        fn meta(&self) -> &ErrorMetadata {
+            &self.0
+        }
+    }
+
+    impl RequestId for TestError {
+        fn request_id(&self) -> Option<&str> {
+            self.0.request_id()
+        }
+    }
+
+    impl RequestIdExt for TestError {
+        fn extended_request_id(&self) -> Option<&str> {
+            self.0.extended_request_id()
+        }
+    }
+
+    #[test]
+    fn request_ids() {
+        let meta = || {
+            ErrorMetadata::builder()
+                .code("AccessDenied")
+                .custom("aws_request_id", "4442587FB7D0A2F9")
+                .custom(
+                    "s3_extended_request_id",
+                    "vlR7PnpV2Ce81l0PRw6jlUpck7Jo5ZsQjryTjKlc5aLWGVHPZLj5NeC6qMa0emYBDXOo6QBU0Wo=",
+                )
+                .build()
+        };
+        let err = RequestError::new(TestError(meta()), false);
+        assert_eq!(err.request_id(), Some("4442587FB7D0A2F9"));
+        assert_eq!(err.code(), Some("AccessDenied"));
+        assert_eq!(err.kind(), ErrorKind::PermissionDenied);
+        assert_eq!(err.to_string(), "access denied");
+        let err = RequestError::new(TestError(meta()), true);
+        assert_eq!(
+            err.to_string(),
+            "access denied (request ID: 4442587FB7D0A2F9, extended request ID: vlR7PnpV2Ce81l0PRw6jlUpck7Jo5ZsQjryTjKlc5aLWGVHPZLj5NeC6qMa0emYBDXOo6QBU0Wo=)"
+        );
+    }
+
+    #[test]
+    fn error_kinds() {
+        let err = anyhow::Error::new(BackendError::new(
+            ErrorKind::NotFound,
+            "object [container/key] not found",
+        ))
+        .context("failed to read object");
+        assert_eq!(ErrorKind::of(&err), ErrorKind::NotFound);
+        assert_eq!(
+            format!("{err:#}"),
+            "failed to read object: object [container/key] not found"
+        );
+        let err = anyhow::Error::new(io::Error::from(io::ErrorKind::PermissionDenied))
+            .context("failed to write object");
+        assert_eq!(ErrorKind::of(&err), ErrorKind::PermissionDenied);
+        assert_eq!(
+            ErrorKind::of(&anyhow::anyhow!("failed")),
+            ErrorKind::Internal
+        );
+        assert_eq!(
+            ErrorKind::of(
+                &RequestError::new(
+                    TestError(ErrorMetadata::builder().code("SlowDown").build()),
+                    false
+                )
+                .into()
+            ),
+            ErrorKind::Throttled
+        );
+        assert!(ErrorKind::Throttled.is_retryable());
+        assert!(!ErrorKind::PreconditionFailed.is_retryable());
+        assert_eq!(ErrorKind::AlreadyExists.to_string(), "already-exists");
+    }
+}
@@ -0,0 +1,161 @@
+//! Events about the state of the provider, published onto the lattice event stream, so that
+//! dashboards and other components can react to them without polling the admin interface
+
+use serde::Serialize;
+use tracing::warn;
+#[cfg(feature = "notifications")]
+use wasmcloud_provider_sdk::get_connection;
+
+use crate::error::RequestError;
+use crate::jobs::{JobOperation, JobState};
+
+/// Error codes of requests rejected by S3 (or compatible services), because they exceed a quota,
+/// including the request rate
+const QUOTA_ERROR_CODES: &[&str] = &[
+    "TooManyBuckets",
+    "QuotaExceeded",
+    "ServiceQuotaExceededException",
+    "SlowDown",
+    "XMinioStorageFull",
+];
+
+/// An event about the state of the provider, links or jobs
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(untagged)]
+pub(crate) enum ProviderEvent {
+    /// A link from a component was established
+    LinkAdded {
+        source_id: String,
+        link_name: String,
+    },
+    /// A link from a component was removed
+    LinkRemoved {
+        source_id: String,
+        link_name: String,
+    },
+    /// The client of a link could not be created, so that its requests fail
+    ClientDegraded {
+        source_id: String,
+        link_name: String,
+        error: String,
+    },
+    /// A request of a component exceeded a limit of the provider or of the storage service
+    QuotaExceeded {
+        component_id: String,
+        quota: String,
+        error: String,
+    },
+    /// A job finished, successfully or not
+    JobCompleted {
+        component_id: String,
+        job_id: String,
+        operation: JobOperation,
+        state: JobState,
+    },
+}
+
+impl ProviderEvent {
+    /// Name of the event, published as the CloudEvent type `com.wasmcloud.lattice.{name}`
+    #[cfg_attr(not(feature = "notifications"), allow(dead_code))]
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            Self::LinkAdded { .. } => "blobstore_s3_link_added",
+            Self::LinkRemoved { .. } => "blobstore_s3_link_removed",
+            Self::ClientDegraded { .. } => "blobstore_s3_client_degraded",
+            Self::QuotaExceeded { .. } => "blobstore_s3_quota_exceeded",
+            Self::JobCompleted { .. } => "blobstore_s3_job_completed",
+        }
+    }
+}
+
+/// Publisher of events, which drops them unless enabled in provider configuration
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct Events {
+    #[cfg_attr(not(feature = "notifications"), allow(dead_code))]
+    enabled: bool,
+}
+
+impl Events {
+    pub(crate) fn new(enabled: bool) -> Self {
+        if enabled && cfg!(not(feature = "notifications")) {
+            warn!(
+                "lattice events require the provider to be built with the `notifications` feature"
+            );
+        }
+        Self { enabled }
+    }
+
+    /// Publish `event` in the background, only logging failures
+    #[cfg(feature = "notifications")]
+    pub(crate) fn publish(self, event: ProviderEvent) {
+        if !self.enabled {
+            return;
+        }
+        tokio::spawn(async move {
+            let name = event.name();
+            let data = match serde_json::to_value(&event) {
+                Ok(data) => data,
+                Err(err) => {
+                    warn!(?err, name, "failed to encode lattice event");
+                    return;
+                }
+            };
+            if let Err(err) = get_connection().publish_event(name, data).await {
+                warn!(?err, name, "failed to publish lattice event");
+            }
+        });
+    }
+
+    /// Drop `event`, since the provider was built without the `notifications` feature
+    #[cfg(not(feature = "notifications"))]
+    pub(crate) fn publish(self, _event: ProviderEvent) {}
+
+    /// Publish an event, if `err` was caused by S3 rejecting a request of `component_id`, because
+    /// it exceeds a quota
+    pub(crate) fn publish_quota_exceeded(self, component_id: &str, err: &anyhow::Error) {
+        if let Some(event) = quota_exceeded(component_id, err) {
+            self.publish(event);
+        }
+    }
+}
+
+/// Event about `err`, if it was caused by S3 rejecting a request because it exceeds a quota
+fn quota_exceeded(component_id: &str, err: &anyhow::Error) -> Option<ProviderEvent> {
+    let code = err
+        .chain()
+        .filter_map(|err| err.downcast_ref::<RequestError>())
+        .find_map(RequestError::code)
+        .filter(|code| QUOTA_ERROR_CODES.contains(code))?;
+    Some(ProviderEvent::QuotaExceeded {
+        component_id: component_id.to_string(),
+        quota: code.to_string(),
+        error: format!("{err:#}"),
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn provider_events() {
+        let event = ProviderEvent::JobCompleted {
+            component_id: "component".into(),
+            job_id: "job".into(),
+            operation: JobOperation::ClearContainer {
+                bucket: "bucket".into(),
+            },
+            state: JobState::Failed("denied".into()),
+        };
+        assert_eq!(event.name(), "blobstore_s3_job_completed");
+        assert_eq!(
+            serde_json::to_value(&event).expect("failed to encode event"),
+            serde_json::json!({
+                "component_id": "component",
+                "job_id": "job",
+                "operation": { "op": "clear_container", "bucket": "bucket" },
+                "state": { "state": "failed", "detail": "denied" },
+            })
+        );
+    }
+}
@@ -0,0 +1,104 @@
+//! Invocation of the hooks components implement in `wasmcloud:provider-blobstore-s3/hooks` on
+//! behalf of the links configuring them
+
+use core::pin::Pin;
+
+use std::time::SystemTime;
+
+use anyhow::{bail, Context as _};
+use bytes::Bytes;
+use futures::Stream;
+use tracing::{debug, warn};
+use wasmcloud_provider_sdk::get_connection;
+
+use crate::bindings::wasmcloud::provider_blobstore_s3::hooks;
+use crate::bindings::wrpc::blobstore0_2_0::types::{ObjectId, ObjectMetadata};
+use crate::error::{BackendError, ErrorKind};
+use crate::sniff::peek;
+use crate::StorageClient;
+
+/// Maximum number of leading bytes of objects passed to pre-write hooks
+pub(crate) const MAX_INSPECT_BYTES: usize = 1024 * 1024;
+
+/// Run the pre-write hooks of a link for an object it is about to write, failing if any of them
+/// rejects the write or cannot be invoked
+///
+/// Returns the data of the object, of which the leading bytes passed to hooks were read.
+pub(crate) async fn pre_write(
+    client: &StorageClient,
+    component_id: &str,
+    link_name: &str,
+    id: &ObjectId,
+    data: impl Stream<Item = Bytes> + Send + 'static,
+) -> anyhow::Result<Pin<Box<dyn Stream<Item = Bytes> + Send>>> {
+    let hooks: Vec<_> = client.pre_write_hooks(&id.object).collect();
+    let data: Pin<Box<dyn Stream<Item = Bytes> + Send>> = Box::pin(data);
+    if hooks.is_empty() {
+        return Ok(data);
+    }
+    let inspect_bytes = hooks
+        .iter()
+        .filter_map(|hook| hook.inspect_bytes)
+        .max()
+        .unwrap_or_default();
+    let (head, data) = peek(data, inspect_bytes).await;
+    // the size of the object is only known if it was read entirely
+    let size = (head.len() < inspect_bytes).then_some(head.len() as u64);
+    let results = futures::future::join_all(hooks.iter().map(|hook| {
+        let head = head.slice(..head.len().min(hook.inspect_bytes.unwrap_or_default()));
+        async move {
+            let wrpc = get_connection().get_wrpc_client(&hook.component_id);
+            let res = hooks::validate_write(&wrpc, None, component_id, link_name, id, size, &head)
+                .await
+                .map_err(|err| BackendError::new(ErrorKind::Unavailable, format!("{err:#}")))
+                .with_context(|| {
+                    format!("failed to invoke pre-write hook `{}`", hook.component_id)
+                })?;
+            anyhow::Ok((hook, res))
+        }
+    }))
+    .await;
+    for res in results {
+        if let (hook, Err(reason)) = res? {
+            bail!(BackendError::new(
+                ErrorKind::PermissionDenied,
+                format!(
+                    "write of object [{}/{}] rejected by `{}`: {reason}",
+                    id.container, id.object, hook.component_id
+                )
+            ))
+        }
+    }
+    debug!(hooks = hooks.len(), "write validated by pre-write hooks");
+    Ok(data)
+}
+
+/// Invoke the post-write hooks of a link for an object it wrote, in the background, so that
+/// writes are not delayed by hooks, e.g. generating thumbnails
+pub(crate) fn post_write(
+    client: &StorageClient,
+    component_id: &str,
+    link_name: &str,
+    id: &ObjectId,
+    size: u64,
+) {
+    let created_at = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    for hook in client.post_write_hooks(&id.object) {
+        let hook_id = hook.component_id.clone();
+        let component_id = component_id.to_string();
+        let link_name = link_name.to_string();
+        let id = id.clone();
+        tokio::spawn(async move {
+            let wrpc = get_connection().get_wrpc_client(&hook_id);
+            let metadata = ObjectMetadata { created_at, size };
+            match hooks::on_write(&wrpc, None, &component_id, &link_name, &id, &metadata).await {
+                Ok(Ok(())) => debug!(hook = hook_id, "post-write hook invoked"),
+                Ok(Err(err)) => warn!(hook = hook_id, err, "post-write hook failed"),
+                Err(err) => warn!(hook = hook_id, ?err, "failed to invoke post-write hook"),
+            }
+        });
+    }
+}
@@ -0,0 +1,456 @@
+//! SDK interceptors applied to all requests made by a [`StorageClient`](crate::StorageClient)
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use std::time::SystemTime;
+
+use anyhow::Context as _;
+use aws_runtime::auth::SigV4OperationSigningConfig;
+use aws_sdk_s3::config::interceptors::{
+    AfterDeserializationInterceptorContextRef, BeforeDeserializationInterceptorContextRef,
+    BeforeSerializationInterceptorContextRef, BeforeTransmitInterceptorContextMut,
+    BeforeTransmitInterceptorContextRef,
+};
+use aws_sdk_s3::config::{ConfigBag, Intercept, RuntimeComponents};
+use aws_sdk_s3::error::BoxError;
+use aws_sdk_s3::primitives::{DateTime, DateTimeFormat, SdkBody};
+use aws_sigv4::http_request::SignableBody;
+use aws_smithy_types::config_bag::{Storable, StoreReplace};
+use base64::Engine as _;
+use hmac::{Hmac, Mac as _};
+use sha1::Sha1;
+use tracing::info;
+
+use crate::config::DebugLoggingConfig;
+use crate::connections::{ConnectionCounters, InFlightRequest};
+use crate::credentials::CredentialsCache;
+use crate::logging::{KeyRedaction, REDACTED};
+
+/// Query parameters included in the canonicalized resource of Signature Version 2 requests
+const SIGV2_SUBRESOURCES: &[&str] = &[
+    "acl",
+    "cors",
+    "delete",
+    "lifecycle",
+    "location",
+    "logging",
+    "notification",
+    "partNumber",
+    "policy",
+    "requestPayment",
+    "response-cache-control",
+    "response-content-disposition",
+    "response-content-encoding",
+    "response-content-language",
+    "response-content-type",
+    "response-expires",
+    "restore",
+    "tagging",
+    "torrent",
+    "uploadId",
+    "uploads",
+    "versionId",
+    "versioning",
+    "versions",
+    "website",
+];
+
+/// Headers set by Signature Version 4 signing, which are replaced when signing with Version 2
+const SIGV4_HEADERS: &[&str] = &["authorization", "x-amz-date", "x-amz-content-sha256"];
+
+/// Headers carrying secrets, whose values are never logged
+const SECRET_HEADERS: &[&str] = &[
+    "authorization",
+    "proxy-authorization",
+    "cookie",
+    "set-cookie",
+    "x-amz-security-token",
+    "x-amz-mfa",
+    "x-amz-server-side-encryption-customer-key",
+    "x-amz-copy-source-server-side-encryption-customer-key",
+];
+
+/// Interceptor adding a fixed set of headers to every request
+///
+/// Headers are added before signing, so they are covered by the request signature
+#[derive(Debug)]
+pub(crate) struct CustomHeaders(Vec<(String, String)>);
+
+impl CustomHeaders {
+    /// Validate custom header names and values
+    pub(crate) fn new(headers: HashMap<String, String>) -> anyhow::Result<Self> {
+        let headers = headers
+            .into_iter()
+            .map(|(name, value)| {
+                http::HeaderName::from_bytes(name.as_bytes())
+                    .with_context(|| format!("invalid custom header name `{name}`"))?;
+                http::HeaderValue::from_str(&value)
+                    .with_context(|| format!("invalid value for custom header `{name}`"))?;
+                Ok((name, value))
+            })
+            .collect::<anyhow::Result<_>>()?;
+        Ok(Self(headers))
+    }
+}
+
+impl Intercept for CustomHeaders {
+    fn name(&self) -> &'static str {
+        "CustomHeaders"
+    }
+
+    fn modify_before_signing(
+        &self,
+        context: &mut BeforeTransmitInterceptorContextMut<'_>,
+        _runtime_components: &RuntimeComponents,
+        _cfg: &mut ConfigBag,
+    ) -> Result<(), BoxError> {
+        let headers = context.request_mut().headers_mut();
+        for (name, value) in &self.0 {
+            headers.try_insert(name.clone(), value.clone())?;
+        }
+        Ok(())
+    }
+}
+
+/// Interceptor signing requests to HTTPS endpoints with `UNSIGNED-PAYLOAD`, which avoids
+/// computing the SHA-256 digest of request bodies
+#[derive(Debug)]
+pub(crate) struct UnsignedPayload;
+
+impl Intercept for UnsignedPayload {
+    fn name(&self) -> &'static str {
+        "UnsignedPayload"
+    }
+
+    fn modify_before_signing(
+        &self,
+        context: &mut BeforeTransmitInterceptorContextMut<'_>,
+        _runtime_components: &RuntimeComponents,
+        cfg: &mut ConfigBag,
+    ) -> Result<(), BoxError> {
+        // Without TLS the payload digest is the only protection of the body integrity
+        if !context.request().uri().starts_with("https://") {
+            return Ok(());
+        }
+        if let Some(mut signing_config) = cfg.load::<SigV4OperationSigningConfig>().cloned() {
+            // Streaming bodies already carry a checksum trailer, which is signed instead
+            if signing_config.signing_options.payload_override.is_none() {
+                signing_config.signing_options.payload_override =
+                    Some(SignableBody::UnsignedPayload);
+                cfg.interceptor_state().store_put(signing_config);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Interceptor replacing the Signature Version 4 signature of requests by a Version 2 one,
+/// for legacy S3-compatible stores
+///
+/// Signing uses the credentials last provided to the SDK, which are resolved before every
+/// request. Only path-style addressing is supported.
+#[derive(Debug)]
+pub(crate) struct SigV2Signer(Arc<CredentialsCache>);
+
+impl SigV2Signer {
+    pub(crate) fn new(credentials: Arc<CredentialsCache>) -> Self {
+        Self(credentials)
+    }
+}
+
+impl Intercept for SigV2Signer {
+    fn name(&self) -> &'static str {
+        "SigV2Signer"
+    }
+
+    fn modify_before_transmit(
+        &self,
+        context: &mut BeforeTransmitInterceptorContextMut<'_>,
+        _runtime_components: &RuntimeComponents,
+        _cfg: &mut ConfigBag,
+    ) -> Result<(), BoxError> {
+        let creds = self
+            .0
+            .get()
+            .ok_or("no credentials available to sign request")?;
+        let request = context.request_mut();
+        let headers = request.headers_mut();
+        for name in SIGV4_HEADERS {
+            headers.remove(name);
+        }
+        headers.try_insert(
+            "date",
+            DateTime::from(SystemTime::now()).fmt(DateTimeFormat::HttpDate)?,
+        )?;
+        if let Some(token) = creds.session_token() {
+            headers.try_insert("x-amz-security-token", token.to_string())?;
+        }
+        let string_to_sign =
+            sigv2_string_to_sign(request.method(), request.uri(), request.headers().iter());
+        let signature = sigv2_signature(creds.secret_access_key(), &string_to_sign);
+        request.headers_mut().try_insert(
+            "authorization",
+            format!("AWS {}:{signature}", creds.access_key_id()),
+        )?;
+        Ok(())
+    }
+}
+
+/// Build the string to sign of a Signature Version 2 request
+fn sigv2_string_to_sign<'a>(
+    method: &str,
+    uri: &str,
+    headers: impl IntoIterator<Item = (&'a str, &'a str)>,
+) -> String {
+    let mut values = BTreeMap::<_, Vec<_>>::new();
+    for (name, value) in headers {
+        values
+            .entry(name.to_ascii_lowercase())
+            .or_default()
+            .push(value.trim());
+    }
+    let header = |name| values.get(name).map(|v| v.join(",")).unwrap_or_default();
+    let mut s = format!(
+        "{method}\n{}\n{}\n{}\n",
+        header("content-md5"),
+        header("content-type"),
+        header("date")
+    );
+    for (name, values) in values.iter().filter(|(name, _)| name.starts_with("x-amz-")) {
+        s.push_str(name);
+        s.push(':');
+        s.push_str(&values.join(","));
+        s.push('\n');
+    }
+
+    // Strip scheme and authority, the path is signed as sent
+    let path_and_query = uri
+        .split_once("://")
+        .map_or(uri, |(_, rest)| rest.find('/').map_or("/", |i| &rest[i..]));
+    let (path, query) = path_and_query
+        .split_once('?')
+        .unwrap_or((path_and_query, ""));
+    s.push_str(path);
+    let mut subresources: Vec<_> = url::form_urlencoded::parse(query.as_bytes())
+        .filter(|(name, _)| SIGV2_SUBRESOURCES.contains(&name.as_ref()))
+        .collect();
+    subresources.sort();
+    for (i, (name, value)) in subresources.iter().enumerate() {
+        s.push(if i == 0 { '?' } else { '&' });
+        s.push_str(name);
+        if !value.is_empty() {
+            s.push('=');
+            s.push_str(value);
+        }
+    }
+    s
+}
+
+/// Compute the Signature Version 2 signature of a string to sign
+fn sigv2_signature(secret_access_key: &str, string_to_sign: &str) -> String {
+    let mut mac = Hmac::<Sha1>::new_from_slice(secret_access_key.as_bytes())
+        .expect("HMAC accepts keys of any length");
+    mac.update(string_to_sign.as_bytes());
+    base64::engine::general_purpose::STANDARD.encode(mac.finalize().into_bytes())
+}
+
+/// Interceptor counting requests, retries and throttled responses in [`ConnectionCounters`]
+#[derive(Debug)]
+pub(crate) struct RequestCounter(Arc<ConnectionCounters>);
+
+impl RequestCounter {
+    pub(crate) fn new(counters: Arc<ConnectionCounters>) -> Self {
+        Self(counters)
+    }
+}
+
+impl Storable for InFlightRequest {
+    type Storer = StoreReplace<Self>;
+}
+
+impl Intercept for RequestCounter {
+    fn name(&self) -> &'static str {
+        "RequestCounter"
+    }
+
+    fn read_before_execution(
+        &self,
+        _context: &BeforeSerializationInterceptorContextRef<'_>,
+        cfg: &mut ConfigBag,
+    ) -> Result<(), BoxError> {
+        // The request remains in flight until the config bag is dropped, which also happens if
+        // the request future is dropped before completing
+        cfg.interceptor_state().store_put(self.0.start_request());
+        Ok(())
+    }
+
+    fn read_before_attempt(
+        &self,
+        _context: &BeforeTransmitInterceptorContextRef<'_>,
+        _runtime_components: &RuntimeComponents,
+        cfg: &mut ConfigBag,
+    ) -> Result<(), BoxError> {
+        if let Some(request) = cfg.load::<InFlightRequest>() {
+            request.attempt();
+        }
+        Ok(())
+    }
+
+    fn read_before_deserialization(
+        &self,
+        context: &BeforeDeserializationInterceptorContextRef<'_>,
+        _runtime_components: &RuntimeComponents,
+        _cfg: &mut ConfigBag,
+    ) -> Result<(), BoxError> {
+        if matches!(context.response().status().as_u16(), 429 | 503) {
+            self.0.record_throttle();
+        }
+        Ok(())
+    }
+}
+
+/// Interceptor logging the parameters and response metadata of every request at `info` level,
+/// for troubleshooting interoperability issues with S3-compatible stores
+///
+/// Values of headers carrying secrets, including custom headers, are never logged. Bodies are
+/// only logged up to `log_bodies_max_bytes` if configured and only if held in memory, so that
+/// streamed object data is never logged.
+#[derive(Debug)]
+pub(crate) struct DebugLogger {
+    redaction: KeyRedaction,
+    /// Names of headers whose values are redacted, in lowercase
+    secret_headers: Vec<String>,
+    max_body_bytes: Option<usize>,
+}
+
+impl DebugLogger {
+    pub(crate) fn new(
+        DebugLoggingConfig {
+            redact_keys,
+            log_bodies_max_bytes,
+        }: DebugLoggingConfig,
+        custom_headers: impl IntoIterator<Item = String>,
+    ) -> Self {
+        let secret_headers = SECRET_HEADERS
+            .iter()
+            .map(|name| (*name).to_string())
+            .chain(
+                custom_headers
+                    .into_iter()
+                    .map(|name| name.to_ascii_lowercase()),
+            )
+            .collect();
+        Self {
+            redaction: KeyRedaction::new(redact_keys),
+            secret_headers,
+            max_body_bytes: log_bodies_max_bytes,
+        }
+    }
+
+    fn headers<'a>(&self, headers: impl Iterator<Item = (&'a str, &'a str)>) -> Vec<String> {
+        headers
+            .map(|(name, value)| {
+                let value = if self
+                    .secret_headers
+                    .iter()
+                    .any(|secret| secret.eq_ignore_ascii_case(name))
+                {
+                    REDACTED
+                } else {
+                    self.redaction.redact_value(value)
+                };
+                format!("{name}: {value}")
+            })
+            .collect()
+    }
+
+    fn body(&self, body: &SdkBody) -> Option<String> {
+        let max = self.max_body_bytes?;
+        let bytes = body.bytes()?;
+        let mut out = String::from_utf8_lossy(&bytes[..bytes.len().min(max)]).into_owned();
+        if bytes.len() > max {
+            out.push_str("...");
+        }
+        Some(out)
+    }
+}
+
+impl Intercept for DebugLogger {
+    fn name(&self) -> &'static str {
+        "DebugLogger"
+    }
+
+    fn read_before_transmit(
+        &self,
+        context: &BeforeTransmitInterceptorContextRef<'_>,
+        _runtime_components: &RuntimeComponents,
+        _cfg: &mut ConfigBag,
+    ) -> Result<(), BoxError> {
+        let request = context.request();
+        info!(
+            method = request.method(),
+            uri = %self.redaction.redact_uri(request.uri()),
+            headers = ?self.headers(request.headers().iter()),
+            body = self.body(request.body()).as_deref(),
+            "sending S3 request"
+        );
+        Ok(())
+    }
+
+    fn read_after_deserialization(
+        &self,
+        context: &AfterDeserializationInterceptorContextRef<'_>,
+        _runtime_components: &RuntimeComponents,
+        _cfg: &mut ConfigBag,
+    ) -> Result<(), BoxError> {
+        let response = context.response();
+        info!(
+            status = response.status().as_u16(),
+            headers = ?self.headers(response.headers().iter()),
+            body = self.body(response.body()).as_deref(),
+            "received S3 response"
+        );
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn sigv2_signatures() {
+        let string_to_sign = sigv2_string_to_sign(
+            "GET",
+            "https://s3.amazonaws.com/johnsmith/photos/puppy.jpg",
+            [
+                ("Date", "Tue, 27 Mar 2007 19:36:42 +0000"),
+                ("User-Agent", "test"),
+            ],
+        );
+        assert_eq!(
+            string_to_sign,
+            "GET\n\n\nTue, 27 Mar 2007 19:36:42 +0000\n/johnsmith/photos/puppy.jpg"
+        );
+        assert_eq!(
+            sigv2_signature("wJalrXUtnFEMI/K7MDENG/bPxRfiCYEXAMPLEKEY", &string_to_sign),
+            "bWq2s1WEIj+Ydj0vQ697zp+IXMU="
+        );
+
+        let string_to_sign = sigv2_string_to_sign(
+            "PUT",
+            "http://localhost:9000/bucket/a%20b?uploadId=x%2By&partNumber=2&x-id=UploadPart",
+            [
+                ("content-type", "text/plain"),
+                ("x-amz-meta-b", "2"),
+                ("X-Amz-Meta-A", " 1 "),
+                ("x-amz-meta-a", "3"),
+                ("date", "Tue, 27 Mar 2007 21:15:45 +0000"),
+            ],
+        );
+        assert_eq!(
+            string_to_sign,
+            "PUT\n\ntext/plain\nTue, 27 Mar 2007 21:15:45 +0000\nx-amz-meta-a:1,3\nx-amz-meta-b:2\n/bucket/a%20b?partNumber=2&uploadId=x+y"
+        );
+    }
+}
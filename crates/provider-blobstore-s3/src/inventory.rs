@@ -0,0 +1,162 @@
+//! Parsing of S3 Inventory reports, which list all objects of a bucket, so that listing giant
+//! buckets does not take millions of `ListObjectsV2` requests
+//!
+//! Only reports in CSV format are supported, links fall back to live listings for other formats.
+
+use core::time::Duration;
+
+use std::time::SystemTime;
+
+use serde::Deserialize;
+
+/// Age of reports, after which they are not used anymore, unless configured otherwise
+pub const DEFAULT_INVENTORY_MAX_AGE: Duration = Duration::from_secs(48 * 60 * 60);
+
+/// Manifest of an inventory report, listing its data files
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct InventoryManifest {
+    /// Time the report was created at in milliseconds since the Unix epoch
+    pub creation_timestamp: String,
+    pub file_format: String,
+    /// Comma-separated names of the columns of data files
+    pub file_schema: String,
+    pub files: Vec<InventoryFile>,
+}
+
+/// A data file of an inventory report
+#[derive(Clone, Debug, Deserialize)]
+pub(crate) struct InventoryFile {
+    pub key: String,
+}
+
+impl InventoryManifest {
+    /// Whether the report can be used at time `now`, i.e. it is in CSV format and not older
+    /// than `max_age`
+    pub(crate) fn is_usable(&self, max_age: Duration, now: SystemTime) -> bool {
+        let Ok(created_ms) = self.creation_timestamp.parse::<u64>() else {
+            return false;
+        };
+        let created = SystemTime::UNIX_EPOCH + Duration::from_millis(created_ms);
+        self.file_format.eq_ignore_ascii_case("CSV")
+            && now.duration_since(created).unwrap_or_default() <= max_age
+    }
+
+    /// Columns of data files
+    pub(crate) fn columns(&self) -> InventoryColumns {
+        let column = |name: &str| {
+            self.file_schema
+                .split(',')
+                .position(|column| column.trim() == name)
+        };
+        InventoryColumns {
+            key: column("Key"),
+            is_latest: column("IsLatest"),
+            is_delete_marker: column("IsDeleteMarker"),
+        }
+    }
+}
+
+/// Indices of the columns of data files used for listings
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct InventoryColumns {
+    key: Option<usize>,
+    /// Only present in reports including all versions of objects
+    is_latest: Option<usize>,
+    /// Only present in reports including all versions of objects
+    is_delete_marker: Option<usize>,
+}
+
+impl InventoryColumns {
+    /// Key of the current version of an object listed by a line of a data file, `None` if the
+    /// line lists a noncurrent version or a delete marker
+    pub(crate) fn key(&self, line: &str) -> Option<String> {
+        let fields: Vec<_> = line
+            .trim_end()
+            .split(',')
+            .map(|field| field.trim_matches('"'))
+            .collect();
+        let flag = |column: Option<usize>| {
+            column.and_then(|column| fields.get(column)).copied() == Some("true")
+        };
+        if (self.is_latest.is_some() && !flag(self.is_latest)) || flag(self.is_delete_marker) {
+            return None;
+        }
+        let key = fields.get(self.key?)?;
+        (!key.is_empty()).then(|| decode_key(key))
+    }
+}
+
+/// Prefix of the reports of the inventory configuration `id` of `bucket`, which are written to
+/// the destination prefix `prefix`
+pub(crate) fn report_prefix(prefix: Option<&str>, bucket: &str, id: &str) -> String {
+    match prefix.map(|prefix| prefix.trim_end_matches('/')) {
+        Some(prefix) if !prefix.is_empty() => format!("{prefix}/{bucket}/{id}/"),
+        _ => format!("{bucket}/{id}/"),
+    }
+}
+
+/// Decode a key of a data file, which are URL-encoded with spaces encoded as `+`
+fn decode_key(key: &str) -> String {
+    let mut out = Vec::with_capacity(key.len());
+    let mut bytes = key.bytes();
+    while let Some(b) = bytes.next() {
+        match b {
+            b'+' => out.push(b' '),
+            b'%' => {
+                let hex = [bytes.next(), bytes.next()];
+                match hex.map(|b| b.and_then(|b| char::from(b).to_digit(16))) {
+                    [Some(hi), Some(lo)] => out.push((hi * 16 + lo) as u8),
+                    _ => {
+                        out.push(b'%');
+                        out.extend(hex.into_iter().flatten());
+                    }
+                }
+            }
+            b => out.push(b),
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn inventory_reports() {
+        let manifest: InventoryManifest = serde_json::from_str(
+            r#"{
+                "sourceBucket": "bucket",
+                "destinationBucket": "arn:aws:s3:::inventory",
+                "version": "2016-11-30",
+                "creationTimestamp": "1709622000000",
+                "fileFormat": "CSV",
+                "fileSchema": "Bucket, Key, VersionId, IsLatest, IsDeleteMarker, Size",
+                "files": [{"key": "inventory/bucket/all/data/1.csv.gz", "size": 42, "MD5checksum": "abc"}]
+            }"#,
+        )
+        .expect("failed to decode manifest");
+        let created = SystemTime::UNIX_EPOCH + Duration::from_millis(1_709_622_000_000);
+        let hour = Duration::from_secs(60 * 60);
+        assert!(manifest.is_usable(DEFAULT_INVENTORY_MAX_AGE, created + hour));
+        assert!(!manifest.is_usable(hour, created + 2 * hour));
+
+        let columns = manifest.columns();
+        assert_eq!(
+            columns.key(r#""bucket","foo/bar+baz%2B%C3%A9","v1","true","false","42""#),
+            Some("foo/bar baz+\u{e9}".into())
+        );
+        assert_eq!(
+            columns.key(r#""bucket","foo","v0","false","false","42""#),
+            None
+        );
+        assert_eq!(columns.key(r#""bucket","foo","v2","true","true","""#), None);
+
+        assert_eq!(
+            report_prefix(Some("inventory/"), "bucket", "all"),
+            "inventory/bucket/all/"
+        );
+        assert_eq!(report_prefix(None, "bucket", "all"), "bucket/all/");
+    }
+}
@@ -0,0 +1,438 @@
+//! Bulk operations running in the background as jobs, so that components can start operations,
+//! which take longer than wRPC invocations may, and poll their status
+//!
+//! The state of jobs of links configuring [`JobsConfig`](crate::JobsConfig) is persisted, so that
+//! jobs interrupted by a restart of the provider are reported as such instead of being forgotten.
+
+use core::future::Future;
+use core::pin::pin;
+use core::time::Duration;
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::{mpsc, Semaphore};
+use tokio::time::Instant;
+use tokio_util::sync::CancellationToken;
+use tracing::debug;
+use uuid::Uuid;
+
+use crate::client::{Estimate, Progress, StorageClient};
+use crate::events::{Events, ProviderEvent};
+use crate::journal::unix_millis;
+
+/// Number of jobs running at the same time, unless configured otherwise
+pub const DEFAULT_MAX_CONCURRENT_JOBS: usize = 4;
+/// Default prefix of the keys of persisted job states
+pub const JOBS_PREFIX: &str = ".wasmcloud-jobs/";
+/// Time finished jobs can still be queried for
+const FINISHED_JOB_RETENTION: Duration = Duration::from_secs(60 * 60);
+/// Minimum interval between persisting the progress of a running job
+const PROGRESS_SAVE_INTERVAL: Duration = Duration::from_secs(10);
+
+/// State of a job
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(tag = "state", content = "detail", rename_all = "snake_case")]
+pub enum JobState {
+    /// Waiting for other jobs to finish
+    Queued,
+    Running,
+    /// Finished successfully, with the ID of the created resource, like a snapshot, if any
+    Succeeded(Option<String>),
+    /// Finished a dry run, with the estimated scope of the operation
+    Estimated(Estimate),
+    /// Finished with an error
+    Failed(String),
+    Cancelled,
+    /// The provider restarted before the job finished
+    Interrupted,
+}
+
+impl JobState {
+    /// Whether the job finished
+    #[must_use]
+    pub fn is_finished(&self) -> bool {
+        !matches!(self, Self::Queued | Self::Running)
+    }
+}
+
+/// Status of a job
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct JobStatus {
+    pub id: String,
+    pub state: JobState,
+    pub progress: Progress,
+}
+
+/// Operation performed by a job
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum JobOperation {
+    ClearContainer {
+        bucket: String,
+    },
+    SnapshotContainer {
+        bucket: String,
+    },
+    RestoreSnapshot {
+        bucket: String,
+        snapshot_id: String,
+    },
+    ReencryptContainer {
+        bucket: String,
+        kms_key_id: String,
+    },
+    SetLegalHolds {
+        bucket: String,
+        prefix: Option<String>,
+        on: bool,
+    },
+}
+
+/// Persisted state of a job
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct JobRecord {
+    pub id: String,
+    /// ID of the component, which started the job
+    pub component_id: String,
+    pub operation: JobOperation,
+    #[serde(flatten)]
+    pub state: JobState,
+    pub progress: Progress,
+    /// Time of the last update in milliseconds since the Unix epoch
+    pub updated_at_ms: u64,
+}
+
+impl JobRecord {
+    /// Status of the job as of the record
+    ///
+    /// Records of unfinished jobs, which are not running in this provider process, were left
+    /// behind by a provider, which was restarted, so their jobs are reported as interrupted.
+    #[must_use]
+    pub fn status(&self) -> JobStatus {
+        JobStatus {
+            id: self.id.clone(),
+            state: if self.state.is_finished() {
+                self.state.clone()
+            } else {
+                JobState::Interrupted
+            },
+            progress: self.progress,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Job {
+    /// Component, which started the job and is the only one allowed to query it
+    component_id: String,
+    operation: JobOperation,
+    state: JobState,
+    progress: Progress,
+    cancel: CancellationToken,
+    finished_at: Option<Instant>,
+}
+
+/// Jobs of all components, of which a bounded number runs at the same time
+#[derive(Clone, Debug)]
+pub(crate) struct Jobs {
+    jobs: Arc<Mutex<HashMap<String, Job>>>,
+    permits: Arc<Semaphore>,
+    /// Publisher of events about queued and finished jobs
+    events: Events,
+}
+
+impl Default for Jobs {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_CONCURRENT_JOBS, Events::default())
+    }
+}
+
+impl Jobs {
+    pub(crate) fn new(max_concurrent: usize, events: Events) -> Self {
+        Self {
+            jobs: Arc::default(),
+            permits: Arc::new(Semaphore::new(max_concurrent.max(1))),
+            events,
+        }
+    }
+
+    /// Start a job performing `operation` on behalf of `component_id`, returning its ID
+    ///
+    /// `work` is passed the sender of its progress and queued until fewer than the maximum number
+    /// of jobs are running, and returns the state the job finished in. The state of the job is
+    /// persisted using `store`, if set.
+    pub(crate) fn spawn<Fut>(
+        &self,
+        component_id: &str,
+        operation: JobOperation,
+        store: Option<StorageClient>,
+        work: impl FnOnce(mpsc::Sender<Progress>) -> Fut,
+    ) -> String
+    where
+        Fut: Future<Output = Result<JobState, String>> + Send + 'static,
+    {
+        let id = Uuid::new_v4().to_string();
+        let cancel = CancellationToken::new();
+        if self.permits.available_permits() == 0 {
+            self.events.publish(ProviderEvent::QuotaExceeded {
+                component_id: component_id.to_string(),
+                quota: "max_concurrent_jobs".into(),
+                error: format!("job `{id}` is queued until other jobs finish"),
+            });
+        }
+        let finished = (component_id.to_string(), operation.clone());
+        {
+            let mut jobs = self.lock();
+            let now = Instant::now();
+            jobs.retain(|_, job| {
+                job.finished_at
+                    .is_none_or(|at| now.duration_since(at) < FINISHED_JOB_RETENTION)
+            });
+            jobs.insert(
+                id.clone(),
+                Job {
+                    component_id: component_id.to_string(),
+                    operation,
+                    state: JobState::Queued,
+                    progress: Progress::default(),
+                    cancel: cancel.clone(),
+                    finished_at: None,
+                },
+            );
+        }
+        let (tx, mut rx) = mpsc::channel(16);
+        let work = work(tx);
+        let jobs = self.clone();
+        let job_id = id.clone();
+        tokio::spawn(async move {
+            jobs.save(&job_id, store.as_ref()).await;
+            let state = tokio::select! {
+                () = cancel.cancelled() => JobState::Cancelled,
+                res = async {
+                    let _permit = jobs.permits.acquire().await;
+                    jobs.update(&job_id, |job| job.state = JobState::Running);
+                    jobs.save(&job_id, store.as_ref()).await;
+                    let mut saved = Instant::now();
+                    let mut work = pin!(work);
+                    loop {
+                        tokio::select! {
+                            res = &mut work => break res,
+                            Some(progress) = rx.recv() => {
+                                jobs.update(&job_id, |job| job.progress = progress);
+                                if saved.elapsed() >= PROGRESS_SAVE_INTERVAL {
+                                    jobs.save(&job_id, store.as_ref()).await;
+                                    saved = Instant::now();
+                                }
+                            }
+                        }
+                    }
+                } => res.unwrap_or_else(JobState::Failed),
+            };
+            while let Ok(progress) = rx.try_recv() {
+                jobs.update(&job_id, |job| job.progress = progress);
+            }
+            debug!(id = job_id, ?state, "job finished");
+            let (component_id, operation) = finished;
+            jobs.events.publish(ProviderEvent::JobCompleted {
+                component_id,
+                job_id: job_id.clone(),
+                operation,
+                state: state.clone(),
+            });
+            jobs.update(&job_id, |job| {
+                job.state = state;
+                job.finished_at = Some(Instant::now());
+            });
+            jobs.save(&job_id, store.as_ref()).await;
+        });
+        id
+    }
+
+    /// Persist the state of the job `id` using `store`
+    async fn save(&self, id: &str, store: Option<&StorageClient>) {
+        let Some(store) = store else {
+            return;
+        };
+        let record = self.lock().get(id).map(|job| JobRecord {
+            id: id.to_string(),
+            component_id: job.component_id.clone(),
+            operation: job.operation.clone(),
+            state: job.state.clone(),
+            progress: job.progress,
+            updated_at_ms: unix_millis(SystemTime::now()),
+        });
+        if let Some(record) = record {
+            store.save_job(&record).await;
+        }
+    }
+
+    /// Status of the job `id` started by `component_id`
+    pub(crate) fn status(&self, component_id: &str, id: &str) -> Option<JobStatus> {
+        self.lock()
+            .get(id)
+            .filter(|job| job.component_id == component_id)
+            .map(|job| JobStatus {
+                id: id.to_string(),
+                state: job.state.clone(),
+                progress: job.progress,
+            })
+    }
+
+    /// Whether the job `id` started by `component_id` is known to this provider process
+    pub(crate) fn contains(&self, component_id: &str, id: &str) -> bool {
+        self.lock()
+            .get(id)
+            .is_some_and(|job| job.component_id == component_id)
+    }
+
+    /// Cancel the job `id` started by `component_id`, returning `false` if it does not exist or
+    /// already finished
+    ///
+    /// Work already done by the job, like deleted or copied objects, is not undone.
+    pub(crate) fn cancel(&self, component_id: &str, id: &str) -> bool {
+        let jobs = self.lock();
+        let Some(job) = jobs.get(id).filter(|job| job.component_id == component_id) else {
+            return false;
+        };
+        if job.state.is_finished() {
+            return false;
+        }
+        job.cancel.cancel();
+        true
+    }
+
+    fn update(&self, id: &str, f: impl FnOnce(&mut Job)) {
+        if let Some(job) = self.lock().get_mut(id) {
+            f(job);
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<String, Job>> {
+        self.jobs.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    async fn wait_finished(jobs: &Jobs, id: &str) -> JobStatus {
+        loop {
+            let status = jobs.status("component", id).expect("job not found");
+            if status.state.is_finished() {
+                return status;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    }
+
+    #[tokio::test]
+    async fn jobs() {
+        let jobs = Jobs::new(1, Events::default());
+        let release = CancellationToken::new();
+        let blocking = jobs.spawn(
+            "component",
+            JobOperation::ClearContainer {
+                bucket: "bucket".into(),
+            },
+            None,
+            |tx| {
+                let release = release.clone();
+                async move {
+                    let progress = Progress {
+                        objects: 1,
+                        ..Default::default()
+                    };
+                    tx.send(progress).await.map_err(|err| err.to_string())?;
+                    release.cancelled().await;
+                    Ok(JobState::Succeeded(Some("snapshot".into())))
+                }
+            },
+        );
+        let queued = jobs.spawn(
+            "component",
+            JobOperation::ClearContainer {
+                bucket: "bucket".into(),
+            },
+            None,
+            |_| async { Err("failed".into()) },
+        );
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(
+            jobs.status("component", &queued).map(|status| status.state),
+            Some(JobState::Queued)
+        );
+        let status = jobs.status("component", &blocking).expect("job not found");
+        assert_eq!(status.state, JobState::Running);
+        assert_eq!(status.progress.objects, 1);
+        // jobs are only visible to the component, which started them
+        assert_eq!(jobs.status("other", &blocking), None);
+        assert!(!jobs.cancel("other", &blocking));
+
+        release.cancel();
+        assert_eq!(
+            wait_finished(&jobs, &blocking).await.state,
+            JobState::Succeeded(Some("snapshot".into()))
+        );
+        assert_eq!(
+            wait_finished(&jobs, &queued).await.state,
+            JobState::Failed("failed".into())
+        );
+        assert!(!jobs.cancel("component", &blocking));
+
+        let pending = jobs.spawn(
+            "component",
+            JobOperation::ClearContainer {
+                bucket: "bucket".into(),
+            },
+            None,
+            |_| std::future::pending(),
+        );
+        assert!(jobs.cancel("component", &pending));
+        assert_eq!(
+            wait_finished(&jobs, &pending).await.state,
+            JobState::Cancelled
+        );
+    }
+
+    #[test]
+    fn records() {
+        let record = JobRecord {
+            id: "job".into(),
+            component_id: "component".into(),
+            operation: JobOperation::RestoreSnapshot {
+                bucket: "bucket".into(),
+                snapshot_id: "2024-03-05T07:00:00.000Z".into(),
+            },
+            state: JobState::Running,
+            progress: Progress::default(),
+            updated_at_ms: 0,
+        };
+        let json = serde_json::to_value(&record).expect("failed to encode record");
+        assert_eq!(json["state"], "running");
+        assert_eq!(json["operation"]["op"], "restore_snapshot");
+        assert_eq!(
+            serde_json::from_value::<JobRecord>(json).expect("failed to decode record"),
+            record
+        );
+        // unfinished jobs, which are only known from their records, were interrupted
+        assert_eq!(record.status().state, JobState::Interrupted);
+        let record = JobRecord {
+            state: JobState::Failed("failed".into()),
+            ..record
+        };
+        assert_eq!(record.status().state, JobState::Failed("failed".into()));
+
+        let estimate = JobState::Estimated(Estimate {
+            objects: 2,
+            bytes: 3,
+            requests: 4,
+        });
+        let json = serde_json::to_value(&estimate).expect("failed to encode state");
+        assert_eq!(json["state"], "estimated");
+        assert_eq!(json["detail"]["requests"], 4);
+    }
+}
@@ -0,0 +1,134 @@
+//! Journal of mutating operations performed on behalf of components, recorded as objects in a
+//! designated bucket, so that the state of containers can be reconstructed for a point in time
+//! and changes can be attributed to the components making them
+
+use core::time::Duration;
+
+use std::time::SystemTime;
+
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Default prefix of the keys of journal records
+pub const JOURNAL_PREFIX: &str = ".wasmcloud-journal/";
+
+/// A successful mutating operation
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum Mutation {
+    /// An object was written
+    Put {
+        bucket: String,
+        key: String,
+        /// Size of the object in bytes
+        size: u64,
+    },
+    /// An object was copied
+    Copy {
+        src_bucket: String,
+        src_key: String,
+        dest_bucket: String,
+        dest_key: String,
+    },
+    /// An object was moved
+    Move {
+        src_bucket: String,
+        src_key: String,
+        dest_bucket: String,
+        dest_key: String,
+    },
+    /// Objects were deleted
+    Delete { bucket: String, keys: Vec<String> },
+    /// A version of an object was deleted permanently
+    DeleteVersion {
+        bucket: String,
+        key: String,
+        version_id: String,
+    },
+    /// The delete marker hiding an object was removed
+    Undelete { bucket: String, key: String },
+}
+
+/// A record of the journal, stored as a single object
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct JournalRecord {
+    /// Time of the operation in milliseconds since the Unix epoch
+    pub at_ms: u64,
+    /// ID of the component, on whose behalf the operation was performed
+    pub component_id: String,
+    #[serde(flatten)]
+    pub mutation: Mutation,
+}
+
+impl JournalRecord {
+    pub fn new(component_id: impl Into<String>, mutation: Mutation, at: SystemTime) -> Self {
+        Self {
+            at_ms: unix_millis(at),
+            component_id: component_id.into(),
+            mutation,
+        }
+    }
+
+    /// Time of the operation
+    #[must_use]
+    pub fn at(&self) -> SystemTime {
+        SystemTime::UNIX_EPOCH + Duration::from_millis(self.at_ms)
+    }
+}
+
+/// Milliseconds since the Unix epoch, saturating at [`u64::MAX`]
+pub(crate) fn unix_millis(t: SystemTime) -> u64 {
+    t.duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis()
+        .try_into()
+        .unwrap_or(u64::MAX)
+}
+
+/// Key of the journal record of an operation at `at_ms` under `prefix`, which sort in the order
+/// the operations were performed
+pub(crate) fn record_key(prefix: &str, at_ms: u64) -> String {
+    format!(
+        "{prefix}{}-{}.json",
+        time_key(at_ms),
+        Uuid::new_v4().simple()
+    )
+}
+
+/// Zero-padded time, which keys of records sort by
+pub(crate) fn time_key(at_ms: u64) -> String {
+    format!("{at_ms:020}")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn records() {
+        let at = SystemTime::UNIX_EPOCH + Duration::from_millis(1_700_000_000_123);
+        let record = JournalRecord::new(
+            "component",
+            Mutation::Delete {
+                bucket: "bucket".into(),
+                keys: vec!["foo".into()],
+            },
+            at,
+        );
+        assert_eq!(record.at(), at);
+        let json = serde_json::to_string(&record).expect("failed to encode record");
+        assert_eq!(
+            json,
+            r#"{"at_ms":1700000000123,"component_id":"component","op":"delete","bucket":"bucket","keys":["foo"]}"#
+        );
+        assert_eq!(
+            serde_json::from_str::<JournalRecord>(&json).expect("failed to decode record"),
+            record
+        );
+
+        let key = record_key(JOURNAL_PREFIX, 9);
+        assert!(key.starts_with(".wasmcloud-journal/00000000000000000009-"));
+        assert!(key < record_key(JOURNAL_PREFIX, 10));
+        assert!(record_key(JOURNAL_PREFIX, 99) < record_key(JOURNAL_PREFIX, 100));
+    }
+}
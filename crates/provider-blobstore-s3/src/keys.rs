@@ -0,0 +1,372 @@
+//! Validation, normalization, hashing and templating of object keys supplied by components, so
+//! that keys, which would create unaddressable objects, are rejected before making any request
+
+use core::fmt;
+
+use std::borrow::Cow;
+use std::time::SystemTime;
+
+use anyhow::{bail, Context as _};
+use aws_sdk_s3::primitives::{DateTime, DateTimeFormat};
+use hmac::{Hmac, Mac as _};
+use sha2::Sha256;
+use unicode_normalization::{is_nfc, UnicodeNormalization as _};
+
+use crate::config::KeyValidationConfig;
+use crate::error::KeyError;
+
+/// Maximum length of S3 object keys in bytes
+pub const MAX_KEY_LENGTH: usize = 1024;
+
+/// Validation, normalization, hashing and templating applied to the object keys of a link
+#[derive(Clone, Debug)]
+pub(crate) struct KeyPolicy {
+    max_length: usize,
+    normalize_nfc: bool,
+    disallowed_characters: Vec<char>,
+    template: Option<KeyTemplate>,
+    hasher: Option<KeyHasher>,
+}
+
+/// HMAC keyed with the secret of a link, which is not revealed by its `Debug` implementation
+#[derive(Clone)]
+struct KeyHasher(Hmac<Sha256>);
+
+impl fmt::Debug for KeyHasher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("KeyHasher(<redacted>)")
+    }
+}
+
+impl KeyHasher {
+    fn new(secret: &str) -> Self {
+        Self(
+            Hmac::<Sha256>::new_from_slice(secret.as_bytes())
+                .expect("HMAC accepts keys of any length"),
+        )
+    }
+
+    /// Hash a key, hex-encoding the digest
+    fn hash(&self, key: &str) -> String {
+        let mut mac = self.0.clone();
+        mac.update(key.as_bytes());
+        format!("{:x}", mac.finalize().into_bytes())
+    }
+}
+
+impl KeyPolicy {
+    pub(crate) fn new(
+        config: Option<KeyValidationConfig>,
+        template: Option<&str>,
+        hmac_secret: Option<&str>,
+    ) -> anyhow::Result<Self> {
+        let KeyValidationConfig {
+            max_length,
+            normalize_nfc,
+            disallowed_characters,
+        } = config.unwrap_or_default();
+        Ok(Self {
+            max_length: max_length.unwrap_or(MAX_KEY_LENGTH).min(MAX_KEY_LENGTH),
+            normalize_nfc,
+            disallowed_characters: disallowed_characters.chars().collect(),
+            template: template.map(KeyTemplate::parse).transpose()?,
+            hasher: hmac_secret.map(KeyHasher::new),
+        })
+    }
+
+    /// Validate a key, normalizing it first and hashing it and applying the key template,
+    /// if configured
+    pub(crate) fn apply<'k>(&self, key: &'k str) -> Result<Cow<'k, str>, KeyError> {
+        self.apply_at(key, SystemTime::now())
+    }
+
+    fn apply_at<'k>(&self, key: &'k str, now: SystemTime) -> Result<Cow<'k, str>, KeyError> {
+        if key.is_empty() {
+            return Err(KeyError::Empty);
+        }
+        let key = if self.normalize_nfc && !is_nfc(key) {
+            Cow::Owned(key.nfc().collect())
+        } else {
+            Cow::Borrowed(key)
+        };
+        let key = match (&self.template, &self.hasher) {
+            // keys laid out according to the template are only used as is by hashing links, if
+            // their `{key}` is a hash, so that components cannot store objects under unhashed keys
+            (Some(template), Some(_)) if template.key_part(&key).is_some_and(is_hash) => key,
+            (Some(template), None) if template.key_part(&key).is_some() => key,
+            (Some(template), Some(hasher)) => Cow::Owned(template.expand(&hasher.hash(&key), now)),
+            (Some(template), None) => Cow::Owned(template.expand(&key, now)),
+            (None, Some(hasher)) => Cow::Owned(hasher.hash(&key)),
+            (None, None) => key,
+        };
+        if key.len() > self.max_length {
+            return Err(KeyError::TooLong {
+                length: key.len(),
+                max_length: self.max_length,
+            });
+        }
+        if let Some(c) = key.chars().find(|c| c.is_control()) {
+            return Err(KeyError::ControlCharacter(c));
+        }
+        if let Some(c) = key.chars().find(|c| self.disallowed_characters.contains(c)) {
+            return Err(KeyError::DisallowedCharacter(c));
+        }
+        Ok(key)
+    }
+}
+
+/// A segment of a key template
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Segment {
+    Literal(String),
+    /// `{yyyy}`
+    Year,
+    /// `{mm}`
+    Month,
+    /// `{dd}`
+    Day,
+    /// `{hh}`
+    Hour,
+}
+
+impl Segment {
+    /// Number of digits of a date segment
+    fn digits(&self) -> Option<usize> {
+        match self {
+            Self::Literal(_) => None,
+            Self::Year => Some(4),
+            Self::Month | Self::Day | Self::Hour => Some(2),
+        }
+    }
+}
+
+/// A template of the keys objects are stored under, containing the key supplied by the component
+/// (`{key}`) and the UTC date (`{yyyy}`, `{mm}`, `{dd}` and `{hh}`) at the time of the request
+///
+/// `{actor}` and `{link}` are replaced by the provider when the link is established.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) struct KeyTemplate {
+    /// Segments preceding `{key}`
+    prefix: Vec<Segment>,
+    /// Segments following `{key}`
+    suffix: Vec<Segment>,
+}
+
+impl KeyTemplate {
+    pub(crate) fn parse(template: &str) -> anyhow::Result<Self> {
+        let mut prefix = Vec::new();
+        let mut suffix = None;
+        let mut rest = template;
+        while !rest.is_empty() {
+            let Some(start) = rest.find('{') else {
+                suffix
+                    .as_mut()
+                    .unwrap_or(&mut prefix)
+                    .push(Segment::Literal(rest.into()));
+                break;
+            };
+            if start > 0 {
+                suffix
+                    .as_mut()
+                    .unwrap_or(&mut prefix)
+                    .push(Segment::Literal(rest[..start].into()));
+            }
+            let end = rest[start..]
+                .find('}')
+                .map(|end| start + end)
+                .context("unterminated placeholder in key template")?;
+            let segment = match &rest[start + 1..end] {
+                "key" if suffix.is_some() => {
+                    bail!("key template must contain `{{key}}` only once")
+                }
+                "key" => {
+                    suffix = Some(Vec::new());
+                    None
+                }
+                "yyyy" => Some(Segment::Year),
+                "mm" => Some(Segment::Month),
+                "dd" => Some(Segment::Day),
+                "hh" => Some(Segment::Hour),
+                placeholder => bail!("unknown placeholder `{{{placeholder}}}` in key template"),
+            };
+            if let Some(segment) = segment {
+                suffix.as_mut().unwrap_or(&mut prefix).push(segment);
+            }
+            rest = &rest[end + 1..];
+        }
+        let suffix = suffix.context("key template must contain `{key}`")?;
+        Ok(Self { prefix, suffix })
+    }
+
+    /// Expand the template for `key` at time `now`
+    fn expand(&self, key: &str, now: SystemTime) -> String {
+        let date = DateTime::from(now)
+            .fmt(DateTimeFormat::DateTime)
+            .unwrap_or_else(|_| "1970-01-01T00:00:00Z".into());
+        let segment = |segment: &Segment| match segment {
+            Segment::Literal(s) => s.clone(),
+            Segment::Year => date[0..4].into(),
+            Segment::Month => date[5..7].into(),
+            Segment::Day => date[8..10].into(),
+            Segment::Hour => date[11..13].into(),
+        };
+        let mut out: String = self.prefix.iter().map(segment).collect();
+        out.push_str(key);
+        out.extend(self.suffix.iter().map(segment));
+        out
+    }
+
+    /// The `{key}` part of `key`, if it is already laid out according to the template, like keys
+    /// returned by listing the container, in which case it is used as is
+    fn key_part<'k>(&self, key: &'k str) -> Option<&'k str> {
+        let mut rest = key;
+        for segment in &self.prefix {
+            let next = match segment {
+                Segment::Literal(s) => rest.strip_prefix(s.as_str()),
+                _ => segment.digits().and_then(|n| {
+                    let digits = rest.get(..n)?;
+                    digits
+                        .bytes()
+                        .all(|b| b.is_ascii_digit())
+                        .then_some(&rest[n..])
+                }),
+            };
+            rest = next?;
+        }
+        for segment in self.suffix.iter().rev() {
+            let next = match segment {
+                Segment::Literal(s) => rest.strip_suffix(s.as_str()),
+                _ => segment.digits().and_then(|n| {
+                    let start = rest.len().checked_sub(n)?;
+                    let digits = rest.get(start..)?;
+                    digits
+                        .bytes()
+                        .all(|b| b.is_ascii_digit())
+                        .then_some(&rest[..start])
+                }),
+            };
+            rest = next?;
+        }
+        (!rest.is_empty()).then_some(rest)
+    }
+}
+
+/// Whether `key` is a hex-encoded HMAC-SHA256 digest, like those of [`KeyHasher::hash`]
+fn is_hash(key: &str) -> bool {
+    key.len() == 64 && key.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'))
+}
+
+#[cfg(test)]
+mod test {
+    use core::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn key_validation() {
+        let policy = KeyPolicy::new(None, None, None).expect("failed to build key policy");
+        assert_eq!(policy.apply("foo/bar").as_deref(), Ok("foo/bar"));
+        assert_eq!(policy.apply(""), Err(KeyError::Empty));
+        assert_eq!(
+            policy.apply(&"a".repeat(MAX_KEY_LENGTH + 1)),
+            Err(KeyError::TooLong {
+                length: MAX_KEY_LENGTH + 1,
+                max_length: MAX_KEY_LENGTH
+            })
+        );
+        assert_eq!(
+            policy.apply("foo\nbar"),
+            Err(KeyError::ControlCharacter('\n'))
+        );
+
+        let policy = KeyPolicy::new(
+            Some(KeyValidationConfig {
+                max_length: Some(8),
+                normalize_nfc: true,
+                disallowed_characters: "#\\".into(),
+            }),
+            None,
+            None,
+        )
+        .expect("failed to build key policy");
+        // "e" followed by a combining acute accent is normalized to "é"
+        assert_eq!(policy.apply("cafe\u{301}").as_deref(), Ok("caf\u{e9}"));
+        assert!(matches!(policy.apply("foo"), Ok(Cow::Borrowed("foo"))));
+        assert_eq!(
+            policy.apply("foo#bar"),
+            Err(KeyError::DisallowedCharacter('#'))
+        );
+        assert!(matches!(
+            policy.apply("foo/bar/baz"),
+            Err(KeyError::TooLong { .. })
+        ));
+    }
+
+    #[test]
+    fn key_templates() {
+        // 2024-03-05T07:00:00Z
+        let now = SystemTime::UNIX_EPOCH + Duration::from_secs(1_709_622_000);
+        let policy = KeyPolicy::new(
+            None,
+            Some("component/{yyyy}/{mm}/{dd}/{hh}/{key}.bin"),
+            None,
+        )
+        .expect("failed to build key policy");
+        assert_eq!(
+            policy.apply_at("foo", now).as_deref(),
+            Ok("component/2024/03/05/07/foo.bin")
+        );
+        // keys laid out according to the template, e.g. from listings, are used as is
+        assert_eq!(
+            policy
+                .apply_at("component/2023/12/31/23/bar.bin", now)
+                .as_deref(),
+            Ok("component/2023/12/31/23/bar.bin")
+        );
+        assert_eq!(
+            policy
+                .apply_at("component/20xx/12/31/23/bar.bin", now)
+                .as_deref(),
+            Ok("component/2024/03/05/07/component/20xx/12/31/23/bar.bin.bin")
+        );
+
+        let policy = KeyPolicy::new(None, Some("{yyyy}/{key}"), Some("secret"))
+            .expect("failed to build key policy");
+        let hashed = policy
+            .apply_at("user@example.com", now)
+            .expect("failed to apply key policy");
+        assert!(hashed.starts_with("2024/"));
+        assert!(!hashed.contains("user"));
+        assert_eq!(hashed.len(), "2024/".len() + 64);
+        assert_eq!(
+            policy.apply_at(&hashed, now).as_deref(),
+            Ok(hashed.as_ref())
+        );
+        // hashes are deterministic, but depend on the secret
+        let hash = |secret| {
+            KeyPolicy::new(None, None, Some(secret))
+                .expect("failed to build key policy")
+                .apply_at("user@example.com", now)
+                .map(Cow::into_owned)
+        };
+        assert_eq!(hash("secret").as_deref(), Ok(&hashed["2024/".len()..]));
+        assert_ne!(hash("secret"), hash("other"));
+        // keys laid out according to the template, which are not hashed, are hashed all the same
+        for key in [
+            "2023/user@example.com".to_string(),
+            format!("2023/{}", "A".repeat(64)),
+            format!("2023/{}", "a".repeat(63)),
+        ] {
+            let applied = policy
+                .apply_at(&key, now)
+                .expect("failed to apply key policy");
+            assert!(applied.starts_with("2024/"), "`{key}` was not hashed");
+            assert!(is_hash(&applied["2024/".len()..]), "`{key}` was not hashed");
+        }
+
+        KeyTemplate::parse("{yyyy}/{mm}").expect_err("template without key should fail");
+        KeyTemplate::parse("{key}/{key}").expect_err("template with two keys should fail");
+        KeyTemplate::parse("{key}/{foo}").expect_err("unknown placeholder should fail");
+        KeyTemplate::parse("{key}/{yyyy").expect_err("unterminated placeholder should fail");
+    }
+}
@@ -6,772 +6,3307 @@
 //! (AKA "blob store") as a [wasmcloud capability](https://wasmcloud.com/docs/concepts/capabilities) which
 //! can be used by actors on your lattice.
 //!
+//! The S3 logic is also usable as a library: [`config`] contains the storage configuration and
+//! [`client`] the [`StorageClient`], neither of which depends on wRPC types.
+//!
 
 use core::future::Future;
-use core::pin::Pin;
-use core::str::FromStr;
+use core::pin::{pin, Pin};
+use core::time::Duration;
 
-use std::collections::HashMap;
-use std::env;
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
+use std::time::SystemTime;
 
 use anyhow::{anyhow, bail, Context as _, Result};
-use aws_config::default_provider::credentials::DefaultCredentialsChain;
-use aws_config::default_provider::region::DefaultRegionChain;
-use aws_config::retry::RetryConfig;
-use aws_config::sts::AssumeRoleProvider;
-use aws_sdk_s3::config::{Region, SharedCredentialsProvider};
-use aws_sdk_s3::error::{ProvideErrorMetadata, SdkError};
-use aws_sdk_s3::operation::create_bucket::{CreateBucketError, CreateBucketOutput};
-use aws_sdk_s3::operation::get_object::GetObjectOutput;
-use aws_sdk_s3::operation::head_bucket::HeadBucketError;
-use aws_sdk_s3::operation::head_object::{HeadObjectError, HeadObjectOutput};
-use aws_sdk_s3::operation::list_objects_v2::ListObjectsV2Output;
-use aws_sdk_s3::types::{
-    BucketLocationConstraint, CreateBucketConfiguration, Delete, Object, ObjectIdentifier,
-};
-use aws_smithy_runtime::client::http::hyper_014::HyperClientBuilder;
-use base64::Engine as _;
 use bytes::{Bytes, BytesMut};
 use futures::{stream, Stream, StreamExt as _};
-use serde::Deserialize;
-use tokio::io::AsyncReadExt as _;
-use tokio::sync::{mpsc, RwLock};
+use tokio::sync::{mpsc, OnceCell, RwLock};
 use tokio_stream::wrappers::ReceiverStream;
-use tokio_util::io::ReaderStream;
-use tracing::{debug, error, instrument, warn};
-use wasmcloud_provider_sdk::core::secrets::SecretValue;
-use wasmcloud_provider_sdk::core::tls;
+use tracing::instrument::WithSubscriber as _;
+use tracing::{debug, error, info, instrument};
+use wasmcloud_provider_sdk::core::HostData;
 use wasmcloud_provider_sdk::{
-    get_connection, initialize_observability, propagate_trace_for_ctx, run_provider,
-    serve_provider_exports, Context, LinkConfig, LinkDeleteInfo, Provider,
+    get_connection, initialize_observability, load_host_data, propagate_trace_for_ctx,
+    run_provider, serve_provider_exports, Context, LinkConfig, LinkDeleteInfo, Provider,
 };
-use wrpc_interface_blobstore::bindings::{
-    exports::wrpc::blobstore::blobstore::Handler,
-    serve,
-    wrpc::blobstore::types::{ContainerMetadata, ObjectId, ObjectMetadata},
+
+mod bindings {
+    wit_bindgen_wrpc::generate!({
+        generate_all,
+    });
+}
+use bindings::exports::wasmcloud::provider_blobstore_s3::{
+    admin, batch, batch_operations, bulk, cdn_signing, conditional, diff, downloads, expiration,
+    hashes, jobs as jobs_api, locks, manifests, ranges, snapshots, stats, time_partitions, trash,
+    uploads, versions, website as website_api,
 };
+use bindings::exports::wrpc::blobstore0_1_0::blobstore as blobstore0_1_0;
+use bindings::exports::wrpc::blobstore0_2_0::blobstore::Handler;
+use bindings::serve;
+use bindings::wrpc::blobstore0_1_0::types as types0_1_0;
+use bindings::wrpc::blobstore0_2_0::types::{ContainerMetadata, ObjectId, ObjectMetadata};
 
-const ALIAS_PREFIX: &str = "alias_";
-const DEFAULT_STS_SESSION: &str = "blobstore_s3_provider";
+mod accounting;
+mod backend;
+#[cfg(feature = "bench")]
+pub mod bench;
+#[cfg(feature = "cdn-invalidation")]
+mod cdn;
+pub mod client;
+#[cfg(feature = "cdn-signing")]
+mod cloudfront;
+pub mod config;
+mod connections;
+mod consistency;
+mod credentials;
+#[cfg(feature = "derivatives")]
+mod derivatives;
+mod error;
+mod events;
+mod hooks;
+mod interceptors;
+mod inventory;
+mod jobs;
+mod journal;
+mod keys;
+mod logging;
+mod partitions;
+mod quirks;
+mod reports;
+mod routing;
+mod scan;
+mod shards;
+mod sniff;
+mod throttle;
+mod tiers;
+mod website;
 
-/// Configuration for connecting to S3-compatible storage
-///
-/// This value is meant to be parsed from link configuration, and can
-/// represent any S3-compatible storage (excluding AWS-specific things like STS)
-///
-/// NOTE that when storage config is provided via link configuration
-#[derive(Clone, Debug, Default, Deserialize)]
-pub struct StorageConfig {
-    /// AWS_ACCESS_KEY_ID, can be specified from environment
-    pub access_key_id: Option<String>,
-    /// AWS_SECRET_ACCESS_KEY, can be in environment
-    pub secret_access_key: Option<String>,
-    /// Session Token
-    pub session_token: Option<String>,
-    /// AWS_REGION
-    pub region: Option<String>,
-    /// override default max_attempts (3) for retries
-    pub max_attempts: Option<u32>,
-    /// optional configuration for STS Assume Role
-    pub sts_config: Option<StsAssumeRoleConfig>,
-    /// optional override for the AWS endpoint
-    pub endpoint: Option<String>,
-    /// optional map of bucket aliases to names
-    #[serde(default)]
-    pub aliases: HashMap<String, String>,
-    /// Region in which buckets will be created
-    pub bucket_region: Option<String>,
-}
-
-#[derive(Clone, Debug, Default, Deserialize)]
-pub struct StsAssumeRoleConfig {
-    /// Role to assume (AWS_ASSUME_ROLE_ARN)
-    /// Should be in the form "arn:aws:iam::123456789012:role/example"
-    pub role: String,
-    /// AWS Region for using sts, not for S3
-    pub region: Option<String>,
-    /// Optional Session name
-    pub session: Option<String>,
-    /// Optional external id
-    pub external_id: Option<String>,
-}
-
-impl StorageConfig {
-    /// initialize from linkdef values
-    pub async fn from_link_config(
-        LinkConfig {
-            config, secrets, ..
-        }: &LinkConfig<'_>,
-    ) -> Result<StorageConfig> {
-        let mut storage_config = if let Some(config_b64) = secrets
-            .get("config_b64")
-            .and_then(SecretValue::as_string)
-            .or_else(|| config.get("config_b64").map(String::as_str))
-        {
-            if secrets.get("config_b64").is_none() {
-                warn!("secret value [config_b64] was not found, but present in configuration. Please prefer using secrets for sensitive values.");
-            }
-            let bytes = base64::engine::general_purpose::STANDARD
-                .decode(config_b64.as_bytes())
-                .context("invalid base64 encoding")?;
-            serde_json::from_slice::<StorageConfig>(&bytes).context("corrupt config_b64")?
-        } else if let Some(encoded) = secrets
-            .get("config_json")
-            .and_then(SecretValue::as_string)
-            .or_else(|| config.get("config_json").map(String::as_str))
-        {
-            if secrets.get("config_json").is_none() {
-                warn!("secret value [config_json] was not found, but was present in configuration. Please prefer using secrets for sensitive values.");
-            }
-            serde_json::from_str::<StorageConfig>(encoded).context("corrupt config_json")?
-        } else {
-            StorageConfig::default()
-        };
+use accounting::{TransferAccounting, TransferStats};
+use events::{Events, ProviderEvent};
+use jobs::Jobs;
+use throttle::TokenBucket;
+
+pub use client::{
+    is_access_point_arn, BatchJobInfo, Change, ChangeKind, ClientRegistry, ContainerInfo,
+    ContainerStats, DeleteFailure, DeleteOptions, DeletedObject, Estimate, HashAlgorithm,
+    LockLease, Manifest, ObjectInfo, ObjectPresence, ObjectSummary, ObjectValidators, PrefixStats,
+    Progress, PublishOptions, ReadConditions, RestoreOptions, SelfTestReport, SelfTestStep,
+    SnapshotInfo, SnapshotOptions, StorageClient, StoredHashes, TrashedObject, VerifiedDelete,
+    WebsiteOptions, DEFAULT_CHUNK_SIZE, LINK_TAG, LOCKS_PREFIX, MANIFESTS_PREFIX, SNAPSHOTS_PREFIX,
+    TEMP_PREFIX, TRASH_PREFIX, TTL_TAG, UPLOADS_PREFIX,
+};
+pub use config::{
+    AntivirusConfig, AzureConfig, BackendProfile, BandwidthConfig, BatchOperationsConfig,
+    CdnInvalidationConfig, CdnPathMapping, ClearContainerConfig, ContainerPolicyConfig,
+    ContentSniffingConfig, DebugLoggingConfig, DerivativesConfig, ExpirationSweeperConfig,
+    FsConfig, HookConfig, HttpClientConfig, HttpVersion, ImdsConfig, InventoryConfig, JobsConfig,
+    JournalConfig, KeyValidationConfig, ListConsistencyConfig, ListingStatsConfig,
+    NatsObjectStoreConfig, PayloadSigning, PrefixRoute, PublicAccessBlockConfig, R2Config,
+    R2Jurisdiction, SignatureVersion, SizeTier, StorageBackend, StorageConfig,
+    StorageConfigBuilder, StsAssumeRoleConfig,
+};
+pub use connections::ConnectionStats;
+pub use error::{BackendError, ErrorKind, KeyError, RequestError};
+pub use jobs::{
+    JobOperation, JobRecord, JobState, JobStatus, DEFAULT_MAX_CONCURRENT_JOBS, JOBS_PREFIX,
+};
+pub use journal::{JournalRecord, Mutation, JOURNAL_PREFIX};
+pub use keys::MAX_KEY_LENGTH;
+pub use routing::{PrefixRouter, Router, Routers};
+pub use shards::ShardRouter;
+
+/// Prefix of provider configuration keys mapping a component ID to the IAM role it assumes
+const ACTOR_ROLE_PREFIX: &str = "actor_role_";
+/// Provider configuration key listing the components allowed to use the admin interface
+const ADMIN_COMPONENTS_KEY: &str = "admin_components";
+/// Provider configuration key of the bandwidth limit of data written to S3 by all links
+const MAX_UPLOAD_BYTES_PER_SEC_KEY: &str = "max_upload_bytes_per_sec";
+/// Provider configuration key of the bandwidth limit of data read from S3 by all links
+const MAX_DOWNLOAD_BYTES_PER_SEC_KEY: &str = "max_download_bytes_per_sec";
+/// Provider configuration key of the number of jobs running at the same time
+const MAX_CONCURRENT_JOBS_KEY: &str = "max_concurrent_jobs";
+/// Provider configuration key enabling events published onto the lattice event stream
+const LATTICE_EVENTS_KEY: &str = "lattice_events";
+/// Version of the provider
+const VERSION: &str = env!("CARGO_PKG_VERSION");
+/// Commit the provider was built from
+const COMMIT: &str = env!("BLOBSTORE_S3_COMMIT");
+/// Target triple the provider was built for
+const TARGET: &str = env!("BLOBSTORE_S3_TARGET");
+/// Bytes reserved in each NATS message for the framing of streamed data by wRPC
+const FRAME_OVERHEAD: usize = 1024;
+/// Number of changes of a diff sent to components at once
+const DIFF_BATCH_SIZE: usize = 1000;
+/// Application name used to identify workloads, unless one is configured explicitly
+const DEFAULT_APP_NAME: &str = "wasmcloud";
 
-        // If a top level BUCKET_REGION was specified config, use it
-        if let Some(region) = config.get("BUCKET_REGION") {
-            storage_config.bucket_region = Some(region.into());
+/// Build an application name identifying a workload, replacing characters not allowed in
+/// application names by `_`
+fn workload_app_name(app_name: &str, lattice: &str, host_id: &str, link_name: &str) -> String {
+    [app_name, lattice, host_id, link_name]
+        .join(".")
+        .chars()
+        .map(|c| match c {
+            '!' | '#' | '$' | '%' | '&' | '\'' | '*' | '+' | '-' | '.' | '^' | '_' | '`' | '|'
+            | '~' => c,
+            _ if c.is_ascii_alphanumeric() => c,
+            _ => '_',
+        })
+        .collect()
+}
+
+/// Size of the chunks object data is streamed in, leaving room for wRPC framing in NATS
+/// messages of at most `max_payload` bytes
+fn chunk_size(configured: Option<usize>, max_payload: usize) -> usize {
+    let size = configured.unwrap_or(DEFAULT_CHUNK_SIZE);
+    if max_payload == 0 {
+        return size;
+    }
+    size.min(max_payload.saturating_sub(FRAME_OVERHEAD).max(1))
+}
+
+impl From<ContainerInfo> for ContainerMetadata {
+    fn from(ContainerInfo { created_at }: ContainerInfo) -> Self {
+        Self { created_at }
+    }
+}
+
+impl From<ObjectInfo> for ObjectMetadata {
+    fn from(ObjectInfo { created_at, size }: ObjectInfo) -> Self {
+        Self { created_at, size }
+    }
+}
+
+impl From<versions::DeleteOptions> for DeleteOptions {
+    fn from(
+        versions::DeleteOptions {
+            mfa,
+            bypass_governance_retention,
+        }: versions::DeleteOptions,
+    ) -> Self {
+        Self {
+            mfa,
+            bypass_governance_retention,
         }
+    }
+}
 
-        if let Ok(arn) = env::var("AWS_ROLE_ARN") {
-            let mut sts_config = storage_config.sts_config.unwrap_or_default();
-            sts_config.role = arn;
-            if let Ok(region) = env::var("AWS_ROLE_REGION") {
-                sts_config.region = Some(region);
-            }
-            if let Ok(session) = env::var("AWS_ROLE_SESSION_NAME") {
-                sts_config.session = Some(session);
-            }
-            if let Ok(external_id) = env::var("AWS_ROLE_EXTERNAL_ID") {
-                sts_config.external_id = Some(external_id);
-            }
-            storage_config.sts_config = Some(sts_config);
+impl From<conditional::ReadConditions> for ReadConditions {
+    fn from(
+        conditional::ReadConditions {
+            if_none_match,
+            if_modified_since,
+        }: conditional::ReadConditions,
+    ) -> Self {
+        Self {
+            if_none_match,
+            if_modified_since: if_modified_since
+                .map(|secs| SystemTime::UNIX_EPOCH + Duration::from_secs(secs)),
         }
+    }
+}
 
-        if let Ok(endpoint) = env::var("AWS_ENDPOINT") {
-            storage_config.endpoint = Some(endpoint);
+impl From<ObjectValidators> for conditional::ObjectValidators {
+    fn from(
+        ObjectValidators {
+            etag,
+            last_modified,
+        }: ObjectValidators,
+    ) -> Self {
+        Self {
+            etag,
+            last_modified: last_modified.and_then(|t| {
+                t.duration_since(SystemTime::UNIX_EPOCH)
+                    .ok()
+                    .map(|d| d.as_secs())
+            }),
         }
+    }
+}
 
-        // aliases are added from linkdefs in StorageClient::new()
-        Ok(storage_config)
+impl From<LockLease> for locks::Lease {
+    fn from(LockLease { etag, expires_at }: LockLease) -> Self {
+        Self {
+            etag,
+            expires_at: expires_at
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+        }
     }
 }
 
-#[derive(Clone)]
-pub struct StorageClient {
-    s3_client: aws_sdk_s3::Client,
-    aliases: Arc<HashMap<String, String>>,
-    /// Preferred region for bucket creation
-    bucket_region: Option<BucketLocationConstraint>,
-}
-
-impl StorageClient {
-    pub async fn new(
-        StorageConfig {
-            access_key_id,
-            secret_access_key,
-            session_token,
-            region,
-            max_attempts,
-            sts_config,
-            endpoint,
-            mut aliases,
-            bucket_region,
-        }: StorageConfig,
-        config_values: &HashMap<String, String>,
+impl From<Manifest> for manifests::Manifest {
+    fn from(
+        Manifest {
+            id,
+            members,
+            created_at,
+        }: Manifest,
     ) -> Self {
-        let region = match region {
-            Some(region) => Some(Region::new(region)),
-            _ => DefaultRegionChain::builder().build().region().await,
-        };
+        Self {
+            id,
+            members,
+            created_at: created_at
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+        }
+    }
+}
 
-        // use static credentials or defaults from environment
-        let mut cred_provider = match (access_key_id, secret_access_key) {
-            (Some(access_key_id), Some(secret_access_key)) => {
-                SharedCredentialsProvider::new(aws_sdk_s3::config::Credentials::new(
-                    access_key_id,
-                    secret_access_key,
-                    session_token,
-                    None,
-                    "static",
-                ))
-            }
-            _ => SharedCredentialsProvider::new(
-                DefaultCredentialsChain::builder()
-                    .region(region.clone())
-                    .build()
-                    .await,
-            ),
-        };
-        if let Some(StsAssumeRoleConfig {
-            role,
-            region,
-            session,
-            external_id,
-        }) = sts_config
-        {
-            let mut role = AssumeRoleProvider::builder(role)
-                .session_name(session.unwrap_or_else(|| DEFAULT_STS_SESSION.to_string()));
-            if let Some(region) = region {
-                role = role.region(Region::new(region));
-            }
-            if let Some(external_id) = external_id {
-                role = role.external_id(external_id);
-            }
-            cred_provider = SharedCredentialsProvider::new(role.build().await);
+impl From<DeleteFailure> for batch::Failure {
+    fn from(DeleteFailure { key, code, message }: DeleteFailure) -> Self {
+        Self {
+            object: key,
+            code,
+            message,
         }
+    }
+}
 
-        let mut retry_config = RetryConfig::standard();
-        if let Some(max_attempts) = max_attempts {
-            retry_config = retry_config.with_max_attempts(max_attempts);
+impl From<DeletedObject> for batch::DeletedObject {
+    fn from(
+        DeletedObject {
+            key,
+            etag,
+            version_id,
+            size,
+        }: DeletedObject,
+    ) -> Self {
+        Self {
+            object: key,
+            etag,
+            version_id,
+            size,
         }
-        let mut loader = aws_config::defaults(aws_config::BehaviorVersion::v2024_03_28())
-            .region(region)
-            .credentials_provider(cred_provider)
-            .retry_config(retry_config);
-        if let Some(endpoint) = endpoint {
-            loader = loader.endpoint_url(endpoint);
-        };
-        let s3_client = aws_sdk_s3::Client::from_conf(
-            aws_sdk_s3::Config::from(&loader.load().await)
-                .to_builder()
-                // Since minio requires force path style,
-                // turn it on since it's disabled by default
-                // due to deprecation by AWS.
-                // https://github.com/awslabs/aws-sdk-rust/issues/390
-                .force_path_style(true)
-                .http_client(
-                    HyperClientBuilder::new().build(
-                        hyper_rustls::HttpsConnectorBuilder::new()
-                            .with_tls_config(
-                                // use `tls::DEFAULT_CLIENT_CONFIG` directly once `rustls` versions
-                                // are in sync
-                                rustls::ClientConfig::builder()
-                                    .with_root_certificates(rustls::RootCertStore {
-                                        roots: tls::DEFAULT_ROOTS.roots.clone(),
-                                    })
-                                    .with_no_client_auth(),
-                            )
-                            .https_or_http()
-                            .enable_all_versions()
-                            .build(),
-                    ),
-                )
-                .build(),
-        );
+    }
+}
 
-        // Process aliases
-        for (k, v) in config_values {
-            if let Some(alias) = k.strip_prefix(ALIAS_PREFIX) {
-                if alias.is_empty() || v.is_empty() {
-                    error!("invalid bucket alias_ key and value must not be empty");
-                } else {
-                    aliases.insert(alias.to_string(), v.to_string());
-                }
-            }
+impl From<VerifiedDelete> for batch::VerifiedDelete {
+    fn from(
+        VerifiedDelete {
+            deleted,
+            missing,
+            failures,
+        }: VerifiedDelete,
+    ) -> Self {
+        Self {
+            deleted: deleted.into_iter().map(Into::into).collect(),
+            missing,
+            failures: failures.into_iter().map(Into::into).collect(),
         }
+    }
+}
 
-        StorageClient {
-            s3_client,
-            aliases: Arc::new(aliases),
-            bucket_region: bucket_region.and_then(|v| BucketLocationConstraint::from_str(&v).ok()),
+impl From<ObjectPresence> for versions::ObjectPresence {
+    fn from(presence: ObjectPresence) -> Self {
+        match presence {
+            ObjectPresence::Present => Self::Present,
+            ObjectPresence::Absent => Self::Absent,
+            ObjectPresence::Deleted => Self::Deleted,
         }
     }
+}
 
-    /// perform alias lookup on bucket name
-    /// This can be used either for giving shortcuts to actors in the linkdefs, for example:
-    /// - component could use bucket names `alias_today`, `alias_images`, etc. and the linkdef aliases
-    ///   will remap them to the real bucket name
-    ///
-    /// The `'alias_'` prefix is not required, so this also works as a general redirect capability
-    pub fn unalias<'n, 's: 'n>(&'s self, bucket_or_alias: &'n str) -> &'n str {
-        debug!(%bucket_or_alias, aliases = ?self.aliases);
-        let name = bucket_or_alias
-            .strip_prefix(ALIAS_PREFIX)
-            .unwrap_or(bucket_or_alias);
-        if let Some(name) = self.aliases.get(name) {
-            name.as_ref()
-        } else {
-            name
-        }
-    }
-
-    /// Check whether a container exists
-    #[instrument(level = "debug", skip(self))]
-    pub async fn container_exists(&self, bucket: &str) -> anyhow::Result<bool> {
-        match self.s3_client.head_bucket().bucket(bucket).send().await {
-            Ok(_) => Ok(true),
-            Err(se) => match se.into_service_error() {
-                HeadBucketError::NotFound(_) => Ok(false),
-                err => {
-                    error!(?err, code = err.code(), "Unable to head bucket");
-                    bail!(anyhow!(err).context("failed to `head` bucket"))
-                }
-            },
+impl From<snapshots::SnapshotOptions> for SnapshotOptions {
+    fn from(
+        snapshots::SnapshotOptions {
+            destination,
+            prefix,
+            incremental,
+        }: snapshots::SnapshotOptions,
+    ) -> Self {
+        Self {
+            destination,
+            prefix,
+            incremental,
         }
     }
+}
 
-    /// Create a bucket
-    #[instrument(level = "debug", skip(self))]
-    pub async fn create_container(&self, bucket: &str) -> anyhow::Result<()> {
-        let mut builder = self.s3_client.create_bucket();
+impl From<TrashedObject> for trash::TrashedObject {
+    fn from(
+        TrashedObject {
+            key,
+            deleted_at,
+            trash_key,
+        }: TrashedObject,
+    ) -> Self {
+        Self {
+            object: key,
+            deleted_at,
+            trash_key,
+        }
+    }
+}
 
-        // Only add BucketLocationConstraint if bucket_region was set.
-        if let Some(bucket_region) = &self.bucket_region {
-            // Build bucket config, using location constraint if necessary
-            let bucket_config = CreateBucketConfiguration::builder()
-                .set_location_constraint(Some(bucket_region.clone()))
-                .build();
+impl From<SnapshotInfo> for snapshots::Snapshot {
+    fn from(
+        SnapshotInfo {
+            id,
+            container,
+            prefix,
+            objects,
+            objects_copied,
+            bytes_copied,
+        }: SnapshotInfo,
+    ) -> Self {
+        Self {
+            id,
+            container,
+            prefix,
+            objects,
+            objects_copied,
+            bytes_copied,
+        }
+    }
+}
 
-            builder = builder.create_bucket_configuration(bucket_config);
+impl From<snapshots::RestoreOptions> for RestoreOptions {
+    fn from(
+        snapshots::RestoreOptions {
+            source,
+            prefix,
+            only_changed,
+        }: snapshots::RestoreOptions,
+    ) -> Self {
+        Self {
+            source,
+            prefix,
+            only_changed,
         }
+    }
+}
 
-        match builder.bucket(bucket).send().await {
-            Ok(CreateBucketOutput { location, .. }) => {
-                debug!(?location, "bucket created");
-                Ok(())
-            }
-            Err(se) => match se.into_service_error() {
-                CreateBucketError::BucketAlreadyOwnedByYou(..) => Ok(()),
-                err => {
-                    error!(?err, code = err.code(), "failed to create bucket");
-                    bail!(anyhow!(err).context("failed to create bucket"))
-                }
-            },
+impl From<website_api::WebsiteOptions> for WebsiteOptions {
+    fn from(
+        website_api::WebsiteOptions {
+            index_document,
+            error_document,
+        }: website_api::WebsiteOptions,
+    ) -> Self {
+        Self {
+            index_document,
+            error_document,
         }
     }
+}
 
-    #[instrument(level = "debug", skip(self))]
-    pub async fn get_container_info(&self, bucket: &str) -> anyhow::Result<ContainerMetadata> {
-        match self.s3_client.head_bucket().bucket(bucket).send().await {
-            Ok(_) => Ok(ContainerMetadata {
-                // unfortunately, HeadBucketOut doesn't include any information
-                // so we can't fill in creation date
-                created_at: 0,
-            }),
-            Err(se) => match se.into_service_error() {
-                HeadBucketError::NotFound(_) => {
-                    error!("bucket [{bucket}] not found");
-                    bail!("bucket [{bucket}] not found")
-                }
-                err => {
-                    error!(?err, code = err.code(), "unexpected error");
-                    bail!(anyhow!(err).context("unexpected error"));
+impl From<website_api::PublishOptions> for PublishOptions {
+    fn from(
+        website_api::PublishOptions {
+            prefix,
+            html_cache_control,
+            cache_control,
+            invalidate_cdn,
+        }: website_api::PublishOptions,
+    ) -> Self {
+        Self {
+            prefix,
+            html_cache_control,
+            cache_control,
+            invalidate_cdn,
+        }
+    }
+}
+
+impl From<Progress> for snapshots::Progress {
+    fn from(
+        Progress {
+            objects,
+            total,
+            skipped,
+            bytes,
+            errors,
+        }: Progress,
+    ) -> Self {
+        Self {
+            objects,
+            total,
+            skipped,
+            bytes,
+            errors,
+        }
+    }
+}
+
+impl From<Estimate> for jobs_api::Estimate {
+    fn from(
+        Estimate {
+            objects,
+            bytes,
+            requests,
+        }: Estimate,
+    ) -> Self {
+        Self {
+            objects,
+            bytes,
+            requests,
+        }
+    }
+}
+
+impl From<JobState> for jobs_api::JobState {
+    fn from(state: JobState) -> Self {
+        match state {
+            JobState::Queued => Self::Queued,
+            JobState::Running => Self::Running,
+            JobState::Succeeded(created) => Self::Succeeded(created),
+            JobState::Estimated(estimate) => Self::Estimated(estimate.into()),
+            JobState::Failed(err) => Self::Failed(err),
+            JobState::Cancelled => Self::Cancelled,
+            JobState::Interrupted => Self::Interrupted,
+        }
+    }
+}
+
+impl From<JobStatus> for jobs_api::JobStatus {
+    fn from(
+        JobStatus {
+            id,
+            state,
+            progress,
+        }: JobStatus,
+    ) -> Self {
+        Self {
+            id,
+            state: state.into(),
+            progress: progress.into(),
+        }
+    }
+}
+
+impl From<BatchJobInfo> for batch_operations::BatchJob {
+    fn from(
+        BatchJobInfo {
+            id,
+            status,
+            total,
+            succeeded,
+            failed,
+            failure_reasons,
+            ..
+        }: BatchJobInfo,
+    ) -> Self {
+        Self {
+            id,
+            status,
+            total,
+            succeeded,
+            failed,
+            failure_reasons,
+        }
+    }
+}
+
+impl From<hashes::HashAlgorithm> for HashAlgorithm {
+    fn from(algorithm: hashes::HashAlgorithm) -> Self {
+        match algorithm {
+            hashes::HashAlgorithm::Sha256 => Self::Sha256,
+            hashes::HashAlgorithm::Md5 => Self::Md5,
+        }
+    }
+}
+
+impl From<StoredHashes> for hashes::StoredHashes {
+    fn from(
+        StoredHashes {
+            etag,
+            checksum_sha256,
+        }: StoredHashes,
+    ) -> Self {
+        Self {
+            etag,
+            checksum_sha256,
+        }
+    }
+}
+
+impl From<ChangeKind> for diff::ChangeKind {
+    fn from(kind: ChangeKind) -> Self {
+        match kind {
+            ChangeKind::Added => Self::Added,
+            ChangeKind::Removed => Self::Removed,
+            ChangeKind::Changed => Self::Changed,
+        }
+    }
+}
+
+impl From<Change> for diff::Change {
+    fn from(Change { key, kind }: Change) -> Self {
+        Self {
+            key,
+            kind: kind.into(),
+        }
+    }
+}
+
+impl From<ObjectSummary> for stats::ObjectSummary {
+    fn from(
+        ObjectSummary {
+            key,
+            size,
+            last_modified,
+        }: ObjectSummary,
+    ) -> Self {
+        Self {
+            key,
+            size,
+            last_modified,
+        }
+    }
+}
+
+impl From<PrefixStats> for stats::PrefixStats {
+    fn from(
+        PrefixStats {
+            prefix,
+            object_count,
+            total_bytes,
+        }: PrefixStats,
+    ) -> Self {
+        Self {
+            prefix,
+            object_count,
+            total_bytes,
+        }
+    }
+}
+
+impl From<ContainerStats> for stats::ContainerStats {
+    fn from(
+        ContainerStats {
+            object_count,
+            total_bytes,
+            updated_at,
+        }: ContainerStats,
+    ) -> Self {
+        Self {
+            object_count,
+            total_bytes,
+            updated_at,
+        }
+    }
+}
+
+/// Description of S3 Batch Operations jobs created on behalf of a component, identifying the
+/// jobs the component may query and cancel
+fn batch_job_description(component_id: &str) -> String {
+    format!("wasmcloud:{component_id}")
+}
+
+/// Look up the S3 Batch Operations job `id`, failing if it was not created by `component_id`
+async fn component_batch_job(
+    client: &StorageClient,
+    component_id: &str,
+    id: &str,
+) -> Result<BatchJobInfo> {
+    let job = client.batch_job(id).await?;
+    if job.description.as_deref() != Some(&batch_job_description(component_id)) {
+        bail!(BackendError::new(
+            ErrorKind::NotFound,
+            format!("S3 Batch Operations job `{id}` not found")
+        ));
+    }
+    Ok(job)
+}
+
+/// ID of the invoking component, empty if unknown
+fn component_id(cx: Option<&Context>) -> String {
+    cx.and_then(|cx| cx.component.clone()).unwrap_or_default()
+}
+
+/// Message of an error returned to a component, prefixed with its [`ErrorKind`], so that
+/// components can handle failures the same way whichever backend a link uses
+fn error_message(err: &anyhow::Error) -> String {
+    format!("{}: {err:#}", ErrorKind::of(err))
+}
+
+/// Fail if any objects could not be moved to the trash, reporting the first of them
+fn trashed(failures: Vec<DeleteFailure>) -> anyhow::Result<()> {
+    if let Some(DeleteFailure { key, message, .. }) = failures.first() {
+        bail!(
+            "failed to move {} object(s) to the trash, first failure: `{key}` ({})",
+            failures.len(),
+            message.as_deref().unwrap_or("no message"),
+        )
+    }
+    Ok(())
+}
+
+/// Encode a self-test report for `wash call`
+fn self_test_json(report: SelfTestReport) -> serde_json::Value {
+    let passed = report.passed();
+    let SelfTestReport { bucket, key, steps } = report;
+    serde_json::json!({
+        "bucket": bucket,
+        "key": key,
+        "passed": passed,
+        "steps": steps
+            .into_iter()
+            .map(|SelfTestStep { name, duration, error }| {
+                serde_json::json!({
+                    "name": name,
+                    "duration_ms": u64::try_from(duration.as_millis()).unwrap_or(u64::MAX),
+                    "error": error,
+                })
+            })
+            .collect::<Vec<_>>(),
+    })
+}
+
+fn object_id(id: types0_1_0::ObjectId) -> ObjectId {
+    ObjectId {
+        container: id.container,
+        object: id.object,
+    }
+}
+
+/// Stream the progress of an operation sent to `rx`
+fn progress_stream(
+    rx: mpsc::Receiver<Progress>,
+) -> Pin<Box<dyn Stream<Item = Vec<snapshots::Progress>> + Send>> {
+    Box::pin(ReceiverStream::new(rx).map(|progress| vec![progress.into()]))
+}
+
+/// Drive a transfer to completion in the background, for `wrpc:blobstore@0.1.0` functions,
+/// which do not return a future reporting its outcome
+fn spawn_completion(
+    operation: &'static str,
+    done: Pin<Box<dyn Future<Output = Result<(), String>> + Send>>,
+) {
+    tokio::spawn(async move {
+        if let Err(err) = done.await {
+            error!(err, "failed to {operation}");
+        }
+    });
+}
+
+/// How objects written by components are stored
+enum WriteMode {
+    /// Put the object in a single request, expiring after the given number of days or the
+    /// period configured for the link
+    Put { expires_after_days: Option<u32> },
+    /// Upload the object in parts, resuming uploads with the same idempotency key
+    Resumable { idempotency_key: String },
+}
+
+/// A link of a component, whose storage client is created when the link is established or, with
+/// `lazy_init` enabled, on its first invocation
+#[derive(Clone)]
+struct Link {
+    client: Arc<OnceCell<StorageClient>>,
+    config: Arc<StorageConfig>,
+    config_values: Arc<HashMap<String, String>>,
+    /// Whether creating the client of the link failed the last time it was attempted
+    degraded: Arc<AtomicBool>,
+}
+
+impl Link {
+    /// Abort all in-flight and background work of the link, if its client was created
+    fn cancel(&self) {
+        if let Some(client) = self.client.get() {
+            client.cancel();
+        }
+    }
+}
+
+/// Blobstore S3 provider
+///
+/// This struct will be the target of generated implementations (via wit-provider-bindgen)
+/// for the blobstore provider WIT contract
+#[derive(Default, Clone)]
+pub struct BlobstoreS3Provider {
+    /// Links, keyed by component ID and link name
+    actors: Arc<RwLock<HashMap<(String, String), Link>>>,
+    /// Roles assumed on behalf of specific components, keyed by component ID
+    actor_roles: Arc<HashMap<String, String>>,
+    /// Lattice the provider runs in, used to identify workloads
+    lattice: Arc<str>,
+    /// Host the provider runs on, used to identify workloads
+    host_id: Arc<str>,
+    /// S3 clients shared between links
+    clients: ClientRegistry,
+    /// Defaults from provider configuration, overridden by link configuration
+    defaults: Arc<StorageConfig>,
+    /// Bytes transferred on behalf of components
+    accounting: TransferAccounting,
+    /// Components allowed to use the admin interface
+    admin_components: Arc<HashSet<String>>,
+    /// Bandwidth limit of data written to S3, shared by all links
+    upload_limit: Option<Arc<TokenBucket>>,
+    /// Bandwidth limit of data read from S3, shared by all links
+    download_limit: Option<Arc<TokenBucket>>,
+    /// Bulk operations running in the background
+    jobs: Jobs,
+    /// Publisher of events onto the lattice event stream
+    events: Events,
+}
+
+pub async fn run() -> anyhow::Result<()> {
+    BlobstoreS3Provider::run().await
+}
+
+impl BlobstoreS3Provider {
+    pub async fn run() -> anyhow::Result<()> {
+        initialize_observability!(
+            "blobstore-s3-provider",
+            std::env::var_os("PROVIDER_BLOBSTORE_S3_FLAMEGRAPH_PATH")
+        );
+
+        info!(
+            version = VERSION,
+            commit = COMMIT,
+            target = TARGET,
+            "starting provider"
+        );
+        let host_data = load_host_data().context("failed to load host data")?;
+        let provider = Self::from_host_data(host_data)?;
+        let shutdown = run_provider(provider.clone(), "blobstore-s3-provider")
+            .await
+            .context("failed to run provider")?;
+        let connection = get_connection();
+        serve_provider_exports(
+            &connection.get_wrpc_client(connection.provider_key()),
+            provider,
+            shutdown,
+            serve,
+        )
+        .await
+        .context("failed to serve provider exports")
+    }
+
+    /// Build a [`BlobstoreS3Provider`] from [`HostData`]
+    ///
+    /// Provider configuration may contain `actor_role_<component id>=<role arn>` entries, which
+    /// make links from that component assume the given role, using the link credentials
+    /// (or those found in the environment) as the base credentials.
+    ///
+    /// A `config_b64` or `config_json` storage config in provider configuration (or secrets)
+    /// provides defaults for all links, which link configuration overrides per field.
+    ///
+    /// `admin_components` lists the IDs of components allowed to use the admin interface,
+    /// separated by commas.
+    ///
+    /// `max_upload_bytes_per_sec` and `max_download_bytes_per_sec` limit the bandwidth used by
+    /// all links together.
+    ///
+    /// `max_concurrent_jobs` is the number of jobs running at the same time, 4 by default.
+    ///
+    /// `lattice_events=true` publishes events about links, clients, quotas and jobs onto the
+    /// lattice event stream.
+    pub fn from_host_data(host_data: &HostData) -> anyhow::Result<BlobstoreS3Provider> {
+        let defaults = StorageConfig::from_values(&host_data.config, &host_data.secrets)
+            .context("failed to parse default storage config")?;
+        let mut actor_roles = HashMap::new();
+        for (k, v) in &host_data.config {
+            if let Some(component_id) = k.strip_prefix(ACTOR_ROLE_PREFIX) {
+                if component_id.is_empty() || v.is_empty() {
+                    error!("invalid actor_role_ key and value must not be empty");
+                } else {
+                    actor_roles.insert(component_id.to_string(), v.to_string());
                 }
-            },
+            }
         }
+        let admin_components = host_data
+            .config
+            .get(ADMIN_COMPONENTS_KEY)
+            .map(|ids| {
+                ids.split(',')
+                    .map(str::trim)
+                    .filter(|id| !id.is_empty())
+                    .map(String::from)
+                    .collect()
+            })
+            .unwrap_or_default();
+        let limit = |key| {
+            host_data
+                .config
+                .get(key)
+                .map(|rate: &String| {
+                    rate.parse()
+                        .with_context(|| format!("invalid `{key}`"))
+                        .map(|rate| Arc::new(TokenBucket::new(rate)))
+                })
+                .transpose()
+        };
+        let max_concurrent_jobs = host_data
+            .config
+            .get(MAX_CONCURRENT_JOBS_KEY)
+            .map(|n| {
+                n.parse()
+                    .with_context(|| format!("invalid `{MAX_CONCURRENT_JOBS_KEY}`"))
+            })
+            .transpose()?
+            .unwrap_or(DEFAULT_MAX_CONCURRENT_JOBS);
+        let events = host_data
+            .config
+            .get(LATTICE_EVENTS_KEY)
+            .map(|enabled| {
+                enabled
+                    .parse()
+                    .with_context(|| format!("invalid `{LATTICE_EVENTS_KEY}`"))
+            })
+            .transpose()?
+            .map(Events::new)
+            .unwrap_or_default();
+        Ok(BlobstoreS3Provider {
+            jobs: Jobs::new(max_concurrent_jobs, events),
+            events,
+            upload_limit: limit(MAX_UPLOAD_BYTES_PER_SEC_KEY)?,
+            download_limit: limit(MAX_DOWNLOAD_BYTES_PER_SEC_KEY)?,
+            actor_roles: Arc::new(actor_roles),
+            admin_components: Arc::new(admin_components),
+            lattice: host_data.lattice_rpc_prefix.as_str().into(),
+            host_id: host_data.host_id.as_str().into(),
+            defaults: Arc::new(defaults),
+            ..Default::default()
+        })
     }
 
-    #[instrument(level = "debug", skip(self))]
-    pub async fn list_container_objects(
-        &self,
-        bucket: &str,
-        limit: Option<u64>,
-        offset: Option<u64>,
-    ) -> anyhow::Result<impl Iterator<Item = String>> {
-        // TODO: Stream names
-        match self
-            .s3_client
-            .list_objects_v2()
-            .bucket(bucket)
-            .set_max_keys(limit.map(|limit| limit.try_into().unwrap_or(i32::MAX)))
-            .send()
+    /// Retrieve the per-component [`StorageClient`] for a given link context
+    async fn client(&self, context: Option<Context>) -> Result<StorageClient> {
+        let Some(ctx) = context else {
+            bail!("failed to lookup invocation context")
+        };
+        let Some(ref source_id) = ctx.component else {
+            // TODO: Support a default here
+            bail!("failed to lookup invocation source ID")
+        };
+        let link_name = ctx.link_name();
+        let link = self
+            .actors
+            .read()
             .await
-        {
-            Ok(ListObjectsV2Output { contents, .. }) => Ok(contents
-                .into_iter()
-                .flatten()
-                .filter_map(|Object { key, .. }| key)
-                .skip(offset.unwrap_or_default().try_into().unwrap_or(usize::MAX))
-                .take(limit.unwrap_or(u64::MAX).try_into().unwrap_or(usize::MAX))),
-            Err(SdkError::ServiceError(err)) => {
-                error!(?err, "service error");
-                bail!(anyhow!("{err:?}").context("service error"))
+            .get(&(source_id.clone(), link_name.to_string()))
+            .with_context(|| {
+                format!("failed to lookup {source_id} configuration for link `{link_name}`")
+            })?
+            .clone();
+        match self.link_client(&link).await {
+            Ok(client) => {
+                link.degraded.store(false, Ordering::Relaxed);
+                Ok(client)
             }
             Err(err) => {
-                error!(%err, code = err.code(), "unexpected error");
-                bail!(anyhow!("{err:?}").context("unexpected error"))
+                if !link.degraded.swap(true, Ordering::Relaxed) {
+                    self.events.publish(ProviderEvent::ClientDegraded {
+                        source_id: source_id.clone(),
+                        link_name: link_name.to_string(),
+                        error: format!("{err:#}"),
+                    });
+                }
+                Err(err)
             }
         }
     }
 
-    #[instrument(level = "debug", skip(self))]
-    pub async fn copy_object(
-        &self,
-        src_bucket: &str,
-        src_key: &str,
-        dest_bucket: &str,
-        dest_key: &str,
-    ) -> anyhow::Result<()> {
-        self.s3_client
-            .copy_object()
-            .copy_source(format!("{src_bucket}/{src_key}"))
-            .bucket(dest_bucket)
-            .key(dest_key)
-            .send()
-            .await
-            .context("failed to copy object")?;
-        Ok(())
+    /// Retrieve the [`StorageClient`] of a link, creating it if it was not created yet
+    async fn link_client(&self, link: &Link) -> Result<StorageClient> {
+        link.client
+            .get_or_try_init(|| async {
+                let config = StorageConfig::clone(&link.config);
+                let expiration_sweeper = config.expiration_sweeper.clone();
+                let warm_up = config.warm_up.unwrap_or_default();
+                let client = self
+                    .clients
+                    .storage_client(config, &link.config_values)
+                    .await
+                    .context("failed to create storage client")?;
+                if warm_up {
+                    client.warm_up().await;
+                }
+                if let Some(sweeper) = expiration_sweeper {
+                    client.spawn(client.clone().sweep_expired(sweeper));
+                }
+                if client.soft_deletes() {
+                    client.spawn(client.clone().purge_trash());
+                }
+                anyhow::Ok(client)
+            })
+            .await
+            .cloned()
     }
 
-    #[instrument(level = "debug", skip(self, object))]
-    pub async fn delete_object(&self, container: &str, object: String) -> anyhow::Result<()> {
-        self.s3_client
-            .delete_object()
-            .bucket(container)
-            .key(object)
-            .send()
+    /// Run `f` with the client of the invoking link, applying the log level and sampling
+    /// configured for the link
+    async fn with_client<T, Fut>(
+        &self,
+        cx: Option<Context>,
+        f: impl FnOnce(StorageClient) -> Fut,
+    ) -> Result<T>
+    where
+        Fut: Future<Output = Result<T>>,
+    {
+        let component_id = component_id(cx.as_ref());
+        let client = self.client(cx).await?;
+        let res = match client.log_dispatch() {
+            Some(dispatch) => f(client).with_subscriber(dispatch).await,
+            None => f(client).await,
+        };
+        if let Err(err) = &res {
+            self.events.publish_quota_exceeded(&component_id, err);
+        }
+        res
+    }
+
+    /// Ensure that the invoking component is allowed to use the admin interface
+    fn ensure_admin(&self, cx: Option<&Context>) -> Result<()> {
+        let Some(component_id) = cx.and_then(|cx| cx.component.as_ref()) else {
+            bail!("failed to lookup invocation source ID")
+        };
+        if !self.admin_components.contains(component_id) {
+            bail!(BackendError::new(
+                ErrorKind::PermissionDenied,
+                format!("component `{component_id}` is not allowed to use the admin interface")
+            ))
+        }
+        Ok(())
+    }
+
+    /// Stream the byte range from `start` to `end` of an object, or of a specific version of it
+    async fn container_data(
+        &self,
+        cx: Option<Context>,
+        id: ObjectId,
+        version_id: Option<String>,
+        start: u64,
+        end: u64,
+    ) -> Result<(
+        Pin<Box<dyn Stream<Item = Bytes> + Send>>,
+        Pin<Box<dyn Future<Output = Result<(), String>> + Send>>,
+    )> {
+        propagate_trace_for_ctx!(cx);
+        let component_id = component_id(cx.as_ref());
+        self.with_client(cx, |client| async move {
+            let bucket = client.unalias(&id.container).to_string();
+            #[cfg(feature = "derivatives")]
+            if let (Some(derivatives), None) = (client.derivatives(), &version_id) {
+                if let Some((source, transform)) = derivatives::parse(&id.object)? {
+                    let source = client.object_key(source)?;
+                    let data = derivatives
+                        .read(&client, &bucket, &source, transform, start, end)
+                        .await?;
+                    return Ok(self.forward_data(client, component_id, bucket, data));
+                }
+            }
+            let key = client.object_key(&id.object)?;
+            let key = if version_id.is_none() {
+                client.latest_key(&bucket, &key).await?.into_owned()
+            } else {
+                key.into_owned()
+            };
+            let data = client
+                .read_object_version(&bucket, &key, version_id.as_deref(), start, end)
+                .await?
+                .boxed();
+            Ok(self.forward_data(client, component_id, bucket, data))
+        })
+        .await
+    }
+
+    /// Forward data read from S3 to a component
+    fn forward_data(
+        &self,
+        client: StorageClient,
+        component_id: String,
+        bucket: String,
+        mut data: impl Stream<Item = std::io::Result<Bytes>> + Send + Unpin + 'static,
+    ) -> (
+        Pin<Box<dyn Stream<Item = Bytes> + Send>>,
+        Pin<Box<dyn Future<Output = Result<(), String>> + Send>>,
+    ) {
+        let accounting = self.accounting.clone();
+        let events = self.events;
+        let limit = self.download_limit.clone();
+        let (tx, rx) = mpsc::channel(16);
+        (
+            Box::pin(ReceiverStream::new(rx)) as Pin<Box<dyn Stream<Item = _> + Send>>,
+            Box::pin(client.clone().abort_on_delete(async move {
+                while let Some(buf) = data.next().await {
+                    let buf = buf.context("failed to read object").map_err(|err| {
+                        events.publish_quota_exceeded(&component_id, &err);
+                        error_message(&err)
+                    })?;
+                    if let Some(limit) = &limit {
+                        limit.take(buf.len()).await;
+                    }
+                    client.throttle_download(buf.len()).await;
+                    accounting.record_download(&component_id, &bucket, buf.len() as u64);
+                    if tx.send(buf).await.is_err() {
+                        return Err("stream receiver closed".to_string());
+                    }
+                }
+                Ok(())
+            })) as Pin<Box<dyn Future<Output = _> + Send>>,
+        )
+    }
+
+    /// Write an object, streamed by a component
+    async fn container_write(
+        &self,
+        cx: Option<Context>,
+        id: ObjectId,
+        data: Pin<Box<dyn Stream<Item = Bytes> + Send>>,
+        mode: WriteMode,
+    ) -> Result<Pin<Box<dyn Future<Output = Result<(), String>> + Send>>> {
+        propagate_trace_for_ctx!(cx);
+        let component_id = component_id(cx.as_ref());
+        let link_name = cx
+            .as_ref()
+            .map(|cx| cx.link_name().to_string())
+            .unwrap_or_default();
+        self.with_client(cx, |client| async move {
+            let bucket = client.unalias(&id.container).to_string();
+            let key = client.object_key(&id.object)?;
+            let key = client.write_once_key(&bucket, &key).await?.into_owned();
+            client.ensure_bucket_not_public(&bucket).await?;
+            client.ensure_new_object(&bucket, &key).await?;
+            let accounting = self.accounting.clone();
+            let events = self.events;
+            let limit = self.upload_limit.clone();
+            let data = {
+                let client = client.clone();
+                data.then(move |chunk| {
+                    let client = client.clone();
+                    let limit = limit.clone();
+                    async move {
+                        if let Some(limit) = &limit {
+                            limit.take(chunk.len()).await;
+                        }
+                        client.throttle_upload(chunk.len()).await;
+                        chunk
+                    }
+                })
+            };
+            Ok(Box::pin(client.clone().abort_on_delete(async move {
+                let data = hooks::pre_write(&client, &component_id, &link_name, &id, data)
+                    .await
+                    .map_err(|err| error_message(&err))?;
+                let (data, content_type) = match client.content_sniffing() {
+                    Some(policy) => sniff::enforce(policy, &bucket, &key, data)
+                        .await
+                        .map_err(|err| error_message(&err))?,
+                    None => (data, None),
+                };
+                // scanned objects are written to quarantine and only moved to `key` once clean
+                let (dest, data, scan) = match client.scanner() {
+                    Some(scanner) => {
+                        let (data, scan) = scanner.tee(data);
+                        (scanner.quarantine_key(&key), data, Some(scan))
+                    }
+                    None => (key.clone(), data, None),
+                };
+                let res = match mode {
+                    WriteMode::Put { expires_after_days } => {
+                        // TODO: Stream data to S3
+                        let data: BytesMut = data.collect().await;
+                        let n = data.len() as u64;
+                        client
+                            .write_object_with_expiry(
+                                &bucket,
+                                &dest,
+                                data.freeze(),
+                                expires_after_days.or(client.expires_after_days()),
+                                content_type,
+                            )
+                            .await
+                            .map(|()| n)
+                    }
+                    WriteMode::Resumable { idempotency_key } => {
+                        client
+                            .write_object_resumable(
+                                &bucket,
+                                &dest,
+                                &idempotency_key,
+                                data,
+                                content_type,
+                            )
+                            .await
+                    }
+                };
+                let n = res.map_err(|err| {
+                    events.publish_quota_exceeded(&component_id, &err);
+                    error_message(&err)
+                })?;
+                if let Some(scan) = scan {
+                    scan_verdict(&client, scan, &bucket, &dest, &key)
+                        .await
+                        .map_err(|err| error_message(&err))?;
+                }
+                accounting.record_upload(&component_id, &bucket, n);
+                client
+                    .journal(
+                        &component_id,
+                        Mutation::Put {
+                            bucket,
+                            key,
+                            size: n,
+                        },
+                    )
+                    .await;
+                hooks::post_write(&client, &component_id, &link_name, &id, n);
+                Ok(())
+            })) as Pin<Box<dyn Future<Output = _> + Send>>)
+        })
+        .await
+    }
+}
+
+/// Move an object written to quarantine to `key` if it was scanned clean, leaving it in
+/// quarantine otherwise
+async fn scan_verdict(
+    client: &StorageClient,
+    scan: scan::Scan,
+    bucket: &str,
+    quarantine_key: &str,
+    key: &str,
+) -> anyhow::Result<()> {
+    let verdict = scan
+        .verdict()
+        .await
+        .map_err(|err| BackendError::new(ErrorKind::Unavailable, err))
+        .with_context(|| {
+            format!("failed to scan object [{bucket}/{key}], quarantined as `{quarantine_key}`")
+        })?;
+    match verdict {
+        scan::Verdict::Clean => client
+            .move_object(bucket, quarantine_key, bucket, key)
+            .await
+            .with_context(|| {
+                format!("failed to move scanned object [{bucket}/{key}] out of quarantine")
+            }),
+        scan::Verdict::Infected(signature) => bail!(BackendError::new(
+            ErrorKind::PermissionDenied,
+            format!(
+                "object [{bucket}/{key}] is infected with `{signature}`, \
+                 quarantined as `{quarantine_key}`"
+            )
+        )),
+    }
+}
+
+impl Handler<Option<Context>> for BlobstoreS3Provider {
+    #[instrument(level = "trace", skip(self))]
+    async fn clear_container(
+        &self,
+        cx: Option<Context>,
+        name: String,
+    ) -> anyhow::Result<Result<(), String>> {
+        Ok(async {
+            propagate_trace_for_ctx!(cx);
+            let component_id = component_id(cx.as_ref());
+            self.with_client(cx, |client| async move {
+                let bucket = client.unalias(&name);
+                client.ensure_mutable("clear containers")?;
+                let keys = client.clear_container(bucket, None).await?;
+                client
+                    .journal(
+                        &component_id,
+                        Mutation::Delete {
+                            bucket: bucket.into(),
+                            keys,
+                        },
+                    )
+                    .await;
+                Ok(())
+            })
+            .await
+        }
+        .await
+        .map_err(|err| error_message(&err)))
+    }
+
+    #[instrument(level = "trace", skip(self))]
+    async fn container_exists(
+        &self,
+        cx: Option<Context>,
+        name: String,
+    ) -> anyhow::Result<Result<bool, String>> {
+        Ok(async {
+            propagate_trace_for_ctx!(cx);
+            self.with_client(cx, |client| async move {
+                client.container_exists(client.unalias(&name)).await
+            })
+            .await
+        }
+        .await
+        .map_err(|err| error_message(&err)))
+    }
+
+    #[instrument(level = "trace", skip(self))]
+    async fn create_container(
+        &self,
+        cx: Option<Context>,
+        name: String,
+    ) -> anyhow::Result<Result<(), String>> {
+        Ok(async {
+            propagate_trace_for_ctx!(cx);
+            self.with_client(cx, |client| async move {
+                client.create_container(client.unalias(&name)).await
+            })
+            .await
+        }
+        .await
+        .map_err(|err| error_message(&err)))
+    }
+
+    #[instrument(level = "trace", skip(self))]
+    async fn delete_container(
+        &self,
+        cx: Option<Context>,
+        name: String,
+    ) -> anyhow::Result<Result<(), String>> {
+        Ok(async {
+            propagate_trace_for_ctx!(cx);
+            self.with_client(cx, |client| async move {
+                client.ensure_mutable("delete containers")?;
+                client.delete_container(client.unalias(&name)).await
+            })
+            .await
+        }
+        .await
+        .map_err(|err| error_message(&err)))
+    }
+
+    #[instrument(level = "trace", skip(self))]
+    async fn get_container_info(
+        &self,
+        cx: Option<Context>,
+        name: String,
+    ) -> anyhow::Result<Result<ContainerMetadata, String>> {
+        Ok(async {
+            propagate_trace_for_ctx!(cx);
+            self.with_client(cx, |client| async move {
+                client
+                    .get_container_info(client.unalias(&name))
+                    .await
+                    .map(Into::into)
+            })
+            .await
+        }
+        .await
+        .map_err(|err| error_message(&err)))
+    }
+
+    #[instrument(level = "trace", skip(self))]
+    async fn list_container_objects(
+        &self,
+        cx: Option<Context>,
+        name: String,
+        limit: Option<u64>,
+        offset: Option<u64>,
+    ) -> anyhow::Result<
+        Result<
+            (
+                Pin<Box<dyn Stream<Item = Vec<String>> + Send>>,
+                Pin<Box<dyn Future<Output = Result<(), String>> + Send>>,
+            ),
+            String,
+        >,
+    > {
+        Ok(async {
+            propagate_trace_for_ctx!(cx);
+            self.with_client(cx, |client| async move {
+                let names = client
+                    .list_container_objects(client.unalias(&name), limit, offset)
+                    .await
+                    .map(Vec::from_iter)?;
+                anyhow::Ok((
+                    Box::pin(stream::iter([names])) as Pin<Box<dyn Stream<Item = _> + Send>>,
+                    Box::pin(async move { Ok(()) }) as Pin<Box<dyn Future<Output = _> + Send>>,
+                ))
+            })
+            .await
+        }
+        .await
+        .map_err(|err| error_message(&err)))
+    }
+
+    #[instrument(level = "trace", skip(self))]
+    async fn copy_object(
+        &self,
+        cx: Option<Context>,
+        src: ObjectId,
+        dest: ObjectId,
+    ) -> anyhow::Result<Result<(), String>> {
+        Ok(async {
+            propagate_trace_for_ctx!(cx);
+            let component_id = component_id(cx.as_ref());
+            self.with_client(cx, |client| async move {
+                let src_bucket = client.unalias(&src.container);
+                let dest_bucket = client.unalias(&dest.container);
+                client.ensure_bucket_not_public(dest_bucket).await?;
+                let src_key = client.object_key(&src.object)?;
+                let src_key = client.latest_key(src_bucket, &src_key).await?.into_owned();
+                let dest_key = client.object_key(&dest.object)?;
+                let dest_key = client.write_once_key(dest_bucket, &dest_key).await?;
+                client.ensure_new_object(dest_bucket, &dest_key).await?;
+                client
+                    .copy_object(src_bucket, &src_key, dest_bucket, &dest_key)
+                    .await?;
+                client
+                    .journal(
+                        &component_id,
+                        Mutation::Copy {
+                            src_bucket: src_bucket.into(),
+                            src_key,
+                            dest_bucket: dest_bucket.into(),
+                            dest_key: dest_key.into_owned(),
+                        },
+                    )
+                    .await;
+                Ok(())
+            })
+            .await
+        }
+        .await
+        .map_err(|err| error_message(&err)))
+    }
+
+    #[instrument(level = "trace", skip(self))]
+    async fn delete_object(
+        &self,
+        cx: Option<Context>,
+        id: ObjectId,
+    ) -> anyhow::Result<Result<(), String>> {
+        Ok(async {
+            propagate_trace_for_ctx!(cx);
+            let component_id = component_id(cx.as_ref());
+            self.with_client(cx, |client| async move {
+                client.ensure_mutable("delete objects")?;
+                let bucket = client.unalias(&id.container);
+                let key = client.object_key(&id.object)?.into_owned();
+                let keys = client.with_key_versions(bucket, vec![key]).await?;
+                if client.soft_deletes() {
+                    trashed(client.trash_objects(bucket, keys.clone()).await)?;
+                } else {
+                    for key in &keys {
+                        client.delete_object(bucket, key.clone()).await?;
+                    }
+                }
+                client
+                    .journal(
+                        &component_id,
+                        Mutation::Delete {
+                            bucket: bucket.into(),
+                            keys,
+                        },
+                    )
+                    .await;
+                Ok(())
+            })
+            .await
+        }
+        .await
+        .map_err(|err| error_message(&err)))
+    }
+
+    #[instrument(level = "trace", skip(self))]
+    async fn delete_objects(
+        &self,
+        cx: Option<Context>,
+        container: String,
+        objects: Vec<String>,
+    ) -> anyhow::Result<Result<(), String>> {
+        Ok(async {
+            propagate_trace_for_ctx!(cx);
+            let component_id = component_id(cx.as_ref());
+            self.with_client(cx, |client| async move {
+                client.ensure_mutable("delete objects")?;
+                let bucket = client.unalias(&container);
+                let keys = client
+                    .with_key_versions(bucket, client.object_keys(objects)?)
+                    .await?;
+                if client.soft_deletes() {
+                    trashed(client.trash_objects(bucket, keys.clone()).await)?;
+                } else {
+                    client.delete_objects(bucket, keys.clone()).await?;
+                }
+                client
+                    .journal(
+                        &component_id,
+                        Mutation::Delete {
+                            bucket: bucket.into(),
+                            keys,
+                        },
+                    )
+                    .await;
+                Ok(())
+            })
+            .await
+        }
+        .await
+        .map_err(|err| error_message(&err)))
+    }
+
+    #[instrument(level = "trace", skip(self))]
+    async fn get_container_data(
+        &self,
+        cx: Option<Context>,
+        id: ObjectId,
+        start: u64,
+        end: u64,
+    ) -> anyhow::Result<
+        Result<
+            (
+                Pin<Box<dyn Stream<Item = Bytes> + Send>>,
+                Pin<Box<dyn Future<Output = Result<(), String>> + Send>>,
+            ),
+            String,
+        >,
+    > {
+        Ok(self
+            .container_data(cx, id, None, start, end)
+            .await
+            .map_err(|err| error_message(&err)))
+    }
+
+    #[instrument(level = "trace", skip(self))]
+    async fn get_object_info(
+        &self,
+        cx: Option<Context>,
+        id: ObjectId,
+    ) -> anyhow::Result<Result<ObjectMetadata, String>> {
+        Ok(async {
+            propagate_trace_for_ctx!(cx);
+            self.with_client(cx, |client| async move {
+                let bucket = client.unalias(&id.container);
+                let key = client.object_key(&id.object)?;
+                client
+                    .get_object_info(bucket, &client.latest_key(bucket, &key).await?)
+                    .await
+                    .map(Into::into)
+            })
+            .await
+        }
+        .await
+        .map_err(|err| error_message(&err)))
+    }
+
+    #[instrument(level = "trace", skip(self))]
+    async fn has_object(
+        &self,
+        cx: Option<Context>,
+        id: ObjectId,
+    ) -> anyhow::Result<Result<bool, String>> {
+        Ok(async {
+            propagate_trace_for_ctx!(cx);
+            self.with_client(cx, |client| async move {
+                client
+                    .has_object(
+                        client.unalias(&id.container),
+                        &client.object_key(&id.object)?,
+                    )
+                    .await
+            })
+            .await
+        }
+        .await
+        .map_err(|err| error_message(&err)))
+    }
+
+    #[instrument(level = "trace", skip(self))]
+    async fn move_object(
+        &self,
+        cx: Option<Context>,
+        src: ObjectId,
+        dest: ObjectId,
+    ) -> anyhow::Result<Result<(), String>> {
+        Ok(async {
+            propagate_trace_for_ctx!(cx);
+            let component_id = component_id(cx.as_ref());
+            self.with_client(cx, |client| async move {
+                client.ensure_mutable("move objects")?;
+                let src_bucket = client.unalias(&src.container);
+                let dest_bucket = client.unalias(&dest.container);
+                client.ensure_bucket_not_public(dest_bucket).await?;
+                let src_key = client.object_key(&src.object)?;
+                let src_key = client.latest_key(src_bucket, &src_key).await?.into_owned();
+                let dest_key = client.object_key(&dest.object)?;
+                let dest_key = client.write_once_key(dest_bucket, &dest_key).await?;
+                client
+                    .move_object(src_bucket, &src_key, dest_bucket, &dest_key)
+                    .await?;
+                client
+                    .journal(
+                        &component_id,
+                        Mutation::Move {
+                            src_bucket: src_bucket.into(),
+                            src_key,
+                            dest_bucket: dest_bucket.into(),
+                            dest_key: dest_key.into_owned(),
+                        },
+                    )
+                    .await;
+                Ok(())
+            })
+            .await
+        }
+        .await
+        .map_err(|err| error_message(&err)))
+    }
+
+    #[instrument(level = "trace", skip(self, data))]
+    async fn write_container_data(
+        &self,
+        cx: Option<Context>,
+        id: ObjectId,
+        data: Pin<Box<dyn Stream<Item = Bytes> + Send>>,
+    ) -> anyhow::Result<Result<Pin<Box<dyn Future<Output = Result<(), String>> + Send>>, String>>
+    {
+        Ok(self
+            .container_write(
+                cx,
+                id,
+                data,
+                WriteMode::Put {
+                    expires_after_days: None,
+                },
+            )
+            .await
+            .map_err(|err| error_message(&err)))
+    }
+}
+
+/// `wrpc:blobstore@0.1.0`, which hosts fall back to if `wrpc:blobstore@0.2.0` is not exported,
+/// implemented by the handlers of `wrpc:blobstore@0.2.0`
+impl blobstore0_1_0::Handler<Option<Context>> for BlobstoreS3Provider {
+    #[instrument(level = "trace", skip(self))]
+    async fn clear_container(
+        &self,
+        cx: Option<Context>,
+        name: String,
+    ) -> anyhow::Result<Result<(), String>> {
+        Handler::clear_container(self, cx, name).await
+    }
+
+    #[instrument(level = "trace", skip(self))]
+    async fn container_exists(
+        &self,
+        cx: Option<Context>,
+        name: String,
+    ) -> anyhow::Result<Result<bool, String>> {
+        Handler::container_exists(self, cx, name).await
+    }
+
+    #[instrument(level = "trace", skip(self))]
+    async fn create_container(
+        &self,
+        cx: Option<Context>,
+        name: String,
+    ) -> anyhow::Result<Result<(), String>> {
+        Handler::create_container(self, cx, name).await
+    }
+
+    #[instrument(level = "trace", skip(self))]
+    async fn delete_container(
+        &self,
+        cx: Option<Context>,
+        name: String,
+    ) -> anyhow::Result<Result<(), String>> {
+        Handler::delete_container(self, cx, name).await
+    }
+
+    #[instrument(level = "trace", skip(self))]
+    async fn get_container_info(
+        &self,
+        cx: Option<Context>,
+        name: String,
+    ) -> anyhow::Result<Result<types0_1_0::ContainerMetadata, String>> {
+        let res = Handler::get_container_info(self, cx, name).await?;
+        Ok(
+            res.map(
+                |ContainerMetadata { created_at }| types0_1_0::ContainerMetadata { created_at },
+            ),
+        )
+    }
+
+    #[instrument(level = "trace", skip(self))]
+    async fn list_container_objects(
+        &self,
+        cx: Option<Context>,
+        name: String,
+        limit: Option<u64>,
+        offset: Option<u64>,
+    ) -> anyhow::Result<Result<Pin<Box<dyn Stream<Item = Vec<String>> + Send>>, String>> {
+        let res = Handler::list_container_objects(self, cx, name, limit, offset).await?;
+        Ok(res.map(|(names, done)| {
+            spawn_completion("list container objects", done);
+            names
+        }))
+    }
+
+    #[instrument(level = "trace", skip(self))]
+    async fn copy_object(
+        &self,
+        cx: Option<Context>,
+        src: types0_1_0::ObjectId,
+        dest: types0_1_0::ObjectId,
+    ) -> anyhow::Result<Result<(), String>> {
+        Handler::copy_object(self, cx, object_id(src), object_id(dest)).await
+    }
+
+    #[instrument(level = "trace", skip(self))]
+    async fn delete_object(
+        &self,
+        cx: Option<Context>,
+        id: types0_1_0::ObjectId,
+    ) -> anyhow::Result<Result<(), String>> {
+        Handler::delete_object(self, cx, object_id(id)).await
+    }
+
+    #[instrument(level = "trace", skip(self))]
+    async fn delete_objects(
+        &self,
+        cx: Option<Context>,
+        container: String,
+        objects: Vec<String>,
+    ) -> anyhow::Result<Result<(), String>> {
+        Handler::delete_objects(self, cx, container, objects).await
+    }
+
+    #[instrument(level = "trace", skip(self))]
+    async fn get_container_data(
+        &self,
+        cx: Option<Context>,
+        id: types0_1_0::ObjectId,
+        start: u64,
+        end: u64,
+    ) -> anyhow::Result<Result<Pin<Box<dyn Stream<Item = Bytes> + Send>>, String>> {
+        let res = Handler::get_container_data(self, cx, object_id(id), start, end).await?;
+        Ok(res.map(|(data, done)| {
+            spawn_completion("read object", done);
+            data
+        }))
+    }
+
+    #[instrument(level = "trace", skip(self))]
+    async fn get_object_info(
+        &self,
+        cx: Option<Context>,
+        id: types0_1_0::ObjectId,
+    ) -> anyhow::Result<Result<types0_1_0::ObjectMetadata, String>> {
+        let res = Handler::get_object_info(self, cx, object_id(id)).await?;
+        Ok(res.map(
+            |ObjectMetadata { created_at, size }| types0_1_0::ObjectMetadata { created_at, size },
+        ))
+    }
+
+    #[instrument(level = "trace", skip(self))]
+    async fn has_object(
+        &self,
+        cx: Option<Context>,
+        id: types0_1_0::ObjectId,
+    ) -> anyhow::Result<Result<bool, String>> {
+        Handler::has_object(self, cx, object_id(id)).await
+    }
+
+    #[instrument(level = "trace", skip(self))]
+    async fn move_object(
+        &self,
+        cx: Option<Context>,
+        src: types0_1_0::ObjectId,
+        dest: types0_1_0::ObjectId,
+    ) -> anyhow::Result<Result<(), String>> {
+        Handler::move_object(self, cx, object_id(src), object_id(dest)).await
+    }
+
+    #[instrument(level = "trace", skip(self, data))]
+    async fn write_container_data(
+        &self,
+        cx: Option<Context>,
+        id: types0_1_0::ObjectId,
+        data: Pin<Box<dyn Stream<Item = Bytes> + Send>>,
+    ) -> anyhow::Result<Result<(), String>> {
+        match Handler::write_container_data(self, cx, object_id(id), data).await? {
+            Ok(done) => Ok(done.await),
+            Err(err) => Ok(Err(err)),
+        }
+    }
+}
+
+impl versions::Handler<Option<Context>> for BlobstoreS3Provider {
+    #[instrument(level = "trace", skip(self))]
+    async fn get_container_data(
+        &self,
+        cx: Option<Context>,
+        id: ObjectId,
+        version_id: String,
+        start: u64,
+        end: u64,
+    ) -> anyhow::Result<
+        Result<
+            (
+                Pin<Box<dyn Stream<Item = Bytes> + Send>>,
+                Pin<Box<dyn Future<Output = Result<(), String>> + Send>>,
+            ),
+            String,
+        >,
+    > {
+        Ok(self
+            .container_data(cx, id, Some(version_id), start, end)
+            .await
+            .map_err(|err| error_message(&err)))
+    }
+
+    #[instrument(level = "trace", skip(self))]
+    async fn get_object_info(
+        &self,
+        cx: Option<Context>,
+        id: ObjectId,
+        version_id: String,
+    ) -> anyhow::Result<Result<ObjectMetadata, String>> {
+        Ok(async {
+            propagate_trace_for_ctx!(cx);
+            self.with_client(cx, |client| async move {
+                client
+                    .get_object_version_info(
+                        client.unalias(&id.container),
+                        &client.object_key(&id.object)?,
+                        Some(&version_id),
+                    )
+                    .await
+                    .map(Into::into)
+            })
+            .await
+        }
+        .await
+        .map_err(|err| error_message(&err)))
+    }
+
+    #[instrument(level = "trace", skip(self))]
+    async fn has_object(
+        &self,
+        cx: Option<Context>,
+        id: ObjectId,
+    ) -> anyhow::Result<Result<versions::ObjectPresence, String>> {
+        Ok(async {
+            propagate_trace_for_ctx!(cx);
+            self.with_client(cx, |client| async move {
+                client
+                    .object_presence(
+                        client.unalias(&id.container),
+                        &client.object_key(&id.object)?,
+                    )
+                    .await
+                    .map(Into::into)
+            })
+            .await
+        }
+        .await
+        .map_err(|err| error_message(&err)))
+    }
+
+    #[instrument(level = "trace", skip(self, options))]
+    async fn delete_object_version(
+        &self,
+        cx: Option<Context>,
+        id: ObjectId,
+        version_id: String,
+        options: versions::DeleteOptions,
+    ) -> anyhow::Result<Result<(), String>> {
+        Ok(async {
+            propagate_trace_for_ctx!(cx);
+            let component_id = component_id(cx.as_ref());
+            self.with_client(cx, |client| async move {
+                client.ensure_mutable("delete object versions")?;
+                let bucket = client.unalias(&id.container);
+                let key = client.object_key(&id.object)?.into_owned();
+                client
+                    .delete_object_version(bucket, &key, &version_id, options.into())
+                    .await?;
+                client
+                    .journal(
+                        &component_id,
+                        Mutation::DeleteVersion {
+                            bucket: bucket.into(),
+                            key,
+                            version_id,
+                        },
+                    )
+                    .await;
+                Ok(())
+            })
+            .await
+        }
+        .await
+        .map_err(|err| error_message(&err)))
+    }
+
+    #[instrument(level = "trace", skip(self))]
+    async fn undelete_object(
+        &self,
+        cx: Option<Context>,
+        id: ObjectId,
+    ) -> anyhow::Result<Result<bool, String>> {
+        Ok(async {
+            propagate_trace_for_ctx!(cx);
+            let component_id = component_id(cx.as_ref());
+            self.with_client(cx, |client| async move {
+                let bucket = client.unalias(&id.container);
+                let key = client.object_key(&id.object)?.into_owned();
+                let undeleted = client.undelete_object(bucket, &key).await?;
+                if undeleted {
+                    client
+                        .journal(
+                            &component_id,
+                            Mutation::Undelete {
+                                bucket: bucket.into(),
+                                key,
+                            },
+                        )
+                        .await;
+                }
+                Ok(undeleted)
+            })
+            .await
+        }
+        .await
+        .map_err(|err| error_message(&err)))
+    }
+}
+
+impl expiration::Handler<Option<Context>> for BlobstoreS3Provider {
+    #[instrument(level = "trace", skip(self, data))]
+    async fn write_container_data(
+        &self,
+        cx: Option<Context>,
+        id: ObjectId,
+        data: Pin<Box<dyn Stream<Item = Bytes> + Send>>,
+        expires_after_days: u32,
+    ) -> anyhow::Result<Result<Pin<Box<dyn Future<Output = Result<(), String>> + Send>>, String>>
+    {
+        Ok(self
+            .container_write(
+                cx,
+                id,
+                data,
+                WriteMode::Put {
+                    expires_after_days: Some(expires_after_days),
+                },
+            )
+            .await
+            .map_err(|err| error_message(&err)))
+    }
+}
+
+impl admin::Handler<Option<Context>> for BlobstoreS3Provider {
+    #[instrument(level = "trace", skip(self))]
+    async fn get_transfer_stats(
+        &self,
+        cx: Option<Context>,
+    ) -> anyhow::Result<Result<Vec<admin::TransferStats>, String>> {
+        propagate_trace_for_ctx!(cx);
+        if let Err(err) = self.ensure_admin(cx.as_ref()) {
+            return Ok(Err(error_message(&err)));
+        }
+        Ok(Ok(self
+            .accounting
+            .snapshot()
+            .into_iter()
+            .map(
+                |(
+                    component_id,
+                    bucket,
+                    TransferStats {
+                        bytes_uploaded,
+                        bytes_downloaded,
+                    },
+                )| admin::TransferStats {
+                    component_id,
+                    bucket,
+                    bytes_uploaded,
+                    bytes_downloaded,
+                },
+            )
+            .collect()))
+    }
+
+    #[instrument(level = "trace", skip(self))]
+    async fn get_connection_stats(
+        &self,
+        cx: Option<Context>,
+    ) -> anyhow::Result<Result<Vec<admin::ConnectionStats>, String>> {
+        propagate_trace_for_ctx!(cx);
+        if let Err(err) = self.ensure_admin(cx.as_ref()) {
+            return Ok(Err(error_message(&err)));
+        }
+        let mut stats: Vec<_> = self
+            .actors
+            .read()
+            .await
+            .iter()
+            .filter_map(|((component_id, link_name), link)| {
+                let ConnectionStats {
+                    open_connections,
+                    connections_opened,
+                    in_flight_requests,
+                    requests,
+                    retries,
+                    throttle_events,
+                } = link.client.get()?.connection_stats();
+                Some(admin::ConnectionStats {
+                    component_id: component_id.clone(),
+                    link_name: link_name.clone(),
+                    open_connections,
+                    connections_opened,
+                    in_flight_requests,
+                    requests,
+                    retries,
+                    throttle_events,
+                })
+            })
+            .collect();
+        stats.sort_unstable_by(|a, b| {
+            (&a.component_id, &a.link_name).cmp(&(&b.component_id, &b.link_name))
+        });
+        Ok(Ok(stats))
+    }
+
+    #[instrument(level = "trace", skip(self))]
+    async fn get_build_info(
+        &self,
+        cx: Option<Context>,
+    ) -> anyhow::Result<Result<admin::BuildInfo, String>> {
+        propagate_trace_for_ctx!(cx);
+        if let Err(err) = self.ensure_admin(cx.as_ref()) {
+            return Ok(Err(error_message(&err)));
+        }
+        Ok(Ok(admin::BuildInfo {
+            version: VERSION.into(),
+            commit: COMMIT.into(),
+            target: TARGET.into(),
+        }))
+    }
+
+    #[instrument(level = "trace", skip(self))]
+    async fn self_test_link(
+        &self,
+        cx: Option<Context>,
+        component_id: String,
+        link_name: String,
+        container: String,
+    ) -> anyhow::Result<Result<admin::SelfTestReport, String>> {
+        Ok(async {
+            propagate_trace_for_ctx!(cx);
+            self.ensure_admin(cx.as_ref())?;
+            let link = self
+                .actors
+                .read()
+                .await
+                .get(&(component_id.clone(), link_name.clone()))
+                .with_context(|| {
+                    format!("failed to lookup {component_id} configuration for link `{link_name}`")
+                })?
+                .clone();
+            let client = self.link_client(&link).await?;
+            let report = client.self_test(client.unalias(&container)).await;
+            let passed = report.passed();
+            let SelfTestReport { bucket, key, steps } = report;
+            anyhow::Ok(admin::SelfTestReport {
+                bucket,
+                key,
+                steps: steps
+                    .into_iter()
+                    .map(
+                        |SelfTestStep {
+                             name,
+                             duration,
+                             error,
+                         }| admin::SelfTestStep {
+                            name,
+                            duration_ms: duration.as_millis().try_into().unwrap_or(u64::MAX),
+                            error,
+                        },
+                    )
+                    .collect(),
+                passed,
+            })
+        }
+        .await
+        .map_err(|err| error_message(&err)))
+    }
+
+    #[instrument(level = "trace", skip(self))]
+    async fn self_test(&self, cx: Option<Context>) -> anyhow::Result<String> {
+        propagate_trace_for_ctx!(cx);
+        if let Err(err) = self.ensure_admin(cx.as_ref()) {
+            return Ok(serde_json::json!({ "error": format!("{err:#}") }).to_string());
+        }
+        let links: Vec<_> = self
+            .actors
+            .read()
+            .await
+            .iter()
+            .map(|(id, link)| (id.clone(), link.clone()))
+            .collect();
+        let reports = futures::future::join_all(links.into_iter().filter_map(
+            |((component_id, link_name), link)| {
+                let container = link.config.self_test_container.clone()?;
+                Some(async move {
+                    let report = match self.link_client(&link).await {
+                        Ok(client) => {
+                            self_test_json(client.self_test(client.unalias(&container)).await)
+                        }
+                        Err(err) => serde_json::json!({
+                            "passed": false,
+                            "error": format!("{err:#}"),
+                        }),
+                    };
+                    serde_json::json!({
+                        "component_id": component_id,
+                        "link_name": link_name,
+                        "report": report,
+                    })
+                })
+            },
+        ))
+        .await;
+        let passed = reports
+            .iter()
+            .all(|link| link["report"]["passed"] == serde_json::Value::Bool(true));
+        Ok(serde_json::json!({ "passed": passed, "links": reports }).to_string())
+    }
+}
+
+impl uploads::Handler<Option<Context>> for BlobstoreS3Provider {
+    #[instrument(level = "trace", skip(self, data))]
+    async fn write_container_data(
+        &self,
+        cx: Option<Context>,
+        id: ObjectId,
+        data: Pin<Box<dyn Stream<Item = Bytes> + Send>>,
+        idempotency_key: String,
+    ) -> anyhow::Result<Result<Pin<Box<dyn Future<Output = Result<(), String>> + Send>>, String>>
+    {
+        Ok(self
+            .container_write(cx, id, data, WriteMode::Resumable { idempotency_key })
+            .await
+            .map_err(|err| error_message(&err)))
+    }
+}
+
+impl conditional::Handler<Option<Context>> for BlobstoreS3Provider {
+    #[instrument(level = "trace", skip(self))]
+    async fn get_container_data(
+        &self,
+        cx: Option<Context>,
+        id: ObjectId,
+        start: u64,
+        end: u64,
+        conditions: conditional::ReadConditions,
+    ) -> anyhow::Result<
+        Result<
+            Option<(
+                Pin<Box<dyn Stream<Item = Bytes> + Send>>,
+                Pin<Box<dyn Future<Output = Result<(), String>> + Send>>,
+            )>,
+            String,
+        >,
+    > {
+        Ok(async {
+            propagate_trace_for_ctx!(cx);
+            let component_id = component_id(cx.as_ref());
+            self.with_client(cx, |client| async move {
+                let bucket = client.unalias(&id.container).to_string();
+                let data = client
+                    .read_object_if(
+                        &bucket,
+                        &client.object_key(&id.object)?,
+                        start,
+                        end,
+                        &conditions.into(),
+                    )
+                    .await?;
+                anyhow::Ok(data.map(|data| self.forward_data(client, component_id, bucket, data)))
+            })
+            .await
+        }
+        .await
+        .map_err(|err| error_message(&err)))
+    }
+
+    #[instrument(level = "trace", skip(self))]
+    async fn get_object_validators(
+        &self,
+        cx: Option<Context>,
+        id: ObjectId,
+    ) -> anyhow::Result<Result<conditional::ObjectValidators, String>> {
+        Ok(async {
+            propagate_trace_for_ctx!(cx);
+            self.with_client(cx, |client| async move {
+                client
+                    .get_object_validators(
+                        client.unalias(&id.container),
+                        &client.object_key(&id.object)?,
+                    )
+                    .await
+                    .map(Into::into)
+            })
+            .await
+        }
+        .await
+        .map_err(|err| error_message(&err)))
+    }
+}
+
+impl hashes::Handler<Option<Context>> for BlobstoreS3Provider {
+    #[instrument(level = "trace", skip(self))]
+    async fn get_stored_hashes(
+        &self,
+        cx: Option<Context>,
+        id: ObjectId,
+    ) -> anyhow::Result<Result<hashes::StoredHashes, String>> {
+        Ok(async {
+            propagate_trace_for_ctx!(cx);
+            self.with_client(cx, |client| async move {
+                let bucket = client.unalias(&id.container);
+                let key = client.object_key(&id.object)?;
+                let key = client.latest_key(bucket, &key).await?;
+                client.get_stored_hashes(bucket, &key).await.map(Into::into)
+            })
+            .await
+        }
+        .await
+        .map_err(|err| error_message(&err)))
+    }
+
+    #[instrument(level = "trace", skip(self))]
+    async fn compute_hash(
+        &self,
+        cx: Option<Context>,
+        id: ObjectId,
+        algorithm: hashes::HashAlgorithm,
+    ) -> anyhow::Result<Result<String, String>> {
+        Ok(async {
+            propagate_trace_for_ctx!(cx);
+            self.with_client(cx, |client| async move {
+                let bucket = client.unalias(&id.container);
+                let key = client.object_key(&id.object)?;
+                let key = client.latest_key(bucket, &key).await?;
+                client.compute_hash(bucket, &key, algorithm.into()).await
+            })
+            .await
+        }
+        .await
+        .map_err(|err| error_message(&err)))
+    }
+}
+
+impl ranges::Handler<Option<Context>> for BlobstoreS3Provider {
+    #[instrument(level = "trace", skip(self))]
+    async fn get_ranges(
+        &self,
+        cx: Option<Context>,
+        id: ObjectId,
+        ranges: Vec<ranges::ByteRange>,
+    ) -> anyhow::Result<
+        Result<
+            (
+                Pin<Box<dyn Stream<Item = Vec<ranges::RangeData>> + Send>>,
+                Pin<Box<dyn Future<Output = Result<(), String>> + Send>>,
+            ),
+            String,
+        >,
+    > {
+        Ok(async {
+            propagate_trace_for_ctx!(cx);
+            let component_id = component_id(cx.as_ref());
+            self.with_client(cx, |client| async move {
+                let bucket = client.unalias(&id.container).to_string();
+                let key = client.object_key(&id.object)?.into_owned();
+                let ranges: Vec<_> = ranges
+                    .into_iter()
+                    .map(|ranges::ByteRange { start, end }| (start, end))
+                    .collect();
+                // fail before streaming if the ranges are invalid
+                client::validate_ranges(&ranges)?;
+                let accounting = self.accounting.clone();
+                let events = self.events;
+                let limit = self.download_limit.clone();
+                let (tx, rx) = mpsc::channel(16);
+                let done = client.clone().abort_on_delete(async move {
+                    let data = client
+                        .read_ranges(&bucket, &key, ranges)
+                        .map_err(|err| error_message(&err))?;
+                    let mut data = pin!(data);
+                    while let Some(res) = data.next().await {
+                        let (offset, data) = res.map_err(|err| {
+                            events.publish_quota_exceeded(&component_id, &err);
+                            error_message(&err)
+                        })?;
+                        if let Some(limit) = &limit {
+                            limit.take(data.len()).await;
+                        }
+                        client.throttle_download(data.len()).await;
+                        accounting.record_download(&component_id, &bucket, data.len() as u64);
+                        if tx
+                            .send(vec![ranges::RangeData { offset, data }])
+                            .await
+                            .is_err()
+                        {
+                            return Err("stream receiver closed".to_string());
+                        }
+                    }
+                    Ok(())
+                });
+                anyhow::Ok((
+                    Box::pin(ReceiverStream::new(rx)) as Pin<Box<dyn Stream<Item = _> + Send>>,
+                    Box::pin(done) as Pin<Box<dyn Future<Output = _> + Send>>,
+                ))
+            })
+            .await
+        }
+        .await
+        .map_err(|err| error_message(&err)))
+    }
+}
+
+impl downloads::Handler<Option<Context>> for BlobstoreS3Provider {
+    #[instrument(level = "trace", skip(self))]
+    async fn get_download_url(
+        &self,
+        cx: Option<Context>,
+        id: ObjectId,
+        expires_in: Option<u32>,
+    ) -> anyhow::Result<Result<String, String>> {
+        Ok(async {
+            propagate_trace_for_ctx!(cx);
+            self.with_client(cx, |client| async move {
+                client
+                    .download_url(
+                        client.unalias(&id.container),
+                        &client.object_key(&id.object)?,
+                        expires_in.map(|secs| Duration::from_secs(secs.into())),
+                    )
+                    .await
+            })
+            .await
+        }
+        .await
+        .map_err(|err| error_message(&err)))
+    }
+}
+
+impl cdn_signing::Handler<Option<Context>> for BlobstoreS3Provider {
+    #[instrument(level = "trace", skip(self))]
+    async fn sign_url(
+        &self,
+        cx: Option<Context>,
+        url: String,
+        expires_in: Option<u32>,
+    ) -> anyhow::Result<Result<String, String>> {
+        Ok(async {
+            propagate_trace_for_ctx!(cx);
+            self.with_client(cx, |client| async move {
+                client.sign_cdn_url(
+                    &url,
+                    expires_in.map(|secs| Duration::from_secs(secs.into())),
+                )
+            })
+            .await
+        }
+        .await
+        .map_err(|err| error_message(&err)))
+    }
+
+    #[instrument(level = "trace", skip(self))]
+    async fn sign_cookies(
+        &self,
+        cx: Option<Context>,
+        url_pattern: String,
+        expires_in: Option<u32>,
+    ) -> anyhow::Result<Result<Vec<cdn_signing::Cookie>, String>> {
+        Ok(async {
+            propagate_trace_for_ctx!(cx);
+            self.with_client(cx, |client| async move {
+                let cookies = client.sign_cdn_cookies(
+                    &url_pattern,
+                    expires_in.map(|secs| Duration::from_secs(secs.into())),
+                )?;
+                Ok(cookies
+                    .into_iter()
+                    .map(|(name, value)| cdn_signing::Cookie { name, value })
+                    .collect())
+            })
+            .await
+        }
+        .await
+        .map_err(|err| error_message(&err)))
+    }
+}
+
+impl locks::Handler<Option<Context>> for BlobstoreS3Provider {
+    #[instrument(level = "trace", skip(self))]
+    async fn acquire(
+        &self,
+        cx: Option<Context>,
+        container: String,
+        name: String,
+        ttl_secs: u32,
+    ) -> anyhow::Result<Result<Option<locks::Lease>, String>> {
+        Ok(async {
+            propagate_trace_for_ctx!(cx);
+            self.with_client(cx, |client| async move {
+                let lease = client
+                    .acquire_lock(
+                        client.unalias(&container),
+                        &name,
+                        Duration::from_secs(ttl_secs.into()),
+                    )
+                    .await?;
+                anyhow::Ok(lease.map(Into::into))
+            })
+            .await
+        }
+        .await
+        .map_err(|err| error_message(&err)))
+    }
+
+    #[instrument(level = "trace", skip(self))]
+    async fn renew(
+        &self,
+        cx: Option<Context>,
+        container: String,
+        name: String,
+        etag: String,
+        ttl_secs: u32,
+    ) -> anyhow::Result<Result<locks::Lease, String>> {
+        Ok(async {
+            propagate_trace_for_ctx!(cx);
+            self.with_client(cx, |client| async move {
+                client
+                    .renew_lock(
+                        client.unalias(&container),
+                        &name,
+                        &etag,
+                        Duration::from_secs(ttl_secs.into()),
+                    )
+                    .await
+                    .map(Into::into)
+            })
+            .await
+        }
+        .await
+        .map_err(|err| error_message(&err)))
+    }
+
+    #[instrument(level = "trace", skip(self))]
+    async fn release(
+        &self,
+        cx: Option<Context>,
+        container: String,
+        name: String,
+        etag: String,
+    ) -> anyhow::Result<Result<(), String>> {
+        Ok(async {
+            propagate_trace_for_ctx!(cx);
+            self.with_client(cx, |client| async move {
+                client
+                    .release_lock(client.unalias(&container), &name, &etag)
+                    .await
+            })
+            .await
+        }
+        .await
+        .map_err(|err| error_message(&err)))
+    }
+}
+
+impl manifests::Handler<Option<Context>> for BlobstoreS3Provider {
+    #[instrument(level = "trace", skip(self, members))]
+    async fn create_manifest(
+        &self,
+        cx: Option<Context>,
+        container: String,
+        name: String,
+        members: Vec<String>,
+    ) -> anyhow::Result<Result<String, String>> {
+        Ok(async {
+            propagate_trace_for_ctx!(cx);
+            self.with_client(cx, |client| async move {
+                client
+                    .create_manifest(client.unalias(&container), &name, members)
+                    .await
+            })
+            .await
+        }
+        .await
+        .map_err(|err| error_message(&err)))
+    }
+
+    #[instrument(level = "trace", skip(self))]
+    async fn get_manifest(
+        &self,
+        cx: Option<Context>,
+        container: String,
+        name: String,
+        id: Option<String>,
+    ) -> anyhow::Result<Result<Option<manifests::Manifest>, String>> {
+        Ok(async {
+            propagate_trace_for_ctx!(cx);
+            self.with_client(cx, |client| async move {
+                let manifest = client
+                    .get_manifest(client.unalias(&container), &name, id.as_deref())
+                    .await?;
+                anyhow::Ok(manifest.map(Into::into))
+            })
+            .await
+        }
+        .await
+        .map_err(|err| error_message(&err)))
+    }
+
+    #[instrument(level = "trace", skip(self))]
+    async fn swap_manifest(
+        &self,
+        cx: Option<Context>,
+        container: String,
+        name: String,
+        id: String,
+        expected: Option<String>,
+    ) -> anyhow::Result<Result<(), String>> {
+        Ok(async {
+            propagate_trace_for_ctx!(cx);
+            self.with_client(cx, |client| async move {
+                client.ensure_mutable("swap manifests")?;
+                client
+                    .swap_manifest(client.unalias(&container), &name, &id, expected.as_deref())
+                    .await
+            })
+            .await
+        }
+        .await
+        .map_err(|err| error_message(&err)))
+    }
+
+    #[instrument(level = "trace", skip(self, live))]
+    async fn collect_garbage(
+        &self,
+        cx: Option<Context>,
+        container: String,
+        prefix: String,
+        live: Vec<manifests::LiveManifest>,
+        grace_period_secs: u32,
+        dry_run: bool,
+    ) -> anyhow::Result<Result<manifests::Garbage, String>> {
+        Ok(async {
+            propagate_trace_for_ctx!(cx);
+            let component_id = component_id(cx.as_ref());
+            self.with_client(cx, |client| async move {
+                if !dry_run {
+                    client.ensure_mutable("collect garbage")?;
+                }
+                let bucket = client.unalias(&container);
+                let live: Vec<_> = live
+                    .into_iter()
+                    .map(|manifests::LiveManifest { name, id }| (name, id))
+                    .collect();
+                let garbage = client
+                    .find_garbage(
+                        bucket,
+                        &prefix,
+                        &live,
+                        Duration::from_secs(grace_period_secs.into()),
+                    )
+                    .await?;
+                let bytes = garbage.iter().map(|object| object.size).sum();
+                let keys: Vec<_> = garbage.into_iter().map(|object| object.key).collect();
+                if !dry_run && !keys.is_empty() {
+                    if client.soft_deletes() {
+                        trashed(client.trash_objects(bucket, keys.clone()).await)?;
+                    } else {
+                        client.delete_objects(bucket, keys.clone()).await?;
+                    }
+                    client
+                        .journal(
+                            &component_id,
+                            Mutation::Delete {
+                                bucket: bucket.into(),
+                                keys: keys.clone(),
+                            },
+                        )
+                        .await;
+                }
+                anyhow::Ok(manifests::Garbage { keys, bytes })
+            })
+            .await
+        }
+        .await
+        .map_err(|err| error_message(&err)))
+    }
+}
+
+impl batch::Handler<Option<Context>> for BlobstoreS3Provider {
+    #[instrument(level = "trace", skip(self))]
+    async fn delete_objects(
+        &self,
+        cx: Option<Context>,
+        container: String,
+        objects: Vec<String>,
+    ) -> anyhow::Result<Result<Vec<batch::Failure>, String>> {
+        Ok(async {
+            propagate_trace_for_ctx!(cx);
+            let component_id = component_id(cx.as_ref());
+            self.with_client(cx, |client| async move {
+                client.ensure_mutable("delete objects")?;
+                let bucket = client.unalias(&container);
+                let keys = client
+                    .with_key_versions(bucket, client.object_keys(objects)?)
+                    .await?;
+                let failures = if client.soft_deletes() {
+                    client.trash_objects(bucket, keys.clone()).await
+                } else {
+                    client.delete_objects_detailed(bucket, keys.clone()).await?
+                };
+                let failed: HashSet<_> = failures.iter().map(|failure| &failure.key).collect();
+                let deleted: Vec<_> = keys
+                    .into_iter()
+                    .filter(|key| !failed.contains(key))
+                    .collect();
+                if !deleted.is_empty() {
+                    client
+                        .journal(
+                            &component_id,
+                            Mutation::Delete {
+                                bucket: bucket.into(),
+                                keys: deleted,
+                            },
+                        )
+                        .await;
+                }
+                anyhow::Ok(failures.into_iter().map(Into::into).collect())
+            })
+            .await
+        }
+        .await
+        .map_err(|err| error_message(&err)))
+    }
+
+    #[instrument(level = "trace", skip(self))]
+    async fn delete_objects_verified(
+        &self,
+        cx: Option<Context>,
+        container: String,
+        objects: Vec<String>,
+    ) -> anyhow::Result<Result<batch::VerifiedDelete, String>> {
+        Ok(async {
+            propagate_trace_for_ctx!(cx);
+            let component_id = component_id(cx.as_ref());
+            self.with_client(cx, |client| async move {
+                client.ensure_mutable("delete objects")?;
+                let bucket = client.unalias(&container);
+                let keys = client.object_keys(objects)?;
+                let verified = client.delete_objects_verified(bucket, keys).await?;
+                if !verified.deleted.is_empty() {
+                    client
+                        .journal(
+                            &component_id,
+                            Mutation::Delete {
+                                bucket: bucket.into(),
+                                keys: verified
+                                    .deleted
+                                    .iter()
+                                    .map(|object| object.key.clone())
+                                    .collect(),
+                            },
+                        )
+                        .await;
+                }
+                anyhow::Ok(verified.into())
+            })
+            .await
+        }
+        .await
+        .map_err(|err| error_message(&err)))
+    }
+}
+
+impl trash::Handler<Option<Context>> for BlobstoreS3Provider {
+    #[instrument(level = "trace", skip(self))]
+    async fn list_trash(
+        &self,
+        cx: Option<Context>,
+        container: String,
+    ) -> anyhow::Result<Result<Vec<trash::TrashedObject>, String>> {
+        Ok(async {
+            propagate_trace_for_ctx!(cx);
+            self.with_client(cx, |client| async move {
+                let trashed = client.list_trash(client.unalias(&container)).await?;
+                anyhow::Ok(trashed.into_iter().map(Into::into).collect())
+            })
+            .await
+        }
+        .await
+        .map_err(|err| error_message(&err)))
+    }
+
+    #[instrument(level = "trace", skip(self))]
+    async fn restore_object(
+        &self,
+        cx: Option<Context>,
+        container: String,
+        object: String,
+        deleted_at: Option<String>,
+    ) -> anyhow::Result<Result<trash::TrashedObject, String>> {
+        Ok(async {
+            propagate_trace_for_ctx!(cx);
+            let component_id = component_id(cx.as_ref());
+            self.with_client(cx, |client| async move {
+                client.ensure_mutable("restore trashed objects")?;
+                let bucket = client.unalias(&container);
+                let key = client.object_key(&object)?;
+                let trashed = client
+                    .restore_object(bucket, &key, deleted_at.as_deref())
+                    .await?;
+                client
+                    .journal(
+                        &component_id,
+                        Mutation::Move {
+                            src_bucket: bucket.into(),
+                            src_key: trashed.trash_key.clone(),
+                            dest_bucket: bucket.into(),
+                            dest_key: trashed.key.clone(),
+                        },
+                    )
+                    .await;
+                anyhow::Ok(trashed.into())
+            })
+            .await
+        }
+        .await
+        .map_err(|err| error_message(&err)))
+    }
+
+    #[instrument(level = "trace", skip(self))]
+    async fn purge_trash(
+        &self,
+        cx: Option<Context>,
+        container: String,
+        before: u64,
+    ) -> anyhow::Result<Result<u64, String>> {
+        Ok(async {
+            propagate_trace_for_ctx!(cx);
+            self.with_client(cx, |client| async move {
+                client.ensure_mutable("purge the trash")?;
+                let n = client
+                    .purge_trash_once(
+                        client.unalias(&container),
+                        SystemTime::UNIX_EPOCH + Duration::from_secs(before),
+                    )
+                    .await?;
+                anyhow::Ok(n as u64)
+            })
+            .await
+        }
+        .await
+        .map_err(|err| error_message(&err)))
+    }
+}
+
+impl diff::Handler<Option<Context>> for BlobstoreS3Provider {
+    #[instrument(level = "trace", skip(self))]
+    async fn diff_prefixes(
+        &self,
+        cx: Option<Context>,
+        source_container: String,
+        source_prefix: String,
+        target_container: String,
+        target_prefix: String,
+    ) -> anyhow::Result<
+        Result<
+            (
+                Pin<Box<dyn Stream<Item = Vec<diff::Change>> + Send>>,
+                Pin<Box<dyn Future<Output = Result<(), String>> + Send>>,
+            ),
+            String,
+        >,
+    > {
+        Ok(async {
+            propagate_trace_for_ctx!(cx);
+            self.with_client(cx, |client| async move {
+                let changes = client
+                    .diff_prefixes(
+                        client.unalias(&source_container),
+                        &source_prefix,
+                        client.unalias(&target_container),
+                        &target_prefix,
+                    )
+                    .await?;
+                let changes = stream::iter(changes.into_iter().map(diff::Change::from))
+                    .chunks(DIFF_BATCH_SIZE);
+                anyhow::Ok((
+                    Box::pin(changes) as Pin<Box<dyn Stream<Item = _> + Send>>,
+                    Box::pin(async move { Ok(()) }) as Pin<Box<dyn Future<Output = _> + Send>>,
+                ))
+            })
+            .await
+        }
+        .await
+        .map_err(|err| error_message(&err)))
+    }
+}
+
+impl time_partitions::Handler<Option<Context>> for BlobstoreS3Provider {
+    #[instrument(level = "trace", skip(self))]
+    async fn list_time_window(
+        &self,
+        cx: Option<Context>,
+        container: String,
+        prefix: String,
+        since: u64,
+        until: u64,
+    ) -> anyhow::Result<Result<Vec<String>, String>> {
+        Ok(async {
+            propagate_trace_for_ctx!(cx);
+            self.with_client(cx, |client| async move {
+                client
+                    .list_time_window(
+                        client.unalias(&container),
+                        &prefix,
+                        SystemTime::UNIX_EPOCH + Duration::from_secs(since),
+                        SystemTime::UNIX_EPOCH + Duration::from_secs(until),
+                    )
+                    .await
+            })
+            .await
+        }
+        .await
+        .map_err(|err| error_message(&err)))
+    }
+}
+
+impl snapshots::Handler<Option<Context>> for BlobstoreS3Provider {
+    #[instrument(level = "trace", skip(self))]
+    async fn snapshot_container(
+        &self,
+        cx: Option<Context>,
+        container: String,
+        options: snapshots::SnapshotOptions,
+    ) -> anyhow::Result<Result<snapshots::Snapshot, String>> {
+        Ok(async {
+            propagate_trace_for_ctx!(cx);
+            self.with_client(cx, |client| async move {
+                let options = SnapshotOptions::from(options);
+                let destination = client
+                    .unalias(options.destination.as_deref().unwrap_or(&container))
+                    .to_string();
+                client.ensure_bucket_not_public(&destination).await?;
+                client
+                    .snapshot_container(client.unalias(&container), options, None)
+                    .await
+                    .map(Into::into)
+            })
+            .await
+        }
+        .await
+        .map_err(|err| error_message(&err)))
+    }
+
+    #[instrument(level = "trace", skip(self))]
+    async fn restore_snapshot(
+        &self,
+        cx: Option<Context>,
+        container: String,
+        id: String,
+        options: snapshots::RestoreOptions,
+    ) -> anyhow::Result<
+        Result<
+            (
+                Pin<Box<dyn Stream<Item = Vec<snapshots::Progress>> + Send>>,
+                Pin<Box<dyn Future<Output = Result<(), String>> + Send>>,
+            ),
+            String,
+        >,
+    > {
+        Ok(async {
+            propagate_trace_for_ctx!(cx);
+            self.with_client(cx, |client| async move {
+                client.ensure_mutable("restore snapshots")?;
+                let bucket = client.unalias(&container).to_string();
+                client.ensure_bucket_not_public(&bucket).await?;
+                let (tx, rx) = mpsc::channel(16);
+                let done = client.clone().abort_on_delete(async move {
+                    client
+                        .restore_snapshot(&bucket, &id, options.into(), Some(&tx))
+                        .await
+                        .map(|_| ())
+                        .map_err(|err| error_message(&err))
+                });
+                anyhow::Ok((
+                    progress_stream(rx),
+                    Box::pin(done) as Pin<Box<dyn Future<Output = _> + Send>>,
+                ))
+            })
             .await
-            .context("failed to delete object")?;
-        Ok(())
-    }
-
-    #[instrument(level = "debug", skip(self, objects))]
-    pub async fn delete_objects(
-        &self,
-        container: &str,
-        objects: impl IntoIterator<Item = String>,
-    ) -> anyhow::Result<()> {
-        let objects: Vec<_> = objects
-            .into_iter()
-            .map(|key| ObjectIdentifier::builder().key(key).build())
-            .collect::<Result<_, _>>()
-            .context("failed to build object identifier list")?;
-        if objects.is_empty() {
-            debug!("no objects to delete, return");
-            return Ok(());
-        }
-        let delete = Delete::builder()
-            .set_objects(Some(objects))
-            .build()
-            .context("failed to build `delete_objects` command")?;
-        let out = self
-            .s3_client
-            .delete_objects()
-            .bucket(container)
-            .delete(delete)
-            .send()
-            .await
-            .context("failed to delete objects")?;
-        let errs = out.errors();
-        if !errs.is_empty() {
-            bail!("failed with errors {errs:?}")
-        }
-        Ok(())
-    }
-
-    #[instrument(level = "debug", skip(self))]
-    pub async fn delete_container(&self, bucket: &str) -> anyhow::Result<()> {
-        match self.s3_client.delete_bucket().bucket(bucket).send().await {
-            Ok(_) => Ok(()),
-            Err(SdkError::ServiceError(err)) => {
-                bail!("{err:?}")
-            }
-            Err(err) => {
-                error!(%err, code = err.code(), "unexpected error");
-                bail!(err)
-            }
         }
+        .await
+        .map_err(|err| error_message(&err)))
     }
+}
 
-    /// Find out whether object exists
-    #[instrument(level = "debug", skip(self))]
-    pub async fn has_object(&self, bucket: &str, key: &str) -> anyhow::Result<bool> {
-        match self
-            .s3_client
-            .head_object()
-            .bucket(bucket)
-            .key(key)
-            .send()
+impl bulk::Handler<Option<Context>> for BlobstoreS3Provider {
+    #[instrument(level = "trace", skip(self))]
+    async fn clear_container(
+        &self,
+        cx: Option<Context>,
+        name: String,
+    ) -> anyhow::Result<
+        Result<
+            (
+                Pin<Box<dyn Stream<Item = Vec<snapshots::Progress>> + Send>>,
+                Pin<Box<dyn Future<Output = Result<(), String>> + Send>>,
+            ),
+            String,
+        >,
+    > {
+        Ok(async {
+            propagate_trace_for_ctx!(cx);
+            let component_id = component_id(cx.as_ref());
+            self.with_client(cx, |client| async move {
+                client.ensure_mutable("clear containers")?;
+                let bucket = client.unalias(&name).to_string();
+                let (tx, rx) = mpsc::channel(16);
+                let done = client.clone().abort_on_delete(async move {
+                    let keys = client
+                        .clear_container(&bucket, Some(&tx))
+                        .await
+                        .map_err(|err| error_message(&err))?;
+                    client
+                        .journal(&component_id, Mutation::Delete { bucket, keys })
+                        .await;
+                    Ok(())
+                });
+                anyhow::Ok((
+                    progress_stream(rx),
+                    Box::pin(done) as Pin<Box<dyn Future<Output = _> + Send>>,
+                ))
+            })
             .await
-        {
-            Ok(_) => Ok(true),
-            Err(se) => match se.into_service_error() {
-                HeadObjectError::NotFound(_) => Ok(false),
-                err => {
-                    error!(
-                        %err,
-                        code = err.code(),
-                        "unexpected error for object_exists"
-                    );
-                    bail!(anyhow!(err).context("unexpected error for object_exists"))
-                }
-            },
         }
+        .await
+        .map_err(|err| error_message(&err)))
     }
 
-    /// Retrieves metadata about the object
-    #[instrument(level = "debug", skip(self))]
-    pub async fn get_object_info(&self, bucket: &str, key: &str) -> anyhow::Result<ObjectMetadata> {
-        match self
-            .s3_client
-            .head_object()
-            .bucket(bucket)
-            .key(key)
-            .send()
+    #[instrument(level = "trace", skip(self))]
+    async fn snapshot_container(
+        &self,
+        cx: Option<Context>,
+        container: String,
+        options: snapshots::SnapshotOptions,
+    ) -> anyhow::Result<
+        Result<
+            (
+                Pin<Box<dyn Stream<Item = Vec<snapshots::Progress>> + Send>>,
+                Pin<Box<dyn Future<Output = Result<snapshots::Snapshot, String>> + Send>>,
+            ),
+            String,
+        >,
+    > {
+        Ok(async {
+            propagate_trace_for_ctx!(cx);
+            self.with_client(cx, |client| async move {
+                let bucket = client.unalias(&container).to_string();
+                let options = SnapshotOptions::from(options);
+                let destination = client
+                    .unalias(options.destination.as_deref().unwrap_or(&bucket))
+                    .to_string();
+                client.ensure_bucket_not_public(&destination).await?;
+                let (tx, rx) = mpsc::channel(16);
+                let done = client.clone().abort_on_delete(async move {
+                    client
+                        .snapshot_container(&bucket, options, Some(&tx))
+                        .await
+                        .map(Into::into)
+                        .map_err(|err| error_message(&err))
+                });
+                anyhow::Ok((
+                    progress_stream(rx),
+                    Box::pin(done) as Pin<Box<dyn Future<Output = _> + Send>>,
+                ))
+            })
             .await
-        {
-            Ok(HeadObjectOutput { content_length, .. }) => {
-                Ok(ObjectMetadata {
-                    // NOTE: The `created_at` value is not reported by S3
-                    created_at: 0,
-                    size: content_length
-                        .and_then(|v| v.try_into().ok())
-                        .unwrap_or_default(),
-                })
-            }
-            Err(se) => match se.into_service_error() {
-                HeadObjectError::NotFound(_) => {
-                    error!("object [{bucket}/{key}] not found");
-                    bail!("object [{bucket}/{key}] not found")
-                }
-                err => {
-                    error!(
-                        ?err,
-                        code = err.code(),
-                        "get_object_metadata failed for object [{bucket}/{key}]"
-                    );
-                    bail!(anyhow!(err).context(format!(
-                        "get_object_metadata failed for object [{bucket}/{key}]"
-                    )))
-                }
-            },
         }
+        .await
+        .map_err(|err| error_message(&err)))
     }
 }
 
-/// Blobstore S3 provider
-///
-/// This struct will be the target of generated implementations (via wit-provider-bindgen)
-/// for the blobstore provider WIT contract
-#[derive(Default, Clone)]
-pub struct BlobstoreS3Provider {
-    /// Per-component storage for NATS connection clients
-    actors: Arc<RwLock<HashMap<String, StorageClient>>>,
-}
-
-pub async fn run() -> anyhow::Result<()> {
-    BlobstoreS3Provider::run().await
-}
-
-impl BlobstoreS3Provider {
-    pub async fn run() -> anyhow::Result<()> {
-        initialize_observability!(
-            "blobstore-s3-provider",
-            std::env::var_os("PROVIDER_BLOBSTORE_S3_FLAMEGRAPH_PATH")
-        );
-
-        let provider = Self::default();
-        let shutdown = run_provider(provider.clone(), "blobstore-s3-provider")
+impl jobs_api::Handler<Option<Context>> for BlobstoreS3Provider {
+    #[instrument(level = "trace", skip(self))]
+    async fn clear_container(
+        &self,
+        cx: Option<Context>,
+        name: String,
+        dry_run: bool,
+    ) -> anyhow::Result<Result<String, String>> {
+        Ok(async {
+            propagate_trace_for_ctx!(cx);
+            let component_id = component_id(cx.as_ref());
+            self.with_client(cx, |client| async move {
+                client.ensure_mutable("clear containers")?;
+                let bucket = client.unalias(&name).to_string();
+                let operation = JobOperation::ClearContainer {
+                    bucket: bucket.clone(),
+                };
+                let store = Some(client.clone());
+                anyhow::Ok(
+                    self.jobs
+                        .spawn(&component_id.clone(), operation, store, |tx| {
+                            client.clone().abort_on_delete(async move {
+                                if dry_run {
+                                    return client
+                                        .estimate_clear_container(&bucket)
+                                        .await
+                                        .map(JobState::Estimated)
+                                        .map_err(|err| error_message(&err));
+                                }
+                                let keys = client
+                                    .clear_container(&bucket, Some(&tx))
+                                    .await
+                                    .map_err(|err| error_message(&err))?;
+                                client
+                                    .journal(&component_id, Mutation::Delete { bucket, keys })
+                                    .await;
+                                Ok(JobState::Succeeded(None))
+                            })
+                        }),
+                )
+            })
             .await
-            .context("failed to run provider")?;
-        let connection = get_connection();
-        serve_provider_exports(
-            &connection.get_wrpc_client(connection.provider_key()),
-            provider,
-            shutdown,
-            serve,
-        )
+        }
         .await
-        .context("failed to serve provider exports")
+        .map_err(|err| error_message(&err)))
     }
 
-    /// Retrieve the per-component [`StorageClient`] for a given link context
-    async fn client(&self, context: Option<Context>) -> Result<StorageClient> {
-        if let Some(ref source_id) = context.and_then(|Context { component, .. }| component) {
-            self.actors
-                .read()
-                .await
-                .get(source_id)
-                .with_context(|| format!("failed to lookup {source_id} configuration"))
-                .cloned()
-        } else {
-            // TODO: Support a default here
-            bail!("failed to lookup invocation source ID")
+    #[instrument(level = "trace", skip(self))]
+    async fn snapshot_container(
+        &self,
+        cx: Option<Context>,
+        container: String,
+        options: snapshots::SnapshotOptions,
+        dry_run: bool,
+    ) -> anyhow::Result<Result<String, String>> {
+        Ok(async {
+            propagate_trace_for_ctx!(cx);
+            let component_id = component_id(cx.as_ref());
+            self.with_client(cx, |client| async move {
+                let bucket = client.unalias(&container).to_string();
+                let options = SnapshotOptions::from(options);
+                let destination = client
+                    .unalias(options.destination.as_deref().unwrap_or(&bucket))
+                    .to_string();
+                client.ensure_bucket_not_public(&destination).await?;
+                let operation = JobOperation::SnapshotContainer {
+                    bucket: bucket.clone(),
+                };
+                let store = Some(client.clone());
+                anyhow::Ok(self.jobs.spawn(&component_id, operation, store, |tx| {
+                    client.clone().abort_on_delete(async move {
+                        if dry_run {
+                            return client
+                                .estimate_snapshot(&bucket, options)
+                                .await
+                                .map(JobState::Estimated)
+                                .map_err(|err| error_message(&err));
+                        }
+                        client
+                            .snapshot_container(&bucket, options, Some(&tx))
+                            .await
+                            .map(|snapshot| JobState::Succeeded(Some(snapshot.id)))
+                            .map_err(|err| error_message(&err))
+                    })
+                }))
+            })
+            .await
         }
+        .await
+        .map_err(|err| error_message(&err)))
     }
-}
 
-impl Handler<Option<Context>> for BlobstoreS3Provider {
     #[instrument(level = "trace", skip(self))]
-    async fn clear_container(
+    async fn restore_snapshot(
         &self,
         cx: Option<Context>,
-        name: String,
-    ) -> anyhow::Result<Result<(), String>> {
+        container: String,
+        id: String,
+        options: snapshots::RestoreOptions,
+        dry_run: bool,
+    ) -> anyhow::Result<Result<String, String>> {
         Ok(async {
             propagate_trace_for_ctx!(cx);
-            let client = self.client(cx).await?;
-            let bucket = client.unalias(&name);
-            let objects = client
-                .list_container_objects(bucket, None, None)
-                .await
-                .context("failed to list container objects")?;
-            client.delete_objects(bucket, objects).await
+            let component_id = component_id(cx.as_ref());
+            self.with_client(cx, |client| async move {
+                client.ensure_mutable("restore snapshots")?;
+                let bucket = client.unalias(&container).to_string();
+                client.ensure_bucket_not_public(&bucket).await?;
+                let operation = JobOperation::RestoreSnapshot {
+                    bucket: bucket.clone(),
+                    snapshot_id: id.clone(),
+                };
+                let store = Some(client.clone());
+                anyhow::Ok(self.jobs.spawn(&component_id, operation, store, |tx| {
+                    client.clone().abort_on_delete(async move {
+                        if dry_run {
+                            return client
+                                .estimate_restore(&bucket, &id, options.into())
+                                .await
+                                .map(JobState::Estimated)
+                                .map_err(|err| error_message(&err));
+                        }
+                        client
+                            .restore_snapshot(&bucket, &id, options.into(), Some(&tx))
+                            .await
+                            .map(|_| JobState::Succeeded(None))
+                            .map_err(|err| error_message(&err))
+                    })
+                }))
+            })
+            .await
         }
         .await
-        .map_err(|err| format!("{err:#}")))
+        .map_err(|err| error_message(&err)))
     }
 
     #[instrument(level = "trace", skip(self))]
-    async fn container_exists(
+    async fn reencrypt_container(
         &self,
         cx: Option<Context>,
-        name: String,
-    ) -> anyhow::Result<Result<bool, String>> {
+        container: String,
+        kms_key_id: String,
+        prefix: Option<String>,
+        dry_run: bool,
+    ) -> anyhow::Result<Result<String, String>> {
         Ok(async {
             propagate_trace_for_ctx!(cx);
-            let client = self.client(cx).await?;
-            client.container_exists(client.unalias(&name)).await
+            let component_id = component_id(cx.as_ref());
+            self.with_client(cx, |client| async move {
+                client.ensure_mutable("re-encrypt containers")?;
+                let bucket = client.unalias(&container).to_string();
+                let operation = JobOperation::ReencryptContainer {
+                    bucket: bucket.clone(),
+                    kms_key_id: kms_key_id.clone(),
+                };
+                let store = Some(client.clone());
+                anyhow::Ok(self.jobs.spawn(&component_id, operation, store, |tx| {
+                    client.clone().abort_on_delete(async move {
+                        if dry_run {
+                            return client
+                                .estimate_reencrypt(&bucket, prefix.as_deref())
+                                .await
+                                .map(JobState::Estimated)
+                                .map_err(|err| error_message(&err));
+                        }
+                        client
+                            .reencrypt_container(&bucket, &kms_key_id, prefix.as_deref(), Some(&tx))
+                            .await
+                            .map(|_| JobState::Succeeded(None))
+                            .map_err(|err| error_message(&err))
+                    })
+                }))
+            })
+            .await
         }
         .await
-        .map_err(|err| format!("{err:#}")))
+        .map_err(|err| error_message(&err)))
     }
 
     #[instrument(level = "trace", skip(self))]
-    async fn create_container(
+    async fn set_legal_holds(
         &self,
         cx: Option<Context>,
-        name: String,
-    ) -> anyhow::Result<Result<(), String>> {
+        container: String,
+        prefix: Option<String>,
+        on: bool,
+    ) -> anyhow::Result<Result<String, String>> {
         Ok(async {
             propagate_trace_for_ctx!(cx);
-            let client = self.client(cx).await?;
-            client.create_container(client.unalias(&name)).await
+            let component_id = component_id(cx.as_ref());
+            self.with_client(cx, |client| async move {
+                let bucket = client.unalias(&container).to_string();
+                let operation = JobOperation::SetLegalHolds {
+                    bucket: bucket.clone(),
+                    prefix: prefix.clone(),
+                    on,
+                };
+                let store = Some(client.clone());
+                anyhow::Ok(self.jobs.spawn(&component_id, operation, store, |tx| {
+                    client.clone().abort_on_delete(async move {
+                        client
+                            .set_legal_holds(&bucket, prefix.as_deref(), on, Some(&tx))
+                            .await
+                            .map(|_| JobState::Succeeded(None))
+                            .map_err(|err| error_message(&err))
+                    })
+                }))
+            })
+            .await
         }
         .await
-        .map_err(|err| format!("{err:#}")))
+        .map_err(|err| error_message(&err)))
     }
 
     #[instrument(level = "trace", skip(self))]
-    async fn delete_container(
+    async fn get_job_status(
         &self,
         cx: Option<Context>,
-        name: String,
-    ) -> anyhow::Result<Result<(), String>> {
+        id: String,
+    ) -> anyhow::Result<Result<jobs_api::JobStatus, String>> {
         Ok(async {
             propagate_trace_for_ctx!(cx);
-            let client = self.client(cx).await?;
-            client.delete_container(client.unalias(&name)).await
+            let component_id = component_id(cx.as_ref());
+            if let Some(status) = self.jobs.status(&component_id, &id) {
+                return anyhow::Ok(status.into());
+            }
+            // jobs started before the provider restarted are only known from their records
+            self.with_client(cx, |client| async move {
+                let record = client
+                    .load_job(&id)
+                    .await?
+                    .filter(|record| record.component_id == component_id)
+                    .with_context(|| format!("job `{id}` not found"))?;
+                Ok(record.status().into())
+            })
+            .await
         }
         .await
-        .map_err(|err| format!("{err:#}")))
+        .map_err(|err| error_message(&err)))
     }
 
     #[instrument(level = "trace", skip(self))]
-    async fn get_container_info(
+    async fn cancel_job(
         &self,
         cx: Option<Context>,
-        name: String,
-    ) -> anyhow::Result<Result<ContainerMetadata, String>> {
+        id: String,
+    ) -> anyhow::Result<Result<bool, String>> {
         Ok(async {
             propagate_trace_for_ctx!(cx);
-            let client = self.client(cx).await?;
-            client.get_container_info(client.unalias(&name)).await
+            let component_id = component_id(cx.as_ref());
+            if self.jobs.contains(&component_id, &id) {
+                return anyhow::Ok(self.jobs.cancel(&component_id, &id));
+            }
+            // jobs only known from their records finished or were interrupted
+            self.with_client(cx, |client| async move {
+                client
+                    .load_job(&id)
+                    .await?
+                    .filter(|record| record.component_id == component_id)
+                    .with_context(|| format!("job `{id}` not found"))?;
+                Ok(false)
+            })
+            .await
         }
         .await
-        .map_err(|err| format!("{err:#}")))
+        .map_err(|err| error_message(&err)))
     }
+}
 
+impl batch_operations::Handler<Option<Context>> for BlobstoreS3Provider {
     #[instrument(level = "trace", skip(self))]
-    async fn list_container_objects(
+    async fn copy_objects(
         &self,
         cx: Option<Context>,
-        name: String,
-        limit: Option<u64>,
-        offset: Option<u64>,
-    ) -> anyhow::Result<
-        Result<
-            (
-                Pin<Box<dyn Stream<Item = Vec<String>> + Send>>,
-                Pin<Box<dyn Future<Output = Result<(), String>> + Send>>,
-            ),
-            String,
-        >,
-    > {
+        source: String,
+        destination: String,
+        prefix: Option<String>,
+    ) -> anyhow::Result<Result<String, String>> {
         Ok(async {
             propagate_trace_for_ctx!(cx);
-            let client = self.client(cx).await?;
-            let names = client
-                .list_container_objects(client.unalias(&name), limit, offset)
-                .await
-                .map(Vec::from_iter)?;
-            anyhow::Ok((
-                Box::pin(stream::iter([names])) as Pin<Box<dyn Stream<Item = _> + Send>>,
-                Box::pin(async move { Ok(()) }) as Pin<Box<dyn Future<Output = _> + Send>>,
-            ))
+            let component_id = component_id(cx.as_ref());
+            self.with_client(cx, |client| async move {
+                client.ensure_mutable("copy objects in bulk")?;
+                let destination = client.unalias(&destination);
+                client.ensure_bucket_not_public(destination).await?;
+                client
+                    .start_batch_copy(
+                        client.unalias(&source),
+                        destination,
+                        prefix.as_deref(),
+                        &batch_job_description(&component_id),
+                    )
+                    .await
+            })
+            .await
         }
         .await
-        .map_err(|err| format!("{err:#}")))
+        .map_err(|err| error_message(&err)))
     }
 
     #[instrument(level = "trace", skip(self))]
-    async fn copy_object(
+    async fn get_batch_job(
         &self,
         cx: Option<Context>,
-        src: ObjectId,
-        dest: ObjectId,
-    ) -> anyhow::Result<Result<(), String>> {
+        id: String,
+    ) -> anyhow::Result<Result<batch_operations::BatchJob, String>> {
         Ok(async {
             propagate_trace_for_ctx!(cx);
-            let client = self.client(cx).await?;
-            let src_bucket = client.unalias(&src.container);
-            let dest_bucket = client.unalias(&dest.container);
-            client
-                .copy_object(src_bucket, &src.object, dest_bucket, &dest.object)
-                .await
+            let component_id = component_id(cx.as_ref());
+            self.with_client(cx, |client| async move {
+                component_batch_job(&client, &component_id, &id)
+                    .await
+                    .map(Into::into)
+            })
+            .await
         }
         .await
-        .map_err(|err| format!("{err:#}")))
+        .map_err(|err| error_message(&err)))
     }
 
     #[instrument(level = "trace", skip(self))]
-    async fn delete_object(
+    async fn cancel_batch_job(
         &self,
         cx: Option<Context>,
-        id: ObjectId,
+        id: String,
     ) -> anyhow::Result<Result<(), String>> {
         Ok(async {
             propagate_trace_for_ctx!(cx);
-            let client = self.client(cx).await?;
-            client
-                .delete_object(client.unalias(&id.container), id.object)
-                .await
+            let component_id = component_id(cx.as_ref());
+            self.with_client(cx, |client| async move {
+                component_batch_job(&client, &component_id, &id).await?;
+                client.cancel_batch_job(&id).await
+            })
+            .await
         }
         .await
-        .map_err(|err| format!("{err:#}")))
+        .map_err(|err| error_message(&err)))
     }
+}
 
+impl website_api::Handler<Option<Context>> for BlobstoreS3Provider {
     #[instrument(level = "trace", skip(self))]
-    async fn delete_objects(
+    async fn configure_website(
         &self,
         cx: Option<Context>,
         container: String,
-        objects: Vec<String>,
+        options: website_api::WebsiteOptions,
     ) -> anyhow::Result<Result<(), String>> {
         Ok(async {
             propagate_trace_for_ctx!(cx);
-            let client = self.client(cx).await?;
-            client
-                .delete_objects(client.unalias(&container), objects)
-                .await
+            self.with_client(cx, |client| async move {
+                client
+                    .configure_website(client.unalias(&container), options.into())
+                    .await
+            })
+            .await
         }
         .await
-        .map_err(|err| format!("{err:#}")))
+        .map_err(|err| error_message(&err)))
     }
 
     #[instrument(level = "trace", skip(self))]
-    async fn get_container_data(
+    async fn publish_site(
         &self,
         cx: Option<Context>,
-        id: ObjectId,
-        start: u64,
-        end: u64,
+        container: String,
+        options: website_api::PublishOptions,
     ) -> anyhow::Result<
         Result<
             (
-                Pin<Box<dyn Stream<Item = Bytes> + Send>>,
+                Pin<Box<dyn Stream<Item = Vec<snapshots::Progress>> + Send>>,
                 Pin<Box<dyn Future<Output = Result<(), String>> + Send>>,
             ),
             String,
@@ -779,129 +3314,118 @@ impl Handler<Option<Context>> for BlobstoreS3Provider {
     > {
         Ok(async {
             propagate_trace_for_ctx!(cx);
-            let limit = end
-                .checked_sub(start)
-                .context("`end` must be greater than `start`")?;
-            let client = self.client(cx).await?;
-            let bucket = client.unalias(&id.container);
-            let GetObjectOutput { body, .. } = client
-                .s3_client
-                .get_object()
-                .bucket(bucket)
-                .key(id.object)
-                .range(format!("bytes={start}-{end}"))
-                .send()
-                .await
-                .context("failed to get object")?;
-            let mut data = ReaderStream::new(body.into_async_read().take(limit));
-            let (tx, rx) = mpsc::channel(16);
-            anyhow::Ok((
-                Box::pin(ReceiverStream::new(rx)) as Pin<Box<dyn Stream<Item = _> + Send>>,
-                Box::pin(async move {
-                    while let Some(buf) = data.next().await {
-                        let buf = buf
-                            .context("failed to read object")
-                            .map_err(|err| format!("{err:#}"))?;
-                        if tx.send(buf).await.is_err() {
-                            return Err("stream receiver closed".to_string());
-                        }
-                    }
-                    Ok(())
-                }) as Pin<Box<dyn Future<Output = _> + Send>>,
-            ))
+            self.with_client(cx, |client| async move {
+                client.ensure_mutable("publish sites")?;
+                let bucket = client.unalias(&container).to_string();
+                let options = PublishOptions::from(options);
+                let (tx, rx) = mpsc::channel(16);
+                let done = client.clone().abort_on_delete(async move {
+                    client
+                        .publish_site(&bucket, &options, Some(&tx))
+                        .await
+                        .map(|_| ())
+                        .map_err(|err| error_message(&err))
+                });
+                anyhow::Ok((
+                    progress_stream(rx),
+                    Box::pin(done) as Pin<Box<dyn Future<Output = _> + Send>>,
+                ))
+            })
+            .await
         }
         .await
-        .map_err(|err| format!("{err:#}")))
+        .map_err(|err| error_message(&err)))
     }
+}
 
+impl stats::Handler<Option<Context>> for BlobstoreS3Provider {
     #[instrument(level = "trace", skip(self))]
-    async fn get_object_info(
+    async fn get_container_stats(
         &self,
         cx: Option<Context>,
-        id: ObjectId,
-    ) -> anyhow::Result<Result<ObjectMetadata, String>> {
+        name: String,
+    ) -> anyhow::Result<Result<stats::ContainerStats, String>> {
         Ok(async {
             propagate_trace_for_ctx!(cx);
-            let client = self.client(cx).await?;
-            client
-                .get_object_info(client.unalias(&id.container), &id.object)
-                .await
+            self.with_client(cx, |client| async move {
+                client
+                    .get_container_stats(client.unalias(&name))
+                    .await
+                    .map(Into::into)
+            })
+            .await
         }
         .await
-        .map_err(|err| format!("{err:#}")))
+        .map_err(|err| error_message(&err)))
     }
 
     #[instrument(level = "trace", skip(self))]
-    async fn has_object(
+    async fn get_prefix_stats(
         &self,
         cx: Option<Context>,
-        id: ObjectId,
-    ) -> anyhow::Result<Result<bool, String>> {
+        name: String,
+        prefix: String,
+    ) -> anyhow::Result<Result<Vec<stats::PrefixStats>, String>> {
         Ok(async {
             propagate_trace_for_ctx!(cx);
-            let client = self.client(cx).await?;
-            client
-                .has_object(client.unalias(&id.container), &id.object)
-                .await
+            self.with_client(cx, |client| async move {
+                let stats = client
+                    .get_prefix_stats(client.unalias(&name), &prefix)
+                    .await?;
+                anyhow::Ok(stats.into_iter().map(Into::into).collect())
+            })
+            .await
         }
         .await
-        .map_err(|err| format!("{err:#}")))
+        .map_err(|err| error_message(&err)))
     }
 
     #[instrument(level = "trace", skip(self))]
-    async fn move_object(
+    async fn get_largest_objects(
         &self,
         cx: Option<Context>,
-        src: ObjectId,
-        dest: ObjectId,
-    ) -> anyhow::Result<Result<(), String>> {
+        name: String,
+        prefix: String,
+        n: u32,
+    ) -> anyhow::Result<Result<Vec<stats::ObjectSummary>, String>> {
         Ok(async {
             propagate_trace_for_ctx!(cx);
-            let client = self.client(cx).await?;
-            let src_bucket = client.unalias(&src.container);
-            let dest_bucket = client.unalias(&dest.container);
-            client
-                .copy_object(src_bucket, &src.object, dest_bucket, &dest.object)
-                .await
-                .context("failed to copy object")?;
-            client
-                .delete_object(src_bucket, src.object)
-                .await
-                .context("failed to delete source object")
+            self.with_client(cx, |client| async move {
+                let objects = client
+                    .get_largest_objects(client.unalias(&name), &prefix, n as usize)
+                    .await?;
+                anyhow::Ok(objects.into_iter().map(Into::into).collect())
+            })
+            .await
         }
         .await
-        .map_err(|err| format!("{err:#}")))
+        .map_err(|err| error_message(&err)))
     }
 
-    #[instrument(level = "trace", skip(self, data))]
-    async fn write_container_data(
+    #[instrument(level = "trace", skip(self))]
+    async fn get_stale_objects(
         &self,
         cx: Option<Context>,
-        id: ObjectId,
-        data: Pin<Box<dyn Stream<Item = Bytes> + Send>>,
-    ) -> anyhow::Result<Result<Pin<Box<dyn Future<Output = Result<(), String>> + Send>>, String>>
-    {
+        name: String,
+        prefix: String,
+        days: u32,
+        limit: u32,
+    ) -> anyhow::Result<Result<Vec<stats::ObjectSummary>, String>> {
         Ok(async {
             propagate_trace_for_ctx!(cx);
-            let client = self.client(cx).await?;
-            let req = client
-                .s3_client
-                .put_object()
-                .bucket(client.unalias(&id.container))
-                .key(&id.object);
-            anyhow::Ok(Box::pin(async {
-                // TODO: Stream data to S3
-                let data: BytesMut = data.collect().await;
-                req.body(data.freeze().into())
-                    .send()
-                    .await
-                    .context("failed to put object")
-                    .map_err(|err| format!("{err:#}"))?;
-                Ok(())
-            }) as Pin<Box<dyn Future<Output = _> + Send>>)
+            self.with_client(cx, |client| async move {
+                let before = SystemTime::now()
+                    .checked_sub(Duration::from_secs(u64::from(days) * 24 * 60 * 60))
+                    .unwrap_or(SystemTime::UNIX_EPOCH);
+                let objects = client
+                    .get_stale_objects(client.unalias(&name), &prefix, before, limit as usize)
+                    .await?;
+                anyhow::Ok(objects.into_iter().map(Into::into).collect())
+            })
+            .await
         }
         .await
-        .map_err(|err| format!("{err:#}")))
+        .map_err(|err| error_message(&err)))
     }
 }
 
@@ -916,28 +3440,125 @@ impl Provider for BlobstoreS3Provider {
         link_config: LinkConfig<'_>,
     ) -> anyhow::Result<()> {
         // Build storage config
-        let config = match StorageConfig::from_link_config(&link_config).await {
-            Ok(v) => v,
+        let mut config = match StorageConfig::from_link_config(&link_config).await {
+            Ok(v) => self.defaults.merge(&v),
             Err(e) => {
                 error!(error = %e, %link_config.source_id, "failed to build storage config");
                 return Err(anyhow!(e).context("failed to build source config"));
             }
         };
 
-        let link = StorageClient::new(config, link_config.config).await;
+        // Roles mapped in provider configuration take precedence over the link configuration
+        if let Some(role) = self.actor_roles.get(link_config.source_id) {
+            debug!(%role, %link_config.source_id, "assuming role mapped for component");
+            let mut sts_config = config.sts_config.unwrap_or_default();
+            sts_config.role.clone_from(role);
+            config.sts_config = Some(sts_config);
+        }
+
+        config.chunk_size = Some(chunk_size(
+            config.chunk_size,
+            get_connection().max_payload(),
+        ));
+
+        // the component and link are known once the link is established, dates per request
+        if let Some(template) = &mut config.key_template {
+            *template = template
+                .replace("{actor}", link_config.source_id)
+                .replace("{link}", link_config.link_name);
+        }
+        if let Some(pattern) = config
+            .container_policy
+            .as_mut()
+            .and_then(|policy| policy.name_pattern.as_mut())
+        {
+            *pattern = pattern
+                .replace("{lattice}", &self.lattice)
+                .replace("{actor}", link_config.source_id)
+                .replace("{link}", link_config.link_name);
+        }
+        // buckets created by the link are counted against its quota by their identity tag
+        if let Some(policy) = config
+            .container_policy
+            .as_mut()
+            .filter(|policy| policy.max_containers.is_some())
+        {
+            policy.tags.entry(LINK_TAG.into()).or_insert_with(|| {
+                format!(
+                    "{}/{}/{}",
+                    self.lattice, link_config.source_id, link_config.link_name
+                )
+            });
+        }
+
+        if config.identify_workload.unwrap_or_default() {
+            config.app_name = Some(workload_app_name(
+                config.app_name.as_deref().unwrap_or(DEFAULT_APP_NAME),
+                &self.lattice,
+                &self.host_id,
+                link_config.link_name,
+            ));
+        }
+
+        info!(
+            %link_config.source_id,
+            %link_config.link_name,
+            config = ?config.redacted(),
+            "resolved link configuration"
+        );
+
+        let link = Link {
+            client: Arc::default(),
+            config: Arc::new(config),
+            config_values: Arc::new(link_config.config.clone()),
+            degraded: Arc::default(),
+        };
+        if link.config.lazy_init.unwrap_or_default() {
+            if let Err(e) = StorageClient::validate_config(&link.config) {
+                error!(error = %e, %link_config.source_id, "invalid storage config");
+                return Err(e.context("invalid storage config"));
+            }
+        } else if let Err(e) = self.link_client(&link).await {
+            error!(error = %e, %link_config.source_id, "failed to create storage client");
+            return Err(e);
+        }
 
         let mut update_map = self.actors.write().await;
-        update_map.insert(link_config.source_id.to_string(), link);
+        if let Some(previous) = update_map.insert(
+            (
+                link_config.source_id.to_string(),
+                link_config.link_name.to_string(),
+            ),
+            link,
+        ) {
+            // in-flight work of the replaced link uses stale configuration
+            previous.cancel();
+        }
+        self.events.publish(ProviderEvent::LinkAdded {
+            source_id: link_config.source_id.to_string(),
+            link_name: link_config.link_name.to_string(),
+        });
 
         Ok(())
     }
 
     /// Handle notification that a link is dropped: close the connection
-    #[instrument(level = "info", skip_all, fields(source_id = info.get_source_id()))]
+    #[instrument(
+        level = "info",
+        skip_all,
+        fields(source_id = info.get_source_id(), link_name = info.get_link_name())
+    )]
     async fn delete_link_as_target(&self, info: impl LinkDeleteInfo) -> anyhow::Result<()> {
         let component_id = info.get_source_id();
+        let link_name = info.get_link_name();
         let mut aw = self.actors.write().await;
-        aw.remove(component_id);
+        if let Some(link) = aw.remove(&(component_id.to_string(), link_name.to_string())) {
+            link.cancel();
+            self.events.publish(ProviderEvent::LinkRemoved {
+                source_id: component_id.to_string(),
+                link_name: link_name.to_string(),
+            });
+        }
         Ok(())
     }
 
@@ -945,30 +3566,53 @@ impl Provider for BlobstoreS3Provider {
     async fn shutdown(&self) -> anyhow::Result<()> {
         let mut aw = self.actors.write().await;
         // empty the component link data and stop all servers
-        aw.drain();
+        for (_, link) in aw.drain() {
+            link.cancel();
+        }
         Ok(())
     }
 }
 
 #[cfg(test)]
 mod test {
+    use aws_sdk_s3::config::AppName;
+
     use super::*;
 
-    #[tokio::test]
-    async fn aliases() {
-        let client = StorageClient::new(
-            StorageConfig::default(),
-            &HashMap::from([(format!("{ALIAS_PREFIX}foo"), "bar".into())]),
-        )
-        .await;
+    #[test]
+    fn chunk_sizes() {
+        assert_eq!(chunk_size(None, 1024 * 1024), DEFAULT_CHUNK_SIZE);
+        assert_eq!(chunk_size(Some(1024), 1024 * 1024), 1024);
+        // chunks of customized NATS servers with small payloads leave room for framing
+        assert_eq!(chunk_size(None, 8 * 1024), 7 * 1024);
+        assert_eq!(chunk_size(Some(4096), 0), 4096);
+    }
+
+    #[test]
+    fn workload_app_names() {
+        let app_name = workload_app_name("wasmcloud", "default", "NABC", "my link/1");
+        assert_eq!(app_name, "wasmcloud.default.NABC.my_link_1");
+        assert!(AppName::new(app_name).is_ok());
+    }
 
-        // no alias
-        assert_eq!(client.unalias("boo"), "boo");
-        // alias without prefix
-        assert_eq!(client.unalias("foo"), "bar");
-        // alias with prefix
-        assert_eq!(client.unalias(&format!("{ALIAS_PREFIX}foo")), "bar");
-        // undefined alias
-        assert_eq!(client.unalias(&format!("{ALIAS_PREFIX}baz")), "baz");
+    #[test]
+    fn actor_roles() {
+        let provider = BlobstoreS3Provider::from_host_data(&HostData {
+            config: HashMap::from([
+                (
+                    format!("{ACTOR_ROLE_PREFIX}foo"),
+                    "arn:aws:iam::123456789012:role/foo".into(),
+                ),
+                (format!("{ACTOR_ROLE_PREFIX}bar"), String::new()),
+                ("unrelated".into(), "value".into()),
+            ]),
+            ..Default::default()
+        })
+        .expect("failed to build provider");
+        assert_eq!(
+            provider.actor_roles.get("foo").map(String::as_str),
+            Some("arn:aws:iam::123456789012:role/foo")
+        );
+        assert_eq!(provider.actor_roles.len(), 1);
     }
 }
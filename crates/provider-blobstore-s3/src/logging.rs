@@ -0,0 +1,338 @@
+//! Per-link log levels and sampling of request logs, so that the logs of a noisy link can be
+//! reduced without affecting other links, and redaction of keys from debug logs of requests
+
+use std::borrow::Cow;
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher as _, Hasher as _};
+
+use tracing::level_filters::LevelFilter;
+use tracing::span::{Attributes, Id, Record};
+use tracing::subscriber::{Interest, Subscriber};
+use tracing::{Dispatch, Event, Metadata};
+use tracing_core::span::Current;
+
+/// Log level of requests of a link, which are only logged at full verbosity if sampled
+#[derive(Clone, Debug)]
+pub(crate) struct LinkLogging {
+    /// Logs of requests, which are sampled
+    sampled: Dispatch,
+    /// Logs of requests, which are not sampled, at most at `info` level
+    unsampled: Dispatch,
+    /// Fraction of requests logged at full verbosity
+    sample_rate: f64,
+}
+
+impl LinkLogging {
+    /// Build the logging of a link, wrapping the current default subscriber
+    pub(crate) fn new(level: LevelFilter, sample_rate: f64) -> Self {
+        let inner = tracing::dispatcher::get_default(Dispatch::clone);
+        Self {
+            sampled: Dispatch::new(LevelSubscriber {
+                inner: inner.clone(),
+                level,
+            }),
+            unsampled: Dispatch::new(LevelSubscriber {
+                inner,
+                level: level.min(LevelFilter::INFO),
+            }),
+            sample_rate: sample_rate.clamp(0.0, 1.0),
+        }
+    }
+
+    /// Choose the dispatcher for the logs of a request
+    pub(crate) fn dispatch(&self) -> Dispatch {
+        let sample = RandomState::new().build_hasher().finish() as f64 / u64::MAX as f64;
+        if sample < self.sample_rate {
+            self.sampled.clone()
+        } else {
+            self.unsampled.clone()
+        }
+    }
+}
+
+/// Placeholder of redacted keys
+pub(crate) const REDACTED: &str = "<redacted>";
+
+/// Patterns of keys redacted from debug logs of requests, in which `*` matches any sequence of
+/// characters
+#[derive(Clone, Debug, Default)]
+pub(crate) struct KeyRedaction(Vec<String>);
+
+impl KeyRedaction {
+    pub(crate) fn new(patterns: Vec<String>) -> Self {
+        Self(patterns)
+    }
+
+    /// Whether `path`, or any part of it following a `/`, matches a pattern
+    ///
+    /// Parts are matched since it is not known whether a request path starts with the bucket.
+    fn matches(&self, path: &str) -> bool {
+        let path = path.strip_prefix('/').unwrap_or(path);
+        core::iter::once(path)
+            .chain(path.match_indices('/').map(|(i, _)| &path[i + 1..]))
+            .any(|part| self.matches_key(part))
+    }
+
+    fn matches_key(&self, key: &str) -> bool {
+        self.0.iter().any(|pattern| glob_matches(pattern, key))
+    }
+
+    /// `value`, or `<redacted>` if it contains a key matching a pattern
+    pub(crate) fn redact_value<'a>(&self, value: &'a str) -> &'a str {
+        if !self.0.is_empty() && self.matches(&percent_decode(value)) {
+            REDACTED
+        } else {
+            value
+        }
+    }
+
+    /// `uri` with the part of its path matching a pattern and query parameter values matching a
+    /// pattern replaced by `<redacted>`
+    pub(crate) fn redact_uri<'a>(&self, uri: &'a str) -> Cow<'a, str> {
+        if self.0.is_empty() {
+            return Cow::Borrowed(uri);
+        }
+        let (base, query) = uri.split_once('?').unwrap_or((uri, ""));
+        let path_start = base
+            .find("://")
+            .and_then(|scheme| base[scheme + 3..].find('/').map(|i| scheme + 3 + i))
+            .unwrap_or(base.len());
+        let (origin, path) = base.split_at(path_start);
+        let segments: Vec<_> = path.split('/').collect();
+        let mut out = origin.to_string();
+        match (1..segments.len())
+            .find(|&i| self.matches_key(&percent_decode(&segments[i..].join("/"))))
+        {
+            Some(i) => {
+                out.push_str(&segments[..i].join("/"));
+                out.push('/');
+                out.push_str(REDACTED);
+            }
+            None => out.push_str(path),
+        }
+        if !query.is_empty() {
+            out.push('?');
+            for (i, param) in query.split('&').enumerate() {
+                if i > 0 {
+                    out.push('&');
+                }
+                match param.split_once('=') {
+                    Some((name, value)) => {
+                        out.push_str(name);
+                        out.push('=');
+                        out.push_str(self.redact_value(value));
+                    }
+                    None => out.push_str(param),
+                }
+            }
+        }
+        Cow::Owned(out)
+    }
+}
+
+/// Whether `text` matches `pattern`, in which `*` matches any sequence of characters
+pub(crate) fn glob_matches(pattern: &str, text: &str) -> bool {
+    let (pattern, text) = (pattern.as_bytes(), text.as_bytes());
+    let (mut p, mut t) = (0, 0);
+    // position in the pattern following the last `*` and the position in the text it matched up to
+    let mut star = None;
+    while t < text.len() {
+        if pattern.get(p) == Some(&b'*') {
+            p += 1;
+            star = Some((p, t));
+        } else if pattern.get(p) == Some(&text[t]) {
+            p += 1;
+            t += 1;
+        } else if let Some((star_p, star_t)) = star {
+            p = star_p;
+            t = star_t + 1;
+            star = Some((star_p, t));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|&b| b == b'*')
+}
+
+/// Decode percent-encoded bytes of `s`, leaving invalid escapes as they are
+fn percent_decode(s: &str) -> Cow<'_, str> {
+    if !s.contains('%') {
+        return Cow::Borrowed(s);
+    }
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = || {
+            let digits = core::str::from_utf8(bytes.get(i + 1..i + 3)?).ok()?;
+            u8::from_str_radix(digits, 16).ok()
+        };
+        match (bytes[i], hex()) {
+            (b'%', Some(b)) => {
+                out.push(b);
+                i += 3;
+            }
+            (b, _) => {
+                out.push(b);
+                i += 1;
+            }
+        }
+    }
+    Cow::Owned(String::from_utf8_lossy(&out).into_owned())
+}
+
+/// A subscriber dropping spans and events more verbose than `level` and passing all others to
+/// `inner`
+struct LevelSubscriber {
+    inner: Dispatch,
+    level: LevelFilter,
+}
+
+impl Subscriber for LevelSubscriber {
+    fn register_callsite(&self, metadata: &'static Metadata<'static>) -> Interest {
+        if self.inner.register_callsite(metadata).is_never() {
+            Interest::never()
+        } else {
+            // callsite interest is shared by all subscribers, so it has to be checked per span
+            // or event for this one to apply its level
+            Interest::sometimes()
+        }
+    }
+
+    fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+        *metadata.level() <= self.level && self.inner.enabled(metadata)
+    }
+
+    fn max_level_hint(&self) -> Option<LevelFilter> {
+        Some(self.level)
+    }
+
+    fn new_span(&self, span: &Attributes<'_>) -> Id {
+        self.inner.new_span(span)
+    }
+
+    fn record(&self, span: &Id, values: &Record<'_>) {
+        self.inner.record(span, values);
+    }
+
+    fn record_follows_from(&self, span: &Id, follows: &Id) {
+        self.inner.record_follows_from(span, follows);
+    }
+
+    fn event(&self, event: &Event<'_>) {
+        self.inner.event(event);
+    }
+
+    fn enter(&self, span: &Id) {
+        self.inner.enter(span);
+    }
+
+    fn exit(&self, span: &Id) {
+        self.inner.exit(span);
+    }
+
+    fn clone_span(&self, id: &Id) -> Id {
+        self.inner.clone_span(id)
+    }
+
+    fn try_close(&self, id: Id) -> bool {
+        self.inner.try_close(id)
+    }
+
+    fn current_span(&self) -> Current {
+        self.inner.current_span()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    use tracing::{debug, info};
+
+    use super::*;
+
+    /// A subscriber counting events
+    struct Counter(Arc<AtomicUsize>);
+
+    impl Subscriber for Counter {
+        fn enabled(&self, _: &Metadata<'_>) -> bool {
+            true
+        }
+
+        fn new_span(&self, _: &Attributes<'_>) -> Id {
+            Id::from_u64(1)
+        }
+
+        fn record(&self, _: &Id, _: &Record<'_>) {}
+
+        fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+        fn event(&self, _: &Event<'_>) {
+            self.0.fetch_add(1, Ordering::Relaxed);
+        }
+
+        fn enter(&self, _: &Id) {}
+
+        fn exit(&self, _: &Id) {}
+    }
+
+    fn count_events(level: LevelFilter, sample_rate: f64) -> usize {
+        let n = Arc::default();
+        tracing::subscriber::with_default(Counter(Arc::clone(&n)), || {
+            let logging = LinkLogging::new(level, sample_rate);
+            tracing::dispatcher::with_default(&logging.dispatch(), || {
+                debug!("debug");
+                info!("info");
+            });
+        });
+        n.load(Ordering::Relaxed)
+    }
+
+    #[test]
+    fn link_levels() {
+        assert_eq!(count_events(LevelFilter::DEBUG, 1.0), 2);
+        // requests, which are not sampled, are logged at `info` level at most
+        assert_eq!(count_events(LevelFilter::DEBUG, 0.0), 1);
+        assert_eq!(count_events(LevelFilter::ERROR, 1.0), 0);
+    }
+
+    #[test]
+    fn key_redaction() {
+        assert!(glob_matches("users/*/private/*", "users/1/private/a.txt"));
+        assert!(glob_matches("*.key", "a/b.key"));
+        assert!(glob_matches("*", ""));
+        assert!(!glob_matches("users/*/private/*", "users/1/public/a.txt"));
+        assert!(!glob_matches("*.key", "a.key.txt"));
+
+        let redaction = KeyRedaction::new(vec!["users/*/private/*".into()]);
+        assert_eq!(
+            redaction.redact_uri(
+                "http://localhost:9000/bucket/users/1/private/a%20b.txt?x-id=GetObject"
+            ),
+            "http://localhost:9000/bucket/<redacted>?x-id=GetObject"
+        );
+        assert_eq!(
+            redaction.redact_uri("https://bucket.s3.amazonaws.com/users/1/private/a"),
+            "https://bucket.s3.amazonaws.com/<redacted>"
+        );
+        assert_eq!(
+            redaction
+                .redact_uri("http://localhost/bucket?list-type=2&prefix=users%2F1%2Fprivate%2F"),
+            "http://localhost/bucket?list-type=2&prefix=<redacted>"
+        );
+        assert_eq!(
+            redaction.redact_uri("http://localhost/bucket/users/1/public/a"),
+            "http://localhost/bucket/users/1/public/a"
+        );
+        assert_eq!(
+            redaction.redact_value("/bucket/users/2/private/b"),
+            "<redacted>"
+        );
+        assert_eq!(
+            KeyRedaction::default().redact_uri("http://localhost/bucket/users/1/private/a"),
+            "http://localhost/bucket/users/1/private/a"
+        );
+    }
+}
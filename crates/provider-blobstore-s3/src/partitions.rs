@@ -0,0 +1,113 @@
+//! Time partitions of keys laid out by date, like `logs/2024/03/05/<key>`, the prefixes of which
+//! are listed instead of whole containers to find the objects of a time window
+
+use std::time::SystemTime;
+
+/// Seconds per day
+const DAY_SECS: u64 = 24 * 60 * 60;
+
+/// Prefixes of the partitions (`yyyy/mm/dd/`) of the UTC days from `since` to `until`, inclusive,
+/// in chronological order
+///
+/// Whole years and months within the window are covered by a single prefix (`yyyy/` or
+/// `yyyy/mm/`), so that a window of a year is listed with one request rather than 365.
+pub(crate) fn window_prefixes(since: SystemTime, until: SystemTime) -> Vec<String> {
+    let day = |time: SystemTime| {
+        time.duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs()
+            / DAY_SECS
+    };
+    let last = day(until);
+    let mut prefixes = Vec::new();
+    let mut n = day(since);
+    while n <= last {
+        let (y, m, d) = civil_date(n);
+        let year = if is_leap_year(y) { 366 } else { 365 };
+        let month = days_in_month(y, m);
+        if m == 1 && d == 1 && n + year - 1 <= last {
+            prefixes.push(format!("{y:04}/"));
+            n += year;
+        } else if d == 1 && n + month - 1 <= last {
+            prefixes.push(format!("{y:04}/{m:02}/"));
+            n += month;
+        } else {
+            prefixes.push(format!("{y:04}/{m:02}/{d:02}/"));
+            n += 1;
+        }
+    }
+    prefixes
+}
+
+/// Year, month and day of the `n`th day since the Unix epoch
+fn civil_date(n: u64) -> (u64, u64, u64) {
+    // http://howardhinnant.github.io/date_algorithms.html#civil_from_days, with days counted
+    // from 0000-03-01, so that leap days end years
+    let z = n + 719_468;
+    let era = z / 146_097;
+    let doe = z % 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = doy - (153 * mp + 2) / 5 + 1;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 };
+    let y = yoe + era * 400 + u64::from(m <= 2);
+    (y, m, d)
+}
+
+fn is_leap_year(y: u64) -> bool {
+    y.is_multiple_of(4) && (!y.is_multiple_of(100) || y.is_multiple_of(400))
+}
+
+fn days_in_month(y: u64, m: u64) -> u64 {
+    match m {
+        2 if is_leap_year(y) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use core::time::Duration;
+
+    /// Time of the UTC midnight starting `n` days since the Unix epoch, plus `secs`
+    fn at(n: u64, secs: u64) -> SystemTime {
+        SystemTime::UNIX_EPOCH + Duration::from_secs(n * DAY_SECS + secs)
+    }
+
+    #[test]
+    fn civil_dates() {
+        assert_eq!(civil_date(0), (1970, 1, 1));
+        assert_eq!(civil_date(19_787), (2024, 3, 5));
+        assert_eq!(civil_date(19_782), (2024, 2, 29));
+        assert_eq!(civil_date(11_016), (2000, 2, 29));
+    }
+
+    #[test]
+    fn prefixes() {
+        // 2024-03-05 to 2024-03-07
+        assert_eq!(
+            window_prefixes(at(19_787, 3600), at(19_789, 7200)),
+            ["2024/03/05/", "2024/03/06/", "2024/03/07/"]
+        );
+        assert_eq!(
+            window_prefixes(at(19_787, 0), at(19_787, DAY_SECS - 1)),
+            ["2024/03/05/"]
+        );
+        assert!(window_prefixes(at(19_789, 0), at(19_787, 0)).is_empty());
+        // 2024-02-28 to 2024-04-01, through the leap day
+        assert_eq!(
+            window_prefixes(at(19_781, 0), at(19_814, 0)),
+            ["2024/02/28/", "2024/02/29/", "2024/03/", "2024/04/01/"]
+        );
+        // 2023-12-31 to 2025-01-31
+        assert_eq!(
+            window_prefixes(at(19_722, 0), at(20_119, 0)),
+            ["2023/12/31/", "2024/", "2025/01/"]
+        );
+    }
+}
@@ -0,0 +1,87 @@
+//! Workarounds for S3-compatible stores deviating from S3, enabled by the `backend_profile` of
+//! a link
+
+use crate::config::BackendProfile;
+
+/// Default size of the parts of resumable uploads
+pub(crate) const DEFAULT_PART_SIZE: usize = 8 * 1024 * 1024;
+
+/// Minimum size of all but the last part of multipart uploads
+pub(crate) const MIN_PART_SIZE: usize = 5 * 1024 * 1024;
+
+/// Maximum size of parts of multipart uploads
+pub(crate) const MAX_PART_SIZE: usize = 5 * 1024 * 1024 * 1024;
+
+/// APIs and semantics of S3 supported by a store
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct Quirks {
+    /// Whether multi-object deletes (`DeleteObjects`) are supported, which are emulated by
+    /// concurrent single deletes otherwise
+    pub(crate) batch_delete: bool,
+    /// Whether the ETags of all objects not uploaded in parts are the MD5 digests of their data,
+    /// so that copies can be verified by comparing them
+    pub(crate) md5_etags: bool,
+    /// Whether public access blocks and the policy status of buckets are supported
+    pub(crate) public_access_block: bool,
+    /// Whether object tagging is supported, which expiring objects relies on
+    pub(crate) object_tagging: bool,
+    /// Whether object lock, and thereby legal holds and governance retention, is supported
+    pub(crate) object_lock: bool,
+    /// Default size of the parts of resumable uploads
+    pub(crate) part_size: usize,
+    /// Whether listings may not include recently written objects, so that they are retried
+    /// unless configured otherwise
+    pub(crate) eventually_consistent_listings: bool,
+    /// Whether writes should carry a SHA-1 checksum, which the store verifies and keeps
+    pub(crate) sha1_checksums: bool,
+}
+
+impl Quirks {
+    pub(crate) fn new(profile: BackendProfile) -> Self {
+        let s3 = Self {
+            batch_delete: true,
+            md5_etags: true,
+            public_access_block: true,
+            object_tagging: true,
+            object_lock: true,
+            part_size: DEFAULT_PART_SIZE,
+            eventually_consistent_listings: false,
+            sha1_checksums: false,
+        };
+        match profile {
+            BackendProfile::Aws | BackendProfile::CephRgw => s3,
+            BackendProfile::Minio => Self {
+                public_access_block: false,
+                ..s3
+            },
+            // large files uploaded via the B2 native API have no MD5 ETag, while B2 stores the
+            // SHA-1 digest of all files
+            BackendProfile::B2 => Self {
+                md5_etags: false,
+                public_access_block: false,
+                object_tagging: false,
+                eventually_consistent_listings: true,
+                sha1_checksums: true,
+                ..s3
+            },
+            // uploaded parts are billed per request, so larger parts make uploads cheaper
+            BackendProfile::R2 => Self {
+                batch_delete: false,
+                public_access_block: false,
+                object_tagging: false,
+                object_lock: false,
+                part_size: 16 * 1024 * 1024,
+                ..s3
+            },
+            // composite objects have CRC32C-based ETags
+            BackendProfile::Gcs => Self {
+                batch_delete: false,
+                md5_etags: false,
+                public_access_block: false,
+                object_tagging: false,
+                object_lock: false,
+                ..s3
+            },
+        }
+    }
+}
@@ -0,0 +1,68 @@
+//! Reports of the objects of buckets, e.g. the largest or least recently modified ones, computed
+//! while listing them, so that only the reported objects are kept rather than whole listings
+
+use core::cmp::Reverse;
+
+use std::collections::BinaryHeap;
+
+/// The `n` greatest of the items pushed into it
+#[derive(Debug)]
+pub(crate) struct Top<T> {
+    n: usize,
+    /// Min-heap of the greatest items, the least of which is replaced by greater ones
+    heap: BinaryHeap<Reverse<T>>,
+}
+
+impl<T: Ord> Top<T> {
+    pub(crate) fn new(n: usize) -> Self {
+        Self {
+            n,
+            heap: BinaryHeap::with_capacity(n),
+        }
+    }
+
+    /// Keep `item` if it is among the `n` greatest items pushed so far
+    pub(crate) fn push(&mut self, item: T) {
+        if self.heap.len() < self.n {
+            self.heap.push(Reverse(item));
+        } else if self.heap.peek().is_some_and(|Reverse(least)| item > *least) {
+            self.heap.pop();
+            self.heap.push(Reverse(item));
+        }
+    }
+
+    /// The greatest items, in descending order
+    pub(crate) fn into_sorted_vec(self) -> Vec<T> {
+        // sorted ascending by `Reverse`, i.e. descending by item
+        self.heap
+            .into_sorted_vec()
+            .into_iter()
+            .map(|Reverse(item)| item)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn top() {
+        let mut top = Top::new(3);
+        for n in [5, 1, 9, 3, 7, 9, 2] {
+            top.push(n);
+        }
+        assert_eq!(top.into_sorted_vec(), [9, 9, 7]);
+
+        let mut top = Top::new(0);
+        top.push(1);
+        assert!(top.into_sorted_vec().is_empty());
+
+        // the least items, by reversing their order
+        let mut top = Top::new(2);
+        for n in [5, 1, 9, 3] {
+            top.push(Reverse(n));
+        }
+        assert_eq!(top.into_sorted_vec(), [Reverse(1), Reverse(3)]);
+    }
+}
@@ -0,0 +1,145 @@
+//! Routing of the objects of containers to the buckets they are stored in
+//!
+//! Links route objects by the [`PrefixRouter`] and [`ShardRouter`] built from their configuration,
+//! embedders reusing [`StorageClient`](crate::StorageClient) can replace them with their own
+//! [`Router`] using [`StorageClient::with_router`](crate::StorageClient::with_router). Size tiers
+//! apply to the bucket chosen by the router.
+
+use std::cmp::Reverse;
+use std::collections::HashMap;
+
+use anyhow::ensure;
+
+use crate::config::PrefixRoute;
+
+/// Policy choosing the bucket each object of a container is stored in
+pub trait Router: Send + Sync {
+    /// Bucket the object `key` of `container` is stored in, `None` if it is stored in `container`
+    fn route<'a>(&'a self, container: &str, key: &str) -> Option<&'a str>;
+
+    /// All buckets objects of `container` may be routed to, including `container` itself if
+    /// objects may remain in it, which is empty if the router does not route `container`
+    ///
+    /// Listings merge the objects of these buckets and creating `container` creates all of them.
+    fn buckets<'a>(&'a self, container: &'a str) -> Vec<&'a str>;
+}
+
+/// Routers consulted in order, the first one routing an object wins
+#[derive(Default)]
+pub struct Routers(Vec<Box<dyn Router>>);
+
+impl Routers {
+    /// Consult `router` after all routers added before it
+    #[must_use]
+    pub fn with(mut self, router: impl Router + 'static) -> Self {
+        self.0.push(Box::new(router));
+        self
+    }
+}
+
+impl Router for Routers {
+    fn route<'a>(&'a self, container: &str, key: &str) -> Option<&'a str> {
+        self.0
+            .iter()
+            .find_map(|router| router.route(container, key))
+    }
+
+    fn buckets<'a>(&'a self, container: &'a str) -> Vec<&'a str> {
+        let mut buckets: Vec<_> = self
+            .0
+            .iter()
+            .flat_map(|router| router.buckets(container))
+            .collect();
+        buckets.sort_unstable();
+        buckets.dedup();
+        buckets
+    }
+}
+
+/// Router storing objects with keys matching a prefix in another bucket under their original
+/// keys, the longest matching prefix wins
+#[derive(Clone, Debug, Default)]
+pub struct PrefixRouter(HashMap<String, Vec<PrefixRoute>>);
+
+impl PrefixRouter {
+    pub fn new(mut routes: HashMap<String, Vec<PrefixRoute>>) -> anyhow::Result<Self> {
+        for (container, rules) in &mut routes {
+            for PrefixRoute { bucket, .. } in rules.iter() {
+                ensure!(
+                    bucket != container,
+                    "prefix routes of `{container}` must route to other buckets"
+                );
+            }
+            rules.sort_by_key(|rule| Reverse(rule.prefix.len()));
+        }
+        Ok(Self(routes))
+    }
+}
+
+impl Router for PrefixRouter {
+    fn route<'a>(&'a self, container: &str, key: &str) -> Option<&'a str> {
+        self.0
+            .get(container)?
+            .iter()
+            .find(|route| key.starts_with(&route.prefix))
+            .map(|route| route.bucket.as_str())
+    }
+
+    fn buckets<'a>(&'a self, container: &'a str) -> Vec<&'a str> {
+        let Some(routes) = self.0.get(container) else {
+            return Vec::default();
+        };
+        let mut buckets: Vec<_> = routes.iter().map(|route| route.bucket.as_str()).collect();
+        buckets.push(container);
+        buckets.sort_unstable();
+        buckets.dedup();
+        buckets
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::ShardRouter;
+
+    fn route(prefix: &str, bucket: &str) -> PrefixRoute {
+        PrefixRoute {
+            prefix: prefix.into(),
+            bucket: bucket.into(),
+        }
+    }
+
+    #[test]
+    fn routers() {
+        let prefixes = PrefixRouter::new(HashMap::from([(
+            "media".to_string(),
+            vec![route("video/", "videos"), route("video/raw/", "raw")],
+        )]))
+        .expect("failed to create prefix router");
+        let shards = ShardRouter::new(HashMap::from([(
+            "media".to_string(),
+            vec!["media-0".to_string(), "media-1".to_string()],
+        )]))
+        .expect("failed to create shard router");
+        let router = Routers::default().with(prefixes).with(shards);
+
+        assert_eq!(router.route("media", "video/raw/a.mp4"), Some("raw"));
+        assert_eq!(router.route("media", "video/a.mp4"), Some("videos"));
+        assert!(matches!(
+            router.route("media", "image/a.png"),
+            Some("media-0" | "media-1")
+        ));
+        assert_eq!(router.route("other", "video/a.mp4"), None);
+        assert_eq!(
+            router.buckets("media"),
+            ["media", "media-0", "media-1", "raw", "videos"]
+        );
+        assert!(router.buckets("other").is_empty());
+
+        assert!(PrefixRouter::new(HashMap::from([(
+            "media".to_string(),
+            vec![route("video/", "media")]
+        )]))
+        .is_err());
+    }
+}
@@ -0,0 +1,167 @@
+//! Scanning of objects written by components with ClamAV, whose daemon (clamd) is streamed the
+//! data of objects as it is written, using the `INSTREAM` command
+//!
+//! Objects are written to a quarantine prefix and only moved to their key once scanned clean, so
+//! that infected objects are never readable at their key.
+
+use core::pin::Pin;
+use core::time::Duration;
+
+use anyhow::{bail, Context as _};
+use bytes::Bytes;
+use futures::{Stream, StreamExt as _};
+use tokio::io::{AsyncReadExt as _, AsyncWriteExt as _};
+use tokio::net::TcpStream;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tracing::{debug, warn};
+
+use crate::config::AntivirusConfig;
+
+/// Prefix of quarantined objects, unless configured
+const DEFAULT_QUARANTINE_PREFIX: &str = "quarantine/";
+/// Time clamd is given to reply once all data was streamed to it, unless configured
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+/// Maximum size of the chunks of `INSTREAM`
+const MAX_CHUNK_SIZE: usize = 64 * 1024;
+/// Number of chunks buffered while clamd is busy, before writes wait for it
+const BUFFERED_CHUNKS: usize = 16;
+
+/// Outcome of a scan
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) enum Verdict {
+    Clean,
+    /// Infected with the signature
+    Infected(String),
+}
+
+/// Scanner of the objects written by a link
+#[derive(Clone, Debug)]
+pub(crate) struct Scanner {
+    address: String,
+    quarantine_prefix: String,
+    timeout: Duration,
+}
+
+impl Scanner {
+    pub(crate) fn new(
+        AntivirusConfig {
+            address,
+            quarantine_prefix,
+            timeout_ms,
+        }: AntivirusConfig,
+    ) -> Self {
+        Self {
+            address,
+            quarantine_prefix: quarantine_prefix
+                .unwrap_or_else(|| DEFAULT_QUARANTINE_PREFIX.to_string()),
+            timeout: timeout_ms.map_or(DEFAULT_TIMEOUT, Duration::from_millis),
+        }
+    }
+
+    /// Key an object is written to until it is scanned clean, and remains at if infected
+    pub(crate) fn quarantine_key(&self, key: &str) -> String {
+        format!("{}{key}", self.quarantine_prefix)
+    }
+
+    /// Scan data as it is consumed, returning the data and the scan, which completes once the data
+    /// was consumed entirely
+    pub(crate) fn tee(
+        &self,
+        data: impl Stream<Item = Bytes> + Send + 'static,
+    ) -> (Pin<Box<dyn Stream<Item = Bytes> + Send>>, Scan) {
+        let (tx, rx) = mpsc::channel(BUFFERED_CHUNKS);
+        let task = tokio::spawn(scan(self.address.clone(), self.timeout, rx));
+        let data = data.then(move |chunk| {
+            let tx = tx.clone();
+            async move {
+                // failures of the scan are reported by its outcome
+                let _ = tx.send(chunk.clone()).await;
+                chunk
+            }
+        });
+        (Box::pin(data), Scan { task })
+    }
+}
+
+/// Scan of data streamed to clamd
+#[derive(Debug)]
+pub(crate) struct Scan {
+    task: JoinHandle<anyhow::Result<Verdict>>,
+}
+
+impl Scan {
+    /// Outcome of the scan, once the data was consumed
+    pub(crate) async fn verdict(self) -> anyhow::Result<Verdict> {
+        self.task.await.context("scan task panicked")?
+    }
+}
+
+/// Stream the chunks received by `rx` to clamd at `address`, until the channel is closed
+async fn scan(
+    address: String,
+    timeout: Duration,
+    mut rx: mpsc::Receiver<Bytes>,
+) -> anyhow::Result<Verdict> {
+    let mut conn = TcpStream::connect(&address)
+        .await
+        .with_context(|| format!("failed to connect to clamd at `{address}`"))?;
+    conn.write_all(b"zINSTREAM\0")
+        .await
+        .context("failed to start scan")?;
+    let mut size = 0;
+    while let Some(chunk) = rx.recv().await {
+        for chunk in chunk.chunks(MAX_CHUNK_SIZE) {
+            let len = u32::try_from(chunk.len()).context("chunk is too large")?;
+            conn.write_all(&len.to_be_bytes())
+                .await
+                .context("failed to stream data to clamd")?;
+            conn.write_all(chunk)
+                .await
+                .context("failed to stream data to clamd")?;
+            size += chunk.len();
+        }
+    }
+    conn.write_all(&[0; 4])
+        .await
+        .context("failed to stream data to clamd")?;
+    let mut reply = Vec::new();
+    tokio::time::timeout(timeout, conn.read_to_end(&mut reply))
+        .await
+        .context("clamd did not reply in time")?
+        .context("failed to read reply of clamd")?;
+    let verdict = parse_reply(&String::from_utf8_lossy(&reply))?;
+    match &verdict {
+        Verdict::Clean => debug!(size, "scanned clean"),
+        Verdict::Infected(signature) => warn!(size, signature, "scanned infected"),
+    }
+    Ok(verdict)
+}
+
+/// Parse the reply of clamd to `INSTREAM`, e.g. `stream: OK` or `stream: Eicar-Signature FOUND`
+fn parse_reply(reply: &str) -> anyhow::Result<Verdict> {
+    let reply = reply.trim_end_matches(['\0', '\n']);
+    let result = reply.strip_prefix("stream: ").unwrap_or(reply);
+    if result == "OK" {
+        Ok(Verdict::Clean)
+    } else if let Some(signature) = result.strip_suffix(" FOUND") {
+        Ok(Verdict::Infected(signature.to_string()))
+    } else {
+        bail!("clamd failed to scan data: {result}")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn replies() {
+        assert_eq!(parse_reply("stream: OK\0").unwrap(), Verdict::Clean);
+        assert_eq!(
+            parse_reply("stream: Win.Test.EICAR_HDB-1 FOUND\0").unwrap(),
+            Verdict::Infected("Win.Test.EICAR_HDB-1".into())
+        );
+        assert!(parse_reply("INSTREAM size limit exceeded. ERROR\0").is_err());
+    }
+}
@@ -0,0 +1,130 @@
+//! Consistent hashing of keys onto the buckets of sharded aliases, so that hot prefixes are
+//! spread across buckets, each of which has its own request rate limits
+//!
+//! Each bucket is placed on a hash ring at [`VIRTUAL_NODES`] points and keys are stored in the
+//! bucket of the first point following their hash, so that adding a bucket only moves the keys
+//! of roughly one in every `n + 1` points to it.
+
+use std::collections::HashMap;
+
+use anyhow::{ensure, Context as _};
+use sha2::{Digest as _, Sha256};
+
+use crate::routing::Router;
+
+/// Number of points of each bucket on the ring
+const VIRTUAL_NODES: usize = 128;
+
+/// Router distributing the objects of sharded aliases across their buckets
+#[derive(Clone, Debug, Default)]
+pub struct ShardRouter(HashMap<String, ShardRing>);
+
+impl ShardRouter {
+    pub fn new(shards: HashMap<String, Vec<String>>) -> anyhow::Result<Self> {
+        shards
+            .into_iter()
+            .map(|(alias, buckets)| {
+                let ring = ShardRing::new(buckets)
+                    .with_context(|| format!("invalid sharded alias `{alias}`"))?;
+                anyhow::Ok((alias, ring))
+            })
+            .collect::<anyhow::Result<_>>()
+            .map(Self)
+    }
+}
+
+impl Router for ShardRouter {
+    fn route<'a>(&'a self, container: &str, key: &str) -> Option<&'a str> {
+        self.0.get(container).map(|ring| ring.bucket(key))
+    }
+
+    fn buckets<'a>(&'a self, container: &'a str) -> Vec<&'a str> {
+        self.0
+            .get(container)
+            .map(|ring| ring.buckets().iter().map(String::as_str).collect())
+            .unwrap_or_default()
+    }
+}
+
+/// Hash ring of the buckets of a sharded alias
+#[derive(Clone, Debug)]
+pub(crate) struct ShardRing {
+    /// Points on the ring and the indices of their buckets, ordered by hash
+    points: Vec<(u64, usize)>,
+    buckets: Vec<String>,
+}
+
+impl ShardRing {
+    pub(crate) fn new(mut buckets: Vec<String>) -> anyhow::Result<Self> {
+        buckets.sort();
+        buckets.dedup();
+        ensure!(
+            !buckets.is_empty(),
+            "sharded aliases require at least one bucket"
+        );
+        let mut points: Vec<_> = buckets
+            .iter()
+            .enumerate()
+            .flat_map(|(i, bucket)| {
+                (0..VIRTUAL_NODES).map(move |node| (hash(&format!("{bucket}#{node}")), i))
+            })
+            .collect();
+        points.sort_unstable();
+        Ok(Self { points, buckets })
+    }
+
+    /// Bucket the object `key` is stored in
+    pub(crate) fn bucket(&self, key: &str) -> &str {
+        let h = hash(key);
+        let i = self.points.partition_point(|(point, _)| *point < h);
+        let (_, bucket) = self.points[i % self.points.len()];
+        &self.buckets[bucket]
+    }
+
+    /// All buckets of the alias
+    pub(crate) fn buckets(&self) -> &[String] {
+        &self.buckets
+    }
+}
+
+/// Hash of `data`, which must not change between versions of the provider, since it determines
+/// the buckets objects are stored in
+fn hash(data: &str) -> u64 {
+    let digest = Sha256::digest(data.as_bytes());
+    let mut bytes = [0; 8];
+    bytes.copy_from_slice(&digest[..8]);
+    u64::from_be_bytes(bytes)
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    #[test]
+    fn shard_rings() {
+        let buckets = |n: usize| (0..n).map(|i| format!("shard-{i}")).collect::<Vec<_>>();
+        let four = ShardRing::new(buckets(4)).expect("failed to create ring");
+        let five = ShardRing::new(buckets(5)).expect("failed to create ring");
+        let keys: Vec<_> = (0..10_000).map(|i| format!("hot/{i}")).collect();
+
+        let mut counts = HashMap::<_, usize>::new();
+        for key in &keys {
+            *counts.entry(four.bucket(key)).or_default() += 1;
+        }
+        assert_eq!(counts.len(), 4);
+        assert!(counts.values().all(|&n| (1_500..=3_500).contains(&n)));
+
+        // adding a bucket only moves keys to the new bucket
+        let moved: Vec<_> = keys
+            .iter()
+            .filter(|key| four.bucket(key) != five.bucket(key))
+            .collect();
+        assert!(moved.iter().all(|key| five.bucket(key) == "shard-4"));
+        assert!(moved.len() < 3_000);
+
+        assert_eq!(four.bucket("key"), four.bucket("key"));
+        assert!(ShardRing::new(Vec::new()).is_err());
+    }
+}
@@ -0,0 +1,191 @@
+//! Sniffing of the content type of objects written by components from their leading bytes
+//! (magic numbers), which links record, compare to the extension of keys or reject
+
+use core::pin::Pin;
+
+use anyhow::bail;
+use bytes::{Bytes, BytesMut};
+use futures::{stream, Stream, StreamExt as _};
+use tracing::{debug, warn};
+
+use crate::config::ContentSniffingConfig;
+use crate::error::{BackendError, ErrorKind};
+
+/// Number of leading bytes of objects content types are sniffed from
+const SNIFF_BYTES: usize = 512;
+
+/// Content types and the magic numbers identifying them at an offset
+const MAGIC: &[(&str, usize, &[u8])] = &[
+    ("image/png", 0, b"\x89PNG\r\n\x1a\n"),
+    ("image/jpeg", 0, b"\xff\xd8\xff"),
+    ("image/gif", 0, b"GIF87a"),
+    ("image/gif", 0, b"GIF89a"),
+    ("image/webp", 8, b"WEBP"),
+    ("image/bmp", 0, b"BM"),
+    ("image/tiff", 0, b"II*\0"),
+    ("image/tiff", 0, b"MM\0*"),
+    ("audio/wav", 8, b"WAVE"),
+    ("audio/mpeg", 0, b"ID3"),
+    ("audio/flac", 0, b"fLaC"),
+    ("audio/ogg", 0, b"OggS"),
+    ("video/mp4", 4, b"ftyp"),
+    ("application/pdf", 0, b"%PDF-"),
+    ("application/zip", 0, b"PK\x03\x04"),
+    ("application/gzip", 0, b"\x1f\x8b"),
+    ("application/x-bzip2", 0, b"BZh"),
+    ("application/x-xz", 0, b"\xfd7zXZ\0"),
+    ("application/zstd", 0, b"\x28\xb5\x2f\xfd"),
+    ("application/x-7z-compressed", 0, b"7z\xbc\xaf\x27\x1c"),
+    ("application/x-tar", 257, b"ustar"),
+    ("application/wasm", 0, b"\0asm"),
+    ("application/x-executable", 0, b"\x7fELF"),
+    ("application/x-msdownload", 0, b"MZ"),
+    ("application/x-mach-binary", 0, b"\xcf\xfa\xed\xfe"),
+    ("application/x-mach-binary", 0, b"\xce\xfa\xed\xfe"),
+    ("text/x-shellscript", 0, b"#!"),
+];
+
+/// Extensions of keys and the content types sniffed from objects with them
+const EXTENSIONS: &[(&str, &str)] = &[
+    ("png", "image/png"),
+    ("jpg", "image/jpeg"),
+    ("jpeg", "image/jpeg"),
+    ("gif", "image/gif"),
+    ("webp", "image/webp"),
+    ("bmp", "image/bmp"),
+    ("tif", "image/tiff"),
+    ("tiff", "image/tiff"),
+    ("wav", "audio/wav"),
+    ("mp3", "audio/mpeg"),
+    ("flac", "audio/flac"),
+    ("ogg", "audio/ogg"),
+    ("mp4", "video/mp4"),
+    ("pdf", "application/pdf"),
+    // formats based on ZIP are sniffed as such
+    ("zip", "application/zip"),
+    ("jar", "application/zip"),
+    ("docx", "application/zip"),
+    ("xlsx", "application/zip"),
+    ("pptx", "application/zip"),
+    ("gz", "application/gzip"),
+    ("tgz", "application/gzip"),
+    ("bz2", "application/x-bzip2"),
+    ("xz", "application/x-xz"),
+    ("zst", "application/zstd"),
+    ("7z", "application/x-7z-compressed"),
+    ("tar", "application/x-tar"),
+    ("wasm", "application/wasm"),
+    ("exe", "application/x-msdownload"),
+    ("dll", "application/x-msdownload"),
+    ("sh", "text/x-shellscript"),
+];
+
+/// Sniff the content type of data from its leading bytes, `None` if it is not recognized
+pub(crate) fn sniff(head: &[u8]) -> Option<&'static str> {
+    MAGIC.iter().find_map(|(content_type, offset, magic)| {
+        head.get(*offset..)?
+            .starts_with(magic)
+            .then_some(*content_type)
+    })
+}
+
+/// Content type objects with the extension of `key` are expected to have, `None` if the
+/// extension is not known
+fn expected(key: &str) -> Option<&'static str> {
+    let (name, ext) = key.rsplit_once('.')?;
+    if name.is_empty() || name.ends_with('/') || ext.contains('/') {
+        return None;
+    }
+    EXTENSIONS
+        .iter()
+        .find(|(known, _)| known.eq_ignore_ascii_case(ext))
+        .map(|(_, content_type)| *content_type)
+}
+
+/// Read the leading `n` bytes of data, or all of it if shorter, returning them along with the
+/// data, of which they were read
+pub(crate) async fn peek(
+    mut data: Pin<Box<dyn Stream<Item = Bytes> + Send>>,
+    n: usize,
+) -> (Bytes, Pin<Box<dyn Stream<Item = Bytes> + Send>>) {
+    let mut chunks = Vec::new();
+    let mut head = BytesMut::new();
+    while head.len() < n {
+        let Some(chunk) = data.next().await else {
+            break;
+        };
+        head.extend_from_slice(&chunk);
+        chunks.push(chunk);
+    }
+    (head.freeze(), Box::pin(stream::iter(chunks).chain(data)))
+}
+
+/// Sniff the content type of an object about to be written by a link and enforce its policy,
+/// failing if the content type is denied
+///
+/// Returns the data of the object and the content type to record, if any.
+pub(crate) async fn enforce(
+    policy: &ContentSniffingConfig,
+    bucket: &str,
+    key: &str,
+    data: Pin<Box<dyn Stream<Item = Bytes> + Send>>,
+) -> anyhow::Result<(
+    Pin<Box<dyn Stream<Item = Bytes> + Send>>,
+    Option<&'static str>,
+)> {
+    let (head, data) = peek(data, SNIFF_BYTES).await;
+    let Some(content_type) = sniff(&head) else {
+        debug!(bucket, key, "content type not recognized");
+        return Ok((data, None));
+    };
+    if policy.denies(content_type) {
+        bail!(BackendError::new(
+            ErrorKind::PermissionDenied,
+            format!("object [{bucket}/{key}] has denied content type `{content_type}`")
+        ))
+    }
+    if policy.warn_on_mismatch {
+        match expected(key) {
+            Some(expected) if expected != content_type => {
+                warn!(
+                    bucket,
+                    key, content_type, expected, "content type does not match key"
+                );
+            }
+            _ => {}
+        }
+    }
+    Ok((data, policy.record.then_some(content_type)))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn magic_numbers() {
+        assert_eq!(sniff(b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR"), Some("image/png"));
+        assert_eq!(sniff(b"RIFF\0\0\0\0WEBPVP8 "), Some("image/webp"));
+        assert_eq!(
+            sniff(b"\x7fELF\x02\x01\x01"),
+            Some("application/x-executable")
+        );
+        assert_eq!(sniff(b"MZ\x90\0"), Some("application/x-msdownload"));
+        assert_eq!(sniff(b"hello, world"), None);
+        assert_eq!(sniff(b""), None);
+
+        let mut tar = vec![0; 512];
+        tar[257..262].copy_from_slice(b"ustar");
+        assert_eq!(sniff(&tar), Some("application/x-tar"));
+    }
+
+    #[test]
+    fn extensions() {
+        assert_eq!(expected("photos/cat.JPG"), Some("image/jpeg"));
+        assert_eq!(expected("report.docx"), Some("application/zip"));
+        assert_eq!(expected("notes.txt"), None);
+        assert_eq!(expected(".png"), None);
+        assert_eq!(expected("dir.png/file"), None);
+        assert_eq!(expected("README"), None);
+    }
+}
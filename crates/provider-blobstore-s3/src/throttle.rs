@@ -0,0 +1,80 @@
+//! Bandwidth limits for data streamed between components and S3
+
+use core::time::Duration;
+
+use std::sync::Mutex;
+
+use tokio::time::Instant;
+
+/// A token bucket limiting the rate of transferred bytes, allowing bursts of up to a second
+/// worth of transfers
+#[derive(Debug)]
+pub(crate) struct TokenBucket {
+    /// Bytes per second
+    rate: f64,
+    state: Mutex<State>,
+}
+
+#[derive(Debug)]
+struct State {
+    /// Available bytes, negative if transfers exceeding the rate are waiting
+    tokens: f64,
+    updated: Instant,
+}
+
+impl TokenBucket {
+    pub(crate) fn new(bytes_per_sec: u64) -> Self {
+        let rate = bytes_per_sec.max(1) as f64;
+        Self {
+            rate,
+            state: Mutex::new(State {
+                tokens: rate,
+                updated: Instant::now(),
+            }),
+        }
+    }
+
+    /// Wait until `n` bytes may be transferred
+    pub(crate) async fn take(&self, n: usize) {
+        let delay = self.reserve(n, Instant::now());
+        if !delay.is_zero() {
+            tokio::time::sleep(delay).await;
+        }
+    }
+
+    /// Reserve `n` bytes at `now`, returning how long to wait before transferring them
+    fn reserve(&self, n: usize, now: Instant) -> Duration {
+        let mut state = self.state.lock().unwrap_or_else(|err| err.into_inner());
+        let elapsed = now.saturating_duration_since(state.updated).as_secs_f64();
+        state.tokens = (state.tokens + elapsed * self.rate).min(self.rate);
+        state.updated = now;
+        state.tokens -= n as f64;
+        if state.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-state.tokens / self.rate)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn token_bucket() {
+        let bucket = TokenBucket::new(1000);
+        let start = bucket.state.lock().unwrap().updated;
+        // the initial burst is allowed immediately
+        assert_eq!(bucket.reserve(1000, start), Duration::ZERO);
+        // further transfers wait until enough tokens are replenished
+        assert_eq!(bucket.reserve(500, start), Duration::from_millis(500));
+        assert_eq!(bucket.reserve(500, start), Duration::from_millis(1000));
+        // after waiting, the debt is paid off
+        let later = start + Duration::from_secs(3);
+        assert_eq!(bucket.reserve(1000, later), Duration::ZERO);
+        // bursts are limited to a second worth of transfers
+        let much_later = later + Duration::from_secs(60);
+        assert_eq!(bucket.reserve(1500, much_later), Duration::from_millis(500));
+    }
+}
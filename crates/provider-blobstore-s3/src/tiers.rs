@@ -0,0 +1,138 @@
+//! Routing of written objects to buckets and storage classes by their size
+//!
+//! Objects routed to a bucket other than the container they are written to are stored under
+//! `<container>/<key>`, so that containers sharing a tier bucket do not overwrite each other.
+
+use anyhow::{bail, ensure, Context as _};
+use aws_sdk_s3::types::StorageClass;
+
+use crate::config::SizeTier;
+
+/// Size tiers of a link, ordered by ascending maximum size
+#[derive(Clone, Debug, Default, PartialEq)]
+pub(crate) struct SizeTiers(Vec<Tier>);
+
+/// A size tier, objects of which are written to `bucket` with `storage_class`
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct Tier {
+    pub max_size: Option<u64>,
+    pub bucket: Option<String>,
+    pub storage_class: Option<StorageClass>,
+}
+
+impl SizeTiers {
+    /// Validate configured tiers, which must be ordered by ascending `max_size`, of which only
+    /// the last one may be unbounded
+    pub(crate) fn new(tiers: Vec<SizeTier>) -> anyhow::Result<Self> {
+        let mut previous = None;
+        for (i, SizeTier { max_size, .. }) in tiers.iter().enumerate() {
+            match (max_size, previous) {
+                (None, _) if i + 1 < tiers.len() => {
+                    bail!("only the last size tier may omit `max_size`")
+                }
+                (Some(max), Some(prev)) if *max <= prev => {
+                    bail!("size tiers must be ordered by ascending `max_size`")
+                }
+                _ => previous = *max_size,
+            }
+        }
+        tiers
+            .into_iter()
+            .map(
+                |SizeTier {
+                     max_size,
+                     bucket,
+                     storage_class,
+                 }| {
+                    let storage_class = storage_class
+                        .map(|class| {
+                            ensure!(
+                                StorageClass::values().contains(&class.as_str()),
+                                "unknown storage class `{class}`"
+                            );
+                            Ok(StorageClass::from(class.as_str()))
+                        })
+                        .transpose()
+                        .context("invalid size tier")?;
+                    Ok(Tier {
+                        max_size,
+                        bucket,
+                        storage_class,
+                    })
+                },
+            )
+            .collect::<anyhow::Result<_>>()
+            .map(Self)
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// The tier objects of `size` bytes are routed to, `None` if they are larger than all tiers
+    pub(crate) fn route(&self, size: u64) -> Option<&Tier> {
+        self.0
+            .iter()
+            .find(|tier| tier.max_size.is_none_or(|max| size <= max))
+    }
+
+    /// Buckets (or aliases) of all tiers, without duplicates
+    pub(crate) fn buckets(&self) -> Vec<&str> {
+        let mut buckets: Vec<_> = self
+            .0
+            .iter()
+            .filter_map(|tier| tier.bucket.as_deref())
+            .collect();
+        buckets.sort_unstable();
+        buckets.dedup();
+        buckets
+    }
+}
+
+/// Key of the object `key` of `container` in a tier bucket other than the container
+pub(crate) fn tiered_key(container: &str, key: &str) -> String {
+    format!("{container}/{key}")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn tier(max_size: Option<u64>, bucket: Option<&str>, storage_class: Option<&str>) -> SizeTier {
+        SizeTier {
+            max_size,
+            bucket: bucket.map(Into::into),
+            storage_class: storage_class.map(Into::into),
+        }
+    }
+
+    #[test]
+    fn size_tiers() {
+        let tiers = SizeTiers::new(vec![
+            tier(Some(128 * 1024), None, Some("STANDARD")),
+            tier(None, Some("large"), Some("STANDARD_IA")),
+        ])
+        .expect("failed to parse tiers");
+        assert_eq!(
+            tiers
+                .route(128 * 1024)
+                .and_then(|tier| tier.bucket.as_deref()),
+            None
+        );
+        let large = tiers
+            .route(128 * 1024 + 1)
+            .expect("no tier for large objects");
+        assert_eq!(large.bucket.as_deref(), Some("large"));
+        assert_eq!(large.storage_class, Some(StorageClass::StandardIa));
+        assert_eq!(tiers.buckets(), ["large"]);
+
+        let bounded = SizeTiers::new(vec![tier(Some(1), None, None)]).expect("failed to parse");
+        assert!(bounded.route(2).is_none());
+        assert!(SizeTiers::new(vec![tier(None, None, None), tier(Some(1), None, None)]).is_err());
+        assert!(
+            SizeTiers::new(vec![tier(Some(2), None, None), tier(Some(1), None, None)]).is_err()
+        );
+        assert!(SizeTiers::new(vec![tier(None, None, Some("FAST"))]).is_err());
+        assert_eq!(tiered_key("container", "key"), "container/key");
+    }
+}
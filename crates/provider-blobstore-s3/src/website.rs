@@ -0,0 +1,104 @@
+//! Headers of objects of static websites served from buckets, which browsers rely on to render
+//! and cache pages, but which components writing objects through `wrpc:blobstore` cannot set
+
+/// `Cache-Control` of HTML documents, unless configured, so that browsers revalidate pages and
+/// see new publishes immediately
+const DEFAULT_HTML_CACHE_CONTROL: &str = "no-cache";
+/// `Cache-Control` of other objects, unless configured
+const DEFAULT_ASSET_CACHE_CONTROL: &str = "public, max-age=3600";
+
+/// Extensions of keys and the content types websites serve objects with them as
+const CONTENT_TYPES: &[(&str, &str)] = &[
+    ("html", "text/html; charset=utf-8"),
+    ("htm", "text/html; charset=utf-8"),
+    ("css", "text/css; charset=utf-8"),
+    ("js", "text/javascript; charset=utf-8"),
+    ("mjs", "text/javascript; charset=utf-8"),
+    ("json", "application/json"),
+    ("map", "application/json"),
+    ("webmanifest", "application/manifest+json"),
+    ("xml", "application/xml"),
+    ("txt", "text/plain; charset=utf-8"),
+    ("md", "text/markdown; charset=utf-8"),
+    ("svg", "image/svg+xml"),
+    ("png", "image/png"),
+    ("jpg", "image/jpeg"),
+    ("jpeg", "image/jpeg"),
+    ("gif", "image/gif"),
+    ("webp", "image/webp"),
+    ("avif", "image/avif"),
+    ("ico", "image/x-icon"),
+    ("woff", "font/woff"),
+    ("woff2", "font/woff2"),
+    ("ttf", "font/ttf"),
+    ("otf", "font/otf"),
+    ("pdf", "application/pdf"),
+    ("wasm", "application/wasm"),
+    ("mp4", "video/mp4"),
+    ("webm", "video/webm"),
+];
+
+/// Content type of the object `key` of a website, `None` if its extension is not known
+pub(crate) fn content_type(key: &str) -> Option<&'static str> {
+    let (name, ext) = key.rsplit_once('.')?;
+    if name.is_empty() || name.ends_with('/') || ext.contains('/') {
+        return None;
+    }
+    CONTENT_TYPES
+        .iter()
+        .find(|(known, _)| known.eq_ignore_ascii_case(ext))
+        .map(|(_, content_type)| *content_type)
+}
+
+/// `Cache-Control` of an object of a website with the content type, HTML documents using
+/// `html_cache_control` and other objects `cache_control`, if set
+pub(crate) fn cache_control<'a>(
+    content_type: Option<&str>,
+    html_cache_control: Option<&'a str>,
+    cache_control: Option<&'a str>,
+) -> &'a str {
+    if content_type.is_some_and(|content_type| content_type.starts_with("text/html")) {
+        html_cache_control.unwrap_or(DEFAULT_HTML_CACHE_CONTROL)
+    } else {
+        cache_control.unwrap_or(DEFAULT_ASSET_CACHE_CONTROL)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn content_types() {
+        assert_eq!(
+            content_type("docs/index.html"),
+            Some("text/html; charset=utf-8")
+        );
+        assert_eq!(
+            content_type("assets/app.JS"),
+            Some("text/javascript; charset=utf-8")
+        );
+        assert_eq!(content_type("fonts/inter.woff2"), Some("font/woff2"));
+        assert_eq!(content_type("CNAME"), None);
+        assert_eq!(content_type(".nojekyll"), None);
+        assert_eq!(content_type("v1.2/README"), None);
+    }
+
+    #[test]
+    fn cache_controls() {
+        let html = content_type("index.html");
+        let css = content_type("style.css");
+        assert_eq!(cache_control(html, None, None), "no-cache");
+        assert_eq!(cache_control(css, None, None), "public, max-age=3600");
+        assert_eq!(cache_control(None, None, None), "public, max-age=3600");
+        assert_eq!(
+            cache_control(
+                css,
+                Some("no-store"),
+                Some("public, max-age=31536000, immutable")
+            ),
+            "public, max-age=31536000, immutable"
+        );
+        assert_eq!(cache_control(html, Some("no-store"), None), "no-store");
+    }
+}
@@ -67,7 +67,7 @@ impl TestEnv {
         })
     }
 
-    pub async fn configure_test_client(&self) -> StorageClient {
+    pub async fn configure_test_client(&self) -> Result<StorageClient> {
         let conf = StorageConfig {
             endpoint: Some(self.endpoint.clone()),
             access_key_id: Self::env_var_or_default("AWS_ACCESS_KEY_ID", Some("test".to_string())),
@@ -75,12 +75,9 @@ impl TestEnv {
                 "AWS_SECRET_ACCESS_KEY",
                 Some("test".to_string()),
             ),
-            aliases: HashMap::new(),
-            max_attempts: None,
             region: Self::env_var_or_default("AWS_REGION", Some("us-east-1".to_string())),
-            session_token: None,
-            sts_config: None,
             bucket_region: Self::env_var_or_default("BUCKET_REGION", None),
+            ..Default::default()
         };
 
         StorageClient::new(conf, &HashMap::new()).await
@@ -100,7 +97,10 @@ async fn test_create_container() {
         .await
         .expect("should have setup the test environment");
 
-    let s3 = env.configure_test_client().await;
+    let s3 = env
+        .configure_test_client()
+        .await
+        .expect("should have configured the test client");
 
     let num = rand::random::<u64>();
     let bucket = format!("test.bucket.{num}");
@@ -7,6 +7,7 @@ use core::time::Duration;
 use std::collections::HashMap;
 use std::io::BufRead;
 use std::sync::Arc;
+use std::time::SystemTime;
 
 use anyhow::{bail, Context as _, Result};
 use async_nats::subject::ToSubject as _;
@@ -1030,6 +1031,12 @@ impl ProviderConnection {
         })
     }
 
+    /// Maximum size of messages accepted by the NATS server of this connection
+    #[must_use]
+    pub fn max_payload(&self) -> usize {
+        self.nats.server_info().max_payload
+    }
+
     /// Retrieve a wRPC client that can be used based on the NATS client of this connection
     ///
     /// # Arguments
@@ -1068,6 +1075,28 @@ impl ProviderConnection {
         &self.provider_id
     }
 
+    /// Publish `data` as a CloudEvent of type `com.wasmcloud.lattice.{name}` on the lattice event
+    /// stream (`wasmbus.evt.{lattice}.{name}`), sourced from this provider
+    ///
+    /// Events are published on the RPC connection of the provider, which is the lattice control
+    /// connection of hosts, unless they are configured to use separate NATS clusters.
+    pub async fn publish_event(&self, name: &str, data: serde_json::Value) -> Result<()> {
+        let ev = serde_json::json!({
+            "specversion": "1.0",
+            "id": uuid::Uuid::from_u128(ulid::Ulid::new().into()).to_string(),
+            "source": &*self.provider_id,
+            "type": format!("com.wasmcloud.lattice.{name}"),
+            "time": humantime::format_rfc3339_millis(SystemTime::now()).to_string(),
+            "datacontenttype": "application/json",
+            "data": data,
+        });
+        let ev = serde_json::to_vec(&ev).context("failed to serialize event")?;
+        self.nats
+            .publish(format!("wasmbus.evt.{}.{name}", self.lattice), ev.into())
+            .await
+            .with_context(|| format!("failed to publish `{name}` event"))
+    }
+
     /// Stores link in the [`ProviderConnection`], either as a source link or target link
     /// depending on if the provider is the source or target of the link
     pub async fn put_link(&self, ld: InterfaceLinkDefinition) {
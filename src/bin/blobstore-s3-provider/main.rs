@@ -2,6 +2,12 @@ use anyhow::Context as _;
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
+    #[cfg(feature = "blobstore-s3-bench")]
+    if let Some(path) = std::env::args().skip_while(|arg| arg != "--bench").nth(1) {
+        return wasmcloud_provider_blobstore_s3::bench::run_file(path)
+            .await
+            .context("failed to run benchmark");
+    }
     wasmcloud_provider_blobstore_s3::run()
         .await
         .context("failed to run provider")?;